@@ -0,0 +1,162 @@
+#ifndef LIBRARYLINK_H
+#define LIBRARYLINK_H
+
+/* Generated by cbindgen from librarylink-ffi/src/lib.rs. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * `scope`: processes anywhere under the activated process's directory may
+ * take over monitoring. Zero, so zero-initialized options get the default.
+ */
+#define LIBRARYLINK_SCOPE_DIR_TREE 0
+
+/**
+ * `scope`: only processes in the activated process's own directory
+ */
+#define LIBRARYLINK_SCOPE_EXACT_DIR 1
+
+/**
+ * `scope`: any process of the app's package
+ */
+#define LIBRARYLINK_SCOPE_PACKAGE 2
+
+/**
+ * A launched app being monitored. Use a session from one thread at a time.
+ */
+typedef struct LibrarylinkSession LibrarylinkSession;
+
+/**
+ * Launch settings. Zero-initialize and fill in what's needed.
+ */
+typedef struct LibrarylinkLaunchOptions {
+  /**
+   * Executable to track instead of the activated process, or NULL
+   */
+  const char *wait_for;
+  /**
+   * One of the `LIBRARYLINK_SCOPE_*` values
+   */
+  uint32_t scope;
+  /**
+   * 1 to kill the game when the session is freed instead of detaching, or 0
+   */
+  uint8_t kill_on_free;
+  /**
+   * Poll at this interval instead of waiting on process handles; 0 for automatic
+   */
+  uint32_t poll_interval_ms;
+  /**
+   * 1 to close store clients that weren't running before the launch once
+   * the game exits, or 0
+   */
+  uint8_t close_launchers;
+} LibrarylinkLaunchOptions;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * The last error on the calling thread, or NULL. Owned by the library and
+ * valid until the next librarylink call on the same thread.
+ */
+const char *librarylink_last_error(void);
+
+/**
+ * Releases a string returned by librarylink.
+ *
+ * # Safety
+ * `value` must be NULL or a string returned by librarylink that hasn't been freed.
+ */
+void librarylink_string_free(char *value);
+
+/**
+ * Lists apps with AUMIDs as a JSON array of `{"name", "aumid"}` objects,
+ * optionally filtered by `search` (NULL for all apps).
+ *
+ * # Safety
+ * `search` must be NULL or a valid null-terminated string.
+ */
+char *librarylink_list_apps(const char *search);
+
+/**
+ * Launches an app and starts monitoring it. `options` may be NULL for the
+ * defaults. Free the session with `librarylink_session_free`.
+ *
+ * # Safety
+ * `aumid` must be a valid null-terminated string, and `options` NULL or a
+ * pointer to an initialized `LibrarylinkLaunchOptions`.
+ */
+struct LibrarylinkSession *librarylink_launch(const char *aumid,
+                                              const struct LibrarylinkLaunchOptions *options);
+
+/**
+ * The session ID, owned by the session.
+ *
+ * # Safety
+ * `session` must be a live session returned by `librarylink_launch`.
+ */
+const char *librarylink_session_id(const struct LibrarylinkSession *session);
+
+/**
+ * The process currently being tracked.
+ *
+ * # Safety
+ * `session` must be a live session returned by `librarylink_launch`.
+ */
+uint32_t librarylink_session_pid(const struct LibrarylinkSession *session);
+
+/**
+ * Whether monitoring is still following the app.
+ *
+ * # Safety
+ * `session` must be a live session returned by `librarylink_launch`.
+ */
+bool librarylink_session_running(const struct LibrarylinkSession *session);
+
+/**
+ * Waits up to `timeout_ms` for the next event and returns it as JSON (the
+ * same objects as `--output json`). Returns NULL on timeout, or once the
+ * session has ended and every event has been read.
+ *
+ * # Safety
+ * `session` must be a live session returned by `librarylink_launch`.
+ */
+char *librarylink_session_next_event(struct LibrarylinkSession *session, uint32_t timeout_ms);
+
+/**
+ * Blocks until the app has exited and returns how long it ran, in seconds,
+ * or -1 if the session was already waited on.
+ *
+ * # Safety
+ * `session` must be a live session returned by `librarylink_launch`.
+ */
+int64_t librarylink_session_wait(struct LibrarylinkSession *session);
+
+/**
+ * Terminates the tracked process and stops following replacements.
+ *
+ * # Safety
+ * `session` must be a live session returned by `librarylink_launch`.
+ */
+bool librarylink_session_kill(struct LibrarylinkSession *session);
+
+/**
+ * Frees a session, killing the game first if `kill_on_free` was set.
+ *
+ * # Safety
+ * `session` must be NULL or a session returned by `librarylink_launch` that
+ * hasn't been freed.
+ */
+void librarylink_session_free(struct LibrarylinkSession *session);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* LIBRARYLINK_H */
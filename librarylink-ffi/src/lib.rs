@@ -0,0 +1,339 @@
+//! C ABI for librarylink, for frontends written in C#, C++ or Python.
+//!
+//! Strings crossing the boundary are UTF-8 and null-terminated. Strings
+//! returned to the caller are owned by the caller and must be released with
+//! `librarylink_string_free`, unless documented otherwise. Functions that fail
+//! return NULL (or false) and leave a description for
+//! `librarylink_last_error` on the calling thread. A panic inside
+//! librarylink is caught at the boundary and reported the same way, so it
+//! never unwinds into the caller.
+//!
+//! The header in `include/librarylink.h` is generated with cbindgen.
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::Duration;
+
+use librarylink::matcher::MonitorScope;
+use librarylink::{DropBehavior, LaunchOptions, Session, apps};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: impl Into<String>) {
+    let message = CString::new(message.into().replace('\0', " ")).ok();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = message);
+}
+
+fn into_c_string(value: String) -> *mut c_char {
+    match CString::new(value) {
+        Ok(value) => value.into_raw(),
+        Err(e) => {
+            set_last_error(format!("Result contained a null byte: {}", e));
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Runs the body of an exported function, returning `fallback` with the
+/// panic as the last error if it panics.
+fn guard<T>(fallback: T, body: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        set_last_error(format!("librarylink panicked: {}", message));
+        fallback
+    })
+}
+
+/// Reads a flag, which C callers may fill with any byte.
+fn flag(value: u8, name: &str) -> Result<bool, String> {
+    match value {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => Err(format!("{} must be 0 or 1, not {}", name, value)),
+    }
+}
+
+/// Reads an optional string argument; NULL means "not given".
+unsafe fn optional_str<'a>(value: *const c_char, name: &str) -> Result<Option<&'a str>, String> {
+    if value.is_null() {
+        return Ok(None);
+    }
+    unsafe { CStr::from_ptr(value) }
+        .to_str()
+        .map(Some)
+        .map_err(|_| format!("{} is not valid UTF-8", name))
+}
+
+/// `scope`: processes anywhere under the activated process's directory may
+/// take over monitoring. Zero, so zero-initialized options get the default.
+pub const LIBRARYLINK_SCOPE_DIR_TREE: u32 = 0;
+/// `scope`: only processes in the activated process's own directory
+pub const LIBRARYLINK_SCOPE_EXACT_DIR: u32 = 1;
+/// `scope`: any process of the app's package
+pub const LIBRARYLINK_SCOPE_PACKAGE: u32 = 2;
+
+/// Reads `scope`, which C callers may fill with any value.
+fn scope(value: u32) -> Result<MonitorScope, String> {
+    match value {
+        LIBRARYLINK_SCOPE_DIR_TREE => Ok(MonitorScope::DirTree),
+        LIBRARYLINK_SCOPE_EXACT_DIR => Ok(MonitorScope::ExactDir),
+        LIBRARYLINK_SCOPE_PACKAGE => Ok(MonitorScope::Package),
+        _ => Err(format!(
+            "scope {} is not a LIBRARYLINK_SCOPE_* value",
+            value
+        )),
+    }
+}
+
+/// Launch settings. Zero-initialize and fill in what's needed.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct LibrarylinkLaunchOptions {
+    /// Executable to track instead of the activated process, or NULL
+    pub wait_for: *const c_char,
+    /// One of the `LIBRARYLINK_SCOPE_*` values
+    pub scope: u32,
+    /// 1 to kill the game when the session is freed instead of detaching, or 0
+    pub kill_on_free: u8,
+    /// Poll at this interval instead of waiting on process handles; 0 for automatic
+    pub poll_interval_ms: u32,
+    /// 1 to close store clients that weren't running before the launch once
+    /// the game exits, or 0
+    pub close_launchers: u8,
+}
+
+/// Applies the caller's options, refusing values outside their range.
+unsafe fn apply_options(
+    mut launch_options: LaunchOptions,
+    options: &LibrarylinkLaunchOptions,
+) -> Result<LaunchOptions, String> {
+    if let Some(executable) = unsafe { optional_str(options.wait_for, "wait_for") }? {
+        launch_options = launch_options.wait_for(executable);
+    }
+    launch_options = launch_options.scope(scope(options.scope)?);
+    if flag(options.kill_on_free, "kill_on_free")? {
+        launch_options = launch_options.on_drop(DropBehavior::Kill);
+    }
+    if options.poll_interval_ms > 0 {
+        launch_options =
+            launch_options.poll_interval(Duration::from_millis(options.poll_interval_ms as u64));
+    }
+    Ok(launch_options.close_launchers(flag(options.close_launchers, "close_launchers")?))
+}
+
+/// A launched app being monitored. Use a session from one thread at a time.
+pub struct LibrarylinkSession {
+    session: Session,
+    id: CString,
+}
+
+/// The last error on the calling thread, or NULL. Owned by the library and
+/// valid until the next librarylink call on the same thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn librarylink_last_error() -> *const c_char {
+    guard(std::ptr::null(), || {
+        LAST_ERROR.with(|last_error| {
+            last_error
+                .borrow()
+                .as_ref()
+                .map_or(std::ptr::null(), |message| message.as_ptr())
+        })
+    })
+}
+
+/// Releases a string returned by librarylink.
+///
+/// # Safety
+/// `value` must be NULL or a string returned by librarylink that hasn't been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn librarylink_string_free(value: *mut c_char) {
+    guard((), || {
+        if !value.is_null() {
+            drop(unsafe { CString::from_raw(value) });
+        }
+    })
+}
+
+/// Lists apps with AUMIDs as a JSON array of `{"name", "aumid"}` objects,
+/// optionally filtered by `search` (NULL for all apps).
+///
+/// # Safety
+/// `search` must be NULL or a valid null-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn librarylink_list_apps(search: *const c_char) -> *mut c_char {
+    guard(std::ptr::null_mut(), || {
+        let search = match unsafe { optional_str(search, "search") } {
+            Ok(search) => search,
+            Err(message) => {
+                set_last_error(message);
+                return std::ptr::null_mut();
+            }
+        };
+
+        match apps::list_apps(search) {
+            Ok(apps) => into_c_string(serde_json::to_string(&apps).unwrap_or_default()),
+            Err(e) => {
+                set_last_error(e.to_string());
+                std::ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Launches an app and starts monitoring it. `options` may be NULL for the
+/// defaults. Free the session with `librarylink_session_free`.
+///
+/// # Safety
+/// `aumid` must be a valid null-terminated string, and `options` NULL or a
+/// pointer to an initialized `LibrarylinkLaunchOptions`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn librarylink_launch(
+    aumid: *const c_char,
+    options: *const LibrarylinkLaunchOptions,
+) -> *mut LibrarylinkSession {
+    guard(std::ptr::null_mut(), || {
+        let aumid = match unsafe { optional_str(aumid, "aumid") } {
+            Ok(Some(aumid)) => aumid,
+            Ok(None) => {
+                set_last_error("aumid is required");
+                return std::ptr::null_mut();
+            }
+            Err(message) => {
+                set_last_error(message);
+                return std::ptr::null_mut();
+            }
+        };
+
+        let mut launch_options = LaunchOptions::new(aumid);
+        if let Some(options) = unsafe { options.as_ref() } {
+            launch_options = match unsafe { apply_options(launch_options, options) } {
+                Ok(launch_options) => launch_options,
+                Err(message) => {
+                    set_last_error(message);
+                    return std::ptr::null_mut();
+                }
+            };
+        }
+
+        match launch_options.launch() {
+            Ok(session) => {
+                let id = CString::new(session.id()).unwrap_or_default();
+                Box::into_raw(Box::new(LibrarylinkSession { session, id }))
+            }
+            Err(e) => {
+                set_last_error(e.to_string());
+                std::ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// The session ID, owned by the session.
+///
+/// # Safety
+/// `session` must be a live session returned by `librarylink_launch`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn librarylink_session_id(
+    session: *const LibrarylinkSession,
+) -> *const c_char {
+    guard(std::ptr::null(), || unsafe { &*session }.id.as_ptr())
+}
+
+/// The process currently being tracked.
+///
+/// # Safety
+/// `session` must be a live session returned by `librarylink_launch`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn librarylink_session_pid(session: *const LibrarylinkSession) -> u32 {
+    guard(0, || unsafe { &*session }.session.pid())
+}
+
+/// Whether monitoring is still following the app.
+///
+/// # Safety
+/// `session` must be a live session returned by `librarylink_launch`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn librarylink_session_running(session: *const LibrarylinkSession) -> bool {
+    guard(false, || unsafe { &*session }.session.is_running())
+}
+
+/// Waits up to `timeout_ms` for the next event and returns it as JSON (the
+/// same objects as `--output json`). Returns NULL on timeout, or once the
+/// session has ended and every event has been read.
+///
+/// # Safety
+/// `session` must be a live session returned by `librarylink_launch`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn librarylink_session_next_event(
+    session: *mut LibrarylinkSession,
+    timeout_ms: u32,
+) -> *mut c_char {
+    guard(std::ptr::null_mut(), || {
+        let session = unsafe { &*session };
+        match session
+            .session
+            .events()
+            .recv_timeout(Duration::from_millis(timeout_ms as u64))
+        {
+            Ok(event) => into_c_string(serde_json::to_string(&event).unwrap_or_default()),
+            Err(RecvTimeoutError::Timeout) => std::ptr::null_mut(),
+            Err(RecvTimeoutError::Disconnected) => {
+                set_last_error("The session has ended");
+                std::ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Blocks until the app has exited and returns how long it ran, in seconds,
+/// or -1 if the session was already waited on.
+///
+/// # Safety
+/// `session` must be a live session returned by `librarylink_launch`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn librarylink_session_wait(session: *mut LibrarylinkSession) -> i64 {
+    guard(-1, || match unsafe { &mut *session }.session.wait() {
+        Some(record) => record.duration_secs as i64,
+        None => {
+            set_last_error("The session was already waited on");
+            -1
+        }
+    })
+}
+
+/// Terminates the tracked process and stops following replacements.
+///
+/// # Safety
+/// `session` must be a live session returned by `librarylink_launch`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn librarylink_session_kill(session: *mut LibrarylinkSession) -> bool {
+    guard(false, || match unsafe { &*session }.session.kill() {
+        Ok(()) => true,
+        Err(e) => {
+            set_last_error(e.to_string());
+            false
+        }
+    })
+}
+
+/// Frees a session, killing the game first if `kill_on_free` was set.
+///
+/// # Safety
+/// `session` must be NULL or a session returned by `librarylink_launch` that
+/// hasn't been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn librarylink_session_free(session: *mut LibrarylinkSession) {
+    guard((), || {
+        if !session.is_null() {
+            drop(unsafe { Box::from_raw(session) });
+        }
+    })
+}
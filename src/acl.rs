@@ -0,0 +1,156 @@
+//! Checks, and where it's safe fixes, the current user's read access to an
+//! app's install directory. Reading the install tree (resolving `wait_for`
+//! patterns, `MicrosoftGame.config`) fails silently when its permissions
+//! don't allow it.
+
+use std::fs;
+use std::io;
+use std::path::{Component, Path};
+use windows::Win32::Foundation::{CloseHandle, HANDLE, HLOCAL, LocalFree};
+use windows::Win32::Security::Authorization::{
+    EXPLICIT_ACCESS_W, GRANT_ACCESS, GetNamedSecurityInfoW, NO_MULTIPLE_TRUSTEE, SE_FILE_OBJECT,
+    SetEntriesInAclW, SetNamedSecurityInfoW, TRUSTEE_IS_SID, TRUSTEE_IS_USER, TRUSTEE_W,
+};
+use windows::Win32::Security::{
+    ACL, DACL_SECURITY_INFORMATION, GetTokenInformation, PSECURITY_DESCRIPTOR,
+    SUB_CONTAINERS_AND_OBJECTS_INHERIT, TOKEN_QUERY, TOKEN_USER, TokenUser,
+};
+use windows::Win32::Storage::FileSystem::{FILE_GENERIC_EXECUTE, FILE_GENERIC_READ};
+use windows::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
+use windows::core::{HSTRING, PWSTR};
+
+use crate::error::WinError;
+
+/// The game configuration Game Pass titles ship in their install directory.
+pub const GAME_CONFIG: &str = "MicrosoftGame.config";
+
+/// What the current user can read of an install directory.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct AccessReport {
+    pub path: String,
+    /// The directory can be listed
+    pub readable: bool,
+    /// `MicrosoftGame.config` can be opened, or `None` when there isn't one
+    /// (or the directory can't be listed to tell)
+    pub config_readable: Option<bool>,
+    /// Under `WindowsApps`, whose permissions Windows manages
+    pub system_managed: bool,
+}
+
+/// Reports the current user's read access to `path`.
+pub fn check(path: &Path) -> AccessReport {
+    let config = path.join(GAME_CONFIG);
+    let config_readable = match fs::metadata(&config) {
+        Ok(_) => Some(fs::File::open(&config).is_ok()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(_) => Some(false),
+    };
+    AccessReport {
+        path: path.display().to_string(),
+        readable: fs::read_dir(path).is_ok(),
+        config_readable,
+        system_managed: is_system_managed(path),
+    }
+}
+
+/// Whether `path` is inside a `WindowsApps` folder. Changing permissions
+/// there breaks Store updates and repairs, so [`grant_read`] refuses.
+pub fn is_system_managed(path: &Path) -> bool {
+    path.components().any(|component| {
+        matches!(component, Component::Normal(name) if name.eq_ignore_ascii_case("WindowsApps"))
+    })
+}
+
+/// Grants the current user read and execute access to `path` and everything
+/// below it, as `icacls <path> /grant <user>:(OI)(CI)RX` would. Existing
+/// entries are kept. Usually needs an elevated prompt.
+pub fn grant_read(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    if is_system_managed(path) {
+        return Err(format!(
+            "{} is managed by Windows; changing its permissions would break Store updates",
+            path.display()
+        )
+        .into());
+    }
+
+    let user = current_user()?;
+    let object = HSTRING::from(path.as_os_str());
+    unsafe {
+        let mut dacl: *mut ACL = std::ptr::null_mut();
+        let mut descriptor = PSECURITY_DESCRIPTOR::default();
+        GetNamedSecurityInfoW(
+            &object,
+            SE_FILE_OBJECT,
+            DACL_SECURITY_INFORMATION,
+            None,
+            None,
+            Some(&mut dacl),
+            None,
+            &mut descriptor,
+        )
+        .ok()
+        .map_err(|e| WinError::new("GetNamedSecurityInfoW", &e))?;
+
+        let token_user = user.as_ptr() as *const TOKEN_USER;
+        let access = EXPLICIT_ACCESS_W {
+            grfAccessPermissions: FILE_GENERIC_READ.0 | FILE_GENERIC_EXECUTE.0,
+            grfAccessMode: GRANT_ACCESS,
+            grfInheritance: SUB_CONTAINERS_AND_OBJECTS_INHERIT,
+            Trustee: TRUSTEE_W {
+                pMultipleTrustee: std::ptr::null_mut(),
+                MultipleTrusteeOperation: NO_MULTIPLE_TRUSTEE,
+                TrusteeForm: TRUSTEE_IS_SID,
+                TrusteeType: TRUSTEE_IS_USER,
+                ptstrName: PWSTR((*token_user).User.Sid.0 as *mut u16),
+            },
+        };
+        let mut updated: *mut ACL = std::ptr::null_mut();
+        let result = SetEntriesInAclW(Some(&[access]), Some(dacl), &mut updated)
+            .ok()
+            .map_err(|e| WinError::new("SetEntriesInAclW", &e));
+        let _ = LocalFree(Some(HLOCAL(descriptor.0)));
+        result?;
+
+        let result = SetNamedSecurityInfoW(
+            &object,
+            SE_FILE_OBJECT,
+            DACL_SECURITY_INFORMATION,
+            None,
+            None,
+            Some(updated),
+            None,
+        )
+        .ok()
+        .map_err(|e| WinError::new("SetNamedSecurityInfoW", &e));
+        let _ = LocalFree(Some(HLOCAL(updated as _)));
+        result?;
+    }
+    Ok(())
+}
+
+/// The current process token's `TOKEN_USER`, in a buffer that keeps the SID
+/// it points to alive.
+fn current_user() -> Result<Vec<u64>, WinError> {
+    unsafe {
+        let mut token = HANDLE::default();
+        OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token)
+            .map_err(|e| WinError::new("OpenProcessToken", &e))?;
+
+        // First call reports the required buffer length
+        let mut length = 0u32;
+        let _ = GetTokenInformation(token, TokenUser, None, 0, &mut length);
+        // u64s keep the buffer aligned for TOKEN_USER
+        let mut buffer = vec![0u64; (length as usize).div_ceil(8)];
+        let result = GetTokenInformation(
+            token,
+            TokenUser,
+            Some(buffer.as_mut_ptr().cast()),
+            length,
+            &mut length,
+        )
+        .map_err(|e| WinError::new("GetTokenInformation(TokenUser)", &e));
+        let _ = CloseHandle(token);
+        result.map(|()| buffer)
+    }
+}
@@ -0,0 +1,307 @@
+use std::error::Error;
+use std::fmt;
+use windows::ApplicationModel::AppInfo;
+use windows::Management::Deployment::PackageManager;
+use windows::core::HSTRING;
+
+use crate::compat::Capability;
+use crate::error::WinError;
+use crate::package;
+use crate::report::{AppListing, Event};
+use crate::search;
+use crate::timings::{self, Phase};
+
+/// Which apps [`list_apps_matching`] lists. Text is matched as
+/// [`search::contains`] does, ignoring case and diacritics.
+#[derive(Debug, Clone, Default)]
+pub struct AppFilter {
+    /// Part of the app's name, or of its package's name without the spaces,
+    /// for names in another script
+    pub search: Option<String>,
+    /// Part of the publisher's name, such as `Xbox Game Studios`
+    pub publisher: Option<String>,
+    /// A package family name, or just its name part before the `_`
+    pub family: Option<String>,
+    /// Also list framework packages such as the VCLibs and .NET runtimes
+    /// games depend on, which have no apps of their own
+    pub include_frameworks: bool,
+}
+
+impl AppFilter {
+    pub fn matches(&self, app: &AppListing) -> bool {
+        let package_name = app
+            .package_family_name
+            .as_deref()
+            .and_then(|family| family.split_once('_'))
+            .map(|(name, _)| name);
+        self.search.as_deref().is_none_or(|term| {
+            search::contains(&app.name, term)
+                || package_name.is_some_and(|name| search::matches_package_name(name, term))
+        }) && self.publisher.as_deref().is_none_or(|publisher| {
+            app.publisher
+                .as_deref()
+                .is_some_and(|text| search::contains(text, publisher))
+        }) && self.family.as_deref().is_none_or(|family| {
+            app.package_family_name.as_deref().is_some_and(|name| {
+                name.eq_ignore_ascii_case(family)
+                    || name
+                        .split_once('_')
+                        .is_some_and(|(name, _)| name.eq_ignore_ascii_case(family))
+            })
+        }) && (self.include_frameworks || !app.framework)
+    }
+}
+
+/// Lists the apps that can be launched: the Start menu's, or the mock apps
+/// under `--provider mock`.
+pub fn list_apps(search_term: Option<&str>) -> Result<Vec<AppListing>, Box<dyn std::error::Error>> {
+    list_apps_matching(&AppFilter {
+        search: search_term.map(str::to_string),
+        ..AppFilter::default()
+    })
+}
+
+/// Lists the apps [`list_apps`] would that pass `filter`.
+pub fn list_apps_matching(
+    filter: &AppFilter,
+) -> Result<Vec<AppListing>, Box<dyn std::error::Error>> {
+    #[cfg(feature = "cli")]
+    if crate::mock::is_enabled() {
+        return Ok(crate::mock::apps(None)
+            .into_iter()
+            .filter(|app| filter.matches(app))
+            .collect());
+    }
+    timings::time(Phase::Enumeration, || find_apps(filter))
+}
+
+/// The name the Start menu shows for `aumid`, or for a mock app its name.
+pub fn display_name(aumid: &str) -> Option<String> {
+    #[cfg(feature = "cli")]
+    if crate::mock::is_enabled() {
+        return crate::mock::apps(None)
+            .into_iter()
+            .find(|app| app.aumid.eq_ignore_ascii_case(aumid))
+            .map(|app| app.name);
+    }
+    if !Capability::AppInfoLookup.is_supported() {
+        return None;
+    }
+    timings::time(Phase::Lookup, || {
+        AppInfo::GetFromAppUserModelId(&HSTRING::from(aumid))
+    })
+    .and_then(|info| info.DisplayInfo())
+    .and_then(|info| info.DisplayName())
+    .map(|name| name.to_string())
+    .ok()
+    .filter(|name| !name.is_empty())
+}
+
+/// What [`find_app`] learned about an installed app, as its `app_found`
+/// event reports it.
+#[derive(Debug, Clone, Default)]
+pub struct AppDetails {
+    pub display_name: Option<String>,
+    pub package_display_name: Option<String>,
+    pub install_path: Option<String>,
+    pub package_full_name: Option<String>,
+    pub package_family_name: Option<String>,
+}
+
+impl AppDetails {
+    pub fn event(&self) -> Event {
+        Event::AppFound {
+            display_name: self.display_name.clone(),
+            package_display_name: self.package_display_name.clone(),
+            install_path: self.install_path.clone(),
+            package_full_name: self.package_full_name.clone(),
+            package_family_name: self.package_family_name.clone(),
+        }
+    }
+}
+
+/// No app with the AUMID is installed for the current user.
+#[derive(Debug)]
+pub struct AppNotFound {
+    pub aumid: String,
+    /// What to do about it, when the package is on the machine
+    pub hint: Option<String>,
+    /// The lookup's own failure
+    pub error: Option<WinError>,
+}
+
+impl fmt::Display for AppNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "No app with the AUMID '{}' is installed for the current user",
+            self.aumid
+        )?;
+        if let Some(error) = &self.error {
+            write!(f, " ({})", error)?;
+        }
+        if let Some(hint) = &self.hint {
+            write!(f, ". {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for AppNotFound {}
+
+/// Looks `aumid` up the way activation will, so a wrong or unregistered
+/// AUMID fails with [`AppNotFound`] rather than an activation error. Builds
+/// without the `AppInfo` lookup go by the package alone.
+pub fn find_app(aumid: &str) -> Result<AppDetails, AppNotFound> {
+    let not_found = |error| {
+        AppNotFound {
+        aumid: aumid.to_string(),
+        hint: package::registration_hint(aumid).or_else(|| {
+            // Installed but unknown to AppInfo: usually a broken registration
+            package::installed_version(aumid)?;
+            Some(format!(
+                "If its Start menu entry is broken too, re-register it with: librarylink package repair {}",
+                package::family_name(aumid)?
+            ))
+        }),
+        error,
+    }
+    };
+
+    let (display_name, package) = if Capability::AppInfoLookup.is_supported() {
+        let info = timings::time(Phase::Lookup, || {
+            AppInfo::GetFromAppUserModelId(&HSTRING::from(aumid))
+        })
+        .map_err(|e| not_found(Some(WinError::new("AppInfo::GetFromAppUserModelId", &e))))?;
+        let display_name = info
+            .DisplayInfo()
+            .and_then(|info| info.DisplayName())
+            .map(|name| name.to_string())
+            .ok()
+            .filter(|name| !name.is_empty());
+        let package = info
+            .Package()
+            .map_err(|e| not_found(Some(WinError::new("AppInfo::Package", &e))))?;
+        (display_name, package)
+    } else {
+        let package = package::installed_package(aumid).ok_or_else(|| not_found(None))?;
+        (None, package)
+    };
+
+    let id = package.Id().ok();
+    Ok(AppDetails {
+        display_name,
+        package_display_name: package.DisplayName().ok().map(|name| name.to_string()),
+        install_path: package.InstalledPath().ok().map(|path| path.to_string()),
+        package_full_name: id
+            .as_ref()
+            .and_then(|id| id.FullName().ok())
+            .map(|name| name.to_string()),
+        package_family_name: id
+            .and_then(|id| id.FamilyName().ok())
+            .map(|name| name.to_string()),
+    })
+}
+
+/// Lists the current user's packaged apps that appear in the Start menu
+/// and pass `filter`, sorted by name. Reads the packages through
+/// `PackageManager` directly, so it works where PowerShell is missing or
+/// blocked by execution policy.
+pub fn find_apps(filter: &AppFilter) -> Result<Vec<AppListing>, Box<dyn std::error::Error>> {
+    if !Capability::AppListAumids.is_supported() {
+        return Err(Capability::AppListAumids.unsupported_message().into());
+    }
+    let manager = PackageManager::new().map_err(|e| WinError::new("PackageManager", &e))?;
+    // An empty SID means the current user
+    let packages = manager
+        .FindPackagesByUserSecurityId(&HSTRING::new())
+        .map_err(|e| WinError::new("PackageManager::FindPackagesByUserSecurityId", &e))?;
+    let provisioned_families = package::provisioned_families();
+
+    let mut apps = Vec::new();
+    for package in packages {
+        let id = package.Id().ok();
+        let package_family_name = id
+            .as_ref()
+            .and_then(|id| id.FamilyName().ok())
+            .map(|name| name.to_string());
+        let provisioned = package_family_name
+            .as_ref()
+            .is_some_and(|family| provisioned_families.contains(&family.to_lowercase()));
+        let version = id.and_then(|id| id.Version().ok()).map(|version| {
+            format!(
+                "{}.{}.{}.{}",
+                version.Major, version.Minor, version.Build, version.Revision
+            )
+        });
+        let install_path = package.InstalledPath().ok().map(|path| path.to_string());
+        let publisher = package
+            .PublisherDisplayName()
+            .ok()
+            .map(|name| name.to_string())
+            .filter(|name| !name.is_empty());
+
+        if package.IsFramework().unwrap_or(false) {
+            if filter.include_frameworks {
+                let name = package
+                    .DisplayName()
+                    .ok()
+                    .map(|name| name.to_string())
+                    .filter(|name| !name.is_empty())
+                    .or_else(|| package_family_name.clone())
+                    .unwrap_or_default();
+                let listing = AppListing {
+                    name,
+                    aumid: String::new(),
+                    package_family_name,
+                    install_path,
+                    version,
+                    publisher,
+                    framework: true,
+                    provisioned,
+                };
+                if filter.matches(&listing) {
+                    apps.push(listing);
+                }
+            }
+            continue;
+        }
+
+        // Resource packages have no entries; packages that can't be read
+        // (being removed, say) are skipped
+        let Ok(entries) = package.GetAppListEntries() else {
+            continue;
+        };
+
+        for entry in entries {
+            let Ok(aumid) = entry.AppUserModelId().map(|aumid| aumid.to_string()) else {
+                continue;
+            };
+            let name = entry
+                .DisplayInfo()
+                .and_then(|info| info.DisplayName())
+                .map(|name| name.to_string())
+                .unwrap_or_default();
+            if aumid.is_empty() || name.is_empty() {
+                continue;
+            }
+            let listing = AppListing {
+                name,
+                aumid,
+                package_family_name: package_family_name.clone(),
+                install_path: install_path.clone(),
+                version: version.clone(),
+                publisher: publisher.clone(),
+                framework: false,
+                provisioned,
+            };
+            if filter.matches(&listing) {
+                apps.push(listing);
+            }
+        }
+    }
+
+    apps.sort_by_key(|app| app.name.to_lowercase());
+
+    Ok(apps)
+}
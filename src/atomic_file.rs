@@ -0,0 +1,77 @@
+//! Writes librarylink's own files so that a crash or a power cut partway
+//! leaves either the old contents or the new ones, never a truncated file.
+//! The new contents go to a temporary file beside the target, are flushed to
+//! disk, and then take the target's place in one rename.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use windows::Win32::Storage::FileSystem::{
+    MOVEFILE_REPLACE_EXISTING, MOVEFILE_WRITE_THROUGH, MoveFileExW, REPLACE_FILE_FLAGS,
+    ReplaceFileW,
+};
+use windows::core::HSTRING;
+
+use crate::error::WinError;
+
+/// Replaces `path` with `contents`, creating its directory if needed.
+pub fn write(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    replace(path, contents.as_ref(), false)
+}
+
+/// As [`write`], keeping what `path` held before as [`backup_path`], for
+/// files that can't be rebuilt when they're lost.
+pub fn write_with_backup(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    replace(path, contents.as_ref(), true)
+}
+
+/// Where [`write_with_backup`] keeps the previous contents: `<file>.bak`.
+pub fn backup_path(path: &Path) -> PathBuf {
+    with_suffix(path, "bak")
+}
+
+/// `path` with `.<suffix>` after its whole file name.
+pub(crate) fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+fn replace(path: &Path, contents: &[u8], backup: bool) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Named after the process, so two writers never share one
+    let temporary = with_suffix(path, &format!("{}.tmp", std::process::id()));
+    let written = File::create(&temporary).and_then(|mut file| {
+        file.write_all(contents)?;
+        file.sync_all()
+    });
+    if let Err(e) = written {
+        let _ = fs::remove_file(&temporary);
+        return Err(e);
+    }
+
+    let target = HSTRING::from(path.as_os_str());
+    let source = HSTRING::from(temporary.as_os_str());
+    let result = if backup && path.exists() {
+        // Swaps the files and keeps the old one in a single step
+        let backup = HSTRING::from(backup_path(path).as_os_str());
+        unsafe { ReplaceFileW(&target, &source, &backup, REPLACE_FILE_FLAGS(0), None, None) }
+            .map_err(|e| WinError::new("ReplaceFileW", &e))
+    } else {
+        unsafe {
+            MoveFileExW(
+                &source,
+                &target,
+                MOVEFILE_REPLACE_EXISTING | MOVEFILE_WRITE_THROUGH,
+            )
+        }
+        .map_err(|e| WinError::new("MoveFileExW", &e))
+    };
+    result.map_err(|e| {
+        let _ = fs::remove_file(&temporary);
+        io::Error::other(e)
+    })
+}
@@ -0,0 +1,205 @@
+//! Audio output devices, with the IDs and exact names configs refer to them
+//! by, and the default device a session can switch for its length.
+
+use windows::Win32::Devices::FunctionDiscovery::PKEY_Device_FriendlyName;
+use windows::Win32::Media::Audio::{
+    DEVICE_STATE_ACTIVE, ERole, IMMDevice, IMMDeviceEnumerator, MMDeviceEnumerator,
+    eCommunications, eConsole, eMultimedia, eRender,
+};
+use windows::Win32::System::Com::StructuredStorage::{PropVariantClear, PropVariantToStringAlloc};
+use windows::Win32::System::Com::{CLSCTX_ALL, CoCreateInstance, CoTaskMemFree, STGM_READ};
+use windows::core::{GUID, HSTRING, PCWSTR, PWSTR};
+
+use policy_config::IPolicyConfig;
+
+use crate::error::WinError;
+use crate::launch::ComApartment;
+
+/// An active render (playback) endpoint.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct AudioDevice {
+    /// Endpoint ID, such as `{0.0.0.00000000}.{a1b2...}`; unlike the name it
+    /// doesn't change when the device is renamed in Sound settings
+    pub id: String,
+    /// The name Sound settings shows, such as `Speakers (Realtek(R) Audio)`
+    pub name: String,
+    /// The default device for games and media
+    pub default: bool,
+    /// The default device for voice chat
+    pub default_communications: bool,
+}
+
+/// Lists the active playback devices, in the order Windows enumerates them.
+pub fn render_devices() -> Result<Vec<AudioDevice>, WinError> {
+    let _com = ComApartment::enter()?;
+    unsafe {
+        let enumerator: IMMDeviceEnumerator =
+            CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)
+                .map_err(|e| WinError::new("CoCreateInstance(MMDeviceEnumerator)", &e))?;
+        let collection = enumerator
+            .EnumAudioEndpoints(eRender, DEVICE_STATE_ACTIVE)
+            .map_err(|e| WinError::new("IMMDeviceEnumerator::EnumAudioEndpoints", &e))?;
+        let count = collection
+            .GetCount()
+            .map_err(|e| WinError::new("IMMDeviceCollection::GetCount", &e))?;
+
+        // No default device (nothing plugged in) isn't an error
+        let default_id = |role: ERole| {
+            enumerator
+                .GetDefaultAudioEndpoint(eRender, role)
+                .ok()
+                .and_then(|device| device_id(&device))
+        };
+        let default = default_id(eConsole);
+        let default_communications = default_id(eCommunications);
+
+        let mut devices = Vec::new();
+        for index in 0..count {
+            let Ok(device) = collection.Item(index) else {
+                continue;
+            };
+            let Some(id) = device_id(&device) else {
+                continue;
+            };
+            devices.push(AudioDevice {
+                name: friendly_name(&device).unwrap_or_else(|| id.clone()),
+                default: default.as_ref() == Some(&id),
+                default_communications: default_communications.as_ref() == Some(&id),
+                id,
+            });
+        }
+        Ok(devices)
+    }
+}
+
+fn device_id(device: &IMMDevice) -> Option<String> {
+    unsafe { device.GetId().ok().map(|id| take_string(id)) }
+}
+
+fn friendly_name(device: &IMMDevice) -> Option<String> {
+    unsafe {
+        let store = device.OpenPropertyStore(STGM_READ).ok()?;
+        let mut value = store.GetValue(&PKEY_Device_FriendlyName).ok()?;
+        let name = PropVariantToStringAlloc(&value)
+            .ok()
+            .map(|name| take_string(name));
+        let _ = PropVariantClear(&mut value);
+        name.filter(|name| !name.is_empty())
+    }
+}
+
+/// Copies a COM-allocated string and frees it.
+unsafe fn take_string(value: PWSTR) -> String {
+    unsafe {
+        let text = value.to_string().unwrap_or_default();
+        CoTaskMemFree(Some(value.0 as *const _));
+        text
+    }
+}
+
+/// The playback device given by its endpoint ID or its exact name, as
+/// `list-audio-devices` shows them.
+pub fn find_render_device(id_or_name: &str) -> Result<AudioDevice, String> {
+    let devices = render_devices().map_err(|e| e.to_string())?;
+    devices
+        .iter()
+        .find(|device| device.id == id_or_name)
+        .or_else(|| devices.iter().find(|device| device.name == id_or_name))
+        .cloned()
+        .ok_or_else(|| {
+            format!(
+                "No active playback device is named '{}'; see 'librarylink list-audio-devices'",
+                id_or_name
+            )
+        })
+}
+
+/// The endpoint ID of the default playback device for games and media, if
+/// there is one.
+pub fn default_render_device() -> Result<Option<String>, WinError> {
+    Ok(render_devices()?
+        .into_iter()
+        .find(|device| device.default)
+        .map(|device| device.id))
+}
+
+/// Makes the device with endpoint ID `id` the default for games and media,
+/// as choosing it in Sound settings does. The default for voice chat is left
+/// alone.
+pub fn set_default_render_device(id: &str) -> Result<(), WinError> {
+    let _com = ComApartment::enter()?;
+    unsafe {
+        let policy: IPolicyConfig = CoCreateInstance(&POLICY_CONFIG_CLIENT, None, CLSCTX_ALL)
+            .map_err(|e| WinError::new("CoCreateInstance(PolicyConfigClient)", &e))?;
+        let id = HSTRING::from(id);
+        for role in [eConsole, eMultimedia] {
+            policy_config::set_default_endpoint(&policy, PCWSTR(id.as_ptr()), role)
+                .map_err(|e| WinError::new("IPolicyConfig::SetDefaultEndpoint", &e))?;
+        }
+    }
+    Ok(())
+}
+
+/// The class behind Sound settings' "Set as default device". Undocumented,
+/// but unchanged since Windows 7 and what every device switcher uses.
+const POLICY_CONFIG_CLIENT: GUID = GUID::from_u128(0x870af99c_171d_4f9e_af0d_e63df40c2bc9);
+
+/// Only `SetDefaultEndpoint` is called; the methods before it are declared
+/// for their place in the vtable.
+#[allow(non_snake_case)]
+mod policy_config {
+    use std::ffi::c_void;
+    use windows::Win32::Media::Audio::ERole;
+    use windows::core::{HRESULT, IUnknown, IUnknown_Vtbl, PCWSTR, interface};
+
+    #[interface("f8679f50-850a-41cf-9c72-430f290290c8")]
+    pub unsafe trait IPolicyConfig: IUnknown {
+        fn GetMixFormat(&self, device: PCWSTR, format: *mut *mut c_void) -> HRESULT;
+        fn GetDeviceFormat(
+            &self,
+            device: PCWSTR,
+            default: i32,
+            format: *mut *mut c_void,
+        ) -> HRESULT;
+        fn ResetDeviceFormat(&self, device: PCWSTR) -> HRESULT;
+        fn SetDeviceFormat(
+            &self,
+            device: PCWSTR,
+            endpoint_format: *mut c_void,
+            mix_format: *mut c_void,
+        ) -> HRESULT;
+        fn GetProcessingPeriod(
+            &self,
+            device: PCWSTR,
+            default: i32,
+            default_period: *mut i64,
+            minimum_period: *mut i64,
+        ) -> HRESULT;
+        fn SetProcessingPeriod(&self, device: PCWSTR, period: *mut i64) -> HRESULT;
+        fn GetShareMode(&self, device: PCWSTR, mode: *mut c_void) -> HRESULT;
+        fn SetShareMode(&self, device: PCWSTR, mode: *mut c_void) -> HRESULT;
+        fn GetPropertyValue(
+            &self,
+            device: PCWSTR,
+            key: *const c_void,
+            value: *mut c_void,
+        ) -> HRESULT;
+        fn SetPropertyValue(
+            &self,
+            device: PCWSTR,
+            key: *const c_void,
+            value: *mut c_void,
+        ) -> HRESULT;
+        fn SetDefaultEndpoint(&self, device: PCWSTR, role: ERole) -> HRESULT;
+        fn SetEndpointVisibility(&self, device: PCWSTR, visible: i32) -> HRESULT;
+    }
+
+    pub(super) unsafe fn set_default_endpoint(
+        policy: &IPolicyConfig,
+        device: PCWSTR,
+        role: ERole,
+    ) -> windows::core::Result<()> {
+        unsafe { policy.SetDefaultEndpoint(device, role).ok() }
+    }
+}
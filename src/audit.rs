@@ -0,0 +1,123 @@
+//! An append-only record of the launches, kills and access changes asked of
+//! the daemon and the `rpc` sidecar, and of who asked, so a shared host can
+//! answer "who killed my game". Entries are JSON lines in
+//! `%APPDATA%\librarylink\audit.jsonl`; nothing librarylink does rewrites
+//! or trims the file.
+
+use serde::Serialize;
+use serde_json::json;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::paths;
+use crate::process;
+use crate::report::warning;
+use crate::session::unix_now;
+use crate::tree;
+
+/// Serializes appends from the daemon's connection threads.
+static AUDIT_LOCK: Mutex<()> = Mutex::new(());
+
+pub fn audit_path() -> PathBuf {
+    paths::data_dir().join("audit.jsonl")
+}
+
+/// Who asked for an action.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Actor {
+    /// The daemon token it authenticated with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    /// The executable name of `pid`, looked up when the actor is created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub process: Option<String>,
+}
+
+impl Actor {
+    /// The process `pid`, with its executable name while it's still running.
+    pub fn process(pid: u32) -> Self {
+        Actor {
+            token: None,
+            pid: Some(pid),
+            process: process::get_process_info(pid).map(|info| info.name),
+        }
+    }
+
+    /// This process's parent, which is the client of a sidecar on stdio.
+    pub fn parent() -> Self {
+        let own = std::process::id();
+        tree::snapshot_processes()
+            .ok()
+            .and_then(|entries| entries.into_iter().find(|entry| entry.pid == own))
+            .map(|entry| Actor::process(entry.parent_pid))
+            .unwrap_or_default()
+    }
+
+    /// This process, for changes made from the command line.
+    pub fn current() -> Self {
+        Actor::process(std::process::id())
+    }
+}
+
+/// How an audited request turned out.
+pub enum Outcome<'a> {
+    Ok,
+    /// Refused for lack of a token or scope
+    Denied(&'a str),
+    Failed(&'a str),
+}
+
+/// Appends an entry for `action` on `target`, requested through `source`
+/// (`daemon`, `rpc`, `control` or `cli`). Failing to write the log is
+/// reported but doesn't stop the action.
+pub fn record(source: &str, actor: &Actor, action: &str, target: &str, outcome: Outcome) {
+    let (outcome, error) = match outcome {
+        Outcome::Ok => ("ok", None),
+        Outcome::Denied(message) => ("denied", Some(message)),
+        Outcome::Failed(message) => ("failed", Some(message)),
+    };
+    let mut entry = json!({
+        "at": unix_now(),
+        "source": source,
+        "actor": actor,
+        "action": action,
+        "target": target,
+        "outcome": outcome,
+    });
+    if let Some(error) = error {
+        entry["error"] = json!(error);
+    }
+
+    if let Err(e) = append(&format!("{}\n", entry)) {
+        warning!("⚠️ Could not write the audit log: {}", e);
+    }
+}
+
+fn append(line: &str) -> std::io::Result<()> {
+    let _guard = AUDIT_LOCK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    fs::create_dir_all(paths::data_dir())?;
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(audit_path())?;
+    file.write_all(line.as_bytes())?;
+    file.sync_data()
+}
+
+/// The log's last `limit` entries, oldest first. Lines that don't parse,
+/// such as one cut short by a crash, are skipped.
+pub fn recent(limit: usize) -> Vec<serde_json::Value> {
+    let contents = fs::read_to_string(audit_path()).unwrap_or_default();
+    let entries: Vec<serde_json::Value> = contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    let skip = entries.len().saturating_sub(limit);
+    entries.into_iter().skip(skip).collect()
+}
@@ -0,0 +1,175 @@
+//! Scripted benchmark runs: launch a profile, sample the game for a fixed
+//! duration, terminate it and write the results, so runs can be compared
+//! across driver or game updates.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::metrics::{ProcessSample, ProcessSampler, UsageSummary};
+use crate::paths;
+use crate::presentmon::{self, FrameRecorder};
+use crate::profile::Profile;
+use crate::report::{self, Event, warning};
+use crate::session::{FrameStats, unix_now};
+
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    pub duration: Duration,
+    /// Time between resource samples
+    pub interval: Duration,
+    /// PresentMon executable to capture frame times with, over the
+    /// profile's own
+    pub presentmon: Option<PathBuf>,
+    pub results_dir: PathBuf,
+}
+
+impl BenchOptions {
+    pub fn new(duration: Duration) -> Self {
+        BenchOptions {
+            duration,
+            interval: Duration::from_secs(1),
+            presentmon: None,
+            results_dir: paths::data_dir().join("bench"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct BenchResults {
+    pub profile: String,
+    pub aumid: String,
+    pub started_at: u64,
+    /// How long the game was sampled; shorter than requested if it exited
+    pub duration_secs: u64,
+    /// The game exited before the duration was up
+    pub ended_early: bool,
+    pub summary: UsageSummary,
+    pub frames: Option<FrameStats>,
+    pub samples: Vec<ProcessSample>,
+}
+
+/// Runs the benchmark and writes `<profile>-<timestamp>.json` with the
+/// summary and samples, and `.csv` with the samples, to the results
+/// directory, plus `-frames.csv` from PresentMon (`-frames-2.csv` and so on
+/// for each process that takes over). Returns the JSON path.
+pub fn run(
+    profile: &Profile,
+    options: &BenchOptions,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let started_at = unix_now();
+    let base = format!("{}-{}", profile.name, started_at);
+    fs::create_dir_all(&options.results_dir)?;
+
+    // The benchmark captures frames itself, so the session mustn't run a
+    // second PresentMon for the profile's `presentmon` or `frame_times`
+    let mut profile = profile.resolved()?;
+    let presentmon = options
+        .presentmon
+        .clone()
+        .or_else(|| profile.presentmon.take())
+        .or_else(|| profile.frame_times.then(presentmon::locate).flatten());
+    if presentmon.is_none() && profile.frame_times {
+        warning!(
+            "⚠️ No PresentMon found next to librarylink or on the PATH; frame times won't be captured"
+        );
+    }
+    profile.frame_times = false;
+    let recorder = presentmon.map(|presentmon| {
+        FrameRecorder::in_dir(
+            &presentmon,
+            &options.results_dir,
+            &format!("{}-frames", base),
+        )
+    });
+
+    let session = profile.launch_resolved()?;
+    let control = session.control();
+
+    // The session reports to its own channel; forward it until the game is gone
+    let monitor = thread::spawn(move || {
+        let mut session = session;
+        for event in session.events() {
+            report::emit(event);
+        }
+        session.wait()
+    });
+
+    let mut sampler = ProcessSampler::new();
+    let mut samples = Vec::new();
+    let sampling = Instant::now();
+    while sampling.elapsed() < options.duration && !monitor.is_finished() {
+        let tracked_pid = control.tracked_pid();
+        // Moves the capture to whatever took over from the launcher
+        if let Some(recorder) = &recorder
+            && tracked_pid != 0
+        {
+            recorder.follow(tracked_pid);
+        }
+        if let Some(sample) = sampler.sample(tracked_pid) {
+            samples.push(sample);
+        }
+        thread::sleep(
+            options
+                .interval
+                .min(options.duration.saturating_sub(sampling.elapsed())),
+        );
+    }
+    let ended_early = monitor.is_finished();
+    let duration_secs = sampling.elapsed().as_secs();
+
+    if !ended_early && let Err(e) = control.kill() {
+        warning!("⚠️ Could not terminate the game: {}", e);
+    }
+    let _ = monitor.join();
+    let frames = recorder.and_then(|recorder| recorder.finish());
+
+    let results = BenchResults {
+        profile: profile.name.clone(),
+        aumid: profile.aumid.clone(),
+        started_at,
+        duration_secs,
+        ended_early,
+        summary: UsageSummary::from_samples(&samples),
+        frames,
+        samples,
+    };
+
+    let json_path = options.results_dir.join(format!("{}.json", base));
+    fs::write(&json_path, serde_json::to_string_pretty(&results)?)?;
+    fs::write(
+        options.results_dir.join(format!("{}.csv", base)),
+        samples_csv(&results.samples),
+    )?;
+
+    report::emit(Event::BenchmarkCompleted {
+        profile: results.profile,
+        results: json_path.display().to_string(),
+        duration_secs,
+        ended_early,
+        summary: results.summary,
+        frames: results.frames,
+    });
+    Ok(json_path)
+}
+
+fn samples_csv(samples: &[ProcessSample]) -> String {
+    let optional =
+        |value: Option<f64>| value.map_or(String::new(), |value| format!("{:.2}", value));
+    let mut csv = String::from("at,pid,cpu_percent,gpu_percent,working_set_bytes,private_bytes\n");
+    for sample in samples {
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{},{}",
+            sample.at,
+            sample.pid,
+            optional(sample.cpu_percent),
+            optional(sample.gpu_percent),
+            sample.working_set_bytes,
+            sample.private_bytes
+        );
+    }
+    csv
+}
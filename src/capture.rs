@@ -0,0 +1,121 @@
+//! Recording a command's stdout and stderr to a log under
+//! `%APPDATA%\librarylink\logs`, so a scripted launch that fails leaves the
+//! program's own error text behind.
+
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::paths;
+use crate::session::unix_now;
+
+/// How much output a session log keeps when no limit is given.
+pub const DEFAULT_LIMIT: u64 = 1024 * 1024;
+
+pub fn logs_dir() -> PathBuf {
+    paths::data_dir().join("logs")
+}
+
+/// The log both streams write to, line by line, tagged with their stream.
+/// Output past `limit` bytes is read and dropped, so the program never
+/// blocks on a full pipe.
+struct OutputLog {
+    file: File,
+    written: u64,
+    limit: u64,
+    truncated: bool,
+}
+
+impl OutputLog {
+    fn write_line(&mut self, stream: &str, line: &[u8]) {
+        if self.truncated {
+            return;
+        }
+        let line = String::from_utf8_lossy(line);
+        let entry = format!("[{}] {}\n", stream, line.trim_end_matches(['\r', '\n']));
+        if self.written + entry.len() as u64 > self.limit {
+            self.truncated = true;
+            let _ = writeln!(
+                self.file,
+                "[librarylink] output truncated after {} bytes",
+                self.written
+            );
+            return;
+        }
+        self.written += entry.len() as u64;
+        let _ = self.file.write_all(entry.as_bytes());
+    }
+}
+
+fn forward(stream: &'static str, pipe: impl Read + Send + 'static, log: Arc<Mutex<OutputLog>>) {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(pipe);
+        let mut line = Vec::new();
+        while reader
+            .read_until(b'\n', &mut line)
+            .is_ok_and(|read| read > 0)
+        {
+            log.lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .write_line(stream, &line);
+            line.clear();
+        }
+    });
+}
+
+/// Starts a program with its stdout and stderr recorded to
+/// `<timestamp>-<pid>.log` in the logs directory, keeping at most `limit`
+/// bytes. Returns the process ID and the log's path without waiting for the
+/// program.
+pub fn spawn_captured(
+    program: &str,
+    arguments: &[String],
+    working_dir: Option<&Path>,
+    limit: u64,
+) -> Result<(u32, PathBuf), Box<dyn std::error::Error>> {
+    let mut command = Command::new(program);
+    if let Some(directory) = working_dir {
+        command.current_dir(directory);
+    }
+    let mut child = command
+        .args(arguments)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Could not start {}: {}", program, e))?;
+    let process_id = child.id();
+
+    let path = logs_dir().join(format!("{}-{}.log", unix_now(), process_id));
+    let file = match open_log(&path) {
+        Ok(file) => file,
+        Err(e) => {
+            // Nothing would drain the pipes
+            let _ = child.kill();
+            return Err(format!("Could not create {}: {}", path.display(), e).into());
+        }
+    };
+    let log = Arc::new(Mutex::new(OutputLog {
+        file,
+        written: 0,
+        limit,
+        truncated: false,
+    }));
+    if let Some(stdout) = child.stdout.take() {
+        forward("stdout", stdout, Arc::clone(&log));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        forward("stderr", stderr, log);
+    }
+    Ok((process_id, path))
+}
+
+fn open_log(path: &Path) -> std::io::Result<File> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    File::create(path)
+}
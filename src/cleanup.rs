@@ -0,0 +1,49 @@
+use std::fmt;
+use std::sync::Mutex;
+
+type Hook = Box<dyn FnOnce() + Send>;
+
+/// Cleanup actions (restoring displays, undoing system tweaks) that must run
+/// exactly once, whichever of the monitor finishing, the session being dropped
+/// or the launch failing happens first.
+#[derive(Default)]
+pub struct CleanupHooks {
+    hooks: Mutex<Vec<Hook>>,
+}
+
+impl CleanupHooks {
+    pub fn push(&mut self, hook: impl FnOnce() + Send + 'static) {
+        self.hooks
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(Box::new(hook));
+    }
+
+    /// Runs every hook that hasn't run yet, in the order they were added.
+    pub fn run(&self) {
+        let hooks = std::mem::take(
+            &mut *self
+                .hooks
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        );
+        for hook in hooks {
+            hook();
+        }
+    }
+}
+
+impl Drop for CleanupHooks {
+    fn drop(&mut self) {
+        self.run();
+    }
+}
+
+impl fmt::Debug for CleanupHooks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let pending = self.hooks.lock().map(|hooks| hooks.len()).unwrap_or(0);
+        f.debug_struct("CleanupHooks")
+            .field("pending", &pending)
+            .finish()
+    }
+}
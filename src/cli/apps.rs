@@ -0,0 +1,331 @@
+//! `alias`, `list-apps`, `list-games`, `resolve` and `pick`.
+
+use librarylink::LaunchOptions;
+use librarylink::exit::ExitReason;
+use librarylink::report::{self, Event, error, info, warning};
+use librarylink::{apps, config, games, mock, package, picker, resolve};
+use serde_json::json;
+
+use super::exit_with;
+use super::launch::{configured_launch, launch_app};
+
+pub fn alias(args: &[String]) {
+    let usage = format!(
+        "Usage: {} alias add <name> <AUMID> | alias remove <name> | alias list [--json]",
+        args[0]
+    );
+    match (
+        args.get(2).map(String::as_str),
+        args.get(3).map(String::as_str),
+        args.get(4),
+    ) {
+        (Some("add"), Some(name), Some(aumid)) if args.len() == 5 => {
+            if name.is_empty() || name.contains('!') || name.contains(char::is_whitespace) {
+                error!("Error: An alias can't be empty or contain '!' or spaces");
+                info!("{}", usage);
+                return;
+            }
+            if !aumid.contains('!') {
+                error!(
+                    "Error: '{}' isn't an AUMID; use librarylink list-apps to find it",
+                    aumid
+                );
+                return;
+            }
+            if package::installed_path(aumid).is_none() && !mock::is_enabled() {
+                warning!("⚠️ {} isn't installed for this user", aumid);
+            }
+            match config::set_alias(name, Some(aumid)) {
+                Ok(existed) => {
+                    let verb = if existed { "now stands" } else { "stands" };
+                    match apps::display_name(aumid) {
+                        Some(display_name) => {
+                            info!("✅ {} {} for {} ({})", name, verb, display_name, aumid)
+                        }
+                        None => info!("✅ {} {} for {}", name, verb, aumid),
+                    }
+                }
+                Err(e) => error!("❌ Could not save the alias: {}", e),
+            }
+        }
+        (Some("remove"), Some(name), None) => match config::set_alias(name, None) {
+            Ok(true) => info!("🗑️ Removed the alias {}", name),
+            Ok(false) => {
+                error!("Error: There's no alias named {}", name);
+                std::process::exit(1);
+            }
+            Err(e) => error!("❌ Could not remove the alias: {}", e),
+        },
+        (Some("list"), option, None) => {
+            let aliases = &config::current().aliases;
+            match option {
+                Some("--json") => println!("{}", json!(aliases)),
+                Some(option) => {
+                    error!("Error: Unknown option '{}'", option);
+                    info!("{}", usage);
+                }
+                None if aliases.is_empty() => info!(
+                    "No aliases yet; add one with {} alias add <name> <AUMID>",
+                    args[0]
+                ),
+                None => {
+                    let width = aliases.keys().map(|name| name.chars().count()).max();
+                    for (name, aumid) in aliases {
+                        info!("{:width$}  {}", name, aumid, width = width.unwrap_or(0));
+                    }
+                }
+            }
+        }
+        _ => {
+            error!("Error: alias requires 'add', 'remove' or 'list'");
+            info!("{}", usage);
+        }
+    }
+}
+
+pub fn list_apps(args: &[String]) {
+    let mut filter = apps::AppFilter::default();
+    let mut as_json = false;
+
+    // Parse arguments
+    let mut i = 2;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--search" => {
+                if i + 1 < args.len() {
+                    filter.search = Some(args[i + 1].clone());
+                    i += 2;
+                } else {
+                    error!("Error: --search requires a search term");
+                    info!("Usage: {} list-apps --search <term>", args[0]);
+                    return;
+                }
+            }
+            "--publisher" => {
+                if i + 1 < args.len() {
+                    filter.publisher = Some(args[i + 1].clone());
+                    i += 2;
+                } else {
+                    error!("Error: --publisher requires a publisher name");
+                    info!("Usage: {} list-apps --publisher <name>", args[0]);
+                    return;
+                }
+            }
+            "--family" => {
+                if i + 1 < args.len() {
+                    filter.family = Some(args[i + 1].clone());
+                    i += 2;
+                } else {
+                    error!("Error: --family requires a package family name");
+                    info!("Usage: {} list-apps --family <name>", args[0]);
+                    return;
+                }
+            }
+            "--include-frameworks" => {
+                filter.include_frameworks = true;
+                i += 1;
+            }
+            "--json" => {
+                as_json = true;
+                i += 1;
+            }
+            _ => {
+                error!("Error: Unknown option '{}'", args[i]);
+                info!(
+                    "Usage: {} list-apps [--search <term>] [--publisher <name>] [--family <name>] [--include-frameworks] [--json]",
+                    args[0]
+                );
+                return;
+            }
+        }
+    }
+
+    match apps::list_apps_matching(&filter) {
+        // One array, for scripts that don't want the event stream
+        Ok(apps) if as_json => println!("{}", json!(apps)),
+        Ok(apps) => report::emit(Event::AppList { apps }),
+        Err(e) => {
+            error!("Error finding applications: {}", e);
+            if as_json {
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+pub fn list_games(args: &[String]) {
+    let mut filter = apps::AppFilter::default();
+    let mut as_json = false;
+    let usage = format!(
+        "Usage: {} list-games [--search <term>] [--publisher <name>] [--json]",
+        args[0]
+    );
+
+    let mut i = 2;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--search" => match args.get(i + 1) {
+                Some(term) => {
+                    filter.search = Some(term.clone());
+                    i += 2;
+                }
+                None => {
+                    error!("Error: --search requires a search term");
+                    info!("{}", usage);
+                    return;
+                }
+            },
+            "--publisher" => match args.get(i + 1) {
+                Some(publisher) => {
+                    filter.publisher = Some(publisher.clone());
+                    i += 2;
+                }
+                None => {
+                    error!("Error: --publisher requires a publisher name");
+                    info!("{}", usage);
+                    return;
+                }
+            },
+            "--json" => {
+                as_json = true;
+                i += 1;
+            }
+            _ => {
+                error!("Error: Unknown option '{}'", args[i]);
+                info!("{}", usage);
+                return;
+            }
+        }
+    }
+
+    match games::list_games(&filter) {
+        Ok(games) if as_json => println!("{}", json!(games)),
+        Ok(games) => report::emit(Event::GameList { games }),
+        Err(e) => {
+            error!("Error finding games: {}", e);
+            if as_json {
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+pub fn resolve(args: &[String]) {
+    let usage = format!(
+        "Usage: {} resolve <name> [--all] [--json] [--launch]",
+        args[0]
+    );
+    let Some(name) = args.get(2).filter(|name| !name.starts_with("--")) else {
+        error!("Error: resolve requires an app name");
+        info!("{}", usage);
+        return;
+    };
+    let mut all = false;
+    let mut as_json = false;
+    let mut launch = false;
+    for arg in &args[3..] {
+        match arg.as_str() {
+            "--all" => all = true,
+            "--json" => as_json = true,
+            "--launch" => launch = true,
+            _ => {
+                error!("Error: Unknown option '{}'", arg);
+                info!("{}", usage);
+                return;
+            }
+        }
+    }
+
+    let mut matches = match resolve::candidates(name) {
+        Ok(matches) => matches,
+        Err(e) => {
+            error!("Error finding applications: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if !all {
+        matches.truncate(1);
+    }
+    if as_json && !launch {
+        println!("{}", json!(matches));
+    } else if matches.is_empty() {
+        error!("❌ No installed app matches '{}'", name);
+        exit_with(ExitReason::NotFound);
+    } else if launch {
+        let aumid = &matches[0].app.aumid;
+        info!("🔎 '{}' is {} ({})", name, matches[0].app.name, aumid);
+        launch_app(aumid, configured_launch(aumid));
+    } else if all {
+        for candidate in &matches {
+            info!(
+                "{:>3}  {}  ({})",
+                candidate.score, candidate.app.name, candidate.app.aumid
+            );
+        }
+    } else {
+        // Printed as is, for `$aumid = librarylink resolve forza`
+        println!("{}", matches[0].app.aumid);
+    }
+}
+
+pub fn pick(args: &[String]) {
+    let mut filter = apps::AppFilter::default();
+    let mut query = String::new();
+    let mut launch = false;
+    let usage = format!(
+        "Usage: {} pick [--search <term>] [--publisher <name>] [--family <name>] [--launch]",
+        args[0]
+    );
+    let mut i = 2;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--search" | "--publisher" | "--family" => {
+                let Some(value) = args.get(i + 1) else {
+                    error!("Error: {} requires a value", args[i]);
+                    info!("{}", usage);
+                    return;
+                };
+                match args[i].as_str() {
+                    "--search" => query = value.clone(),
+                    "--publisher" => filter.publisher = Some(value.clone()),
+                    _ => filter.family = Some(value.clone()),
+                }
+                i += 2;
+            }
+            "--launch" => {
+                launch = true;
+                i += 1;
+            }
+            _ => {
+                error!("Error: Unknown option '{}'", args[i]);
+                info!("{}", usage);
+                return;
+            }
+        }
+    }
+
+    let apps = match apps::list_apps_matching(&filter) {
+        Ok(apps) if apps.is_empty() => {
+            error!("Error: No applications found");
+            std::process::exit(1);
+        }
+        Ok(apps) => apps,
+        Err(e) => {
+            error!("Error finding applications: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let action = if launch { "launch" } else { "print its AUMID" };
+    match picker::pick_app(&apps, &query, action) {
+        Ok(Some(index)) if launch => {
+            launch_app(&apps[index].aumid, LaunchOptions::new(&apps[index].aumid))
+        }
+        // Printed as is, for `$aumid = librarylink pick`
+        Ok(Some(index)) => println!("{}", apps[index].aumid),
+        Ok(None) => std::process::exit(1),
+        Err(e) => {
+            error!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
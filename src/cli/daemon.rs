@@ -0,0 +1,174 @@
+//! `daemon`, its `token`s, and `audit`.
+
+use librarylink::audit::{self, Actor};
+use librarylink::report::{error, info, warning};
+use librarylink::token::{self, Scope};
+use librarylink::{clock, daemon, system};
+
+pub fn daemon(args: &[String]) {
+    if args.get(2).map(String::as_str) == Some("token") {
+        token(args);
+        return;
+    }
+    // Put changed system settings back even if the console is closed mid-session
+    system::install_exit_handler();
+    if let Err(e) = daemon::serve() {
+        error!("❌ {}", e);
+        std::process::exit(1);
+    }
+}
+
+pub fn audit(args: &[String]) {
+    let usage = format!("Usage: {} audit [--limit <n>] [--json]", args[0]);
+    let mut limit = 20;
+    let mut as_json = false;
+    let mut i = 2;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--limit" => match args.get(i + 1).map(|limit| limit.parse::<usize>()) {
+                Some(Ok(value)) => {
+                    limit = value;
+                    i += 1;
+                }
+                _ => {
+                    error!("Error: --limit requires a number of entries");
+                    info!("{}", usage);
+                    return;
+                }
+            },
+            "--json" => as_json = true,
+            _ => {
+                error!("Error: Unknown option '{}'", args[i]);
+                info!("{}", usage);
+                return;
+            }
+        }
+        i += 1;
+    }
+    show_audit(limit, as_json);
+}
+
+fn token(args: &[String]) {
+    let usage = format!(
+        "Usage: {} daemon token add <name> --scope <list|launch|admin> | remove <name> | list",
+        args[0]
+    );
+    let subcommand = args.get(3).map(String::as_str);
+    if subcommand == Some("list") {
+        let entries = match token::list() {
+            Ok(entries) => entries,
+            Err(e) => {
+                error!("❌ {}", e);
+                std::process::exit(1);
+            }
+        };
+        for entry in entries {
+            info!(
+                "  {:<20}  {:<6}  added {}",
+                entry.name,
+                entry.scope,
+                clock::format_local(entry.created_at)
+            );
+        }
+        return;
+    }
+    let Some(name) = args.get(4) else {
+        error!("Error: daemon token requires a subcommand and a token name");
+        info!("{}", usage);
+        return;
+    };
+    let result = match subcommand {
+        Some("add") => {
+            let scope = match args.get(5).map(String::as_str) {
+                Some("--scope") => args.get(6).and_then(|scope| Scope::parse(scope)),
+                _ => None,
+            };
+            let Some(scope) = scope else {
+                error!("Error: daemon token add requires --scope list, launch or admin");
+                info!("{}", usage);
+                return;
+            };
+            let added = token::add(name, scope);
+            let error = added.as_ref().err().map(|e| e.to_string());
+            let outcome = match &error {
+                None => audit::Outcome::Ok,
+                Some(error) => audit::Outcome::Failed(error),
+            };
+            let target = format!("{} ({})", name, scope);
+            audit::record("cli", &Actor::current(), "token add", &target, outcome);
+            added.map(|token| {
+                info!(
+                    "🔑 Added {} token '{}'. It won't be shown again:",
+                    scope, name
+                );
+                println!("{}", token);
+            })
+        }
+        Some("remove") => token::remove(name).map(|removed| {
+            if removed {
+                audit::record(
+                    "cli",
+                    &Actor::current(),
+                    "token remove",
+                    name,
+                    audit::Outcome::Ok,
+                );
+                info!("✅ Revoked token '{}'", name);
+            } else {
+                warning!("⚠️ No token named '{}'", name);
+            }
+        }),
+        _ => {
+            error!("Error: daemon token requires 'add', 'remove' or 'list'");
+            info!("{}", usage);
+            return;
+        }
+    };
+    if let Err(e) = result {
+        error!("❌ {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn show_audit(limit: usize, as_json: bool) {
+    let entries = audit::recent(limit);
+    if as_json {
+        for entry in &entries {
+            println!("{}", entry);
+        }
+        return;
+    }
+    if entries.is_empty() {
+        info!("Nothing has been recorded in the audit log.");
+        return;
+    }
+    for entry in entries {
+        let text = |key: &str| entry[key].as_str().unwrap_or("").to_string();
+        let actor = &entry["actor"];
+        let mut who = Vec::new();
+        if let Some(token) = actor["token"].as_str() {
+            who.push(format!("token '{}'", token));
+        }
+        match (actor["process"].as_str(), actor["pid"].as_u64()) {
+            (Some(process), Some(pid)) => who.push(format!("{} ({})", process, pid)),
+            (None, Some(pid)) => who.push(format!("PID {}", pid)),
+            _ => {}
+        }
+        if who.is_empty() {
+            who.push("unknown".to_string());
+        }
+        let outcome = match entry["error"].as_str() {
+            Some(error) => format!("{}: {}", text("outcome"), error),
+            None => text("outcome"),
+        };
+        info!(
+            "  {}  {:<6}  {:<12}  {:<28}  {}  {}",
+            clock::format_local(entry["at"].as_u64().unwrap_or(0)),
+            text("source"),
+            text("action"),
+            text("target"),
+            who.join(", "),
+            outcome
+        );
+    }
+}
@@ -0,0 +1,275 @@
+//! `tree` and `debug`.
+
+use librarylink::matcher::{MonitorScope, MonitorTarget};
+use librarylink::report::{error, info, warning};
+use librarylink::snapshot::Snapshot;
+use librarylink::{acl, package, tree};
+use serde_json::json;
+
+pub fn tree(args: &[String]) {
+    let root_pid = match args.get(2).map(|pid| pid.parse::<u32>()) {
+        Some(Ok(pid)) => pid,
+        _ => {
+            error!("Error: tree requires a process ID");
+            info!("Usage: {} tree <PID> [--tracked <PID>]", args[0]);
+            return;
+        }
+    };
+
+    let mut tracked_pid: Option<u32> = None;
+
+    // Parse arguments
+    let mut i = 3;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--tracked" => match args.get(i + 1).map(|pid| pid.parse::<u32>()) {
+                Some(Ok(pid)) => {
+                    tracked_pid = Some(pid);
+                    i += 2;
+                }
+                _ => {
+                    error!("Error: --tracked requires a process ID");
+                    info!("Usage: {} tree <PID> [--tracked <PID>]", args[0]);
+                    return;
+                }
+            },
+            _ => {
+                error!("Error: Unknown option '{}'", args[i]);
+                info!("Usage: {} tree <PID> [--tracked <PID>]", args[0]);
+                return;
+            }
+        }
+    }
+
+    tree::print_process_tree(root_pid, tracked_pid);
+}
+
+pub fn debug(args: &[String]) {
+    let usage = format!(
+        "Usage: {0} debug snapshot [<file>]\n       {0} debug acl <AUMID> [--grant] [--json]\n       {0} debug select --snapshot <file> --dir <path> [--scope <exact-dir|dir-tree|package>] [--package <family>] [--wait-for <exe|pattern>] [--json]",
+        args[0]
+    );
+    match args.get(2).map(String::as_str) {
+        Some("snapshot") => {
+            let path = args
+                .get(3)
+                .map_or("librarylink-snapshot.json", String::as_str);
+            take_snapshot(std::path::Path::new(path));
+        }
+        Some("acl") => {
+            let Some(aumid) = args.get(3) else {
+                error!("Error: debug acl requires an Application User Model ID");
+                info!("{}", usage);
+                return;
+            };
+            let mut grant = false;
+            let mut as_json = false;
+            for arg in &args[4..] {
+                match arg.as_str() {
+                    "--grant" => grant = true,
+                    "--json" => as_json = true,
+                    _ => {
+                        error!("Error: Unknown option '{}'", arg);
+                        info!("{}", usage);
+                        return;
+                    }
+                }
+            }
+            check_install_access(aumid, grant, as_json);
+        }
+        Some("select") => {
+            let mut snapshot_path = None;
+            let mut directory = None;
+            let mut scope = MonitorScope::DirTree;
+            let mut package_family_name = None;
+            let mut wait_for = None;
+            let mut as_json = false;
+
+            let mut i = 3;
+            while i < args.len() {
+                match (args[i].as_str(), args.get(i + 1)) {
+                    ("--json", _) => {
+                        as_json = true;
+                        i += 1;
+                    }
+                    ("--snapshot", Some(path)) => {
+                        snapshot_path = Some(std::path::PathBuf::from(path));
+                        i += 2;
+                    }
+                    ("--dir", Some(path)) => {
+                        directory = Some(path.clone());
+                        i += 2;
+                    }
+                    ("--package", Some(family)) => {
+                        package_family_name = Some(family.clone());
+                        i += 2;
+                    }
+                    ("--wait-for", Some(executable)) => {
+                        wait_for = Some(executable.clone());
+                        i += 2;
+                    }
+                    ("--scope", value) => {
+                        match value.and_then(|value| MonitorScope::parse(value)) {
+                            Some(value) => {
+                                scope = value;
+                                i += 2;
+                            }
+                            None => {
+                                error!(
+                                    "Error: --scope requires 'exact-dir', 'dir-tree' or 'package'"
+                                );
+                                info!("{}", usage);
+                                return;
+                            }
+                        }
+                    }
+                    ("--snapshot" | "--dir" | "--package" | "--wait-for", None) => {
+                        error!("Error: {} requires a value", args[i]);
+                        info!("{}", usage);
+                        return;
+                    }
+                    _ => {
+                        error!("Error: Unknown option '{}'", args[i]);
+                        info!("{}", usage);
+                        return;
+                    }
+                }
+            }
+
+            let (Some(snapshot_path), Some(directory)) = (snapshot_path, directory) else {
+                error!("Error: debug select requires --snapshot and --dir");
+                info!("{}", usage);
+                return;
+            };
+            let target = MonitorTarget::new(&directory, package_family_name, scope);
+            if scope == MonitorScope::Package && target.scope != MonitorScope::Package {
+                warning!("⚠️ No --package given; falling back to dir-tree scope");
+            }
+            replay_selection(&snapshot_path, &target, wait_for.as_deref(), as_json);
+        }
+        _ => {
+            error!("Error: debug requires the 'snapshot', 'acl' or 'select' action");
+            info!("{}", usage);
+        }
+    }
+}
+
+fn take_snapshot(path: &std::path::Path) {
+    match Snapshot::take().and_then(|snapshot| {
+        snapshot.save(path)?;
+        Ok(snapshot)
+    }) {
+        Ok(snapshot) => info!(
+            "📸 Saved {} processes to {}",
+            snapshot.processes.len(),
+            path.display()
+        ),
+        Err(e) => {
+            error!("❌ {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Runs a snapshot through the replacement search's candidate checks and
+/// shows which processes qualify, first the one monitoring would track.
+fn replay_selection(
+    path: &std::path::Path,
+    target: &MonitorTarget,
+    wait_for: Option<&str>,
+    as_json: bool,
+) {
+    let snapshot = match Snapshot::load(path) {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            error!("❌ {}", e);
+            std::process::exit(1);
+        }
+    };
+    let candidates = snapshot.select(target, wait_for);
+
+    if as_json {
+        println!(
+            "{}",
+            json!({
+                "target": target,
+                "selected": candidates.first().map(|process| process.pid),
+                "candidates": candidates,
+            })
+        );
+        return;
+    }
+
+    info!(
+        "🔍 Replaying {} processes against {}",
+        snapshot.processes.len(),
+        target.describe()
+    );
+    match candidates.split_first() {
+        Some((selected, others)) => {
+            info!(
+                "✅ Would track {} ({}): {}",
+                selected.pid, selected.name, selected.path
+            );
+            for process in others {
+                info!(
+                    "   Also qualifies: {} ({}): {}",
+                    process.pid, process.name, process.path
+                );
+            }
+        }
+        None => info!("💀 No process in the snapshot qualifies"),
+    }
+}
+
+/// Reports whether the current user can read the app's install directory,
+/// granting read access first with `grant` where that's safe.
+fn check_install_access(aumid: &str, grant: bool, as_json: bool) {
+    let Some(path) = package::installed_path(aumid) else {
+        error!("❌ Could not find where {} is installed", aumid);
+        std::process::exit(1);
+    };
+    let mut report = acl::check(&path);
+    let mut granted = false;
+    if grant && !report.readable {
+        match acl::grant_read(&path) {
+            Ok(()) => {
+                granted = true;
+                report = acl::check(&path);
+            }
+            Err(e) => error!("❌ Could not grant read access: {}", e),
+        }
+    }
+
+    if as_json {
+        let mut value = json!(report);
+        value["granted"] = json!(granted);
+        println!("{}", value);
+    } else {
+        info!("📁 {}", report.path);
+        if granted {
+            info!("🔓 Granted read access to the current user");
+        }
+        if report.readable {
+            info!("✅ The install directory is readable");
+        } else {
+            info!("❌ The install directory can't be read by the current user");
+        }
+        match report.config_readable {
+            Some(true) => info!("✅ {} is readable", acl::GAME_CONFIG),
+            Some(false) => info!("❌ {} can't be read", acl::GAME_CONFIG),
+            None if report.readable => info!("ℹ️ No {}", acl::GAME_CONFIG),
+            None => {}
+        }
+        if !report.readable && report.system_managed {
+            info!(
+                "ℹ️ Permissions under WindowsApps are managed by Windows and aren't changed; install the game to a folder of your choice (such as XboxGames) to make it readable"
+            );
+        } else if !report.readable && !grant {
+            info!("ℹ️ Run again with --grant, from an elevated prompt, to grant read access");
+        }
+    }
+    if !report.readable {
+        std::process::exit(1);
+    }
+}
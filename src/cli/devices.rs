@@ -0,0 +1,43 @@
+//! `list-monitors` and `list-audio-devices`.
+
+use librarylink::report::{self, Event, error, info};
+use librarylink::{audio, placement};
+use serde_json::json;
+
+pub fn list_monitors(args: &[String]) {
+    let as_json = match args.get(2).map(String::as_str) {
+        None => false,
+        Some("--json") => true,
+        Some(other) => {
+            error!("Error: Unknown option '{}'", other);
+            info!("Usage: {} list-monitors [--json]", args[0]);
+            return;
+        }
+    };
+    let monitors = placement::monitors();
+    if as_json {
+        println!("{}", json!(monitors));
+    } else {
+        report::emit(Event::MonitorList { monitors });
+    }
+}
+
+pub fn list_audio_devices(args: &[String]) {
+    let as_json = match args.get(2).map(String::as_str) {
+        None => false,
+        Some("--json") => true,
+        Some(other) => {
+            error!("Error: Unknown option '{}'", other);
+            info!("Usage: {} list-audio-devices [--json]", args[0]);
+            return;
+        }
+    };
+    match audio::render_devices() {
+        Ok(devices) if as_json => println!("{}", json!(devices)),
+        Ok(devices) => report::emit(Event::AudioDeviceList { devices }),
+        Err(e) => {
+            report::failure("Could not list audio devices", e);
+            std::process::exit(1);
+        }
+    }
+}
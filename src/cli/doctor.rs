@@ -0,0 +1,195 @@
+//! `preflight`, `doctor` and `capabilities`.
+
+use librarylink::compat::{self, Capability};
+use librarylink::profile::Profile;
+use librarylink::report::{self, Event, error, info, warning};
+use librarylink::{doctor, features};
+use serde_json::json;
+
+use super::{confirm, exit_with, profile_load_failure};
+
+pub fn preflight(args: &[String]) {
+    let Some(name) = args.get(2) else {
+        error!("Error: preflight requires a profile name");
+        info!("Usage: {} preflight <profile> [--json]", args[0]);
+        return;
+    };
+    let mut as_json = false;
+    for option in &args[3..] {
+        match option.as_str() {
+            "--json" => as_json = true,
+            _ => {
+                error!("Error: Unknown option '{}'", option);
+                info!("Usage: {} preflight <profile> [--json]", args[0]);
+                return;
+            }
+        }
+    }
+    run_preflight(name, as_json);
+}
+
+pub fn doctor(args: &[String]) {
+    let usage = format!("Usage: {} doctor [--fix [--yes] | --json]", args[0]);
+    let mut fix = false;
+    let mut yes = false;
+    let mut as_json = false;
+    for option in &args[2..] {
+        match option.as_str() {
+            "--fix" => fix = true,
+            "--yes" => yes = true,
+            "--json" => as_json = true,
+            _ => {
+                error!("Error: Unknown option '{}'", option);
+                info!("{}", usage);
+                return;
+            }
+        }
+    }
+    if yes && !fix {
+        error!("Error: --yes only applies with --fix");
+        info!("{}", usage);
+        return;
+    }
+    if as_json && fix {
+        error!("Error: --json only reports; run --fix without it");
+        info!("{}", usage);
+        return;
+    }
+    if as_json {
+        report_doctor_json();
+    } else {
+        run_doctor(fix, yes);
+    }
+}
+
+pub fn capabilities(args: &[String]) {
+    let as_json = match args.get(2).map(String::as_str) {
+        None => false,
+        Some("--json") => true,
+        Some(option) => {
+            error!("Error: Unknown option '{}'", option);
+            info!("Usage: {} capabilities [--json]", args[0]);
+            return;
+        }
+    };
+    let capabilities = features::detect();
+    if as_json {
+        println!("{}", json!(capabilities));
+        return;
+    }
+    if let Some(version) = capabilities.windows {
+        info!("🪟 {}", version);
+    }
+    for status in &capabilities.features {
+        match &status.reason {
+            None => info!("✅ {}", status.feature),
+            Some(reason) => info!("❌ {}: {}", status.feature, reason),
+        }
+    }
+}
+
+/// Prints a profile's preflight results, or with `as_json` one JSON object
+/// with a `ready` flag for frontends. Exits with 1 when a check fails.
+fn run_preflight(name: &str, as_json: bool) {
+    let profile = match Profile::load(name) {
+        Ok(profile) => profile,
+        Err(e) => {
+            error!("❌ {}", e);
+            exit_with(profile_load_failure(name));
+        }
+    };
+
+    let results = profile.preflight();
+    let ready = results.iter().all(|result| result.passed);
+    if as_json {
+        println!(
+            "{}",
+            json!({ "profile": profile.name, "ready": ready, "results": results })
+        );
+    } else {
+        if !results.is_empty() {
+            report::emit(Event::PreflightCompleted { results });
+        }
+        if ready {
+            info!("✅ {} is ready to play", profile.name);
+        } else {
+            info!("❌ {} isn't ready to play", profile.name);
+        }
+    }
+    if !ready {
+        std::process::exit(1);
+    }
+}
+
+/// Prints what `doctor` found and, with `fix`, applies the fixes, asking
+/// before each one unless `yes`. Exits with 1 when a problem is left.
+fn run_doctor(fix: bool, yes: bool) {
+    if let Some(version) = compat::WindowsVersion::current() {
+        info!("🪟 {}", version);
+    }
+    for capability in Capability::ALL {
+        if !capability.is_supported() {
+            warning!(
+                "⚠️ {}; {}",
+                capability.unsupported_message(),
+                capability.fallback()
+            );
+        }
+    }
+    let findings = doctor::diagnose();
+    let mut problems = 0;
+    for finding in &findings {
+        if finding.passed {
+            info!("✅ {}: {}", finding.check, finding.detail);
+            continue;
+        }
+        info!("❌ {}: {}", finding.check, finding.detail);
+        let Some(remedy) = &finding.fix else {
+            problems += 1;
+            continue;
+        };
+        if !fix {
+            info!("   🔧 doctor --fix can {}", remedy);
+            problems += 1;
+            continue;
+        }
+        if !yes && !confirm(&format!("   Do you want to {}?", remedy)) {
+            problems += 1;
+            continue;
+        }
+        match remedy.apply() {
+            Ok(()) => info!("   ✅ Done: {}", remedy),
+            Err(e) => {
+                error!("   ❌ Could not {}: {}", remedy, e);
+                problems += 1;
+            }
+        }
+    }
+
+    if problems == 0 {
+        info!("✅ No problems left");
+    } else {
+        info!("❌ {} problem(s) left", problems);
+        std::process::exit(1);
+    }
+}
+
+/// Prints the Windows build, which features it supports and the doctor's
+/// findings as one JSON object for frontends, with a `ready` flag. Exits
+/// with 1 when a check fails.
+fn report_doctor_json() {
+    let findings = doctor::diagnose();
+    let ready = findings.iter().all(|finding| finding.passed);
+    println!(
+        "{}",
+        json!({
+            "windows": compat::WindowsVersion::current(),
+            "capabilities": compat::capabilities(),
+            "findings": findings,
+            "ready": ready,
+        })
+    );
+    if !ready {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,352 @@
+//! `export`, `add-to-steam`, `get-icon` and `make-shortcut`: getting games
+//! into other launchers and tools.
+
+use librarylink::metadata::{self, Igdb, MetadataProvider};
+use librarylink::report::{error, info, warning};
+use librarylink::{config, export, icon, shortcut, steam};
+
+pub fn export(args: &[String]) {
+    let usage = format!(
+        "Usage: {0} export powershell-module [--dir <directory>]\n       {0} export library [--file <path>] [--enrich igdb]",
+        args[0]
+    );
+    match args.get(2).map(String::as_str) {
+        Some("powershell-module") => {}
+        Some("library") => {
+            export_library(&args[3..], &usage);
+            return;
+        }
+        _ => {
+            error!("Error: export requires a target ('powershell-module' or 'library')");
+            info!("{}", usage);
+            return;
+        }
+    }
+
+    let mut directory = std::path::PathBuf::from("LibraryLink");
+
+    // Parse arguments
+    let mut i = 3;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--dir" => {
+                if i + 1 < args.len() {
+                    directory = std::path::PathBuf::from(&args[i + 1]);
+                    i += 2;
+                } else {
+                    error!("Error: --dir requires a directory");
+                    info!("{}", usage);
+                    return;
+                }
+            }
+            _ => {
+                error!("Error: Unknown option '{}'", args[i]);
+                info!("{}", usage);
+                return;
+            }
+        }
+    }
+
+    match export::write_powershell_module(&directory) {
+        Ok(files) => {
+            for file in files {
+                info!("✅ Wrote {}", file.display());
+            }
+            info!("");
+            info!("Import it with: Import-Module {}", directory.display());
+        }
+        Err(e) => error!("❌ Could not write the PowerShell module: {}", e),
+    }
+}
+
+pub fn add_to_steam(args: &[String]) {
+    let usage = format!(
+        "Usage: {} add-to-steam <AUMID> [--name <name>] [--steam-user <account ID>]",
+        args[0]
+    );
+    let Some(aumid) = args.get(2).filter(|aumid| !aumid.starts_with("--")) else {
+        error!("Error: add-to-steam requires an Application User Model ID");
+        info!("{}", usage);
+        return;
+    };
+    let mut name = None;
+    let mut steam_user = None;
+    let mut i = 3;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--name" => {
+                if let Some(value) = args.get(i + 1) {
+                    name = Some(value.clone());
+                    i += 2;
+                } else {
+                    error!("Error: --name requires the name to show in Steam");
+                    info!("{}", usage);
+                    return;
+                }
+            }
+            "--steam-user" => match args.get(i + 1).and_then(|value| value.parse::<u32>().ok()) {
+                Some(id) => {
+                    steam_user = Some(id);
+                    i += 2;
+                }
+                None => {
+                    error!("Error: --steam-user requires a Steam account ID");
+                    info!("{}", usage);
+                    return;
+                }
+            },
+            _ => {
+                error!("Error: Unknown option '{}'", args[i]);
+                info!("{}", usage);
+                return;
+            }
+        }
+    }
+    match steam::add_app(aumid, name, steam_user) {
+        Ok(steam::AddedApp {
+            user,
+            shortcut,
+            change: steam::ShortcutChange::Added { app_id },
+        }) => info!(
+            "✅ Added '{}' to Steam account {} (app ID {})",
+            shortcut.name, user.id, app_id
+        ),
+        Ok(steam::AddedApp {
+            user,
+            shortcut,
+            change: steam::ShortcutChange::Updated { app_id },
+        }) => info!(
+            "✅ Updated the existing '{}' entry for Steam account {} (app ID {})",
+            shortcut.name, user.id, app_id
+        ),
+        Err(e) => {
+            error!("❌ {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+pub fn get_icon(args: &[String]) {
+    let usage = format!(
+        "Usage: {} get-icon <AUMID> [--out <path.png|path.ico>] [--size <pixels>]",
+        args[0]
+    );
+    let Some(aumid) = args.get(2).filter(|aumid| !aumid.starts_with("--")) else {
+        error!("Error: get-icon requires an Application User Model ID");
+        info!("{}", usage);
+        return;
+    };
+    let mut out = None;
+    let mut size = 256;
+    let mut i = 3;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--out" => {
+                if let Some(value) = args.get(i + 1) {
+                    out = Some(std::path::PathBuf::from(value));
+                    i += 2;
+                } else {
+                    error!("Error: --out requires a path");
+                    info!("{}", usage);
+                    return;
+                }
+            }
+            "--size" => match args
+                .get(i + 1)
+                .and_then(|value| value.parse::<u32>().ok())
+                .filter(|&size| size > 0)
+            {
+                Some(value) => {
+                    size = value;
+                    i += 2;
+                }
+                None => {
+                    error!("Error: --size requires a size in pixels");
+                    info!("{}", usage);
+                    return;
+                }
+            },
+            _ => {
+                error!("Error: Unknown option '{}'", args[i]);
+                info!("{}", usage);
+                return;
+            }
+        }
+    }
+    save_icon(aumid, out, size);
+}
+
+pub fn make_shortcut(args: &[String]) {
+    let usage = format!(
+        "Usage: {} make-shortcut <AUMID|alias> --out <path.lnk|path.url|directory> [--wrapper] [--name <name>]",
+        args[0]
+    );
+    let Some(aumid) = args.get(2).filter(|aumid| !aumid.starts_with("--")) else {
+        error!("Error: make-shortcut requires an Application User Model ID");
+        info!("{}", usage);
+        return;
+    };
+    let aumid = config::current().alias(aumid).unwrap_or(aumid);
+    let mut out = None;
+    let mut name = None;
+    let mut target = shortcut::ShortcutTarget::AppsFolder;
+    let mut i = 3;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--out" | "--name" => {
+                let Some(value) = args.get(i + 1) else {
+                    error!("Error: {} requires a value", args[i]);
+                    info!("{}", usage);
+                    return;
+                };
+                if args[i] == "--out" {
+                    out = Some(std::path::PathBuf::from(value));
+                } else {
+                    name = Some(value.clone());
+                }
+                i += 2;
+            }
+            "--wrapper" => {
+                target = shortcut::ShortcutTarget::Wrapper;
+                i += 1;
+            }
+            _ => {
+                error!("Error: Unknown option '{}'", args[i]);
+                info!("{}", usage);
+                return;
+            }
+        }
+    }
+    let Some(out) = out else {
+        error!("Error: make-shortcut requires --out");
+        info!("{}", usage);
+        return;
+    };
+    match shortcut::create(aumid, out, name, target) {
+        Ok(out) => info!("✅ Created {}", out.display()),
+        Err(e) => {
+            error!("❌ {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Saves the app's logo closest to `size` pixels to `out`, by default
+/// `<app ID>.png` in the current directory.
+fn save_icon(aumid: &str, out: Option<std::path::PathBuf>, size: u32) {
+    let Some(asset) = icon::logo_for_size(aumid, size) else {
+        error!("❌ Could not find a logo in {}'s package", aumid);
+        std::process::exit(1);
+    };
+    let out = out.unwrap_or_else(|| {
+        let app_id = aumid.split_once('!').map_or(aumid, |(_, app_id)| app_id);
+        std::path::PathBuf::from(format!("{}.png", app_id))
+    });
+    match icon::write_logo(&asset, &out) {
+        Ok((width, height)) => {
+            info!("✅ Saved {}x{} icon to {}", width, height, out.display());
+            if width < size {
+                warning!(
+                    "⚠️ The package's largest logo is smaller than the {} pixels asked for",
+                    size
+                );
+            }
+        }
+        Err(e) => {
+            error!("❌ Could not write {}: {}", out.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// `export library`: writes the installed games as JSON to `--file` or
+/// stdout, with metadata from `--enrich`'s provider.
+fn export_library(args: &[String], usage: &str) {
+    let mut file = None;
+    let mut igdb = None;
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--file" => match args.get(i + 1) {
+                Some(path) => {
+                    file = Some(std::path::PathBuf::from(path));
+                    i += 2;
+                }
+                None => {
+                    error!("Error: --file requires a path");
+                    info!("{}", usage);
+                    return;
+                }
+            },
+            "--enrich" => match args.get(i + 1).map(String::as_str) {
+                Some("igdb") => {
+                    let Some(provider) = Igdb::from_env() else {
+                        error!(
+                            "Error: --enrich igdb needs a Twitch application's credentials in {} and {} (or the {} secret)",
+                            metadata::IGDB_CLIENT_ID_VARIABLE,
+                            metadata::IGDB_CLIENT_SECRET_VARIABLE,
+                            metadata::IGDB_CLIENT_SECRET_NAME
+                        );
+                        return;
+                    };
+                    igdb = Some(provider);
+                    i += 2;
+                }
+                Some(other) => {
+                    error!(
+                        "Error: Unknown metadata provider '{}'; expected 'igdb'",
+                        other
+                    );
+                    return;
+                }
+                None => {
+                    error!("Error: --enrich requires a provider ('igdb')");
+                    info!("{}", usage);
+                    return;
+                }
+            },
+            _ => {
+                error!("Error: Unknown option '{}'", args[i]);
+                info!("{}", usage);
+                return;
+            }
+        }
+    }
+
+    let entries = match export::installed_library(
+        igdb.as_mut()
+            .map(|provider| provider as &mut dyn MetadataProvider),
+    ) {
+        Ok(entries) => entries,
+        Err(e) => {
+            error!("❌ {}", e);
+            std::process::exit(1);
+        }
+    };
+    let document = serde_json::to_string_pretty(&entries).unwrap_or_default();
+    match file {
+        Some(path) => match std::fs::write(&path, document) {
+            Ok(()) => {
+                let enriched = entries
+                    .iter()
+                    .filter(|entry| entry.metadata.is_some())
+                    .count();
+                if igdb.is_some() {
+                    info!(
+                        "✅ Wrote {} games to {} ({} matched on IGDB)",
+                        entries.len(),
+                        path.display(),
+                        enriched
+                    );
+                } else {
+                    info!("✅ Wrote {} games to {}", entries.len(), path.display());
+                }
+            }
+            Err(e) => {
+                error!("❌ Could not write {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        },
+        None => println!("{}", document),
+    }
+}
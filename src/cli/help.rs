@@ -0,0 +1,216 @@
+//! The usage text shown when librarylink is run without a command.
+
+use librarylink::report::info;
+
+/// Prints the commands and options, with `program` as the executable.
+pub fn print(program: &str) {
+    info!(
+        "Usage: {} [--output <human|json|porcelain|silent>|--porcelain] [--verbose|--quiet] [--timings] [--log-file <path>] [--sink <spec>]... [--ready-url <url>] [--splash] [--notify] [--portable] [--provider <windows|mock>] <command> [arguments]",
+        program
+    );
+    info!("Commands:");
+    info!("  uwp-launch <AUMID|alias> [options] - Look up UWP app info and launch it");
+    info!("  uwp-launch --by-name <name> [options] - Launch the app whose name best matches");
+    info!("  launch <profile|alias>      - Launch a profile and the clients it depends on");
+    info!("                                (--no-preflight skips its preflight checks)");
+    info!("  alias add <name> <AUMID> | remove <name> | list [--json]");
+    info!("                              - Name an AUMID for uwp-launch and launch");
+    info!("  config repair [--json]      - Recover librarylink's files from a crash mid-write");
+    info!("  secret set <name> [<value>] | get <name> | remove <name> | list");
+    info!("  package repair <PFN>        - Re-register a package for the current user");
+    info!("                              - Keep integration credentials encrypted for this user");
+    info!("  preflight <profile> [--json] - Check a profile's prerequisites without launching");
+    info!(
+        "  doctor [--fix [--yes] | --json] - Check this machine for common launch problems and fix them"
+    );
+    info!("  capabilities [--json]       - Show which optional features work on this machine");
+    info!("  win32-launch <path> [options] - Launch a desktop game and monitor it");
+    info!("  uri-launch <uri> [options]  - Launch a protocol URI with its handler and monitor it");
+    info!("  list-apps [options]         - List apps with AUMIDs (likely UWP/Store apps)");
+    info!(
+        "  list-games [options]        - List the installed apps that are games, with their AUMIDs"
+    );
+    info!("  pick [options]              - Choose an app from a searchable list");
+    info!("  resolve <name> [options]    - Print the AUMID of the app whose name best matches");
+    info!("  list-monitors [--json]      - List monitors with the numbers --monitor takes");
+    info!("  list-audio-devices [--json] - List audio outputs with their IDs and exact names");
+    info!("  tree <PID> [options]        - Print the process tree rooted at a process");
+    info!("  sessions [--all] [--json]   - List the games librarylink is monitoring");
+    info!("  resume [session-id]         - Re-attach to a session orphaned by a restart");
+    info!(
+        "  control suspend|resume|kill|status <session-id> - Pause, unpause or end a running session's game"
+    );
+    info!("  bench <profile> [options]   - Launch a profile, sample it for a while, then end it");
+    info!("  schedule <profile> [options] - Queue a profile launch with the daemon");
+    info!("  queue [--cancel <id>]       - Show or cancel the daemon's queued launches");
+    info!("  daemon                      - Run the launch queue in the background");
+    info!("  daemon token add|remove|list - Manage the daemon's scoped API tokens");
+    info!("  audit [--limit <n>] [--json] - Show who asked the daemon or rpc to do what");
+    info!("  maintenance clean [options] - Remove old logs, traces and history");
+    info!("  rpc                         - Serve JSON-RPC 2.0 requests over stdin/stdout");
+    info!("  export powershell-module    - Write a PowerShell module wrapping librarylink");
+    info!("  export library [options]    - Write the installed games as JSON");
+    info!("  open-uri <URI>              - Handle a librarylink://launch/<AUMID> link");
+    info!("  add-to-steam <AUMID> [options] - Add a non-Steam game entry that launches the app");
+    info!("  get-icon <AUMID> [options]  - Save the app's logo as a PNG or ICO");
+    info!(
+        "  make-shortcut <AUMID> --out <path> [options] - Create a .lnk or .url that launches the app"
+    );
+    info!(
+        "  debug <snapshot|acl|select> - Record processes, check install folder access, or replay process selection offline"
+    );
+    info!("");
+    info!("Launch Options (uwp-launch, wrap, win32-launch and uri-launch):");
+    info!("  --debug-tree                - Print the process tree during replacement scans");
+    info!("  --elevated                  - Run the launch as administrator, after the UAC prompt");
+    info!("  --session <any|console>     - Require the launch to happen in the console session");
+    info!("  --poll-interval <ms>        - Poll for process changes instead of waiting on handles");
+    info!("  --close-launchers           - Close store clients the game started once it exits");
+    info!(
+        "  --kill-on-exit              - Terminate the game's process tree if librarylink is stopped"
+    );
+    info!("  --min-lifetime <seconds>    - Skip replacement processes that exit sooner than this");
+    info!(
+        "  --follow-restarts <seconds> - Keep the session if the game restarts within this window"
+    );
+    info!(
+        "  --wait-for <exe|pattern>    - Track the first process with this executable name or path pattern"
+    );
+    info!(
+        "  --activation-timeout <seconds> - Exit with code 3 if --wait-for's process, or any when the launched one exits at once, doesn't appear in time (default 60)"
+    );
+    info!(
+        "  --handoff-timeout <seconds> - Exit with code 4 if nothing takes over from the launcher in time"
+    );
+    info!("  --frame-times               - Capture frame times with a bundled PresentMon");
+    info!("  --presentmon <path>         - Capture frame times with this PresentMon");
+    info!(
+        "  --if-updating <wait|fail>   - Wait for (default) or fail on a Store update in progress"
+    );
+    info!(
+        "  --monitor <n>               - Move the game's window to this monitor (see list-monitors)"
+    );
+    info!(
+        "  --window-size <WxH>         - Resize the game window's client area, in physical pixels"
+    );
+    info!(
+        "  --resolution <WxH[@Hz]|client> - Change the display mode for the session; client takes the streaming client's"
+    );
+    info!("  --auto-restore              - Restore the game's window whenever it minimizes itself");
+    info!(
+        "  --restore-minimized <seconds> - Bring the game's window back once it's been minimized or in the background this long"
+    );
+    info!(
+        "  --priority <class>          - Run the game at idle, belownormal, normal, abovenormal or high priority"
+    );
+    info!(
+        "  --affinity <mask>           - Keep the game on these CPUs, such as 0xFF for the first eight"
+    );
+    info!(
+        "  --tune-replacements         - Give processes that take over the priority and affinity too"
+    );
+    info!("  --no-monitor                - Exit once the app is activated");
+    info!(
+        "  --grace-period <seconds>    - How long a replacement has to appear before the app counts as exited (default 5)"
+    );
+    info!(
+        "  --watch-dir <path>          - Search this directory for the game instead of the activated process's; repeat to search several"
+    );
+    info!(
+        "  --ignore-process <exe>      - Never track processes with this name or glob, such as crashpad_handler.exe"
+    );
+    info!(
+        "  --allow-process <exe>       - Track processes with this name or glob wherever they run from"
+    );
+    info!("");
+    info!("Wrap, Win32 and URI Launch Options:");
+    info!(
+        "  --working-dir <dir>         - Start in this directory (win32-launch: the executable's; not uri-launch)"
+    );
+    info!(
+        "  --capture-limit <KB>        - Keep up to this much of the command's output (default 1024; not uri-launch)"
+    );
+    info!(
+        "  --no-capture                - Leave the command's output on the console (not uri-launch)"
+    );
+    info!("");
+    info!("Bench Options:");
+    info!("  --duration <minutes>        - How long to run the game (required)");
+    info!("  --interval <seconds>        - Time between CPU/GPU/memory samples (default: 1)");
+    info!("  --presentmon <path>         - Capture frame times with PresentMon");
+    info!(
+        "  --results-dir <dir>         - Where to write results (default: %APPDATA%\\librarylink\\bench)"
+    );
+    info!("");
+    info!("Schedule Options:");
+    info!("  --at <time>                 - HH:MM, YYYY-MM-DD HH:MM or +<n>(s|m|h) (default: now)");
+    info!("  --priority <n>              - Launch before lower priorities once due (default: 0)");
+    info!("");
+    info!("Tree Options:");
+    info!("  --tracked <PID>             - Mark a process as the one being tracked");
+    info!("");
+    info!("List Apps Options:");
+    info!("  --search <term>             - Search for apps containing the term");
+    info!("  --publisher <name>          - Only apps whose publisher's name contains this");
+    info!("  --family <name>             - Only apps from this package family");
+    info!("  --include-frameworks        - Also list framework packages, such as runtimes");
+    info!(
+        "  --json                      - Print the apps as a JSON array with package family, publisher, install path and version"
+    );
+    info!("  list-games takes --search, --publisher and --json too");
+    info!("");
+    info!("Pick Options:");
+    info!("  --search <term>             - Start with the search already typed");
+    info!("  --publisher <name>          - Only apps whose publisher's name contains this");
+    info!("  --family <name>             - Only apps from this package family");
+    info!("  --launch                    - Launch the chosen app instead of printing its AUMID");
+    info!("");
+    info!("Global Options:");
+    info!("  --output <human|json|porcelain|silent> - Output format (default: human)");
+    info!(
+        "  --porcelain                 - Stable tab-separated lines for scripts, as --output porcelain"
+    );
+    info!("  --verbose                   - Also show debug detail");
+    info!("  --quiet                     - Only show warnings and errors");
+    info!("  --timings                   - Report how long each phase of a launch took");
+    info!(
+        "  --log-file <path>           - Write everything, debug detail included, to a timestamped log"
+    );
+    info!("  --provider <windows|mock>   - List and launch mock apps instead of installed ones");
+    info!(
+        "  --sink <spec>               - Also send events as JSON lines to stdout, file:<path>, pipe:<name> or udp:<host>:<port>"
+    );
+    info!(
+        "  --ready-url <url>           - POST the game_ready event to this URL once the game is up"
+    );
+    info!("  --notify                    - Raise a Windows notification when a launch fails");
+    info!(
+        "  --portable                  - Keep config, cache and history in librarylink-data beside the executable"
+    );
+    info!(
+        "  --splash                    - Show launch progress and errors full screen until the game's window appears"
+    );
+    info!("");
+    info!("Examples:");
+    info!(
+        "  {} uwp-launch Microsoft.WindowsCalculator_8wekyb3d8bbwe!App",
+        program
+    );
+    info!("  {} launch battlefield", program);
+    info!(
+        "  {} wrap --wait-for game.exe -- \"C:\\Games\\Launcher.exe\" -play",
+        program
+    );
+    info!(
+        "  {} win32-launch \"C:\\Games\\Hades\\x64\\Hades.exe\" -- -DebugDraw=false",
+        program
+    );
+    info!("  {} schedule battlefield --at 21:30", program);
+    info!("  {} list-apps", program);
+    info!("  {} list-apps --search forza", program);
+    info!("  {} list-apps --publisher \"Xbox Game Studios\"", program);
+    info!("  {} pick --launch", program);
+    info!("  {} tree 1234", program);
+    info!("  {} resume", program);
+    info!("  {} --output json list-apps", program);
+}
@@ -0,0 +1,744 @@
+//! `uwp-launch`, `launch`, `wrap`, `win32-launch`, `uri-launch` and
+//! `open-uri`.
+
+use librarylink::LaunchOptions;
+use librarylink::exit::ExitReason;
+use librarylink::matcher::MonitorScope;
+use librarylink::package::UpdateGuard;
+use librarylink::placement::WindowPlacement;
+use librarylink::poll::PollSettings;
+use librarylink::priority::{PriorityClass, ProcessTuning};
+use librarylink::profile::{self, Profile};
+use librarylink::report::{self, error, info, warning};
+use librarylink::sunshine::{self, Announcement, SunshineConfig};
+use librarylink::{apps, capture, config, presentmon, protocol, quit, system, wts};
+
+use super::{
+    exit_with, exit_with_outcome, parse_seconds, profile_load_failure, relaunch_elevated,
+    resolve_app_name,
+};
+
+pub fn uwp_launch(args: &[String]) {
+    if args.len() < 3 {
+        error!(
+            "Error: UWP launch requires an Application User Model ID. Try using librarylink list-apps to find it."
+        );
+        info!("Usage: {} uwp-launch <AUMID> [options]", args[0]);
+        return;
+    }
+
+    // With --by-name the app is found by its display name, and its
+    // options start after the name
+    let (aumid, first_option) = if args[2] == "--by-name" {
+        let Some(name) = args.get(3) else {
+            error!("Error: --by-name requires an app name");
+            info!("Usage: {} uwp-launch --by-name <name> [options]", args[0]);
+            return;
+        };
+        (resolve_app_name(name), 4)
+    } else {
+        // An alias from config.toml stands for its AUMID
+        let aumid = config::current().alias(&args[2]).unwrap_or(&args[2]);
+        (aumid.to_string(), 3)
+    };
+    let usage = format!(
+        "Usage: {} uwp-launch <AUMID|alias|--by-name <name>> [--elevated] [--user-session <ID|user>] {}",
+        args[0],
+        LaunchFlags::USAGE
+    );
+    let mut flags = LaunchFlags::new(configured_launch(&aumid));
+    let mut user_session: Option<&str> = None;
+    let mut elevated = false;
+    // Arguments forwarded when relaunching inside another user's session
+    let mut forwarded_args: Vec<String> = vec!["uwp-launch".to_string(), aumid.clone()];
+
+    let mut i = first_option;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--elevated" => {
+                elevated = true;
+                i += 1;
+            }
+            "--user-session" => {
+                if i + 1 < args.len() {
+                    user_session = Some(&args[i + 1]);
+                    i += 2;
+                } else {
+                    error!("Error: --user-session requires a session ID or user name");
+                    info!("{}", usage);
+                    return;
+                }
+            }
+            _ => match flags.parse(args, i) {
+                Ok(0) => {
+                    error!("Error: Unknown option '{}'", args[i]);
+                    info!("{}", usage);
+                    return;
+                }
+                Ok(taken) => {
+                    forwarded_args.extend_from_slice(&args[i..i + taken]);
+                    i += taken;
+                }
+                Err(message) => {
+                    error!("Error: {}", message);
+                    info!("{}", usage);
+                    return;
+                }
+            },
+        }
+    }
+    // Sessions started for another user don't inherit the client's
+    // environment, so pass the mode itself
+    if let Some(mode) = &flags.display
+        && let Some(position) = forwarded_args.iter().position(|arg| arg == "--resolution")
+    {
+        forwarded_args[position + 1] = format!(
+            "{}x{}{}",
+            mode.width,
+            mode.height,
+            mode.refresh_rate
+                .map_or(String::new(), |rate| format!("@{}", rate))
+        );
+    }
+    let options = match flags.finish() {
+        Ok(options) => options,
+        Err(message) => {
+            error!("Error: {}", message);
+            info!("{}", usage);
+            return;
+        }
+    };
+
+    if elevated {
+        if user_session.is_some() {
+            error!("Error: --elevated can't be combined with --user-session");
+            info!("{}", usage);
+            return;
+        }
+        relaunch_elevated();
+    }
+
+    if let Some(spec) = user_session {
+        let session_id = match wts::resolve_user_session(spec) {
+            Ok(session_id) => session_id,
+            Err(message) => {
+                error!("Error: {}", message);
+                return;
+            }
+        };
+
+        info!(
+            "Launching inside session {} on behalf of its user...",
+            session_id
+        );
+        match wts::run_in_user_session(session_id, &forwarded_args) {
+            Ok(exit_code) => {
+                info!(
+                    "librarylink in session {} exited with code {}",
+                    session_id, exit_code
+                );
+                std::process::exit(exit_code as i32);
+            }
+            Err(message) => {
+                error!("❌ {}", message);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    launch_app(&aumid, options);
+}
+
+pub fn launch(args: &[String]) {
+    let Some(name) = args.get(2) else {
+        error!(
+            "Error: launch requires a profile name (a file in {})",
+            profile::profiles_dir().display()
+        );
+        info!("Usage: {} launch <profile|alias> [--no-preflight]", args[0]);
+        return;
+    };
+    let mut preflight = true;
+    for option in &args[3..] {
+        match option.as_str() {
+            "--no-preflight" => preflight = false,
+            _ => {
+                error!("Error: Unknown option '{}'", option);
+                info!("Usage: {} launch <profile|alias> [--no-preflight]", args[0]);
+                return;
+            }
+        }
+    }
+    // A name without a profile may be an alias, launched as uwp-launch would
+    let has_profile = profile::profiles_dir()
+        .join(format!("{}.toml", name))
+        .is_file();
+    match config::current().alias(name) {
+        Some(aumid) if !has_profile => {
+            launch_app(aumid, configured_launch(aumid));
+        }
+        _ => launch_profile(name, preflight),
+    }
+}
+
+pub fn open_uri(args: &[String]) {
+    let Some(uri) = args.get(2) else {
+        error!("Error: open-uri requires a librarylink:// URI");
+        info!("Usage: {} open-uri librarylink://launch/<AUMID>", args[0]);
+        return;
+    };
+
+    match parse_librarylink_uri(uri) {
+        Ok((aumid, scope)) => {
+            let mut options = LaunchOptions::new(aumid.clone());
+            if let Some(scope) = scope {
+                options = options.scope(scope);
+            }
+            launch_app(&aumid, options);
+        }
+        Err(message) => error!("Error: {}", message),
+    }
+}
+
+pub fn wrap(args: &[String]) {
+    let win32 = args[1] == "win32-launch";
+    let uri = args[1] == "uri-launch";
+    let options_usage = format!(
+        "[--working-dir <dir>] [--elevated] {} [--capture-limit <KB>] [--no-capture]",
+        LaunchFlags::USAGE
+    );
+    let usage = if uri {
+        format!("Usage: {} uri-launch <uri> {}", args[0], LaunchFlags::USAGE)
+    } else if win32 {
+        format!(
+            "Usage: {} win32-launch <path> {} [-- arguments]",
+            args[0], options_usage
+        )
+    } else {
+        format!(
+            "Usage: {} wrap {} -- <command> [arguments]",
+            args[0], options_usage
+        )
+    };
+    // Options run up to `separator`; the program's own arguments follow it
+    let (program, mut i, separator, arguments) = if uri {
+        let Some(uri) = args.get(2).filter(|uri| protocol::scheme(uri).is_some()) else {
+            error!("Error: uri-launch requires a protocol URI such as discord://");
+            info!("{}", usage);
+            return;
+        };
+        (uri, 3, args.len(), &args[args.len()..])
+    } else if win32 {
+        let Some(program) = args.get(2).filter(|path| !path.starts_with("--")) else {
+            error!("Error: win32-launch requires the path to an executable");
+            info!("{}", usage);
+            return;
+        };
+        let separator = args
+            .iter()
+            .position(|arg| arg == "--")
+            .unwrap_or(args.len());
+        (
+            program,
+            3,
+            separator,
+            args.get(separator + 1..).unwrap_or_default(),
+        )
+    } else {
+        let Some(separator) = args.iter().position(|arg| arg == "--") else {
+            error!("Error: wrap requires a command after '--'");
+            info!("{}", usage);
+            return;
+        };
+        let Some(program) = args.get(separator + 1) else {
+            error!("Error: wrap requires a command after '--'");
+            info!("{}", usage);
+            return;
+        };
+        (program, 2, separator, &args[separator + 2..])
+    };
+    let defaults = config::current().defaults_for(program);
+    let mut flags = LaunchFlags::new(defaults.apply(if uri {
+        LaunchOptions::uri(program)
+    } else {
+        LaunchOptions::command(program).args(arguments)
+    }));
+    // The shell starts a URI's handler, so its output isn't ours
+    let mut capture_limit = (!uri).then_some(capture::DEFAULT_LIMIT);
+    // Games look for their data next to the executable, whatever
+    // directory librarylink was started from
+    let mut elevated = false;
+    let mut working_dir = win32
+        .then(|| std::path::Path::new(program).parent())
+        .flatten()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .map(std::path::Path::to_path_buf);
+
+    let options_args = &args[..separator];
+    while i < separator {
+        match args[i].as_str() {
+            "--no-capture" | "--working-dir" | "--capture-limit" if uri => {
+                error!("Error: {} doesn't apply to uri-launch", args[i]);
+                info!("{}", usage);
+                return;
+            }
+            "--no-capture" => {
+                capture_limit = None;
+                i += 1;
+            }
+            "--elevated" => {
+                elevated = true;
+                i += 1;
+            }
+            "--working-dir" => {
+                if i + 1 < separator {
+                    working_dir = Some(std::path::PathBuf::from(&args[i + 1]));
+                    i += 2;
+                } else {
+                    error!("Error: --working-dir requires a directory");
+                    info!("{}", usage);
+                    return;
+                }
+            }
+            "--capture-limit" => {
+                match options_args
+                    .get(i + 1)
+                    .and_then(|value| value.parse::<u64>().ok())
+                {
+                    Some(kilobytes) => {
+                        capture_limit = Some(kilobytes * 1024);
+                        i += 2;
+                    }
+                    None => {
+                        error!("Error: --capture-limit requires a size in kilobytes");
+                        info!("{}", usage);
+                        return;
+                    }
+                }
+            }
+            _ => match flags.parse(options_args, i) {
+                Ok(0) => {
+                    error!("Error: Unknown option '{}'", args[i]);
+                    info!("{}", usage);
+                    return;
+                }
+                Ok(taken) => i += taken,
+                Err(message) => {
+                    error!("Error: {}", message);
+                    info!("{}", usage);
+                    return;
+                }
+            },
+        }
+    }
+
+    if elevated {
+        relaunch_elevated();
+    }
+    let mut options = match flags.finish() {
+        Ok(options) => options,
+        Err(message) => {
+            error!("Error: {}", message);
+            info!("{}", usage);
+            return;
+        }
+    };
+    if let Some(limit) = capture_limit {
+        options = options.capture_output(limit);
+    }
+    if let Some(directory) = working_dir {
+        options = options.working_dir(directory);
+    }
+    let name = if uri {
+        protocol::handler(program)
+            .ok()
+            .and_then(|handler| handler.display_name())
+            .unwrap_or_else(|| program.clone())
+    } else {
+        std::path::Path::new(program).file_stem().map_or_else(
+            || program.clone(),
+            |stem| stem.to_string_lossy().into_owned(),
+        )
+    };
+    run_session(program, &name, options, false);
+}
+
+/// A launch as the monitoring flags every launch subcommand takes describe
+/// it. The flags the builder takes together are collected until
+/// [`finish`](Self::finish).
+struct LaunchFlags {
+    options: LaunchOptions,
+    frame_times: bool,
+    presentmon: Option<std::path::PathBuf>,
+    placement: WindowPlacement,
+    /// Display mode set for the session and put back afterwards
+    display: Option<system::DisplayMode>,
+    no_monitor: bool,
+}
+
+impl LaunchFlags {
+    const USAGE: &str = "[--debug-tree] [--session <any|console>] [--scope <exact-dir|dir-tree|package>] [--wait-for <exe>] [--poll-interval <ms>] [--close-launchers] [--kill-on-exit] [--min-lifetime <seconds>] [--follow-restarts <seconds>] [--activation-timeout <seconds>] [--handoff-timeout <seconds>] [--grace-period <seconds>] [--frame-times] [--presentmon <path>] [--if-updating <wait|fail>] [--monitor <n>] [--window-size <WxH>] [--resolution <WxH[@Hz]|client>] [--auto-restore] [--restore-minimized <seconds>] [--priority <class>] [--affinity <mask>] [--tune-replacements] [--no-monitor] [--watch-dir <path>]... [--ignore-process <exe>]... [--allow-process <exe>]...";
+
+    fn new(options: LaunchOptions) -> Self {
+        LaunchFlags {
+            options,
+            frame_times: false,
+            presentmon: None,
+            placement: WindowPlacement::default(),
+            display: None,
+            no_monitor: false,
+        }
+    }
+
+    fn set(&mut self, set: impl FnOnce(LaunchOptions) -> LaunchOptions) {
+        let options = std::mem::replace(&mut self.options, LaunchOptions::new(""));
+        self.options = set(options);
+    }
+
+    /// Takes the flag at `args[i]` and its value, returning how many
+    /// arguments that was, or 0 for a flag that isn't one of these.
+    fn parse(&mut self, args: &[String], i: usize) -> Result<usize, String> {
+        let value = args.get(i + 1).map(String::as_str);
+        let seconds = |message: &str| value.and_then(parse_seconds).ok_or(message.to_string());
+        match args[i].as_str() {
+            "--debug-tree" => self.set(|options| options.debug_tree(true)),
+            "--close-launchers" => self.set(|options| options.close_launchers(true)),
+            "--kill-on-exit" => self.set(|options| options.kill_on_exit(true)),
+            "--auto-restore" => self.set(|options| options.auto_restore(true)),
+            "--tune-replacements" => self.set(|options| options.tune_replacements(true)),
+            "--frame-times" => self.frame_times = true,
+            "--no-monitor" => self.no_monitor = true,
+            flag => {
+                match flag {
+                    "--wait-for" => {
+                        let executable = value.ok_or("--wait-for requires an executable name")?;
+                        self.set(|options| options.wait_for(executable));
+                    }
+                    "--scope" => {
+                        let scope = value
+                            .and_then(MonitorScope::parse)
+                            .ok_or("--scope requires 'exact-dir', 'dir-tree' or 'package'")?;
+                        self.set(|options| options.scope(scope));
+                    }
+                    "--session" => {
+                        let requirement = value
+                            .and_then(wts::SessionRequirement::parse)
+                            .ok_or("--session requires 'any' or 'console'")?;
+                        self.set(|options| options.session(requirement));
+                    }
+                    "--poll-interval" => {
+                        let polling = value
+                            .and_then(PollSettings::parse)
+                            .ok_or("--poll-interval requires a positive number of milliseconds")?;
+                        self.set(|options| options.poll_interval(polling.interval));
+                    }
+                    "--min-lifetime" => {
+                        let lifetime = seconds("--min-lifetime requires a number of seconds")?;
+                        self.set(|options| options.min_lifetime(lifetime));
+                    }
+                    "--follow-restarts" => {
+                        let window = seconds("--follow-restarts requires a window in seconds")?;
+                        self.set(|options| options.follow_restarts(window));
+                    }
+                    "--activation-timeout" => {
+                        let timeout = seconds("--activation-timeout requires a number of seconds")?;
+                        self.set(|options| options.activation_timeout(timeout));
+                    }
+                    "--handoff-timeout" => {
+                        let timeout = seconds("--handoff-timeout requires a number of seconds")?;
+                        self.set(|options| options.handoff_timeout(timeout));
+                    }
+                    "--grace-period" => {
+                        let period = seconds("--grace-period requires a number of seconds")?;
+                        self.set(|options| options.grace_period(period));
+                    }
+                    "--restore-minimized" => {
+                        let after = seconds("--restore-minimized requires a number of seconds")?;
+                        self.set(|options| options.restore_minimized(after));
+                    }
+                    "--presentmon" => {
+                        let path = value.ok_or("--presentmon requires the path to PresentMon")?;
+                        self.presentmon = Some(std::path::PathBuf::from(path));
+                    }
+                    "--if-updating" => {
+                        let guard = value
+                            .and_then(UpdateGuard::parse)
+                            .ok_or("--if-updating requires 'wait' or 'fail'")?;
+                        self.set(|options| options.if_updating(guard));
+                    }
+                    "--monitor" => {
+                        let index = value
+                            .and_then(|value| value.parse::<usize>().ok())
+                            .filter(|&index| index > 0)
+                            .ok_or("--monitor requires a monitor number, starting at 1")?;
+                        self.placement.monitor = Some(index);
+                    }
+                    "--window-size" => {
+                        let size = value
+                            .and_then(WindowPlacement::parse_size)
+                            .ok_or("--window-size requires a size such as 1920x1080")?;
+                        self.placement.size = Some(size);
+                    }
+                    "--resolution" => {
+                        let value = value.ok_or(
+                            "--resolution requires a mode such as 1920x1080@60, or client",
+                        )?;
+                        self.display = Some(system::DisplayMode::parse(value)?);
+                    }
+                    "--priority" => {
+                        let priority = value.and_then(PriorityClass::parse).ok_or_else(|| {
+                            format!("--priority requires {}", PriorityClass::NAMES)
+                        })?;
+                        self.set(|options| options.priority(priority));
+                    }
+                    "--affinity" => {
+                        let mask = value
+                            .and_then(ProcessTuning::parse_affinity)
+                            .ok_or("--affinity requires a CPU mask such as 0xFF")?;
+                        self.set(|options| options.affinity(mask));
+                    }
+                    "--watch-dir" => {
+                        let path = value
+                            .filter(|path| std::path::Path::new(path).is_dir())
+                            .ok_or("--watch-dir requires an existing directory")?;
+                        self.set(|options| options.watch_dir(path));
+                    }
+                    "--ignore-process" | "--allow-process" => {
+                        let name =
+                            value.ok_or_else(|| format!("{} requires an executable name", flag))?;
+                        self.set(|options| {
+                            if flag == "--ignore-process" {
+                                options.ignore_process(name)
+                            } else {
+                                options.allow_process(name)
+                            }
+                        });
+                    }
+                    _ => return Ok(0),
+                }
+                return Ok(2);
+            }
+        }
+        Ok(1)
+    }
+
+    /// The launch the flags describe.
+    fn finish(mut self) -> Result<LaunchOptions, String> {
+        if self.no_monitor && self.display.is_some() {
+            // The mode is put back when the session ends, which is
+            // straight away without monitoring
+            return Err("--resolution can't be combined with --no-monitor".to_string());
+        }
+        if self.frame_times && self.presentmon.is_none() {
+            self.presentmon = Some(presentmon::locate().ok_or(
+                "--frame-times found no PresentMon next to librarylink or on the PATH; pass --presentmon <path>",
+            )?);
+        }
+        let mut options = self
+            .options
+            .placement(self.placement)
+            .monitor(!self.no_monitor);
+        if let Some(path) = self.presentmon {
+            options = options.presentmon(path);
+        }
+        if self.display.is_some() {
+            options = options.system(system::SystemSettings {
+                display: self.display,
+                ..Default::default()
+            });
+        }
+        Ok(options)
+    }
+}
+
+/// Decodes `%XX` escapes, which browsers apply to characters such as `!`.
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(byte) = value
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Parses `librarylink://launch/<AUMID>[?scope=<scope>]`, the links the
+/// installer registers librarylink to handle.
+fn parse_librarylink_uri(uri: &str) -> Result<(String, Option<MonitorScope>), String> {
+    let rest = uri
+        .strip_prefix("librarylink:")
+        .ok_or_else(|| format!("'{}' is not a librarylink:// URI", uri))?
+        .trim_start_matches('/');
+    let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+
+    let aumid = path
+        .strip_prefix("launch/")
+        .map(|aumid| percent_decode(aumid.trim_end_matches('/')))
+        .filter(|aumid| !aumid.is_empty())
+        .ok_or_else(|| format!("Expected librarylink://launch/<AUMID>, got '{}'", uri))?;
+
+    let mut scope = None;
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        match pair.split_once('=') {
+            Some(("scope", value)) => {
+                scope = Some(
+                    MonitorScope::parse(&percent_decode(value))
+                        .ok_or_else(|| format!("Unknown scope '{}'", value))?,
+                );
+            }
+            _ => return Err(format!("Unknown URI parameter '{}'", pair)),
+        }
+    }
+
+    Ok((aumid, scope))
+}
+
+/// The launch of `aumid` with the defaults config.toml sets for it, for the
+/// flags to go over.
+pub fn configured_launch(aumid: &str) -> LaunchOptions {
+    config::current()
+        .defaults_for(aumid)
+        .apply(LaunchOptions::new(aumid))
+}
+
+/// Launches an app and follows it as `uwp-launch` does. When activation
+/// fails the shell is asked to start the app instead, unmonitored.
+pub fn launch_app(aumid: &str, options: LaunchOptions) {
+    let name = apps::display_name(aumid).unwrap_or_else(|| aumid.to_string());
+    run_session(aumid, &name, options, true);
+}
+
+fn launch_profile(name: &str, preflight: bool) {
+    // Put changed system settings back even if the console is closed mid-session
+    system::install_exit_handler();
+
+    let mut profile = match Profile::load(name) {
+        Ok(profile) => profile,
+        Err(e) => {
+            error!("❌ {}", e);
+            exit_with(profile_load_failure(name));
+        }
+    };
+    profile.skip_preflight = !preflight;
+
+    let mut session = match profile.launch() {
+        Ok(session) => session,
+        Err(e) => {
+            error!("❌ Could not launch profile '{}': {}", name, e);
+            exit_with(ExitReason::of(e.as_ref()));
+        }
+    };
+
+    let announcement =
+        announce_to_sunshine(&apps::display_name(&profile.aumid).unwrap_or(profile.name));
+    quit::watch(session.control(), report::reporter());
+
+    // Library sessions report to their own channel; show the events as uwp-launch would
+    for event in session.events() {
+        report::emit(event);
+    }
+    session.wait();
+    restore_sunshine(announcement);
+    exit_with_outcome(session.state(), session.exit_code());
+}
+
+/// Runs a library launch and shows its events; `name` is what Sunshine is
+/// told is streaming. With `shell_fallback`, `program` is an app the shell
+/// starts unmonitored if activation fails.
+pub fn run_session(program: &str, name: &str, options: LaunchOptions, shell_fallback: bool) {
+    system::install_exit_handler();
+
+    let mut session = match options.launch() {
+        Ok(session) => session,
+        Err(e) => {
+            let reason = ExitReason::of(e.as_ref());
+            error!("❌ Could not launch {}: {}", program, e);
+            if shell_fallback
+                && matches!(
+                    reason,
+                    ExitReason::ActivationFailed | ExitReason::ComInitFailed
+                )
+            {
+                info!("Trying fallback launch method...");
+                match launch_app_with_shell_execute(program) {
+                    Ok(()) => {
+                        info!("✅ App launched using fallback method (no process ID available)");
+                        warning!("⚠️ Process monitoring not available with fallback method");
+                        return;
+                    }
+                    Err(e) => error!("❌ All launch methods failed: {}", e),
+                }
+            }
+            exit_with(reason);
+        }
+    };
+
+    // Only monitored sessions are saved, under an ID
+    let monitored = !session.id().is_empty();
+    let announcement = monitored.then(|| announce_to_sunshine(name)).flatten();
+    quit::watch(session.control(), report::reporter());
+    // Library sessions report to their own channel; show the events here
+    for event in session.events() {
+        report::emit(event);
+    }
+    if !monitored {
+        info!("✅ Launched; not monitoring it (--no-monitor)");
+        return;
+    }
+    session.wait();
+    restore_sunshine(announcement);
+    exit_with_outcome(session.state(), session.exit_code());
+}
+
+/// Renames Sunshine's app entry after `name` for the session, when Sunshine
+/// is configured in the environment.
+fn announce_to_sunshine(name: &str) -> Option<Announcement> {
+    let config = SunshineConfig::from_env()?;
+    match sunshine::announce(&config, name) {
+        Ok(announcement) => {
+            info!("📡 Sunshine is showing the stream as '{}'", name);
+            Some(announcement)
+        }
+        Err(e) => {
+            warning!("⚠️ Could not update Sunshine at {}: {}", config.url, e);
+            None
+        }
+    }
+}
+
+fn restore_sunshine(announcement: Option<Announcement>) {
+    if let Some(announcement) = announcement
+        && let Err(e) = announcement.restore()
+    {
+        warning!("⚠️ Could not restore Sunshine's app name: {}", e);
+    }
+}
+
+fn launch_app_with_shell_execute(aumid: &str) -> Result<(), Box<dyn std::error::Error>> {
+    use std::process::Command;
+
+    // Use PowerShell to launch the UWP app
+    let powershell_command = format!("Start-Process \"shell:appsFolder\\{}\"", aumid);
+
+    let output = Command::new("powershell")
+        .args(["-Command", &powershell_command])
+        .output()
+        .map_err(|e| format!("Failed to execute PowerShell command: {}", e))?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        Err(format!("PowerShell command failed: {}", error_msg).into())
+    }
+}
@@ -0,0 +1,74 @@
+//! `maintenance clean`.
+
+use librarylink::report::{error, info};
+use librarylink::retention::{self, DataKind, RetentionPolicy};
+
+pub fn maintenance(args: &[String]) {
+    let usage = format!(
+        "Usage: {} maintenance clean [--max-age <days>] [--max-size <MB>]",
+        args[0]
+    );
+    if args.get(2).map(String::as_str) != Some("clean") {
+        error!("Error: maintenance requires the 'clean' action");
+        info!("{}", usage);
+        return;
+    }
+
+    let mut overrides = RetentionPolicy::default();
+    let mut i = 3;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--max-age" => match args.get(i + 1).and_then(|value| value.parse::<u64>().ok()) {
+                Some(days) => {
+                    overrides.max_age = Some(std::time::Duration::from_secs(days * 24 * 60 * 60));
+                    i += 2;
+                }
+                None => {
+                    error!("Error: --max-age requires a number of days");
+                    info!("{}", usage);
+                    return;
+                }
+            },
+            "--max-size" => match args.get(i + 1).and_then(|value| value.parse::<u64>().ok()) {
+                Some(megabytes) => {
+                    overrides.max_bytes = Some(megabytes * 1024 * 1024);
+                    i += 2;
+                }
+                None => {
+                    error!("Error: --max-size requires a size in megabytes");
+                    info!("{}", usage);
+                    return;
+                }
+            },
+            _ => {
+                error!("Error: Unknown option '{}'", args[i]);
+                info!("{}", usage);
+                return;
+            }
+        }
+    }
+    clean_data(&overrides);
+}
+
+fn clean_data(overrides: &RetentionPolicy) {
+    for (kind, result) in retention::enforce_all(overrides) {
+        match result {
+            Ok(cleaned) if cleaned.removed == 0 => info!("✅ {}: nothing to remove", kind),
+            Ok(cleaned) => {
+                let unit = if kind == DataKind::History {
+                    "records"
+                } else {
+                    "files"
+                };
+                info!(
+                    "🧹 {}: removed {} {} ({:.1} MB)",
+                    kind,
+                    cleaned.removed,
+                    unit,
+                    cleaned.freed_bytes as f64 / (1024.0 * 1024.0)
+                );
+            }
+            Err(e) => error!("❌ {}: {}", kind, e),
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! The subcommands, one module per group. Each parses its own arguments and
+//! calls into the library; what they share, such as exiting with an
+//! [`ExitReason`], lives here.
+
+use librarylink::exit::ExitReason;
+use librarylink::monitor::{EndReason, MonitorState};
+use librarylink::report::{self, Event, error, info};
+use librarylink::{elevation, process, profile, resolve, sink, timings};
+use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+pub mod apps;
+pub mod daemon;
+pub mod debug;
+pub mod devices;
+pub mod doctor;
+pub mod export;
+pub mod help;
+pub mod launch;
+pub mod maintenance;
+pub mod repair;
+pub mod schedule;
+pub mod secret;
+pub mod sessions;
+
+/// Set for `--porcelain`, whose scripts get [`ExitReason::GameFailed`]'s code
+/// in place of the game's own, so it can't collide with librarylink's.
+pub static PORCELAIN: AtomicBool = AtomicBool::new(false);
+
+/// Parses a non-negative number of seconds, such as `--min-lifetime 2.5`.
+pub fn parse_seconds(value: &str) -> Option<std::time::Duration> {
+    let seconds = value
+        .parse::<f64>()
+        .ok()
+        .filter(|seconds| *seconds >= 0.0)?;
+    std::time::Duration::try_from_secs_f64(seconds).ok()
+}
+
+/// Exits with the timeout's exit code when monitoring gave up on a state,
+/// or with the game's own when its final process exited with a failure, so
+/// wrappers can tell a crash from a clean exit; `--porcelain` exits with
+/// `GameFailed`'s instead. Other sessions return normally.
+pub fn exit_with_outcome(state: Option<MonitorState>, exit_code: Option<u32>) {
+    timings::report(report::reporter());
+    match state {
+        Some(MonitorState::Ended { reason }) if let Some(failure) = ExitReason::for_end(reason) => {
+            error!("❌ Monitoring gave up: {}", reason);
+            exit_with(failure);
+        }
+        Some(MonitorState::Ended {
+            reason: EndReason::Exited,
+        }) => {
+            if let Some(code) = exit_code.filter(|&code| code != 0) {
+                if PORCELAIN.load(Ordering::Relaxed) {
+                    fail(ExitReason::GameFailed, Some(code));
+                }
+                sink::flush();
+                // Windows exit codes are unsigned; NTSTATUS crash codes wrap
+                // to negative here and come back out unchanged
+                std::process::exit(code as i32);
+            }
+        }
+        _ => {}
+    }
+    sink::flush();
+}
+
+/// Ends the process with `reason`'s exit code, after a `failed` event so
+/// frontends get the reason by name too.
+pub fn exit_with(reason: ExitReason) -> ! {
+    fail(reason, None)
+}
+
+pub fn fail(reason: ExitReason, game_exit_code: Option<u32>) -> ! {
+    report::emit(Event::Failed {
+        reason,
+        code: reason.code(),
+        game_exit_code,
+    });
+    sink::flush();
+    std::process::exit(reason.code());
+}
+
+/// For `--elevated`: unless librarylink already runs elevated, runs the
+/// whole command again from an elevated copy, after the UAC prompt, and
+/// exits with its exit code. Returns when there's nothing to do.
+pub fn relaunch_elevated() {
+    if process::is_elevated() {
+        return;
+    }
+    info!("🛡️ Asking to run as administrator; the launch continues in a window of its own");
+    let arguments: Vec<String> = env::args().skip(1).collect();
+    match elevation::run_elevated(&arguments) {
+        Ok(exit_code) => {
+            info!("Elevated librarylink exited with code {}", exit_code);
+            std::process::exit(exit_code as i32);
+        }
+        Err(e @ elevation::ElevationError::Declined) => {
+            error!("❌ {}", e);
+            exit_with(ExitReason::ElevationDeclined);
+        }
+        Err(e) => {
+            error!("❌ {}", e);
+            exit_with(ExitReason::Failed);
+        }
+    }
+}
+
+/// Why a profile didn't load: it doesn't exist, or it's broken.
+pub fn profile_load_failure(name: &str) -> ExitReason {
+    if profile::profiles_dir()
+        .join(format!("{}.toml", name))
+        .is_file()
+    {
+        ExitReason::Failed
+    } else {
+        ExitReason::NotFound
+    }
+}
+
+/// Asks a yes/no question on the console; anything but yes, including no
+/// console to answer from, is no.
+pub fn confirm(question: &str) -> bool {
+    use std::io::Write;
+    eprint!("{} [y/N] ", question);
+    let _ = std::io::stderr().flush();
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes")
+}
+
+/// The AUMID of the installed app whose name best matches `name`, exiting
+/// when none does.
+pub fn resolve_app_name(name: &str) -> String {
+    match resolve::resolve(name) {
+        Ok(Some(found)) => {
+            info!("🔎 '{}' is {} ({})", name, found.app.name, found.app.aumid);
+            found.app.aumid
+        }
+        Ok(None) => {
+            error!(
+                "❌ No installed app matches '{}'. Try librarylink list-apps --search <term>",
+                name
+            );
+            exit_with(ExitReason::NotFound);
+        }
+        Err(e) => {
+            error!("❌ Error finding applications: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
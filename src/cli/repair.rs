@@ -0,0 +1,86 @@
+//! `config repair` and `package repair`.
+
+use librarylink::report::{error, info};
+use librarylink::{package, repair};
+use serde_json::json;
+
+pub fn config(args: &[String]) {
+    let usage = format!("Usage: {} config repair [--json]", args[0]);
+    if args.get(2).map(String::as_str) != Some("repair") {
+        error!("Error: config requires a subcommand ('repair')");
+        info!("{}", usage);
+        return;
+    }
+    let mut as_json = false;
+    for arg in &args[3..] {
+        match arg.as_str() {
+            "--json" => as_json = true,
+            _ => {
+                error!("Error: Unknown option '{}'", arg);
+                info!("{}", usage);
+                return;
+            }
+        }
+    }
+    let repairs = repair::repair();
+    if as_json {
+        println!("{}", json!(repairs));
+    } else if repairs.is_empty() {
+        info!("✅ librarylink has no files to check yet");
+    } else {
+        for file in &repairs {
+            let icon = match file.action {
+                repair::RepairAction::Healthy => "✅",
+                repair::RepairAction::Unrecoverable { .. } => "❌",
+                _ => "🔧",
+            };
+            info!("{} {}: {}", icon, file.path.display(), file.action);
+        }
+    }
+    if repairs
+        .iter()
+        .any(|file| matches!(file.action, repair::RepairAction::Unrecoverable { .. }))
+    {
+        std::process::exit(1);
+    }
+}
+
+pub fn package(args: &[String]) {
+    let usage = format!("Usage: {} package repair <package-family-name>", args[0]);
+    match (args.get(2).map(String::as_str), args.get(3)) {
+        (Some("repair"), Some(name)) => repair_package(name),
+        (Some("repair"), None) => {
+            error!("Error: package repair requires a package family name");
+            info!("{}", usage);
+        }
+        _ => {
+            error!("Error: package requires the 'repair' action");
+            info!("{}", usage);
+        }
+    }
+}
+
+fn repair_package(name: &str) {
+    // An AUMID names its package's family too
+    let family_name = package::family_name(name).unwrap_or(name);
+    info!("🔧 Re-registering {} for the current user...", family_name);
+    match package::repair(family_name) {
+        Ok(package::Repair::Reregistered { full_name }) => {
+            info!("✅ Re-registered {} from its manifest", full_name);
+        }
+        Ok(package::Repair::Registered { install }) => {
+            let was = match install {
+                package::MachineInstall::Provisioned => "provisioned for all users",
+                package::MachineInstall::OtherUser => "installed for another user",
+            };
+            info!(
+                "✅ Registered {} for the current user; it was {}",
+                family_name, was
+            );
+        }
+        Err(e) => {
+            error!("❌ Could not re-register {}: {}", family_name, e);
+            std::process::exit(1);
+        }
+    }
+}
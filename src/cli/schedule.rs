@@ -0,0 +1,232 @@
+//! `bench`, `schedule` and `queue`.
+
+use librarylink::profile::Profile;
+use librarylink::report::{error, info};
+use librarylink::{bench, clock, daemon, session, system};
+
+pub fn bench(args: &[String]) {
+    let usage = format!(
+        "Usage: {} bench <profile> --duration <minutes> [--interval <seconds>] [--presentmon <path>] [--results-dir <dir>]",
+        args[0]
+    );
+    let Some(name) = args.get(2).filter(|name| !name.starts_with("--")) else {
+        error!("Error: bench requires a profile name");
+        info!("{}", usage);
+        return;
+    };
+    let mut duration = None;
+    let mut interval = None;
+    let mut presentmon = None;
+    let mut results_dir = None;
+
+    let mut i = 3;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--duration" => match args.get(i + 1).map(|value| value.parse::<f64>()) {
+                Some(Ok(minutes)) if minutes > 0.0 => {
+                    duration = Some(std::time::Duration::from_secs_f64(minutes * 60.0));
+                    i += 2;
+                }
+                _ => {
+                    error!("Error: --duration requires a positive number of minutes");
+                    info!("{}", usage);
+                    return;
+                }
+            },
+            "--interval" => match args.get(i + 1).map(|value| value.parse::<f64>()) {
+                Some(Ok(seconds)) if seconds > 0.0 => {
+                    interval = Some(std::time::Duration::from_secs_f64(seconds));
+                    i += 2;
+                }
+                _ => {
+                    error!("Error: --interval requires a positive number of seconds");
+                    info!("{}", usage);
+                    return;
+                }
+            },
+            "--presentmon" => {
+                if let Some(path) = args.get(i + 1) {
+                    presentmon = Some(std::path::PathBuf::from(path));
+                    i += 2;
+                } else {
+                    error!("Error: --presentmon requires the path to PresentMon");
+                    info!("{}", usage);
+                    return;
+                }
+            }
+            "--results-dir" => {
+                if let Some(dir) = args.get(i + 1) {
+                    results_dir = Some(std::path::PathBuf::from(dir));
+                    i += 2;
+                } else {
+                    error!("Error: --results-dir requires a directory");
+                    info!("{}", usage);
+                    return;
+                }
+            }
+            _ => {
+                error!("Error: Unknown option '{}'", args[i]);
+                info!("{}", usage);
+                return;
+            }
+        }
+    }
+
+    let Some(duration) = duration else {
+        error!("Error: bench requires --duration");
+        info!("{}", usage);
+        return;
+    };
+    let mut options = bench::BenchOptions::new(duration);
+    if let Some(interval) = interval {
+        options.interval = interval;
+    }
+    options.presentmon = presentmon;
+    if let Some(results_dir) = results_dir {
+        options.results_dir = results_dir;
+    }
+    run_benchmark(name, &options);
+}
+
+pub fn schedule(args: &[String]) {
+    let usage = format!(
+        "Usage: {} schedule <profile> [--at <time>] [--priority <n>]",
+        args[0]
+    );
+    let Some(name) = args.get(2).filter(|name| !name.starts_with("--")) else {
+        error!("Error: schedule requires a profile name");
+        info!("{}", usage);
+        return;
+    };
+    let mut at = None;
+    let mut priority = 0;
+
+    let mut i = 3;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--at" => match args.get(i + 1).map(|time| clock::parse_time(time)) {
+                Some(Ok(time)) => {
+                    at = Some(time);
+                    i += 2;
+                }
+                Some(Err(message)) => {
+                    error!("Error: {}", message);
+                    return;
+                }
+                None => {
+                    error!("Error: --at requires a time");
+                    info!("{}", usage);
+                    return;
+                }
+            },
+            "--priority" => match args.get(i + 1).map(|value| value.parse::<i32>()) {
+                Some(Ok(value)) => {
+                    priority = value;
+                    i += 2;
+                }
+                _ => {
+                    error!("Error: --priority requires an integer");
+                    info!("{}", usage);
+                    return;
+                }
+            },
+            _ => {
+                error!("Error: Unknown option '{}'", args[i]);
+                info!("{}", usage);
+                return;
+            }
+        }
+    }
+
+    schedule_profile(name, at, priority);
+}
+
+pub fn queue(args: &[String]) {
+    match args.get(2).map(String::as_str) {
+        None => show_queue(),
+        Some("--cancel") => match args.get(3).map(|id| id.parse::<u64>()) {
+            Some(Ok(id)) => cancel_queued_launch(id),
+            _ => {
+                error!("Error: --cancel requires a queued launch ID");
+                info!("Usage: {} queue [--cancel <id>]", args[0]);
+            }
+        },
+        Some(option) => {
+            error!("Error: Unknown option '{}'", option);
+            info!("Usage: {} queue [--cancel <id>]", args[0]);
+        }
+    }
+}
+
+fn run_benchmark(name: &str, options: &bench::BenchOptions) {
+    system::install_exit_handler();
+
+    let profile = match Profile::load(name) {
+        Ok(profile) => profile,
+        Err(e) => {
+            error!("❌ {}", e);
+            return;
+        }
+    };
+
+    info!(
+        "⏱️ Benchmarking '{}' for {}",
+        name,
+        session::format_duration(options.duration.as_secs())
+    );
+    info!("");
+    if let Err(e) = bench::run(&profile, options) {
+        error!("❌ Benchmark of '{}' failed: {}", name, e);
+        std::process::exit(1);
+    }
+}
+
+fn schedule_profile(name: &str, at: Option<u64>, priority: i32) {
+    match daemon::schedule(name, at, priority) {
+        Ok(launch) => info!(
+            "📥 Queued launch {} of '{}' for {}",
+            launch.id,
+            launch.profile,
+            clock::format_local(launch.at)
+        ),
+        Err(e) => error!("❌ Could not schedule '{}': {}", name, e),
+    }
+}
+
+fn show_queue() {
+    let launches = match daemon::queued_launches() {
+        Ok(launches) => launches,
+        Err(e) => {
+            error!("❌ {}", e);
+            return;
+        }
+    };
+
+    if launches.is_empty() {
+        info!("No launches queued.");
+        return;
+    }
+    let now = session::unix_now();
+    for launch in launches {
+        let status = if launch.at <= now {
+            "waiting"
+        } else {
+            "scheduled"
+        };
+        info!(
+            "  {:>4}  {:<20}  {}  priority {:<3}  {}",
+            launch.id,
+            launch.profile,
+            clock::format_local(launch.at),
+            launch.priority,
+            status
+        );
+    }
+}
+
+fn cancel_queued_launch(id: u64) {
+    match daemon::cancel_queued(id) {
+        Ok(()) => info!("🗑️ Cancelled queued launch {}", id),
+        Err(e) => error!("❌ {}", e),
+    }
+}
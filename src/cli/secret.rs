@@ -0,0 +1,69 @@
+//! `secret`.
+
+use librarylink::report::{error, info, warning};
+use librarylink::secret;
+
+/// `secret set|get|remove|list`. `set` without a value reads it from stdin,
+/// so it stays out of the shell's history.
+pub fn secret(args: &[String]) {
+    let usage = format!(
+        "Usage: {} secret set <name> [<value>] | get <name> | remove <name> | list",
+        args[0]
+    );
+    let subcommand = args.get(2).map(String::as_str);
+    if subcommand == Some("list") {
+        for name in secret::names() {
+            println!("{}", name);
+        }
+        return;
+    }
+    let Some(name) = args.get(3) else {
+        error!("Error: secret requires a subcommand and a secret name");
+        info!("{}", usage);
+        return;
+    };
+    let result = match subcommand {
+        Some("set") => {
+            let value = match args.get(4) {
+                Some(value) => value.clone(),
+                None => {
+                    let mut value = String::new();
+                    if let Err(e) = std::io::stdin().read_line(&mut value) {
+                        error!("❌ Could not read the secret from stdin: {}", e);
+                        std::process::exit(1);
+                    }
+                    value.trim_end_matches(['\r', '\n']).to_string()
+                }
+            };
+            if value.is_empty() {
+                error!("Error: secret set requires a value");
+                info!("{}", usage);
+                return;
+            }
+            secret::set(name, &value).map(|()| info!("🔒 Stored secret '{}'", name))
+        }
+        Some("get") => secret::get(name).map(|value| match value {
+            Some(value) => println!("{}", value),
+            None => {
+                error!("❌ No secret named '{}'", name);
+                std::process::exit(1);
+            }
+        }),
+        Some("remove") => secret::remove(name).map(|removed| {
+            if removed {
+                info!("✅ Removed secret '{}'", name);
+            } else {
+                warning!("⚠️ No secret named '{}'", name);
+            }
+        }),
+        _ => {
+            error!("Error: secret requires 'set', 'get', 'remove' or 'list'");
+            info!("{}", usage);
+            return;
+        }
+    };
+    if let Err(e) = result {
+        error!("❌ {}", e);
+        std::process::exit(1);
+    }
+}
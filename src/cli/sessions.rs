@@ -0,0 +1,165 @@
+//! `sessions`, `resume` and `control`.
+
+use librarylink::process::get_process_info;
+use librarylink::report::{self, error, info};
+use librarylink::session::{self, SessionState};
+use librarylink::{clock, control, monitor};
+use serde_json::{Value, json};
+
+pub fn sessions(args: &[String]) {
+    let usage = format!("Usage: {} sessions [--all] [--json]", args[0]);
+    let mut all = false;
+    let mut as_json = false;
+    for arg in &args[2..] {
+        match arg.as_str() {
+            "--all" => all = true,
+            "--json" => as_json = true,
+            _ => {
+                error!("Error: Unknown option '{}'", arg);
+                info!("{}", usage);
+                return;
+            }
+        }
+    }
+    show_sessions(all, as_json);
+}
+
+pub fn resume(args: &[String]) {
+    resume_session(args.get(2).map(String::as_str));
+}
+
+pub fn control(args: &[String]) {
+    let usage = format!(
+        "Usage: {} control suspend|resume|kill|status <session-id>",
+        args[0]
+    );
+    match (args.get(2).map(String::as_str), args.get(3)) {
+        (Some(action), Some(session_id)) if control::ACTIONS.contains(&action) => {
+            control_session(action, session_id);
+        }
+        (Some(action), _) if !control::ACTIONS.contains(&action) => {
+            error!("Error: Unknown control action '{}'", action);
+            info!("{}", usage);
+        }
+        _ => {
+            error!("Error: control requires an action and a session ID");
+            info!("{}", usage);
+        }
+    }
+}
+
+fn control_session(action: &str, session_id: &str) {
+    let result = match control::request(session_id, action) {
+        Ok(result) => result,
+        Err(e) => {
+            error!("❌ {}", e);
+            std::process::exit(1);
+        }
+    };
+    let processes = result["processes"].as_u64().unwrap_or(0);
+    match action {
+        "suspend" if processes == 0 => info!("⏸️ Session {} is already suspended", session_id),
+        "suspend" => info!(
+            "⏸️ Suspended session {} ({} process(es))",
+            session_id, processes
+        ),
+        "resume" if processes == 0 => info!("▶️ Session {} isn't suspended", session_id),
+        "resume" => info!(
+            "▶️ Resumed session {} ({} process(es))",
+            session_id, processes
+        ),
+        "kill" => info!("⏹️ Terminated the game of session {}", session_id),
+        _ => {
+            let state = if result["suspended"].as_bool() == Some(true) {
+                "suspended"
+            } else {
+                "running"
+            };
+            info!(
+                "Session {}: process {}, {}",
+                session_id, result["trackedPid"], state
+            );
+        }
+    }
+}
+
+fn show_sessions(all: bool, as_json: bool) {
+    let sessions: Vec<(SessionState, bool)> = session::list_sessions(all)
+        .into_iter()
+        .map(|state| {
+            let orphaned = !state.owner_alive();
+            (state, orphaned)
+        })
+        .collect();
+
+    if as_json {
+        let entries: Vec<Value> = sessions
+            .iter()
+            .map(|(state, orphaned)| {
+                json!({
+                    "id": state.id,
+                    "aumid": state.aumid,
+                    "owner_pid": state.owner_pid,
+                    "tracked_pid": state.tracked_pid,
+                    "started_at": state.started_at,
+                    "exclusive": state.exclusive,
+                    "orphaned": orphaned,
+                })
+            })
+            .collect();
+        println!("{}", json!(entries));
+        return;
+    }
+    if sessions.is_empty() {
+        info!("No games are being monitored.");
+        return;
+    }
+
+    let now = session::unix_now();
+    for (state, orphaned) in &sessions {
+        let tracked = match get_process_info(state.tracked_pid) {
+            Some(info) if state.tracked_alive() => {
+                format!("tracking {} ({})", state.tracked_pid, info.name)
+            }
+            _ => format!("process {} has exited", state.tracked_pid),
+        };
+        let owner = if *orphaned {
+            "orphaned; see 'resume'".to_string()
+        } else {
+            format!("librarylink {}", state.owner_pid)
+        };
+        let exclusive = if state.exclusive { ", exclusive" } else { "" };
+        info!("  {}  {}", state.id, state.aumid);
+        info!(
+            "      {}, {}, started {} ({} ago){}",
+            owner,
+            tracked,
+            clock::format_local(state.started_at),
+            session::format_duration(now.saturating_sub(state.started_at)),
+            exclusive
+        );
+    }
+}
+
+fn resume_session(session_id: Option<&str>) {
+    // Only sessions whose librarylink process has gone away can be resumed
+    let mut orphaned = session::orphaned_sessions(session_id);
+
+    if orphaned.is_empty() {
+        match session_id {
+            Some(id) => info!("No orphaned session found with ID '{}'", id),
+            None => info!("No orphaned sessions to resume."),
+        }
+        return;
+    }
+
+    if orphaned.len() > 1 {
+        info!("Multiple orphaned sessions found. Specify one to resume:");
+        for state in &orphaned {
+            info!("  {}  {}", state.id, state.aumid);
+        }
+        return;
+    }
+
+    monitor::resume(orphaned.remove(0), report::reporter());
+}
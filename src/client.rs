@@ -0,0 +1,37 @@
+//! The display mode of the client a stream is going to, as Sunshine (and
+//! Apollo) pass it in the environment of the commands they run for an app.
+//! A launch can match the host's resolution and the game's window to it
+//! without writing each client's mode into the profile.
+
+use std::env;
+
+pub const WIDTH_VARIABLE: &str = "SUNSHINE_CLIENT_WIDTH";
+pub const HEIGHT_VARIABLE: &str = "SUNSHINE_CLIENT_HEIGHT";
+pub const FPS_VARIABLE: &str = "SUNSHINE_CLIENT_FPS";
+
+/// The mode the client asked the stream for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientDisplay {
+    pub width: u32,
+    pub height: u32,
+    /// Frames per second, when the host passed it
+    pub fps: Option<u32>,
+}
+
+impl ClientDisplay {
+    /// The client's mode, or `None` when librarylink wasn't started for a
+    /// stream. Both dimensions must be set and non-zero.
+    pub fn from_environment() -> Option<ClientDisplay> {
+        let width = read(WIDTH_VARIABLE).filter(|&width| width > 0)?;
+        let height = read(HEIGHT_VARIABLE).filter(|&height| height > 0)?;
+        Some(ClientDisplay {
+            width,
+            height,
+            fps: read(FPS_VARIABLE).filter(|&fps| fps > 0),
+        })
+    }
+}
+
+fn read(variable: &str) -> Option<u32> {
+    env::var(variable).ok()?.trim().parse().ok()
+}
@@ -0,0 +1,151 @@
+//! Local wall-clock times for scheduling, converted with the system's time
+//! zone rules so daylight saving is handled.
+
+use windows::Win32::Foundation::{FILETIME, SYSTEMTIME};
+use windows::Win32::System::Time::{
+    FileTimeToSystemTime, SystemTimeToFileTime, SystemTimeToTzSpecificLocalTime,
+    TzSpecificLocalTimeToSystemTime,
+};
+
+use crate::session::unix_now;
+
+/// Seconds between the FILETIME epoch (1601) and the Unix epoch.
+const FILETIME_UNIX_OFFSET: u64 = 11_644_473_600;
+const FILETIME_TICKS_PER_SECOND: u64 = 10_000_000;
+
+fn utc_from_unix(unix: u64) -> Option<SYSTEMTIME> {
+    let ticks = (unix + FILETIME_UNIX_OFFSET) * FILETIME_TICKS_PER_SECOND;
+    let filetime = FILETIME {
+        dwLowDateTime: ticks as u32,
+        dwHighDateTime: (ticks >> 32) as u32,
+    };
+    let mut utc = SYSTEMTIME::default();
+    unsafe { FileTimeToSystemTime(&filetime, &mut utc).ok()? };
+    Some(utc)
+}
+
+fn local_from_unix(unix: u64) -> Option<SYSTEMTIME> {
+    let utc = utc_from_unix(unix)?;
+    let mut local = SYSTEMTIME::default();
+    unsafe { SystemTimeToTzSpecificLocalTime(None, &utc, &mut local).ok()? };
+    Some(local)
+}
+
+fn unix_from_local(local: &SYSTEMTIME) -> Option<u64> {
+    let mut utc = SYSTEMTIME::default();
+    let mut filetime = FILETIME::default();
+    unsafe {
+        TzSpecificLocalTimeToSystemTime(None, local, &mut utc).ok()?;
+        SystemTimeToFileTime(&utc, &mut filetime).ok()?;
+    }
+    let ticks = ((filetime.dwHighDateTime as u64) << 32) | filetime.dwLowDateTime as u64;
+    (ticks / FILETIME_TICKS_PER_SECOND).checked_sub(FILETIME_UNIX_OFFSET)
+}
+
+fn parse_clock(clock: &str) -> Option<(u16, u16)> {
+    let (hour, minute) = clock.split_once(':')?;
+    let hour: u16 = hour.parse().ok().filter(|&hour| hour < 24)?;
+    let minute: u16 = minute.parse().ok().filter(|&minute| minute < 60)?;
+    Some((hour, minute))
+}
+
+/// Parses a time to run at into a Unix timestamp:
+///
+/// - `HH:MM`: the next time the local clock shows it (today or tomorrow)
+/// - `YYYY-MM-DD HH:MM` (or with a `T`): a local date and time
+/// - `+<n>s`, `+<n>m`, `+<n>h`: relative to now
+pub fn parse_time(spec: &str) -> Result<u64, String> {
+    let invalid = || {
+        format!(
+            "Invalid time '{}'; expected HH:MM, YYYY-MM-DD HH:MM or +<n>(s|m|h)",
+            spec
+        )
+    };
+    let spec = spec.trim();
+
+    if let Some(relative) = spec.strip_prefix('+') {
+        let (amount, unit) = relative.split_at(relative.len().saturating_sub(1));
+        let amount: u64 = amount.parse().map_err(|_| invalid())?;
+        let seconds = match unit {
+            "s" => amount,
+            "m" => amount * 60,
+            "h" => amount * 3600,
+            _ => return Err(invalid()),
+        };
+        return Ok(unix_now() + seconds);
+    }
+
+    if let Some((hour, minute)) = parse_clock(spec) {
+        let now = unix_now();
+        // Today's occurrence, or tomorrow's if it has passed
+        for day in [now, now + 86_400] {
+            let local = local_from_unix(day).ok_or_else(invalid)?;
+            let candidate = unix_from_local(&SYSTEMTIME {
+                wHour: hour,
+                wMinute: minute,
+                wSecond: 0,
+                wMilliseconds: 0,
+                ..local
+            })
+            .ok_or_else(invalid)?;
+            if candidate > now {
+                return Ok(candidate);
+            }
+        }
+        return Err(invalid());
+    }
+
+    let (date, clock) = spec.split_once(['T', ' ']).ok_or_else(invalid)?;
+    let mut parts = date.splitn(3, '-').map(|part| part.parse::<u16>().ok());
+    let (Some(Some(year)), Some(Some(month)), Some(Some(day))) =
+        (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(invalid());
+    };
+    let (hour, minute) = parse_clock(clock.trim()).ok_or_else(invalid)?;
+    unix_from_local(&SYSTEMTIME {
+        wYear: year,
+        wMonth: month,
+        wDay: day,
+        wHour: hour,
+        wMinute: minute,
+        ..Default::default()
+    })
+    .ok_or_else(invalid)
+}
+
+/// The current local time as `YYYY-MM-DD HH:MM:SS.mmm`, for log lines.
+pub fn local_timestamp() -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    match local_from_unix(now.as_secs()) {
+        Some(local) => format!(
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:03}",
+            local.wYear,
+            local.wMonth,
+            local.wDay,
+            local.wHour,
+            local.wMinute,
+            local.wSecond,
+            now.subsec_millis()
+        ),
+        None => format!("{}.{:03}", now.as_secs(), now.subsec_millis()),
+    }
+}
+
+/// The UTC year of a Unix timestamp, as release dates are given.
+pub fn utc_year(unix: u64) -> Option<u16> {
+    utc_from_unix(unix).map(|utc| utc.wYear)
+}
+
+/// Formats a Unix timestamp as local `YYYY-MM-DD HH:MM`.
+pub fn format_local(unix: u64) -> String {
+    match local_from_unix(unix) {
+        Some(local) => format!(
+            "{:04}-{:02}-{:02} {:02}:{:02}",
+            local.wYear, local.wMonth, local.wDay, local.wHour, local.wMinute
+        ),
+        None => unix.to_string(),
+    }
+}
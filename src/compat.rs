@@ -0,0 +1,188 @@
+//! The Windows build librarylink is running on, and the features that need a
+//! newer one. On older builds those features are turned off with a message
+//! saying why, instead of failing partway with an HRESULT from an API that
+//! doesn't exist there. `doctor` lists them.
+
+use std::fmt;
+use std::sync::OnceLock;
+
+use crate::registry::{self, RegistryData};
+
+const CURRENT_VERSION_KEY: &str = r"HKLM\SOFTWARE\Microsoft\Windows NT\CurrentVersion";
+
+/// The first Windows 11 build.
+const WINDOWS_11_BUILD: u32 = 22000;
+
+/// The running Windows build, as the registry reports it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+pub struct WindowsVersion {
+    pub build: u32,
+    /// The update revision, the part after the build's `.`
+    pub revision: u32,
+    /// The release name, such as `1809` or `23H2`
+    #[cfg_attr(
+        feature = "json",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub release: Option<String>,
+}
+
+impl WindowsVersion {
+    /// The running build, read once. `None` if the registry can't say, in
+    /// which case every feature is tried.
+    pub fn current() -> Option<&'static WindowsVersion> {
+        static VERSION: OnceLock<Option<WindowsVersion>> = OnceLock::new();
+        VERSION.get_or_init(read_version).as_ref()
+    }
+}
+
+impl fmt::Display for WindowsVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let product = if self.build >= WINDOWS_11_BUILD {
+            "Windows 11"
+        } else {
+            "Windows 10"
+        };
+        match &self.release {
+            Some(release) => write!(
+                f,
+                "{} {} (build {}.{})",
+                product, release, self.build, self.revision
+            ),
+            None => write!(f, "{} (build {}.{})", product, self.build, self.revision),
+        }
+    }
+}
+
+fn read_version() -> Option<WindowsVersion> {
+    let string = |name: &str| match registry::read(CURRENT_VERSION_KEY, name) {
+        Ok(Some(RegistryData::String(value))) => Some(value).filter(|value| !value.is_empty()),
+        _ => None,
+    };
+    let build = string("CurrentBuildNumber")?.parse().ok()?;
+    let revision = match registry::read(CURRENT_VERSION_KEY, "UBR") {
+        Ok(Some(RegistryData::Dword(revision))) => revision,
+        _ => 0,
+    };
+    // DisplayVersion replaced ReleaseId, which stopped at 2009, with 20H2
+    let release = string("DisplayVersion").or_else(|| string("ReleaseId"));
+    Some(WindowsVersion {
+        build,
+        revision,
+        release,
+    })
+}
+
+/// A feature that needs a newer Windows build than librarylink itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+#[cfg_attr(feature = "json", serde(rename_all = "snake_case"))]
+pub enum Capability {
+    /// `AppInfo.GetFromAppUserModelId`, for an app's details by AUMID
+    AppInfoLookup,
+    /// `AppListEntry.AppUserModelId`, for listing apps with their AUMIDs
+    AppListAumids,
+    /// Per-monitor DPI awareness v2, for placing windows in physical pixels
+    PerMonitorDpi,
+    /// Advanced color display info, for monitors' HDR state
+    HdrState,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 4] = [
+        Capability::AppInfoLookup,
+        Capability::AppListAumids,
+        Capability::PerMonitorDpi,
+        Capability::HdrState,
+    ];
+
+    /// The first build with the feature.
+    pub fn min_build(self) -> u32 {
+        match self {
+            Capability::AppInfoLookup => 19041,
+            Capability::AppListAumids | Capability::HdrState => 16299,
+            Capability::PerMonitorDpi => 15063,
+        }
+    }
+
+    /// The release that build shipped in.
+    fn min_release(self) -> &'static str {
+        match self {
+            Capability::AppInfoLookup => "Windows 10 2004",
+            Capability::AppListAumids | Capability::HdrState => "Windows 10 1709",
+            Capability::PerMonitorDpi => "Windows 10 1703",
+        }
+    }
+
+    /// What librarylink does without the feature.
+    pub fn fallback(self) -> &'static str {
+        match self {
+            Capability::AppInfoLookup => {
+                "app details come from the package instead, and display names aren't shown"
+            }
+            Capability::AppListAumids => "list-apps, list-games and pick aren't available",
+            Capability::PerMonitorDpi => "windows aren't placed on monitors",
+            Capability::HdrState => "list-monitors doesn't show HDR",
+        }
+    }
+
+    /// Whether the running build has the feature. An unknown build is
+    /// assumed to.
+    pub fn is_supported(self) -> bool {
+        WindowsVersion::current().is_none_or(|version| version.build >= self.min_build())
+    }
+
+    /// Why the feature is off, for the error or warning of whatever needed it.
+    pub fn unsupported_message(self) -> String {
+        let running = WindowsVersion::current()
+            .map(|version| version.to_string())
+            .unwrap_or_else(|| "an unknown build".to_string());
+        format!(
+            "{} needs {} (build {}) or later, and this is {}",
+            self,
+            self.min_release(),
+            self.min_build(),
+            running
+        )
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Capability::AppInfoLookup => "Looking up apps by AUMID",
+            Capability::AppListAumids => "Listing apps with their AUMIDs",
+            Capability::PerMonitorDpi => "Window placement",
+            Capability::HdrState => "Reading monitors' HDR state",
+        })
+    }
+}
+
+/// Whether one feature is available, as `doctor --json` reports it.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct CapabilityStatus {
+    pub capability: Capability,
+    pub supported: bool,
+    pub min_build: u32,
+    /// What happens instead, when it isn't available
+    #[cfg_attr(feature = "json", serde(skip_serializing_if = "Option::is_none"))]
+    pub fallback: Option<&'static str>,
+}
+
+/// Every feature's availability on the running build.
+pub fn capabilities() -> Vec<CapabilityStatus> {
+    Capability::ALL
+        .iter()
+        .map(|&capability| {
+            let supported = capability.is_supported();
+            CapabilityStatus {
+                capability,
+                supported,
+                min_build: capability.min_build(),
+                fallback: (!supported).then(|| capability.fallback()),
+            }
+        })
+        .collect()
+}
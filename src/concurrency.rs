@@ -0,0 +1,170 @@
+//! Arbitration between a new launch and sessions that are already running,
+//! for hosts where two games at once just means both run badly.
+//!
+//! Launches arbitrate under the `Local\librarylink-launch` mutex and hold
+//! it until their session is saved, so two of them can't both find the way
+//! clear.
+
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+use crate::named_mutex::NamedMutex;
+use crate::process::terminate_process;
+use crate::report::{self, Event};
+use crate::session::{self, SessionState};
+
+/// How often sessions are rechecked while queued or terminating.
+const RECHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// How long terminated sessions get to wind down before giving up.
+const TERMINATE_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long a queued launch waits for conflicting sessions by default.
+pub const QUEUE_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+const LAUNCH_MUTEX: &str = r"Local\librarylink-launch";
+
+/// What an exclusive launch does about sessions standing in its way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "json", serde(rename_all = "kebab-case"))]
+pub enum ConflictAction {
+    /// Wait until they have ended
+    #[default]
+    Queue,
+    /// Terminate their games
+    Terminate,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ConcurrencyPolicy {
+    /// Run only while no other session is active, and keep other launches
+    /// from starting until this one ends
+    pub exclusive: bool,
+    /// Allow launching an app that already has an active session
+    pub allow_multiple: bool,
+    pub on_conflict: ConflictAction,
+    /// How long to wait for conflicting sessions, and for other launches
+    /// to finish starting, before giving up
+    pub queue_timeout: Duration,
+}
+
+impl Default for ConcurrencyPolicy {
+    fn default() -> Self {
+        ConcurrencyPolicy {
+            exclusive: false,
+            allow_multiple: true,
+            on_conflict: ConflictAction::default(),
+            queue_timeout: QUEUE_TIMEOUT,
+        }
+    }
+}
+
+/// Held from [`arbitrate`] until the launched session has been saved;
+/// dropping it lets the next launch arbitrate. It has to be dropped on the
+/// thread that arbitrated.
+#[derive(Debug)]
+pub struct LaunchLock {
+    _mutex: NamedMutex,
+}
+
+impl LaunchLock {
+    fn acquire(timeout: Duration) -> Result<Self, Box<dyn Error>> {
+        match NamedMutex::acquire(LAUNCH_MUTEX, Some(timeout))? {
+            Some(mutex) => Ok(LaunchLock { _mutex: mutex }),
+            None => Err(format!(
+                "Another launch did not finish starting within {} seconds",
+                timeout.as_secs()
+            )
+            .into()),
+        }
+    }
+}
+
+/// Sessions whose librarylink process is still running them.
+fn active_sessions() -> Vec<SessionState> {
+    session::load_sessions()
+        .into_iter()
+        .filter(|state| state.owner_alive())
+        .collect()
+}
+
+/// Active sessions an exclusive launch must wait for or terminate: all of
+/// them for an exclusive policy, otherwise the exclusive ones.
+fn conflicting_sessions(policy: &ConcurrencyPolicy) -> Vec<SessionState> {
+    conflicts(policy, active_sessions())
+}
+
+fn conflicts(policy: &ConcurrencyPolicy, sessions: Vec<SessionState>) -> Vec<SessionState> {
+    sessions
+        .into_iter()
+        .filter(|state| policy.exclusive || state.exclusive)
+        .collect()
+}
+
+/// Whether `arbitrate` would currently wait for other sessions to end, so a
+/// scheduler can hold the launch back instead of blocking on it.
+pub fn is_blocked(policy: &ConcurrencyPolicy) -> bool {
+    policy.on_conflict == ConflictAction::Queue && !conflicting_sessions(policy).is_empty()
+}
+
+/// Waits for, terminates or refuses to launch alongside conflicting active
+/// sessions, as the policy says. Returns once `aumid` may be launched, with
+/// the lock to hold until its session is saved.
+pub fn arbitrate(aumid: &str, policy: &ConcurrencyPolicy) -> Result<LaunchLock, Box<dyn Error>> {
+    let started = Instant::now();
+    let mut reported = false;
+    loop {
+        let lock = LaunchLock::acquire(policy.queue_timeout.saturating_sub(started.elapsed()))?;
+        let sessions = active_sessions();
+        if !policy.allow_multiple
+            && let Some(running) = sessions
+                .iter()
+                .find(|state| state.aumid.eq_ignore_ascii_case(aumid))
+        {
+            return Err(format!("{} is already running in session {}", aumid, running.id).into());
+        }
+        let pending = conflicts(policy, sessions);
+        if pending.is_empty() {
+            return Ok(lock);
+        }
+        // Other launches may go ahead while this one waits
+        drop(lock);
+
+        if !reported {
+            report::emit(Event::SessionConflict {
+                sessions: pending.iter().map(|state| state.id.clone()).collect(),
+                action: policy.on_conflict,
+            });
+            reported = true;
+        }
+        match policy.on_conflict {
+            ConflictAction::Queue => {
+                if started.elapsed() >= policy.queue_timeout {
+                    return Err(format!(
+                        "Session {} was still active after waiting {} seconds for it",
+                        pending[0].id,
+                        policy.queue_timeout.as_secs()
+                    )
+                    .into());
+                }
+            }
+            ConflictAction::Terminate => {
+                if started.elapsed() >= TERMINATE_TIMEOUT {
+                    return Err(format!(
+                        "Session {} did not end within {} seconds of being terminated",
+                        pending[0].id,
+                        TERMINATE_TIMEOUT.as_secs()
+                    )
+                    .into());
+                }
+                // Monitors may latch onto a replacement process, so keep
+                // terminating whatever each session tracks until it ends
+                for state in &pending {
+                    if state.tracked_alive() {
+                        let _ = terminate_process(state.tracked_pid);
+                    }
+                }
+            }
+        }
+        std::thread::sleep(RECHECK_INTERVAL);
+    }
+}
@@ -0,0 +1,320 @@
+//! The per-user config file, `%APPDATA%\librarylink\config.toml`: defaults
+//! for monitoring and logging, aliases for AUMIDs, and overrides for single
+//! apps, so Steam shortcuts don't have to repeat long flag strings:
+//!
+//! ```toml
+//! [monitor]
+//! scope = "package"
+//! grace_period = 10
+//!
+//! [logging]
+//! verbosity = "quiet"
+//! log_file = 'D:\Logs\librarylink.log'
+//!
+//! [aliases]
+//! forza = "Microsoft.624F8B84B80_8wekyb3d8bbwe!Forzahorizon5"
+//!
+//! [apps."Microsoft.624F8B84B80_8wekyb3d8bbwe!Forzahorizon5"]
+//! handoff_timeout = 60
+//! ```
+//!
+//! Flags and profile keys take precedence over the file. It's only read by
+//! the CLI, which installs it with [`install`]; library users get empty
+//! defaults unless they do the same.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::atomic_file;
+use crate::launch::LaunchOptions;
+use crate::matcher::MonitorScope;
+use crate::paths;
+use crate::toml;
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Defaults for every launch
+    #[serde(default)]
+    pub monitor: MonitorDefaults,
+    #[serde(default)]
+    pub logging: LoggingDefaults,
+    /// Short names that stand for AUMIDs
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
+    /// Defaults for one app, by AUMID, over those in `[monitor]`
+    #[serde(default)]
+    pub apps: BTreeMap<String, MonitorDefaults>,
+}
+
+/// Monitoring options, as the flags of the same names set them.
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MonitorDefaults {
+    #[serde(default)]
+    pub scope: Option<MonitorScope>,
+    /// Milliseconds
+    #[serde(default)]
+    pub poll_interval: Option<u64>,
+    /// Seconds, as are the rest of the durations
+    #[serde(default)]
+    pub min_lifetime: Option<f64>,
+    #[serde(default)]
+    pub follow_restarts: Option<f64>,
+    #[serde(default)]
+    pub activation_timeout: Option<f64>,
+    #[serde(default)]
+    pub handoff_timeout: Option<f64>,
+    #[serde(default)]
+    pub grace_period: Option<f64>,
+    #[serde(default)]
+    pub close_launchers: Option<bool>,
+    #[serde(default)]
+    pub kill_on_exit: Option<bool>,
+    #[serde(default)]
+    pub ignore_processes: Vec<String>,
+    #[serde(default)]
+    pub allow_processes: Vec<String>,
+}
+
+/// Logging options, as the global flags set them.
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LoggingDefaults {
+    /// `human`, `json` or `silent`, as `--output`
+    #[serde(default)]
+    pub output: Option<String>,
+    /// `quiet`, `normal` or `verbose`
+    #[serde(default)]
+    pub verbosity: Option<String>,
+    /// As `--log-file`
+    #[serde(default)]
+    pub log_file: Option<PathBuf>,
+    /// As `--sink`, each added to every invocation
+    #[serde(default)]
+    pub sinks: Vec<String>,
+}
+
+pub fn config_path() -> PathBuf {
+    paths::data_dir().join("config.toml")
+}
+
+impl Config {
+    /// Reads the config file. A missing file is an empty config.
+    pub fn load() -> Result<Config, String> {
+        let path = config_path();
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(e) => return Err(format!("Could not read {}: {}", path.display(), e)),
+        };
+        let document = toml::parse(&contents).map_err(|e| format!("{}: {}", path.display(), e))?;
+        serde_json::from_value(document).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// The AUMID `name` stands for, if it's an alias.
+    pub fn alias(&self, name: &str) -> Option<&str> {
+        self.aliases
+            .iter()
+            .find(|(alias, _)| alias.eq_ignore_ascii_case(name))
+            .map(|(_, aumid)| aumid.as_str())
+    }
+
+    /// The defaults for launching `aumid`: its own over `[monitor]`'s.
+    pub fn defaults_for(&self, aumid: &str) -> MonitorDefaults {
+        match self
+            .apps
+            .iter()
+            .find(|(app, _)| app.eq_ignore_ascii_case(aumid))
+        {
+            Some((_, app)) => self.monitor.overridden_by(app),
+            None => self.monitor.clone(),
+        }
+    }
+}
+
+/// Sets `name` to stand for `aumid` in the config file's `[aliases]`, or
+/// removes it when `aumid` is `None`, leaving the rest of the file as it
+/// was. Returns whether the alias was already there.
+pub fn set_alias(name: &str, aumid: Option<&str>) -> Result<bool, Box<dyn std::error::Error>> {
+    let path = config_path();
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("Could not read {}: {}", path.display(), e).into()),
+    };
+    let mut lines: Vec<String> = contents.lines().map(str::to_string).collect();
+    let entry = aumid.map(|aumid| format!("{} = {}", toml_key(name), toml_string(aumid)));
+
+    let header = lines.iter().position(|line| {
+        line.split('#')
+            .next()
+            .is_some_and(|header| header.trim() == "[aliases]")
+    });
+    let existed = match header {
+        Some(header) => {
+            let end = lines[header + 1..]
+                .iter()
+                .position(|line| line.trim_start().starts_with('['))
+                .map_or(lines.len(), |offset| header + 1 + offset);
+            let existing = (header + 1..end).find(|&index| {
+                lines[index]
+                    .split_once('=')
+                    .is_some_and(|(key, _)| unquote(key.trim()).eq_ignore_ascii_case(name))
+            });
+            match (existing, entry) {
+                (Some(index), Some(entry)) => lines[index] = entry,
+                (Some(index), None) => {
+                    lines.remove(index);
+                }
+                (None, Some(entry)) => {
+                    // After the table's last entry, before any blank lines
+                    let last = (header..end)
+                        .rev()
+                        .find(|&index| !lines[index].trim().is_empty())
+                        .unwrap_or(header);
+                    lines.insert(last + 1, entry);
+                }
+                (None, None) => {}
+            }
+            existing.is_some()
+        }
+        None => {
+            if let Some(entry) = entry {
+                if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+                    lines.push(String::new());
+                }
+                lines.push("[aliases]".to_string());
+                lines.push(entry);
+            }
+            false
+        }
+    };
+
+    let mut updated = lines.join("\n");
+    updated.push('\n');
+    // Never leave behind a file the next command can't read
+    let document = toml::parse(&updated).map_err(|e| format!("{}: {}", path.display(), e))?;
+    serde_json::from_value::<Config>(document).map_err(|e| format!("{}: {}", path.display(), e))?;
+    atomic_file::write_with_backup(&path, updated)?;
+    Ok(existed)
+}
+
+/// A key as TOML needs it written: bare when it can be, quoted otherwise.
+fn toml_key(key: &str) -> String {
+    let bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if bare {
+        key.to_string()
+    } else {
+        toml_string(key)
+    }
+}
+
+fn toml_string(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// A key as written, without the quotes around it.
+fn unquote(key: &str) -> &str {
+    key.strip_prefix('"')
+        .and_then(|key| key.strip_suffix('"'))
+        .or_else(|| {
+            key.strip_prefix('\'')
+                .and_then(|key| key.strip_suffix('\''))
+        })
+        .unwrap_or(key)
+}
+
+/// Makes `config` the one [`current`] returns. Only the first call has an
+/// effect.
+pub fn install(config: Config) {
+    let _ = CONFIG.set(config);
+}
+
+/// The installed config, or an empty one.
+pub fn current() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+fn seconds(value: Option<f64>) -> Option<Duration> {
+    value.and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+}
+
+impl MonitorDefaults {
+    /// These defaults with `other`'s set values in place of them. Lists are
+    /// joined.
+    pub fn overridden_by(&self, other: &MonitorDefaults) -> MonitorDefaults {
+        MonitorDefaults {
+            scope: other.scope.or(self.scope),
+            poll_interval: other.poll_interval.or(self.poll_interval),
+            min_lifetime: other.min_lifetime.or(self.min_lifetime),
+            follow_restarts: other.follow_restarts.or(self.follow_restarts),
+            activation_timeout: other.activation_timeout.or(self.activation_timeout),
+            handoff_timeout: other.handoff_timeout.or(self.handoff_timeout),
+            grace_period: other.grace_period.or(self.grace_period),
+            close_launchers: other.close_launchers.or(self.close_launchers),
+            kill_on_exit: other.kill_on_exit.or(self.kill_on_exit),
+            ignore_processes: [&self.ignore_processes[..], &other.ignore_processes[..]].concat(),
+            allow_processes: [&self.allow_processes[..], &other.allow_processes[..]].concat(),
+        }
+    }
+
+    /// `options` with these defaults, for the caller's own settings to go
+    /// over.
+    pub fn apply(&self, mut options: LaunchOptions) -> LaunchOptions {
+        if let Some(scope) = self.scope {
+            options = options.scope(scope);
+        }
+        if let Some(interval) = self.poll_interval.filter(|&millis| millis > 0) {
+            options = options.poll_interval(Duration::from_millis(interval));
+        }
+        if let Some(lifetime) = seconds(self.min_lifetime) {
+            options = options.min_lifetime(lifetime);
+        }
+        if let Some(window) = seconds(self.follow_restarts) {
+            options = options.follow_restarts(window);
+        }
+        if let Some(period) = seconds(self.grace_period) {
+            options = options.grace_period(period);
+        }
+        if let Some(timeout) = seconds(self.activation_timeout) {
+            options = options.activation_timeout(timeout);
+        }
+        if let Some(timeout) = seconds(self.handoff_timeout) {
+            options = options.handoff_timeout(timeout);
+        }
+        if let Some(close_launchers) = self.close_launchers {
+            options = options.close_launchers(close_launchers);
+        }
+        if let Some(kill_on_exit) = self.kill_on_exit {
+            options = options.kill_on_exit(kill_on_exit);
+        }
+        for name in &self.ignore_processes {
+            options = options.ignore_process(name);
+        }
+        for name in &self.allow_processes {
+            options = options.allow_process(name);
+        }
+        options
+    }
+}
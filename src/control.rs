@@ -0,0 +1,339 @@
+//! `librarylink control`: suspends, resumes or kills the game of a running
+//! session from another prompt or a script. Each monitoring session listens
+//! on `\\.\pipe\librarylink-session-<id>` until it ends, with the same
+//! line-delimited JSON-RPC 2.0 framing as `rpc` and one request per
+//! connection. The pipe's default security lets only the user who started
+//! the session, administrators and SYSTEM send requests. Requests are
+//! recorded in the `audit` log with the client process.
+//!
+//! The server does blocking pipe I/O on its own thread. A client that
+//! connects without sending a request has it cancelled after
+//! [`READ_TIMEOUT`], and the end of the session cancels whatever it's
+//! waiting on, so neither can keep the session from ending.
+
+use serde_json::{Value, json};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Write};
+use std::os::windows::io::{FromRawHandle, RawHandle};
+use std::sync::atomic::{AtomicIsize, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::thread::{self, ScopedJoinHandle};
+use std::time::Duration;
+use windows::Win32::Foundation::{
+    CloseHandle, ERROR_PIPE_BUSY, ERROR_PIPE_CONNECTED, HANDLE, INVALID_HANDLE_VALUE,
+};
+use windows::Win32::Storage::FileSystem::{FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_DUPLEX};
+use windows::Win32::System::IO::CancelSynchronousIo;
+use windows::Win32::System::Pipes::{
+    ConnectNamedPipe, CreateNamedPipeW, GetNamedPipeClientProcessId, PIPE_READMODE_BYTE,
+    PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE, PIPE_WAIT,
+};
+use windows::Win32::System::Threading::{GetCurrentThreadId, OpenThread, THREAD_TERMINATE};
+use windows::core::HSTRING;
+
+use crate::audit::{self, Actor, Outcome};
+use crate::error::WinError;
+use crate::monitor::{MonitorControl, MonitorState};
+use crate::report::{Event, Reporter};
+use crate::rpc::{self, METHOD_NOT_FOUND, OPERATION_FAILED, PARSE_ERROR, RpcError};
+
+const PIPE_BUFFER_SIZE: u32 = 4 * 1024;
+
+/// How often [`Server::stop`] cancels the server's pipe I/O until it has
+/// seen the session end.
+const STOP_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long a client has to send its request once connected.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The methods a session answers, as the `control` command takes them.
+pub const ACTIONS: [&str; 4] = ["suspend", "resume", "kill", "status"];
+
+pub fn pipe_name(session_id: &str) -> String {
+    format!(r"\\.\pipe\librarylink-session-{}", session_id)
+}
+
+/// A session's control server, shared by the thread that runs
+/// [`serve`](Self::serve) and the one that [`stop`](Self::stop)s it.
+#[derive(Debug, Default)]
+pub(crate) struct Server {
+    /// The serving thread's handle, so its blocking pipe I/O can be
+    /// cancelled; 0 until it starts
+    thread: AtomicIsize,
+}
+
+impl Server {
+    /// Answers control requests for the session `session_id` until it ends
+    /// and [`stop`](Self::stop) is called. Meant for a thread of its own.
+    pub(crate) fn serve(
+        &self,
+        session_id: &str,
+        control: &MonitorControl,
+        reporter: &dyn Reporter,
+    ) {
+        // Without a handle to cancel its I/O through, a stuck client could
+        // keep the session from ending
+        match unsafe { OpenThread(THREAD_TERMINATE, false, GetCurrentThreadId()) } {
+            Ok(thread) => self.thread.store(thread.0 as isize, Ordering::SeqCst),
+            Err(e) => {
+                reporter.event(&Event::Error {
+                    context: "The session can't be controlled with 'librarylink control'"
+                        .to_string(),
+                    error: WinError::new("OpenThread", &e),
+                });
+                return;
+            }
+        }
+        serve(self, session_id, control, reporter);
+    }
+
+    /// Ends the server once the session has ended, cancelling the wait for
+    /// a client or a request until it returns.
+    pub(crate) fn stop(&self, server: &ScopedJoinHandle<'_, ()>) {
+        while !server.is_finished() {
+            self.cancel_io();
+            thread::sleep(STOP_INTERVAL);
+        }
+    }
+
+    fn cancel_io(&self) {
+        let thread = self.thread.load(Ordering::SeqCst);
+        if thread != 0 {
+            // Fails harmlessly when no I/O is pending
+            unsafe {
+                let _ = CancelSynchronousIo(HANDLE(thread as *mut _));
+            }
+        }
+    }
+
+    /// Runs `io` on this thread, cancelling it if it's still blocked after
+    /// `timeout`.
+    fn with_deadline<T>(&self, timeout: Duration, io: impl FnOnce() -> T) -> T {
+        let done = (Mutex::new(false), Condvar::new());
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                let (finished, wake) = &done;
+                let finished = finished.lock().unwrap_or_else(|p| p.into_inner());
+                let (finished, _) = wake
+                    .wait_timeout_while(finished, timeout, |finished| !*finished)
+                    .unwrap_or_else(|p| p.into_inner());
+                if !*finished {
+                    self.cancel_io();
+                }
+            });
+            let result = io();
+            *done.0.lock().unwrap_or_else(|p| p.into_inner()) = true;
+            done.1.notify_one();
+            result
+        })
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let thread = *self.thread.get_mut();
+        if thread != 0 {
+            unsafe {
+                let _ = CloseHandle(HANDLE(thread as *mut _));
+            }
+        }
+    }
+}
+
+fn serve(server: &Server, session_id: &str, control: &MonitorControl, reporter: &dyn Reporter) {
+    let mut first = true;
+    loop {
+        let pipe = match create_pipe(session_id, first) {
+            Ok(pipe) => pipe,
+            Err(error) => {
+                reporter.event(&Event::Error {
+                    context: "The session can't be controlled with 'librarylink control'"
+                        .to_string(),
+                    error,
+                });
+                return;
+            }
+        };
+        first = false;
+
+        let connected = unsafe { ConnectNamedPipe(pipe, None) };
+        if let Err(e) = connected
+            && e.code() != ERROR_PIPE_CONNECTED.to_hresult()
+        {
+            unsafe {
+                let _ = CloseHandle(pipe);
+            }
+            // Cancelled by `stop`
+            if matches!(control.state(), Some(MonitorState::Ended { .. })) {
+                return;
+            }
+            reporter.event(&Event::Error {
+                context: "The session stopped listening for 'librarylink control'".to_string(),
+                error: WinError::new("ConnectNamedPipe", &e),
+            });
+            return;
+        }
+
+        let mut client_pid = 0u32;
+        let actor = unsafe { GetNamedPipeClientProcessId(pipe, &mut client_pid) }
+            .ok()
+            .map(|()| Actor::process(client_pid))
+            .unwrap_or_default();
+        let connection = unsafe { File::from_raw_handle(pipe.0 as RawHandle) };
+        // Dropping the connection unanswered tells the client the session ended
+        if matches!(control.state(), Some(MonitorState::Ended { .. })) {
+            return;
+        }
+        answer(server, connection, session_id, &actor, control, reporter);
+    }
+}
+
+fn create_pipe(session_id: &str, first: bool) -> Result<HANDLE, WinError> {
+    let mut open_mode = PIPE_ACCESS_DUPLEX;
+    if first {
+        open_mode |= FILE_FLAG_FIRST_PIPE_INSTANCE;
+    }
+    let pipe = unsafe {
+        CreateNamedPipeW(
+            &HSTRING::from(pipe_name(session_id)),
+            open_mode,
+            PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+            1,
+            PIPE_BUFFER_SIZE,
+            PIPE_BUFFER_SIZE,
+            0,
+            None,
+        )
+    };
+    if pipe == INVALID_HANDLE_VALUE {
+        return Err(WinError::from_last_error("CreateNamedPipeW"));
+    }
+    Ok(pipe)
+}
+
+fn answer(
+    server: &Server,
+    connection: File,
+    session_id: &str,
+    actor: &Actor,
+    control: &MonitorControl,
+    reporter: &dyn Reporter,
+) {
+    let Ok(mut writer) = connection.try_clone() else {
+        return;
+    };
+    let mut line = String::new();
+    let read = server.with_deadline(READ_TIMEOUT, || {
+        BufReader::new(connection).read_line(&mut line)
+    });
+    if read.is_err() {
+        return;
+    }
+
+    let response = match serde_json::from_str::<Value>(&line) {
+        Ok(request) => {
+            let id = request.get("id").cloned().unwrap_or(Value::Null);
+            let result = handle(&request, session_id, actor, control, reporter);
+            rpc::response(&id, result)
+        }
+        Err(e) => rpc::response(&Value::Null, Err(RpcError::new(PARSE_ERROR, e.to_string()))),
+    };
+    let _ = writeln!(writer, "{}", response);
+}
+
+fn handle(
+    request: &Value,
+    session_id: &str,
+    actor: &Actor,
+    control: &MonitorControl,
+    reporter: &dyn Reporter,
+) -> Result<Value, RpcError> {
+    let method = request.get("method").and_then(Value::as_str).unwrap_or("");
+    let result = match method {
+        "status" => {
+            return Ok(json!({
+                "trackedPid": control.tracked_pid(),
+                "suspended": control.is_suspended(),
+            }));
+        }
+        "suspend" => control.suspend().map(|processes| {
+            if processes > 0 {
+                reporter.event(&Event::Suspended { processes });
+            }
+            json!({ "processes": processes })
+        }),
+        "resume" => control.resume().map(|processes| {
+            if processes > 0 {
+                reporter.event(&Event::Resumed { processes });
+            }
+            json!({ "processes": processes })
+        }),
+        "kill" => control.kill_tree().map(|()| json!({})),
+        _ => {
+            return Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Unknown method '{}'", method),
+            ));
+        }
+    };
+
+    let error = result.as_ref().err().map(|e| e.to_string());
+    let outcome = match &error {
+        Some(message) => Outcome::Failed(message),
+        None => Outcome::Ok,
+    };
+    let target = format!("session {}", session_id);
+    audit::record("control", actor, method, &target, outcome);
+    result.map_err(|e| RpcError::new(OPERATION_FAILED, e.to_string()))
+}
+
+/// Sends `method` to the running session `session_id` and returns its
+/// result.
+pub fn request(session_id: &str, method: &str) -> Result<Value, Box<dyn Error>> {
+    let mut connection = None;
+    // The only instance is busy while another request is answered
+    for _ in 0..20 {
+        match fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(pipe_name(session_id))
+        {
+            Ok(pipe) => {
+                connection = Some(pipe);
+                break;
+            }
+            Err(e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY.0 as i32) => {
+                thread::sleep(Duration::from_millis(100));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(format!(
+                    "No running session has the ID '{}'; see 'librarylink sessions'",
+                    session_id
+                )
+                .into());
+            }
+            Err(e) => return Err(format!("Could not connect to the session: {}", e).into()),
+        }
+    }
+    let mut connection = connection.ok_or("The session is busy; try again")?;
+
+    let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method });
+    writeln!(connection, "{}", request)?;
+    let mut line = String::new();
+    BufReader::new(connection).read_line(&mut line)?;
+    if line.trim().is_empty() {
+        return Err(format!("Session '{}' has ended", session_id).into());
+    }
+
+    let mut response: Value = serde_json::from_str(&line)
+        .map_err(|e| format!("Invalid response from the session: {}", e))?;
+    if let Some(error) = response.get("error") {
+        let message = error
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or("The session reported an error");
+        return Err(message.into());
+    }
+    Ok(response["result"].take())
+}
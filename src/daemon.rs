@@ -0,0 +1,541 @@
+//! A background service that queues profile launches, holding them back
+//! while an exclusive session is active and starting scheduled ones on time.
+//!
+//! Clients talk to it over the `\\.\pipe\librarylink` named pipe with the
+//! same line-delimited JSON-RPC 2.0 framing as `rpc`. The queue is persisted
+//! to `%APPDATA%\librarylink\queue.json`, so scheduled launches survive a
+//! restart of the daemon.
+//!
+//! Once a token has been added (see `token`), each connection has to
+//! `authenticate` before anything but `ping`, and is held to its token's
+//! scope from then on. Launches and cancellations, including refused ones,
+//! are recorded in the `audit` log with the token and client process.
+
+use serde_json::{Value, json};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Write};
+use std::os::windows::io::{FromRawHandle, RawHandle};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+use windows::Win32::Foundation::{ERROR_PIPE_BUSY, ERROR_PIPE_CONNECTED, INVALID_HANDLE_VALUE};
+use windows::Win32::Storage::FileSystem::{FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_DUPLEX};
+use windows::Win32::System::Pipes::{
+    ConnectNamedPipe, CreateNamedPipeW, GetNamedPipeClientProcessId, PIPE_READMODE_BYTE,
+    PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+};
+use windows::core::HSTRING;
+
+use crate::atomic_file;
+use crate::audit::{self, Actor, Outcome};
+use crate::clock;
+use crate::concurrency;
+use crate::error::WinError;
+use crate::paths;
+use crate::profile::Profile;
+use crate::report::{self, info, warning};
+use crate::rpc::{self, FORBIDDEN, METHOD_NOT_FOUND, OPERATION_FAILED, PARSE_ERROR, RpcError};
+use crate::secret;
+use crate::session::unix_now;
+use crate::token::{self, Scope};
+
+pub const PIPE_NAME: &str = r"\\.\pipe\librarylink";
+/// Where clients find the token they authenticate with.
+pub const TOKEN_VARIABLE: &str = "LIBRARYLINK_DAEMON_TOKEN";
+pub const TOKEN_SECRET_NAME: &str = "daemon_token";
+
+/// How often the queue is checked for launches that are due and unblocked.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+const PIPE_BUFFER_SIZE: u32 = 64 * 1024;
+
+/// A profile launch waiting in the queue.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct QueuedLaunch {
+    pub id: u64,
+    pub profile: String,
+    /// Higher priorities launch first once several are due
+    pub priority: i32,
+    /// Unix time the launch is due; queued-now launches are due immediately
+    pub at: u64,
+    pub queued_at: u64,
+    /// The name of the token that queued it, when the daemon requires one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queued_by: Option<String>,
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct Queue {
+    next_id: u64,
+    launches: Vec<QueuedLaunch>,
+}
+
+pub(crate) fn queue_path() -> PathBuf {
+    paths::data_dir().join("queue.json")
+}
+
+impl Queue {
+    fn load() -> Queue {
+        fs::read_to_string(queue_path())
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) {
+        let result = atomic_file::write_with_backup(
+            &queue_path(),
+            serde_json::to_string_pretty(self).unwrap_or_default(),
+        );
+        if let Err(e) = result {
+            warning!("⚠️ Could not save the launch queue: {}", e);
+        }
+    }
+
+    /// The due launch that goes next: highest priority, then the one due
+    /// first, then the one queued first.
+    fn next_due(&self, now: u64) -> Option<&QueuedLaunch> {
+        self.launches
+            .iter()
+            .filter(|launch| launch.at <= now)
+            .min_by_key(|launch| (-(launch.priority as i64), launch.at, launch.id))
+    }
+}
+
+#[derive(Clone, Default)]
+struct SharedQueue {
+    inner: Arc<Mutex<Queue>>,
+}
+
+impl SharedQueue {
+    fn lock(&self) -> MutexGuard<'_, Queue> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Runs the daemon until the process is stopped.
+pub fn serve() -> Result<(), Box<dyn std::error::Error>> {
+    // Claim the pipe name first, so a second daemon fails before touching the queue
+    let mut pipe = create_pipe(true).map_err(|e| {
+        format!(
+            "Could not create {} (is the daemon already running?): {}",
+            PIPE_NAME, e
+        )
+    })?;
+
+    let queue = SharedQueue {
+        inner: Arc::new(Mutex::new(Queue::load())),
+    };
+    let pending = queue.lock().launches.len();
+    info!("🛰️ librarylink daemon listening on {}", PIPE_NAME);
+    if pending > 0 {
+        info!("📋 {} launch(es) restored from the queue", pending);
+    }
+    if let Err(e) = token::list() {
+        warning!("⚠️ {}; every client will be refused until it's fixed", e);
+    }
+    info!("");
+
+    let worker_queue = queue.clone();
+    thread::spawn(move || run_queue(&worker_queue));
+
+    loop {
+        let connected = unsafe { ConnectNamedPipe(pipe, None) };
+        if let Err(e) = connected
+            && e.code() != ERROR_PIPE_CONNECTED.to_hresult()
+        {
+            warning!("⚠️ {}", WinError::new("ConnectNamedPipe", &e));
+            continue;
+        }
+
+        let mut client_pid = 0u32;
+        let client_pid = unsafe { GetNamedPipeClientProcessId(pipe, &mut client_pid) }
+            .ok()
+            .map(|()| client_pid);
+
+        // Serve the client on its own thread and listen on a fresh instance
+        let connection = unsafe { File::from_raw_handle(pipe.0 as RawHandle) };
+        let connection_queue = queue.clone();
+        thread::spawn(move || serve_client(connection, client_pid, &connection_queue));
+        pipe = create_pipe(false)?;
+    }
+}
+
+fn create_pipe(first: bool) -> Result<windows::Win32::Foundation::HANDLE, WinError> {
+    let mut open_mode = PIPE_ACCESS_DUPLEX;
+    if first {
+        open_mode |= FILE_FLAG_FIRST_PIPE_INSTANCE;
+    }
+    let pipe = unsafe {
+        CreateNamedPipeW(
+            &HSTRING::from(PIPE_NAME),
+            open_mode,
+            PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+            PIPE_UNLIMITED_INSTANCES,
+            PIPE_BUFFER_SIZE,
+            PIPE_BUFFER_SIZE,
+            0,
+            None,
+        )
+    };
+    if pipe == INVALID_HANDLE_VALUE {
+        return Err(WinError::from_last_error("CreateNamedPipeW"));
+    }
+    Ok(pipe)
+}
+
+/// Who a connection has authenticated as.
+struct Client {
+    /// The token's name, or `None` while no tokens are required
+    name: Option<String>,
+    /// `None` until a required token has been presented
+    scope: Option<Scope>,
+    /// The client's process, for the audit log
+    process: Actor,
+}
+
+impl Client {
+    fn actor(&self) -> Actor {
+        Actor {
+            token: self.name.clone(),
+            ..self.process.clone()
+        }
+    }
+
+    fn audit(&self, action: &str, target: &str, result: &Result<Value, RpcError>) {
+        let outcome = match result {
+            Ok(_) => Outcome::Ok,
+            Err(error) if error.code == FORBIDDEN => Outcome::Denied(&error.message),
+            Err(error) => Outcome::Failed(&error.message),
+        };
+        audit::record("daemon", &self.actor(), action, target, outcome);
+    }
+
+    fn require(&self, scope: Scope) -> Result<(), RpcError> {
+        match self.scope {
+            Some(granted) if granted >= scope => Ok(()),
+            Some(granted) => Err(RpcError::new(
+                FORBIDDEN,
+                format!(
+                    "This needs the '{}' scope; token '{}' has '{}'",
+                    scope,
+                    self.name.as_deref().unwrap_or(""),
+                    granted
+                ),
+            )),
+            None => Err(RpcError::new(
+                FORBIDDEN,
+                "This daemon requires a token; call 'authenticate' first",
+            )),
+        }
+    }
+}
+
+/// Answers one client's requests until it disconnects.
+fn serve_client(connection: File, pid: Option<u32>, queue: &SharedQueue) {
+    let Ok(mut writer) = connection.try_clone() else {
+        return;
+    };
+    let mut client = Client {
+        name: None,
+        scope: (!token::required()).then_some(Scope::Admin),
+        process: pid.map(Actor::process).unwrap_or_default(),
+    };
+    for line in BufReader::new(connection).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(request) => {
+                let id = request.get("id").cloned().unwrap_or(Value::Null);
+                rpc::response(&id, handle(&request, queue, &mut client))
+            }
+            Err(e) => rpc::response(&Value::Null, Err(RpcError::new(PARSE_ERROR, e.to_string()))),
+        };
+        if writeln!(writer, "{}", response).is_err() {
+            break;
+        }
+    }
+}
+
+fn handle(request: &Value, queue: &SharedQueue, client: &mut Client) -> Result<Value, RpcError> {
+    let method = request.get("method").and_then(Value::as_str).unwrap_or("");
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+    match method {
+        "authenticate" => {
+            let result = authenticate(&params, client);
+            if result.is_err() {
+                client.audit("authenticate", "", &result);
+            }
+            result
+        }
+        "enqueue" => {
+            let result = client
+                .require(Scope::Launch)
+                .and_then(|()| enqueue(&params, queue, client));
+            let profile = params.get("profile").and_then(Value::as_str);
+            client.audit("enqueue", profile.unwrap_or(""), &result);
+            result
+        }
+        "queue" => {
+            client.require(Scope::List)?;
+            Ok(json!(queue.lock().launches))
+        }
+        "cancel" => {
+            let result = client
+                .require(Scope::Launch)
+                .and_then(|()| cancel(&params, queue, client));
+            let target = match &result {
+                Ok(launch) => format!(
+                    "launch {} of '{}'",
+                    launch["id"],
+                    launch["profile"].as_str().unwrap_or("")
+                ),
+                Err(_) => format!("launch {}", params.get("id").unwrap_or(&Value::Null)),
+            };
+            client.audit("cancel", &target, &result);
+            result
+        }
+        "ping" => Ok(json!({ "pid": std::process::id() })),
+        _ => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("Unknown method '{}'", method),
+        )),
+    }
+}
+
+fn authenticate(params: &Value, client: &mut Client) -> Result<Value, RpcError> {
+    let presented = rpc::string_param(params, "token")?
+        .ok_or_else(|| RpcError::invalid_params("'token' is required"))?;
+    if !token::required() {
+        return Ok(json!({ "scope": Scope::Admin }));
+    }
+    let entry = token::verify(presented).map_err(|message| RpcError::new(FORBIDDEN, message))?;
+    client.name = Some(entry.name.clone());
+    client.scope = Some(entry.scope);
+    Ok(json!({ "name": entry.name, "scope": entry.scope }))
+}
+
+fn enqueue(params: &Value, queue: &SharedQueue, client: &Client) -> Result<Value, RpcError> {
+    let profile = rpc::string_param(params, "profile")?
+        .ok_or_else(|| RpcError::invalid_params("'profile' is required"))?;
+    // Refuse broken profiles now rather than when they come up
+    Profile::load(profile).map_err(|e| RpcError::new(OPERATION_FAILED, e.to_string()))?;
+
+    let priority = match params.get("priority") {
+        None | Some(Value::Null) => 0,
+        Some(priority) => priority
+            .as_i64()
+            .and_then(|priority| i32::try_from(priority).ok())
+            .ok_or_else(|| RpcError::invalid_params("'priority' must be an integer"))?,
+    };
+    let now = unix_now();
+    let at = match params.get("at") {
+        None | Some(Value::Null) => now,
+        Some(at) => at
+            .as_u64()
+            .ok_or_else(|| RpcError::invalid_params("'at' must be a Unix timestamp"))?,
+    };
+
+    let mut queue = queue.lock();
+    queue.next_id += 1;
+    let launch = QueuedLaunch {
+        id: queue.next_id,
+        profile: profile.to_string(),
+        priority,
+        at,
+        queued_at: now,
+        queued_by: client.name.clone(),
+    };
+    queue.launches.push(launch.clone());
+    queue.save();
+
+    info!(
+        "📥 Queued launch {} of '{}' for {} (priority {})",
+        launch.id,
+        launch.profile,
+        clock::format_local(launch.at),
+        launch.priority
+    );
+    Ok(json!(launch))
+}
+
+fn cancel(params: &Value, queue: &SharedQueue, client: &Client) -> Result<Value, RpcError> {
+    let id = params
+        .get("id")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| RpcError::invalid_params("'id' must be a queued launch ID"))?;
+
+    let mut queue = queue.lock();
+    let position = queue
+        .launches
+        .iter()
+        .position(|launch| launch.id == id)
+        .ok_or_else(|| RpcError::invalid_params(format!("No queued launch with ID {}", id)))?;
+    // Launch tokens may only take back what they queued themselves
+    if queue.launches[position].queued_by != client.name {
+        client.require(Scope::Admin)?;
+    }
+    let launch = queue.launches.remove(position);
+    queue.save();
+
+    info!("🗑️ Cancelled launch {} of '{}'", launch.id, launch.profile);
+    Ok(json!(launch))
+}
+
+/// Starts queued launches as they become due, one at a time in priority
+/// order. A launch that would have to wait for an exclusive session stays
+/// queued, and so does everything behind it.
+fn run_queue(queue: &SharedQueue) {
+    loop {
+        thread::sleep(CHECK_INTERVAL);
+
+        let Some(next) = queue.lock().next_due(unix_now()).cloned() else {
+            continue;
+        };
+        let profile = match Profile::load(&next.profile) {
+            Ok(profile) => profile,
+            Err(e) => {
+                warning!("⚠️ Dropping queued launch {}: {}", next.id, e);
+                remove(queue, next.id);
+                continue;
+            }
+        };
+        if concurrency::is_blocked(&profile.concurrency()) {
+            continue;
+        }
+
+        remove(queue, next.id);
+        info!(
+            "🚀 Starting queued launch {} of '{}'",
+            next.id, next.profile
+        );
+        let actor = Actor {
+            token: next.queued_by.clone(),
+            ..Actor::default()
+        };
+        let launched = profile.launch();
+        let error = launched.as_ref().err().map(|e| e.to_string());
+        let outcome = match &error {
+            None => Outcome::Ok,
+            Some(error) => Outcome::Failed(error),
+        };
+        audit::record("daemon", &actor, "launch", &next.profile, outcome);
+        match launched {
+            Ok(mut session) => {
+                thread::spawn(move || {
+                    for event in session.events() {
+                        report::emit(event);
+                    }
+                    session.wait();
+                });
+            }
+            Err(e) => warning!(
+                "⚠️ Queued launch {} of '{}' failed: {}",
+                next.id,
+                next.profile,
+                e
+            ),
+        }
+    }
+}
+
+fn remove(queue: &SharedQueue, id: u64) {
+    let mut queue = queue.lock();
+    queue.launches.retain(|launch| launch.id != id);
+    queue.save();
+}
+
+/// Sends one request to the running daemon and returns its result,
+/// authenticating first with the token in `LIBRARYLINK_DAEMON_TOKEN` or the
+/// `daemon_token` secret if there is one.
+pub fn request(method: &str, params: Value) -> Result<Value, Box<dyn std::error::Error>> {
+    let mut connection = None;
+    // Every instance can briefly be busy while the daemon opens the next one
+    for _ in 0..20 {
+        match fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(PIPE_NAME)
+        {
+            Ok(pipe) => {
+                connection = Some(pipe);
+                break;
+            }
+            Err(e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY.0 as i32) => {
+                thread::sleep(Duration::from_millis(100));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(
+                    "The librarylink daemon is not running; start it with 'librarylink daemon'"
+                        .into(),
+                );
+            }
+            Err(e) => return Err(format!("Could not connect to the daemon: {}", e).into()),
+        }
+    }
+    let mut connection = connection.ok_or("The daemon is busy; try again")?;
+    let mut reader = BufReader::new(connection.try_clone()?);
+
+    if let Some(token) = secret::setting(TOKEN_VARIABLE, TOKEN_SECRET_NAME) {
+        call(
+            &mut connection,
+            &mut reader,
+            "authenticate",
+            json!({ "token": token }),
+        )?;
+    }
+    call(&mut connection, &mut reader, method, params)
+}
+
+/// Asks the running daemon to launch profile `name` at `at`, a Unix time,
+/// or as soon as it can.
+pub fn schedule(
+    name: &str,
+    at: Option<u64>,
+    priority: i32,
+) -> Result<QueuedLaunch, Box<dyn std::error::Error>> {
+    let params = json!({ "profile": name, "priority": priority, "at": at });
+    Ok(serde_json::from_value(request("enqueue", params)?)?)
+}
+
+/// The running daemon's queued launches.
+pub fn queued_launches() -> Result<Vec<QueuedLaunch>, Box<dyn std::error::Error>> {
+    Ok(serde_json::from_value(request("queue", Value::Null)?)?)
+}
+
+/// Takes launch `id` off the running daemon's queue.
+pub fn cancel_queued(id: u64) -> Result<(), Box<dyn std::error::Error>> {
+    request("cancel", json!({ "id": id }))?;
+    Ok(())
+}
+
+fn call(
+    connection: &mut File,
+    reader: &mut BufReader<File>,
+    method: &str,
+    params: Value,
+) -> Result<Value, Box<dyn std::error::Error>> {
+    let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+    writeln!(connection, "{}", request)?;
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+
+    let mut response: Value = serde_json::from_str(&line)
+        .map_err(|e| format!("Invalid response from the daemon: {}", e))?;
+    if let Some(error) = response.get("error") {
+        let message = error
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or("The daemon reported an error");
+        return Err(message.into());
+    }
+    Ok(response["result"].take())
+}
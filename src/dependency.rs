@@ -0,0 +1,208 @@
+//! Store clients and other programs a game needs running first, such as the
+//! EA app for EA Play titles on Game Pass.
+
+use std::time::{Duration, Instant};
+
+use crate::launch;
+use crate::poll::PollSettings;
+use crate::probe::Probe;
+use crate::process::{
+    find_processes_by_name, get_process_creation_time, is_same_process_running, join_arguments,
+    spawn_process, terminate_process,
+};
+use crate::report::{self, Event, warning};
+
+/// A program started before the game and, if librarylink started it, stopped
+/// after the game exits.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "json", derive(serde::Deserialize))]
+#[cfg_attr(feature = "json", serde(deny_unknown_fields))]
+pub struct Dependency {
+    /// Shown in progress output
+    pub name: String,
+    /// Executable to start, for desktop clients such as the EA app
+    #[cfg_attr(feature = "json", serde(default))]
+    pub path: Option<String>,
+    /// AUMID to activate instead, for Store clients such as the Xbox app
+    #[cfg_attr(feature = "json", serde(default))]
+    pub aumid: Option<String>,
+    #[cfg_attr(feature = "json", serde(default))]
+    pub args: Vec<String>,
+    /// Executable name of the client's main process. Its presence means the
+    /// client is running, and stopping the client terminates it.
+    pub process: String,
+    /// Start the window minimized without taking focus (`path` only)
+    #[cfg_attr(feature = "json", serde(default = "default_minimized"))]
+    pub minimized: bool,
+    /// Conditions that must all hold, once `process` is running, before the
+    /// client counts as ready
+    #[cfg_attr(feature = "json", serde(default))]
+    pub ready: Vec<Probe>,
+    /// Seconds to wait for `process` and the probes
+    #[cfg_attr(feature = "json", serde(default = "default_ready_timeout"))]
+    pub ready_timeout: u64,
+    /// Extra seconds to wait once ready, for clients no probe can observe
+    #[cfg_attr(feature = "json", serde(default))]
+    pub ready_delay: u64,
+    /// Leave the client running after the game exits even if librarylink
+    /// started it
+    #[cfg_attr(feature = "json", serde(default))]
+    pub keep_running: bool,
+}
+
+#[cfg(feature = "json")]
+fn default_minimized() -> bool {
+    true
+}
+
+#[cfg(feature = "json")]
+fn default_ready_timeout() -> u64 {
+    60
+}
+
+/// A dependency librarylink started, to be stopped once the session ends.
+#[derive(Debug)]
+pub struct StartedDependency {
+    name: String,
+    /// The processes librarylink started or saw the client start, with
+    /// their creation times so reused PIDs are left alone
+    processes: Vec<(u32, u64)>,
+}
+
+impl StartedDependency {
+    /// Terminates the client's processes that librarylink started, leaving
+    /// any instance of it started otherwise since.
+    pub fn stop(&self) {
+        for &(process_id, created) in &self.processes {
+            if !is_same_process_running(process_id, created) {
+                continue;
+            }
+            if let Err(e) = terminate_process(process_id) {
+                warning!(
+                    "⚠️ Could not stop {} (process {}): {}",
+                    self.name,
+                    process_id,
+                    e
+                );
+            }
+        }
+        report::emit(Event::DependencyStopped {
+            name: self.name.clone(),
+        });
+    }
+}
+
+/// Starts a dependency unless it's already running and waits until it's
+/// ready. Returns `None` when it was already running, since then it isn't
+/// librarylink's to stop. Progress goes to the process-wide reporter.
+pub fn start(
+    dependency: &Dependency,
+) -> Result<Option<StartedDependency>, Box<dyn std::error::Error>> {
+    if let Some(&process_id) = find_processes_by_name(&dependency.process).first() {
+        report::emit(Event::DependencyRunning {
+            name: dependency.name.clone(),
+            pid: process_id,
+        });
+        return Ok(None);
+    }
+
+    report::emit(Event::DependencyStarting {
+        name: dependency.name.clone(),
+    });
+    let mut spawned = Vec::new();
+    match (&dependency.path, &dependency.aumid) {
+        (Some(path), None) => {
+            let process_id = spawn_process(path, &dependency.args, None, dependency.minimized)?;
+            spawned.extend(with_creation_time(process_id));
+        }
+        (None, Some(aumid)) => {
+            let arguments = (!dependency.args.is_empty()).then(|| join_arguments(&dependency.args));
+            launch::activate(aumid, arguments.as_deref())?;
+        }
+        _ => {
+            return Err(format!(
+                "Dependency '{}' needs exactly one of 'path' or 'aumid'",
+                dependency.name
+            )
+            .into());
+        }
+    }
+
+    // The client's processes are all librarylink's: none ran before
+    let started = |spawned: &[(u32, u64)]| {
+        let mut processes = spawned.to_vec();
+        processes.extend(
+            find_processes_by_name(&dependency.process)
+                .into_iter()
+                .filter_map(with_creation_time),
+        );
+        processes.sort_unstable();
+        processes.dedup();
+        StartedDependency {
+            name: dependency.name.clone(),
+            processes,
+        }
+    };
+
+    if !dependency.ready.is_empty() {
+        report::emit(Event::DependencyWaiting {
+            name: dependency.name.clone(),
+            probes: dependency.ready.iter().map(Probe::to_string).collect(),
+        });
+    }
+
+    // Launcher stubs often hand off to the real client, so wait for the
+    // client's own process rather than the one that was started
+    let timeout = Duration::from_secs(dependency.ready_timeout);
+    let polling = PollSettings::default();
+    let begun = Instant::now();
+    let process_id = loop {
+        let process_id = find_processes_by_name(&dependency.process).first().copied();
+        let pending: Vec<&Probe> = match process_id {
+            Some(_) => dependency
+                .ready
+                .iter()
+                .filter(|probe| !probe.check(&dependency.process))
+                .collect(),
+            None => Vec::new(),
+        };
+        if let Some(process_id) = process_id
+            && pending.is_empty()
+        {
+            break process_id;
+        }
+
+        if begun.elapsed() >= timeout {
+            if !dependency.keep_running {
+                started(&spawned).stop();
+            }
+            let waiting_for = match process_id {
+                None => dependency.process.clone(),
+                Some(_) => pending
+                    .iter()
+                    .map(|probe| probe.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            };
+            return Err(format!(
+                "{} was not ready within {} seconds (waiting for {})",
+                dependency.name,
+                timeout.as_secs(),
+                waiting_for
+            )
+            .into());
+        }
+        polling.sleep();
+    };
+    std::thread::sleep(Duration::from_secs(dependency.ready_delay));
+
+    report::emit(Event::DependencyReady {
+        name: dependency.name.clone(),
+        pid: process_id,
+    });
+    Ok((!dependency.keep_running).then(|| started(&spawned)))
+}
+
+fn with_creation_time(process_id: u32) -> Option<(u32, u64)> {
+    get_process_creation_time(process_id).map(|created| (process_id, created))
+}
@@ -0,0 +1,323 @@
+//! `doctor`: checks for the usual reasons Game Pass games fail to launch on
+//! a machine, as opposed to a single profile's prerequisites (see
+//! [`preflight`](crate::preflight)), and the fixes that are safe to apply
+//! automatically. Each fix is applied only when asked for.
+
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+use windows::Management::Deployment::{DeploymentOptions, PackageManager};
+use windows::Win32::Foundation::ERROR_SERVICE_DOES_NOT_EXIST;
+use windows::Win32::System::Services::{
+    CloseServiceHandle, OpenSCManagerW, OpenServiceW, QueryServiceStatus, SC_HANDLE,
+    SC_MANAGER_CONNECT, SERVICE_QUERY_STATUS, SERVICE_RUNNING, SERVICE_START,
+    SERVICE_START_PENDING, SERVICE_STATUS, SERVICE_STATUS_CURRENT_STATE, StartServiceW,
+};
+use windows::core::{HSTRING, PCWSTR};
+
+use crate::error::WinError;
+use crate::executable;
+use crate::package;
+use crate::session::{self, SessionState};
+
+/// Services Game Pass games need running, with the names shown for them.
+const SERVICES: &[(&str, &str)] = &[
+    ("GamingServices", "Gaming Services"),
+    ("GamingServicesNet", "Gaming Services networking"),
+];
+
+/// Packages Game Pass games need registered, by family name.
+const PACKAGES: &[(&str, &str)] = &[
+    ("Microsoft.WindowsStore_8wekyb3d8bbwe", "Microsoft Store"),
+    ("Microsoft.GamingServices_8wekyb3d8bbwe", "Gaming Services"),
+    ("Microsoft.GamingApp_8wekyb3d8bbwe", "Xbox app"),
+];
+
+/// How long a started service gets to report that it's running.
+const SERVICE_START_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// The outcome of one check.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Finding {
+    pub check: String,
+    pub passed: bool,
+    pub detail: String,
+    /// What would put a failed check right, when librarylink can do it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fix: Option<Fix>,
+}
+
+/// A remediation for a failed check.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "fix", rename_all = "snake_case")]
+pub enum Fix {
+    /// Start a stopped service; needs an elevated prompt
+    StartService { name: String },
+    /// Register an installed package again, as `Add-AppxPackage -Register`
+    /// with its manifest does, which repairs a broken registration without
+    /// touching the package's data
+    RegisterPackage { full_name: String },
+    /// Delete a librarylink file that can't be read, which is recreated as
+    /// needed
+    DeleteFile { path: PathBuf },
+}
+
+impl fmt::Display for Fix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fix::StartService { name } => write!(f, "start the {} service", name),
+            Fix::RegisterPackage { full_name } => write!(f, "re-register {}", full_name),
+            Fix::DeleteFile { path } => write!(f, "delete {}", path.display()),
+        }
+    }
+}
+
+impl Fix {
+    pub fn apply(&self) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            Fix::StartService { name } => start_service(name),
+            Fix::RegisterPackage { full_name } => {
+                let manager =
+                    PackageManager::new().map_err(|e| WinError::new("PackageManager::new", &e))?;
+                manager
+                    .RegisterPackageByFullNameAsync(
+                        &HSTRING::from(full_name.as_str()),
+                        None,
+                        DeploymentOptions::ForceApplicationShutdown,
+                    )
+                    .and_then(|operation| operation.get())
+                    .map_err(|e| {
+                        WinError::new("PackageManager::RegisterPackageByFullNameAsync", &e)
+                    })?;
+                Ok(())
+            }
+            Fix::DeleteFile { path } => Ok(fs::remove_file(path)?),
+        }
+    }
+}
+
+/// Runs every check.
+pub fn diagnose() -> Vec<Finding> {
+    let mut findings: Vec<Finding> = SERVICES
+        .iter()
+        .map(|(name, label)| check_service(name, label))
+        .collect();
+    findings.extend(
+        PACKAGES
+            .iter()
+            .map(|(family_name, label)| check_package(family_name, label)),
+    );
+    findings.extend(check_data_files());
+    findings
+}
+
+fn check_service(name: &str, label: &str) -> Finding {
+    let check = format!("{} service", label);
+    match service_state(name) {
+        Ok(Some(SERVICE_RUNNING)) => Finding {
+            check,
+            passed: true,
+            detail: "running".to_string(),
+            fix: None,
+        },
+        Ok(Some(SERVICE_START_PENDING)) => Finding {
+            check,
+            passed: true,
+            detail: "starting".to_string(),
+            fix: None,
+        },
+        Ok(Some(_)) => Finding {
+            check,
+            passed: false,
+            detail: format!("{} isn't running", name),
+            fix: Some(Fix::StartService {
+                name: name.to_string(),
+            }),
+        },
+        Ok(None) => Finding {
+            check,
+            passed: false,
+            detail: format!(
+                "{} isn't installed; install Gaming Services from the Microsoft Store",
+                name
+            ),
+            fix: None,
+        },
+        Err(e) => Finding {
+            check,
+            passed: false,
+            detail: format!("could not query {}: {}", name, e),
+            fix: None,
+        },
+    }
+}
+
+/// Opens the service control manager and `name`, runs `f` with the service
+/// and closes both.
+fn with_service<T>(
+    name: &str,
+    access: u32,
+    f: impl FnOnce(SC_HANDLE) -> Result<T, WinError>,
+) -> Result<Option<T>, WinError> {
+    unsafe {
+        let manager = OpenSCManagerW(PCWSTR::null(), PCWSTR::null(), SC_MANAGER_CONNECT)
+            .map_err(|e| WinError::new("OpenSCManagerW", &e))?;
+        let result = match OpenServiceW(manager, &HSTRING::from(name), access) {
+            Ok(service) => {
+                let result = f(service);
+                let _ = CloseServiceHandle(service);
+                result.map(Some)
+            }
+            Err(e) if e.code() == ERROR_SERVICE_DOES_NOT_EXIST.to_hresult() => Ok(None),
+            Err(e) => Err(WinError::new("OpenServiceW", &e)),
+        };
+        let _ = CloseServiceHandle(manager);
+        result
+    }
+}
+
+/// The service's state, or `None` if it isn't installed.
+fn service_state(name: &str) -> Result<Option<SERVICE_STATUS_CURRENT_STATE>, WinError> {
+    with_service(name, SERVICE_QUERY_STATUS, |service| {
+        let mut status = SERVICE_STATUS::default();
+        unsafe { QueryServiceStatus(service, &mut status) }
+            .map_err(|e| WinError::new("QueryServiceStatus", &e))?;
+        Ok(status.dwCurrentState)
+    })
+}
+
+/// Starts the service and waits for it to report that it's running.
+fn start_service(name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let started = with_service(
+        name,
+        SERVICE_START | SERVICE_QUERY_STATUS,
+        |service| unsafe {
+            StartServiceW(service, None).map_err(|e| WinError::new("StartServiceW", &e))
+        },
+    )?;
+    if started.is_none() {
+        return Err(format!("{} isn't installed", name).into());
+    }
+    let deadline = Instant::now() + SERVICE_START_TIMEOUT;
+    loop {
+        match service_state(name)? {
+            Some(SERVICE_RUNNING) => return Ok(()),
+            Some(SERVICE_START_PENDING) if Instant::now() < deadline => {
+                thread::sleep(Duration::from_millis(250));
+            }
+            _ => return Err(format!("{} started but didn't keep running", name).into()),
+        }
+    }
+}
+
+fn check_package(family_name: &str, label: &str) -> Finding {
+    let check = format!("{} package", label);
+    let Some(package) = package::find_packages(family_name).into_iter().next() else {
+        return Finding {
+            check,
+            passed: false,
+            detail: format!("{} isn't installed for this user", family_name),
+            fix: None,
+        };
+    };
+    let full_name = package
+        .Id()
+        .and_then(|id| id.FullName())
+        .map(|name| name.to_string())
+        .unwrap_or_else(|_| family_name.to_string());
+    let Ok(status) = package.Status() else {
+        return Finding {
+            check,
+            passed: false,
+            detail: format!("could not read the status of {}", full_name),
+            fix: None,
+        };
+    };
+    if status.VerifyIsOK().unwrap_or(false) {
+        return Finding {
+            check,
+            passed: true,
+            detail: full_name,
+            fix: None,
+        };
+    }
+
+    let flag = |name: &str, value: windows::core::Result<bool>| {
+        value.unwrap_or(false).then(|| name.to_string())
+    };
+    let problems: Vec<String> = [
+        flag("not available", status.NotAvailable()),
+        flag("offline", status.PackageOffline()),
+        flag("data offline", status.DataOffline()),
+        flag("disabled", status.Disabled()),
+        flag("needs remediation", status.NeedsRemediation()),
+        flag("license issue", status.LicenseIssue()),
+        flag("modified", status.Modified()),
+        flag("tampered", status.Tampered()),
+        flag("dependency issue", status.DependencyIssue()),
+        flag("partially staged", status.IsPartiallyStaged()),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    // Registering during a Store update would interrupt it
+    let deploying = package::is_deploying(family_name);
+    Finding {
+        check,
+        passed: false,
+        detail: if deploying {
+            format!("{} is being updated by the Microsoft Store", full_name)
+        } else if problems.is_empty() {
+            format!("{} reports a problem", full_name)
+        } else {
+            format!("{}: {}", full_name, problems.join(", "))
+        },
+        fix: (!deploying).then_some(Fix::RegisterPackage { full_name }),
+    }
+}
+
+/// librarylink's own files that fail to parse: the `wait_for` cache and
+/// session state. Unreadable ones are skipped wherever they're used, so
+/// they never break a launch, but a corrupt cache is never refreshed.
+fn check_data_files() -> Vec<Finding> {
+    let mut corrupt = Vec::new();
+    let cache = executable::cache_path();
+    if let Ok(contents) = fs::read_to_string(&cache)
+        && serde_json::from_str::<serde_json::Value>(&contents).is_err()
+    {
+        corrupt.push(cache);
+    }
+    if let Ok(entries) = fs::read_dir(session::sessions_dir()) {
+        corrupt.extend(
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+                .filter(|path| {
+                    fs::read_to_string(path).is_ok_and(|contents| {
+                        serde_json::from_str::<SessionState>(&contents).is_err()
+                    })
+                }),
+        );
+    }
+
+    if corrupt.is_empty() {
+        return vec![Finding {
+            check: "librarylink data".to_string(),
+            passed: true,
+            detail: "readable".to_string(),
+            fix: None,
+        }];
+    }
+    corrupt
+        .into_iter()
+        .map(|path| Finding {
+            check: "librarylink data".to_string(),
+            passed: false,
+            detail: format!("{} can't be read", path.display()),
+            fix: Some(Fix::DeleteFile { path }),
+        })
+        .collect()
+}
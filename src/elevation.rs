@@ -0,0 +1,145 @@
+//! `--elevated`: runs a launch from an elevated copy of librarylink, for
+//! games and tools that need administrator rights and for monitoring
+//! processes a normal prompt can't open. The copy is started through the
+//! shell's `runas` verb, which shows the UAC prompt, and inherits the
+//! command line it was given.
+//!
+//! A `runas` copy starts in System32 with a fresh environment, so the
+//! working directory and librarylink's and Sunshine's variables are passed
+//! ahead of the command line, and taken back up by [`adopt_context`].
+
+use std::env;
+use std::error::Error;
+use std::fmt;
+use windows::Win32::Foundation::{CloseHandle, ERROR_CANCELLED, WAIT_FAILED};
+use windows::Win32::System::Threading::{GetExitCodeProcess, INFINITE, WaitForSingleObject};
+use windows::Win32::UI::Shell::{SEE_MASK_NOCLOSEPROCESS, SHELLEXECUTEINFOW, ShellExecuteExW};
+use windows::Win32::UI::WindowsAndMessaging::SW_SHOWNORMAL;
+use windows::core::{HRESULT, HSTRING, PCWSTR, w};
+
+use crate::error::WinError;
+use crate::process;
+
+/// Hidden argument with the working directory the copy continues in.
+const DIRECTORY_ARGUMENT: &str = "--elevated-dir";
+
+/// Hidden argument with one `NAME=VALUE` variable the copy sets.
+const VARIABLE_ARGUMENT: &str = "--elevated-env";
+
+/// Variables the copy needs from the original's environment: librarylink's
+/// own, and the streaming client's from Sunshine.
+const VARIABLE_PREFIXES: [&str; 2] = ["LIBRARYLINK_", "SUNSHINE_"];
+
+/// Why the elevated copy didn't run.
+#[derive(Debug)]
+pub enum ElevationError {
+    /// The UAC prompt was declined or dismissed
+    Declined,
+    /// The copy couldn't be started or waited for
+    Failed(String),
+}
+
+impl fmt::Display for ElevationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ElevationError::Declined => {
+                f.write_str("The administrator prompt was declined, so nothing was launched")
+            }
+            ElevationError::Failed(message) => f.write_str(message),
+        }
+    }
+}
+
+impl Error for ElevationError {}
+
+impl From<WinError> for ElevationError {
+    fn from(error: WinError) -> Self {
+        ElevationError::Failed(format!(
+            "Could not start librarylink as administrator: {}",
+            error
+        ))
+    }
+}
+
+/// Runs librarylink elevated with `arguments` and waits for it to exit,
+/// returning its exit code. The copy continues in this working directory
+/// with this process's librarylink and Sunshine variables, and opens a
+/// console window of its own, since an elevated process can't write to
+/// this one.
+pub fn run_elevated(arguments: &[String]) -> Result<u32, ElevationError> {
+    let executable = std::env::current_exe().map_err(|e| {
+        ElevationError::Failed(format!(
+            "Could not locate the librarylink executable: {}",
+            e
+        ))
+    })?;
+    let file = HSTRING::from(executable.as_os_str());
+    let mut forwarded = Vec::new();
+    if let Ok(directory) = env::current_dir() {
+        forwarded.push(DIRECTORY_ARGUMENT.to_string());
+        forwarded.push(directory.to_string_lossy().into_owned());
+    }
+    for (name, value) in env::vars() {
+        if VARIABLE_PREFIXES
+            .iter()
+            .any(|prefix| name.to_ascii_uppercase().starts_with(prefix))
+        {
+            forwarded.push(VARIABLE_ARGUMENT.to_string());
+            forwarded.push(format!("{}={}", name, value));
+        }
+    }
+    forwarded.extend_from_slice(arguments);
+    let parameters = HSTRING::from(process::join_arguments(&forwarded));
+    let mut info = SHELLEXECUTEINFOW {
+        cbSize: size_of::<SHELLEXECUTEINFOW>() as u32,
+        fMask: SEE_MASK_NOCLOSEPROCESS,
+        lpVerb: w!("runas"),
+        lpFile: PCWSTR(file.as_ptr()),
+        lpParameters: PCWSTR(parameters.as_ptr()),
+        nShow: SW_SHOWNORMAL.0,
+        ..Default::default()
+    };
+    unsafe {
+        if let Err(e) = ShellExecuteExW(&mut info) {
+            if e.code() == HRESULT::from_win32(ERROR_CANCELLED.0) {
+                return Err(ElevationError::Declined);
+            }
+            return Err(WinError::new("ShellExecuteExW", &e).into());
+        }
+        let process = info.hProcess;
+        if WaitForSingleObject(process, INFINITE) == WAIT_FAILED {
+            let error = WinError::from_last_error("WaitForSingleObject");
+            let _ = CloseHandle(process);
+            return Err(error.into());
+        }
+        let mut exit_code = 0u32;
+        let result = GetExitCodeProcess(process, &mut exit_code)
+            .map_err(|e| WinError::new("GetExitCodeProcess", &e).into());
+        let _ = CloseHandle(process);
+        result.map(|()| exit_code)
+    }
+}
+
+/// In a copy started by [`run_elevated`], takes up the working directory and
+/// variables passed ahead of the command line, removing them from `args`
+/// (which include the executable). Returns whether this is such a copy.
+pub fn adopt_context(args: &mut Vec<String>) -> bool {
+    let mut adopted = false;
+    while args.len() >= 3 {
+        match args[1].as_str() {
+            DIRECTORY_ARGUMENT => {
+                let _ = env::set_current_dir(&args[2]);
+            }
+            VARIABLE_ARGUMENT => {
+                if let Some((name, value)) = args[2].split_once('=') {
+                    // Only called first thing in `main`, before any other thread
+                    unsafe { env::set_var(name, value) };
+                }
+            }
+            _ => break,
+        }
+        args.drain(1..3);
+        adopted = true;
+    }
+    adopted
+}
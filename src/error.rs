@@ -0,0 +1,51 @@
+use std::fmt;
+use windows::core::HRESULT;
+
+/// A failed Windows API call, keeping the details needed to triage reports:
+/// which call failed, its HRESULT and the system-provided message.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct WinError {
+    pub call: String,
+    pub hresult: String,
+    pub message: String,
+}
+
+impl WinError {
+    pub fn new(call: &str, error: &windows::core::Error) -> Self {
+        WinError {
+            call: call.to_string(),
+            hresult: format_hresult(error.code()),
+            message: error.message().trim().to_string(),
+        }
+    }
+
+    pub fn from_hresult(call: &str, hresult: HRESULT) -> Self {
+        WinError {
+            call: call.to_string(),
+            hresult: format_hresult(hresult),
+            message: hresult.message().trim().to_string(),
+        }
+    }
+
+    /// Captures the calling thread's last Win32 error.
+    pub fn from_last_error(call: &str) -> Self {
+        Self::new(call, &windows::core::Error::from_win32())
+    }
+}
+
+fn format_hresult(hresult: HRESULT) -> String {
+    format!("0x{:08X}", hresult.0 as u32)
+}
+
+impl fmt::Display for WinError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} ({} failed with HRESULT {})",
+            self.message, self.call, self.hresult
+        )
+    }
+}
+
+impl std::error::Error for WinError {}
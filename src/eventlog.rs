@@ -0,0 +1,92 @@
+//! Failures written to the Windows Event Log under the `librarylink` source
+//! the installer registers, so a host that launches games unattended keeps
+//! a record of what went wrong where its other services' are. Nothing is
+//! written where the source isn't registered, as for a portable install.
+
+use windows::Win32::Foundation::HANDLE;
+use windows::Win32::System::EventLog::{EVENTLOG_ERROR_TYPE, RegisterEventSourceW, ReportEventW};
+use windows::core::{HSTRING, PCWSTR};
+
+use crate::registry;
+use crate::report::{Event, Level, Reporter};
+
+/// The event source, as the installer registers it.
+pub const SOURCE: &str = "librarylink";
+
+const SOURCE_KEY: &str = r"HKLM\SYSTEM\CurrentControlSet\Services\EventLog\Application\librarylink";
+
+/// The ID failures are logged with. The message file the installer
+/// registers shows any ID's text as is.
+const FAILURE_EVENT_ID: u32 = 1;
+
+/// Whether the installer registered the event source.
+pub fn is_registered() -> bool {
+    registry::read(SOURCE_KEY, "EventMessageFile").is_ok_and(|value| value.is_some())
+}
+
+/// Reports to `primary`, and writes errors and failed launches to the
+/// Application event log.
+pub struct EventLogReporter {
+    primary: Box<dyn Reporter>,
+    /// The event source's handle, stored as an integer so the reporter is
+    /// `Send` and `Sync`
+    source: isize,
+}
+
+impl EventLogReporter {
+    /// Wraps `primary`, or returns it as is if the event source can't be
+    /// opened.
+    pub fn wrap(primary: Box<dyn Reporter>) -> Box<dyn Reporter> {
+        match unsafe { RegisterEventSourceW(None, &HSTRING::from(SOURCE)) } {
+            Ok(source) => Box::new(EventLogReporter {
+                primary,
+                source: source.0 as isize,
+            }),
+            Err(_) => primary,
+        }
+    }
+
+    fn write(&self, text: &str) {
+        let text = HSTRING::from(text);
+        unsafe {
+            let _ = ReportEventW(
+                HANDLE(self.source as *mut _),
+                EVENTLOG_ERROR_TYPE,
+                0,
+                FAILURE_EVENT_ID,
+                None,
+                0,
+                Some(&[PCWSTR(text.as_ptr())]),
+                None,
+            );
+        }
+    }
+}
+
+impl Reporter for EventLogReporter {
+    fn message(&self, level: Level, text: &str) {
+        self.primary.message(level, text);
+        if level == Level::Error {
+            self.write(text);
+        }
+    }
+
+    fn event(&self, event: &Event) {
+        self.primary.event(event);
+        match event {
+            Event::Error { context, error } => self.write(&format!("{}: {}", context, error)),
+            Event::Failed {
+                reason,
+                code,
+                game_exit_code,
+            } => self.write(&match game_exit_code {
+                Some(game_code) => format!(
+                    "librarylink failed: {} (exit code {}, game exit code {})",
+                    reason, code, game_code
+                ),
+                None => format!("librarylink failed: {} (exit code {})", reason, code),
+            }),
+            _ => {}
+        }
+    }
+}
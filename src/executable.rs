@@ -0,0 +1,184 @@
+//! `wait_for` executables given as patterns, for games whose real executable
+//! lives in a folder that changes between patches, such as
+//! `Binaries/Win64-*/Game.exe` or `**/Game-Win64-Shipping.exe`.
+//!
+//! A pattern is a path relative to the launched process's directory where
+//! `*` and `?` match within one component and `**` matches any number of
+//! folders. Patterns are resolved against the install directory before
+//! waiting, and each resolution is cached per package version, so a game
+//! update re-resolves it once.
+
+#[cfg(feature = "json")]
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+#[cfg(feature = "json")]
+use crate::atomic_file;
+#[cfg(feature = "json")]
+use crate::paths;
+
+/// Whether a `wait_for` value is a pattern rather than an executable name.
+pub fn is_pattern(spec: &str) -> bool {
+    spec.contains(['*', '?', '/', '\\'])
+}
+
+fn components(path: &str) -> Vec<&str> {
+    path.split(['\\', '/'])
+        .filter(|component| !component.is_empty() && *component != ".")
+        .collect()
+}
+
+/// Matches one path component against a glob with `*` and `?`, ignoring case.
+pub(crate) fn glob_matches(glob: &str, text: &str) -> bool {
+    let glob: Vec<char> = glob.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+
+    // Greedy matching with backtracking to the last `*`
+    let (mut g, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if g < glob.len() && (glob[g] == '?' || glob[g] == text[t]) {
+            g += 1;
+            t += 1;
+        } else if g < glob.len() && glob[g] == '*' {
+            star = Some((g, t));
+            g += 1;
+        } else if let Some((star_g, star_t)) = star {
+            g = star_g + 1;
+            t = star_t + 1;
+            star = Some((star_g, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    glob[g..].iter().all(|&c| c == '*')
+}
+
+fn components_match(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| components_match(rest, &path[skip..])),
+        Some((glob, rest)) => path.split_first().is_some_and(|(component, path)| {
+            glob_matches(glob, component) && components_match(rest, path)
+        }),
+    }
+}
+
+/// Whether `path` (a process's executable) matches `pattern` relative to
+/// `directory`.
+pub fn matches(pattern: &str, directory: &str, path: &str) -> bool {
+    let directory = components(directory);
+    let path = components(path);
+    if path.len() < directory.len()
+        || !path
+            .iter()
+            .zip(&directory)
+            .all(|(a, b)| a.eq_ignore_ascii_case(b))
+    {
+        return false;
+    }
+    components_match(&components(pattern), &path[directory.len()..])
+}
+
+/// Files under `directory` matching the pattern's components.
+fn find_matches(directory: &Path, pattern: &[&str], found: &mut Vec<PathBuf>) {
+    let Some((component, rest)) = pattern.split_first() else {
+        if directory.is_file() {
+            found.push(directory.to_path_buf());
+        }
+        return;
+    };
+    if *component == "**" {
+        find_matches(directory, rest, found);
+    }
+
+    let Ok(entries) = fs::read_dir(directory) else {
+        return;
+    };
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+        let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+        if *component == "**" {
+            if is_dir {
+                find_matches(&entry.path(), pattern, found);
+            }
+        } else if glob_matches(component, name) && (is_dir || rest.is_empty()) {
+            find_matches(&entry.path(), rest, found);
+        }
+    }
+}
+
+/// Resolves a pattern to the executable it names under `directory`. When
+/// several files match (an old version folder left behind), the most recently
+/// modified one wins.
+fn resolve_uncached(pattern: &str, directory: &str) -> Option<PathBuf> {
+    let mut found = Vec::new();
+    find_matches(Path::new(directory), &components(pattern), &mut found);
+    found.into_iter().max_by_key(|path| {
+        fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .unwrap_or(SystemTime::UNIX_EPOCH)
+    })
+}
+
+#[cfg(feature = "json")]
+pub(crate) fn cache_path() -> PathBuf {
+    paths::data_dir().join("wait-for-cache.json")
+}
+
+#[cfg(feature = "json")]
+fn load_cache() -> HashMap<String, PathBuf> {
+    fs::read_to_string(cache_path())
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// Resolves a `wait_for` value to the executable name to wait for. Names are
+/// returned as given. A pattern resolves to the file name of the executable
+/// it matches, using the cached resolution for this package version if it
+/// still exists; if nothing matches yet, the pattern itself is returned and
+/// matched against process paths as they appear.
+pub fn resolve(spec: &str, directory: &str, package_full_name: Option<&str>) -> String {
+    if !is_pattern(spec) {
+        return spec.to_string();
+    }
+
+    #[cfg(feature = "json")]
+    let key = package_full_name.map(|package| format!("{}|{}", package, spec));
+    #[cfg(feature = "json")]
+    if let Some(key) = &key
+        && let Some(path) = load_cache().get(key).filter(|path| path.is_file())
+        && let Some(name) = path.file_name().and_then(|name| name.to_str())
+    {
+        return name.to_string();
+    }
+    #[cfg(not(feature = "json"))]
+    let _ = package_full_name;
+
+    let Some(path) = resolve_uncached(spec, directory) else {
+        return spec.to_string();
+    };
+
+    #[cfg(feature = "json")]
+    if let (Some(key), Some(package)) = (key, package_full_name) {
+        // Drop resolutions for other versions of the same package
+        let name = package.split('_').next();
+        let suffix = format!("|{}", spec);
+        let mut cache = load_cache();
+        cache.retain(|cached, _| !(cached.ends_with(&suffix) && cached.split('_').next() == name));
+        cache.insert(key, path.clone());
+        if let Ok(contents) = serde_json::to_string_pretty(&cache) {
+            let _ = atomic_file::write(&cache_path(), contents);
+        }
+    }
+
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map_or_else(|| spec.to_string(), str::to_string)
+}
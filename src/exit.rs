@@ -0,0 +1,117 @@
+//! The CLI's exit codes, one per way a launch can fail, so automation can
+//! tell causes apart without parsing English text. The codes are stable:
+//! new failures get new codes, and existing ones never change meaning.
+//! Sessions whose game ran exit with 0, or with the game's own exit code
+//! when its final process failed. Under `--porcelain` they exit with
+//! [`GameFailed`](ExitReason::GameFailed)'s code instead, and the game's is
+//! only reported, since it could be any of these.
+
+use std::error::Error;
+use std::fmt;
+
+use crate::apps::AppNotFound;
+use crate::error::WinError;
+use crate::monitor::{EndReason, StateTimeout};
+
+/// Why librarylink gave up, as reported in the `failed` event and by the
+/// exit code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+#[cfg_attr(feature = "json", serde(rename_all = "snake_case"))]
+pub enum ExitReason {
+    /// Any failure without a code of its own: 1
+    Failed,
+    /// No installed app or profile matched the AUMID, alias, profile or name
+    /// given: 2
+    NotFound,
+    /// The game never appeared after activation: 3
+    ActivationTimeout,
+    /// The activated process exited and nothing took over from it: 4
+    HandoffTimeout,
+    /// Activation failed, fallbacks included: 5
+    ActivationFailed,
+    /// COM couldn't be initialized for activation: 6
+    ComInitFailed,
+    /// The app started but there was no process to monitor: 7
+    MonitoringFailed,
+    /// `--elevated` was declined at the UAC prompt: 8
+    ElevationDeclined,
+    /// The game ran, but its final process exited with a non-zero exit
+    /// code: 9
+    GameFailed,
+}
+
+impl ExitReason {
+    pub fn code(self) -> i32 {
+        match self {
+            ExitReason::Failed => 1,
+            ExitReason::NotFound => 2,
+            ExitReason::ActivationTimeout => 3,
+            ExitReason::HandoffTimeout => 4,
+            ExitReason::ActivationFailed => 5,
+            ExitReason::ComInitFailed => 6,
+            ExitReason::MonitoringFailed => 7,
+            ExitReason::ElevationDeclined => 8,
+            ExitReason::GameFailed => 9,
+        }
+    }
+
+    /// The reason's name, as in JSON and `--porcelain` output.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ExitReason::Failed => "failed",
+            ExitReason::NotFound => "not_found",
+            ExitReason::ActivationTimeout => "activation_timeout",
+            ExitReason::HandoffTimeout => "handoff_timeout",
+            ExitReason::ActivationFailed => "activation_failed",
+            ExitReason::ComInitFailed => "com_init_failed",
+            ExitReason::MonitoringFailed => "monitoring_failed",
+            ExitReason::ElevationDeclined => "elevation_declined",
+            ExitReason::GameFailed => "game_failed",
+        }
+    }
+
+    /// The failure a session ending this way counts as, if it's one.
+    pub fn for_end(reason: EndReason) -> Option<Self> {
+        match reason {
+            EndReason::Exited | EndReason::Killed | EndReason::Quit => None,
+            EndReason::ActivationTimeout => Some(ExitReason::ActivationTimeout),
+            EndReason::HandoffTimeout => Some(ExitReason::HandoffTimeout),
+        }
+    }
+
+    /// The failure a library launch's error stands for, going by the call
+    /// that failed.
+    pub fn of(error: &(dyn Error + 'static)) -> Self {
+        if let Some(timeout) = error.downcast_ref::<StateTimeout>() {
+            return Self::for_end(timeout.reason).unwrap_or(ExitReason::Failed);
+        }
+        if error.is::<AppNotFound>() {
+            return ExitReason::NotFound;
+        }
+        match error.downcast_ref::<WinError>() {
+            Some(error) => Self::of_call(&error.call),
+            None => ExitReason::Failed,
+        }
+    }
+
+    /// The failure a Windows API error stands for, going by the call that
+    /// failed.
+    pub fn of_call(call: &str) -> Self {
+        if call == "CoInitializeEx" {
+            ExitReason::ComInitFailed
+        } else if call.starts_with("IApplicationActivationManager::")
+            || call == "CoCreateInstance(ApplicationActivationManager)"
+        {
+            ExitReason::ActivationFailed
+        } else {
+            ExitReason::Failed
+        }
+    }
+}
+
+impl fmt::Display for ExitReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
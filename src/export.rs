@@ -0,0 +1,82 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::apps::AppFilter;
+use crate::games::{self, GameListing};
+use crate::metadata::{self, GameMetadata, MetadataProvider};
+
+const PSM1_TEMPLATE: &str = include_str!("../assets/powershell/LibraryLink.psm1");
+const PSD1_TEMPLATE: &str = include_str!("../assets/powershell/LibraryLink.psd1");
+
+fn fill_template(template: &str, executable: &Path) -> String {
+    template
+        .replace("{{VERSION}}", env!("CARGO_PKG_VERSION"))
+        // The path sits in a single-quoted PowerShell string
+        .replace(
+            "{{EXECUTABLE}}",
+            &executable.to_string_lossy().replace('\'', "''"),
+        )
+}
+
+/// Writes the LibraryLink PowerShell module (`Get-LLApps`, `Start-LLGame`,
+/// `Stop-LLGame`, `Get-LLSession`) into `directory`, pointing it at the
+/// running librarylink executable. Returns the files written.
+pub fn write_powershell_module(
+    directory: &Path,
+) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let executable = std::env::current_exe()?;
+    fs::create_dir_all(directory)?;
+
+    let mut written = Vec::new();
+    for (name, template) in [
+        ("LibraryLink.psm1", PSM1_TEMPLATE),
+        ("LibraryLink.psd1", PSD1_TEMPLATE),
+    ] {
+        let path = directory.join(name);
+        fs::write(&path, fill_template(template, &executable))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// A game as `export library` writes it.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct LibraryEntry {
+    #[serde(flatten)]
+    pub game: GameListing,
+    /// What the metadata provider knows, when one was asked and matched it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<GameMetadata>,
+}
+
+/// The installed games as `export library` writes them, with metadata from
+/// `provider` if given.
+pub fn installed_library(
+    provider: Option<&mut dyn MetadataProvider>,
+) -> Result<Vec<LibraryEntry>, Box<dyn std::error::Error>> {
+    let games = games::list_games(&AppFilter::default())
+        .map_err(|e| format!("Error finding games: {}", e))?;
+    Ok(library(games, provider))
+}
+
+/// The library entries for `games`, with metadata from `provider` if given.
+pub fn library(
+    games: Vec<GameListing>,
+    provider: Option<&mut dyn MetadataProvider>,
+) -> Vec<LibraryEntry> {
+    let mut found = match provider {
+        Some(provider) => {
+            let titles: Vec<String> = games.iter().map(|game| game.app.name.clone()).collect();
+            metadata::enrich(provider, &titles)
+        }
+        None => Vec::new(),
+    }
+    .into_iter();
+    games
+        .into_iter()
+        .map(|game| LibraryEntry {
+            game,
+            metadata: found.next().flatten(),
+        })
+        .collect()
+}
@@ -0,0 +1,156 @@
+//! `capabilities`: which of librarylink's optional features work on this
+//! machine, for frontends that would rather hide what the host can't do
+//! than offer it and show the error. Besides the Windows build (see
+//! [`compat`](crate::compat)), some features need rights or tools the
+//! account may not have.
+
+use std::fmt;
+use windows::Win32::Security::{
+    CheckTokenMembership, CreateWellKnownSid, PSID, SECURITY_MAX_SID_SIZE,
+    WinBuiltinPerfLoggingUsersSid,
+};
+use windows::core::BOOL;
+
+use crate::compat::{Capability, WindowsVersion};
+#[cfg(feature = "presentmon")]
+use crate::presentmon;
+use crate::process;
+
+/// An optional feature, as `capabilities` names it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Feature {
+    /// App names and details looked up by AUMID
+    AppDetails,
+    /// `list-apps`, `list-games` and `pick`
+    AppList,
+    /// `--monitor` and `--window-size`
+    WindowPlacement,
+    /// HDR state in `list-monitors`
+    HdrState,
+    /// Event Tracing for Windows sessions, which PresentMon runs
+    EtwTracing,
+    /// `--frame-times` and `bench` frame pacing
+    FrameTimes,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 6] = [
+        Feature::AppDetails,
+        Feature::AppList,
+        Feature::WindowPlacement,
+        Feature::HdrState,
+        Feature::EtwTracing,
+        Feature::FrameTimes,
+    ];
+
+    /// The Windows build capability the feature needs, if any.
+    fn capability(self) -> Option<Capability> {
+        match self {
+            Feature::AppDetails => Some(Capability::AppInfoLookup),
+            Feature::AppList => Some(Capability::AppListAumids),
+            Feature::WindowPlacement => Some(Capability::PerMonitorDpi),
+            Feature::HdrState => Some(Capability::HdrState),
+            Feature::EtwTracing | Feature::FrameTimes => None,
+        }
+    }
+
+    /// Why the feature isn't available, or `None` if it is.
+    fn unavailable_reason(self) -> Option<String> {
+        if let Some(capability) = self.capability() {
+            return (!capability.is_supported()).then(|| capability.unsupported_message());
+        }
+        let tracing = || {
+            (!can_trace()).then(|| {
+                "tracing ETW events needs an elevated prompt or membership in Performance Log Users"
+                    .to_string()
+            })
+        };
+        match self {
+            Feature::EtwTracing => tracing(),
+            #[cfg(feature = "presentmon")]
+            Feature::FrameTimes => match presentmon::locate() {
+                None => Some(
+                    "PresentMon wasn't found next to librarylink or on the PATH; pass --presentmon with its path"
+                        .to_string(),
+                ),
+                Some(_) => tracing(),
+            },
+            #[cfg(not(feature = "presentmon"))]
+            Feature::FrameTimes => {
+                Some("librarylink was built without the presentmon feature".to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Feature::AppDetails => "App details by AUMID",
+            Feature::AppList => "App listing",
+            Feature::WindowPlacement => "Window placement",
+            Feature::HdrState => "HDR state",
+            Feature::EtwTracing => "ETW tracing",
+            Feature::FrameTimes => "Frame times",
+        })
+    }
+}
+
+/// Whether one feature is available.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FeatureStatus {
+    pub feature: Feature,
+    pub available: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// What `capabilities --json` reports.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Capabilities {
+    /// librarylink's own version, for features that depend on it
+    pub version: &'static str,
+    pub windows: Option<&'static WindowsVersion>,
+    pub features: Vec<FeatureStatus>,
+}
+
+/// Checks every feature on this machine, as the current user.
+pub fn detect() -> Capabilities {
+    Capabilities {
+        version: env!("CARGO_PKG_VERSION"),
+        windows: WindowsVersion::current(),
+        features: Feature::ALL
+            .iter()
+            .map(|&feature| {
+                let reason = feature.unavailable_reason();
+                FeatureStatus {
+                    feature,
+                    available: reason.is_none(),
+                    reason,
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Whether this process may start ETW trace sessions: elevated, or in the
+/// Performance Log Users group.
+fn can_trace() -> bool {
+    process::is_elevated() || is_performance_log_user()
+}
+
+fn is_performance_log_user() -> bool {
+    // SIDs are DWORD-aligned
+    let mut buffer = [0u32; SECURITY_MAX_SID_SIZE as usize / 4];
+    let sid = PSID(buffer.as_mut_ptr().cast());
+    let mut size = SECURITY_MAX_SID_SIZE;
+    unsafe {
+        if CreateWellKnownSid(WinBuiltinPerfLoggingUsersSid, None, Some(sid), &mut size).is_err() {
+            return false;
+        }
+        let mut member = BOOL::default();
+        CheckTokenMembership(None, sid, &mut member).is_ok() && member.as_bool()
+    }
+}
@@ -0,0 +1,136 @@
+//! `list-games`: the installed apps that are games, as opposed to the
+//! hundreds of system apps and tools `list-apps` shows alongside them. No
+//! package says outright that it's a game, so a few signals the Xbox app and
+//! Gaming Services leave behind are checked; any one of them is enough.
+
+use std::env;
+use std::fmt;
+use std::path::{Component, Path};
+
+use crate::acl;
+use crate::apps::{self, AppFilter};
+use crate::registry;
+use crate::report::AppListing;
+
+/// Where Gaming Services lists the games it installed, by package full name.
+const GAMING_SERVICES_PACKAGES: &str =
+    r"HKLM\SOFTWARE\Microsoft\GamingServices\PackageRepository\Package";
+
+/// What marked an app as a game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+#[cfg_attr(feature = "json", serde(rename_all = "snake_case"))]
+pub enum GameSignal {
+    /// Installed under an `XboxGames` folder, where the Xbox app puts games
+    XboxGames,
+    /// Installed to a `WindowsApps` folder on another drive than Windows,
+    /// as the Xbox app did before `XboxGames`
+    WindowsApps,
+    /// Ships the `MicrosoftGame.config` of GDK titles
+    GameConfig,
+    /// Registered in Gaming Services' package repository
+    GamingServices,
+}
+
+impl fmt::Display for GameSignal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            GameSignal::XboxGames => "XboxGames folder",
+            GameSignal::WindowsApps => "WindowsApps on a games drive",
+            GameSignal::GameConfig => "MicrosoftGame.config",
+            GameSignal::GamingServices => "Gaming Services",
+        })
+    }
+}
+
+/// An app `list-games` shows: its listing, and why it's taken for a game.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct GameListing {
+    #[cfg_attr(feature = "json", serde(flatten))]
+    pub app: AppListing,
+    pub detected_by: Vec<GameSignal>,
+}
+
+/// Lists the apps [`apps::list_apps_matching`] would that look like games.
+/// Framework packages never are. Mock apps all count as games.
+pub fn list_games(filter: &AppFilter) -> Result<Vec<GameListing>, Box<dyn std::error::Error>> {
+    let filter = AppFilter {
+        include_frameworks: false,
+        ..filter.clone()
+    };
+    let listings = apps::list_apps_matching(&filter)?;
+    #[cfg(feature = "cli")]
+    if crate::mock::is_enabled() {
+        return Ok(listings
+            .into_iter()
+            .map(|app| GameListing {
+                app,
+                detected_by: Vec::new(),
+            })
+            .collect());
+    }
+
+    // Missing when Gaming Services isn't installed, which only loses the one signal
+    let registered = registry::subkeys(GAMING_SERVICES_PACKAGES).unwrap_or_default();
+    Ok(listings
+        .into_iter()
+        .filter_map(|app| {
+            let detected_by = signals(&app, &registered);
+            (!detected_by.is_empty()).then_some(GameListing { app, detected_by })
+        })
+        .collect())
+}
+
+fn signals(app: &AppListing, registered: &[String]) -> Vec<GameSignal> {
+    let mut signals = Vec::new();
+    if let Some(install_path) = &app.install_path {
+        let path = Path::new(install_path);
+        if has_folder(path, "XboxGames") {
+            signals.push(GameSignal::XboxGames);
+        }
+        if has_folder(path, "WindowsApps") && !on_system_drive(path) {
+            signals.push(GameSignal::WindowsApps);
+        }
+        if path.join(acl::GAME_CONFIG).is_file() {
+            signals.push(GameSignal::GameConfig);
+        }
+    }
+    if let Some(family) = &app.package_family_name
+        && registered
+            .iter()
+            .any(|full_name| in_family(full_name, family))
+    {
+        signals.push(GameSignal::GamingServices);
+    }
+    signals
+}
+
+fn has_folder(path: &Path, name: &str) -> bool {
+    path.components().any(|component| {
+        matches!(component, Component::Normal(folder) if folder.eq_ignore_ascii_case(name))
+    })
+}
+
+fn on_system_drive(path: &Path) -> bool {
+    let system_drive = env::var("SystemDrive").unwrap_or_else(|_| "C:".to_string());
+    path.to_string_lossy()
+        .get(..system_drive.len())
+        .is_some_and(|drive| drive.eq_ignore_ascii_case(&system_drive))
+}
+
+/// Whether the package full name, `<name>_<version>_<arch>_<resource>_<publisher
+/// ID>`, belongs to the family `<name>_<publisher ID>`.
+fn in_family(full_name: &str, family: &str) -> bool {
+    let (Some((name, _)), Some((_, publisher))) =
+        (full_name.split_once('_'), full_name.rsplit_once('_'))
+    else {
+        return false;
+    };
+    family
+        .split_once('_')
+        .is_some_and(|(family_name, family_publisher)| {
+            name.eq_ignore_ascii_case(family_name)
+                && publisher.eq_ignore_ascii_case(family_publisher)
+        })
+}
@@ -0,0 +1,64 @@
+//! The machine's graphics adapters and their driver versions, as recorded
+//! with each session: a driver update is the usual answer to why a game
+//! that ran last week doesn't now.
+
+use windows::Win32::Graphics::Dxgi::{
+    CreateDXGIFactory1, DXGI_ADAPTER_FLAG_SOFTWARE, IDXGIDevice, IDXGIFactory1,
+};
+use windows::core::Interface;
+
+/// A hardware graphics adapter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+pub struct GpuAdapter {
+    pub name: String,
+    /// PCI vendor ID, such as `0x10de` for NVIDIA
+    pub vendor_id: u32,
+    /// The user-mode driver's version, such as `32.0.15.6094`
+    #[cfg_attr(
+        feature = "json",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub driver_version: Option<String>,
+}
+
+/// The hardware adapters DXGI reports, leaving out software renderers such
+/// as the Basic Render Driver. Empty when DXGI can't be created.
+pub fn adapters() -> Vec<GpuAdapter> {
+    let Ok(factory) = (unsafe { CreateDXGIFactory1::<IDXGIFactory1>() }) else {
+        return Vec::new();
+    };
+    let mut adapters = Vec::new();
+    // EnumAdapters1 fails with DXGI_ERROR_NOT_FOUND past the last adapter
+    for index in 0.. {
+        let Ok(adapter) = (unsafe { factory.EnumAdapters1(index) }) else {
+            break;
+        };
+        let Ok(desc) = (unsafe { adapter.GetDesc1() }) else {
+            continue;
+        };
+        if desc.Flags & DXGI_ADAPTER_FLAG_SOFTWARE.0 as u32 != 0 {
+            continue;
+        }
+        let length = desc
+            .Description
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(desc.Description.len());
+        // Asking about IDXGIDevice is how DXGI gives out the UMD version
+        let driver_version = unsafe { adapter.CheckInterfaceSupport(&IDXGIDevice::IID) }
+            .ok()
+            .map(|version| {
+                let part = |shift: u32| (version >> shift) & 0xffff;
+                format!("{}.{}.{}.{}", part(48), part(32), part(16), part(0))
+            });
+        adapters.push(GpuAdapter {
+            name: String::from_utf16_lossy(&desc.Description[..length])
+                .trim()
+                .to_string(),
+            vendor_id: desc.VendorId,
+            driver_version,
+        });
+    }
+    adapters
+}
@@ -0,0 +1,259 @@
+//! A small HTTP client over WinHTTP, for talking to local services such as a
+//! streaming host's web API. HTTPS uses the system's TLS stack.
+
+use std::ffi::c_void;
+use std::net::IpAddr;
+use std::time::Duration;
+use windows::Win32::Networking::WinHttp::{
+    SECURITY_FLAG_IGNORE_CERT_CN_INVALID, SECURITY_FLAG_IGNORE_CERT_DATE_INVALID,
+    SECURITY_FLAG_IGNORE_UNKNOWN_CA, WINHTTP_ACCESS_TYPE_AUTOMATIC_PROXY, WINHTTP_FLAG_SECURE,
+    WINHTTP_OPEN_REQUEST_FLAGS, WINHTTP_OPTION_SECURITY_FLAGS, WINHTTP_QUERY_FLAG_NUMBER,
+    WINHTTP_QUERY_STATUS_CODE, WinHttpCloseHandle, WinHttpConnect, WinHttpOpen, WinHttpOpenRequest,
+    WinHttpQueryHeaders, WinHttpReadData, WinHttpReceiveResponse, WinHttpSendRequest,
+    WinHttpSetOption, WinHttpSetTimeouts,
+};
+use windows::core::{HSTRING, PCWSTR};
+
+use crate::error::WinError;
+
+/// How long connecting, sending and receiving may each take.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Default)]
+pub struct Request<'a> {
+    pub headers: Vec<(&'a str, String)>,
+    pub body: Option<&'a [u8]>,
+    /// Accept self-signed and mismatched certificates, as local services
+    /// like Sunshine use
+    pub insecure: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+impl Response {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// A WinHTTP handle, closed when dropped.
+struct Handle(*mut c_void);
+
+impl Handle {
+    fn open(handle: *mut c_void, call: &str) -> Result<Handle, WinError> {
+        if handle.is_null() {
+            return Err(WinError::from_last_error(call));
+        }
+        Ok(Handle(handle))
+    }
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        let _ = unsafe { WinHttpCloseHandle(self.0) };
+    }
+}
+
+/// The parts of an `http://` or `https://` URL WinHTTP needs.
+struct Url<'a> {
+    secure: bool,
+    host: &'a str,
+    port: u16,
+    path: &'a str,
+}
+
+fn parse_url(url: &str) -> Option<Url<'_>> {
+    let (secure, rest) = if let Some(rest) = url.strip_prefix("https://") {
+        (true, rest)
+    } else {
+        (false, url.strip_prefix("http://")?)
+    };
+    let (authority, path) = match rest.find('/') {
+        Some(index) => rest.split_at(index),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().ok()?),
+        None => (authority, if secure { 443 } else { 80 }),
+    };
+    if host.is_empty() {
+        return None;
+    }
+    Some(Url {
+        secure,
+        host,
+        port,
+        path,
+    })
+}
+
+/// Whether `url` points at this machine, by name or loopback address.
+pub fn is_loopback(url: &str) -> bool {
+    let Some(parsed) = parse_url(url) else {
+        return false;
+    };
+    let host = parsed.host.trim_start_matches('[').trim_end_matches(']');
+    host.eq_ignore_ascii_case("localhost")
+        || host
+            .parse::<IpAddr>()
+            .is_ok_and(|address| address.is_loopback())
+}
+
+/// Sends a request and reads the whole response. Any status is a response;
+/// only failing to get one is an error.
+pub fn send(
+    method: &str,
+    url: &str,
+    request: &Request,
+) -> Result<Response, Box<dyn std::error::Error>> {
+    let parsed = parse_url(url).ok_or_else(|| format!("Not an http(s) URL: {}", url))?;
+    let timeout = TIMEOUT.as_millis() as i32;
+
+    unsafe {
+        let session = Handle::open(
+            WinHttpOpen(
+                &HSTRING::from(concat!("librarylink/", env!("CARGO_PKG_VERSION"))),
+                WINHTTP_ACCESS_TYPE_AUTOMATIC_PROXY,
+                PCWSTR::null(),
+                PCWSTR::null(),
+                0,
+            ),
+            "WinHttpOpen",
+        )?;
+        WinHttpSetTimeouts(session.0, timeout, timeout, timeout, timeout)
+            .map_err(|e| WinError::new("WinHttpSetTimeouts", &e))?;
+        let connection = Handle::open(
+            WinHttpConnect(session.0, &HSTRING::from(parsed.host), parsed.port, 0),
+            "WinHttpConnect",
+        )?;
+        let flags = if parsed.secure {
+            WINHTTP_FLAG_SECURE
+        } else {
+            WINHTTP_OPEN_REQUEST_FLAGS(0)
+        };
+        let handle = Handle::open(
+            WinHttpOpenRequest(
+                connection.0,
+                &HSTRING::from(method),
+                &HSTRING::from(parsed.path),
+                PCWSTR::null(),
+                PCWSTR::null(),
+                std::ptr::null(),
+                flags,
+            ),
+            "WinHttpOpenRequest",
+        )?;
+        if parsed.secure && request.insecure {
+            let ignore = SECURITY_FLAG_IGNORE_UNKNOWN_CA
+                | SECURITY_FLAG_IGNORE_CERT_CN_INVALID
+                | SECURITY_FLAG_IGNORE_CERT_DATE_INVALID;
+            WinHttpSetOption(
+                Some(handle.0),
+                WINHTTP_OPTION_SECURITY_FLAGS,
+                Some(&ignore.to_ne_bytes()),
+            )
+            .map_err(|e| WinError::new("WinHttpSetOption(SECURITY_FLAGS)", &e))?;
+        }
+
+        let headers: Vec<u16> = request
+            .headers
+            .iter()
+            .map(|(name, value)| format!("{}: {}\r\n", name, value))
+            .collect::<String>()
+            .encode_utf16()
+            .collect();
+        let body = request.body.unwrap_or_default();
+        WinHttpSendRequest(
+            handle.0,
+            (!headers.is_empty()).then_some(headers.as_slice()),
+            (!body.is_empty()).then_some(body.as_ptr().cast()),
+            body.len() as u32,
+            body.len() as u32,
+            0,
+        )
+        .map_err(|e| WinError::new("WinHttpSendRequest", &e))?;
+        WinHttpReceiveResponse(handle.0, std::ptr::null_mut())
+            .map_err(|e| WinError::new("WinHttpReceiveResponse", &e))?;
+
+        let mut status = 0u32;
+        let mut length = std::mem::size_of::<u32>() as u32;
+        WinHttpQueryHeaders(
+            handle.0,
+            WINHTTP_QUERY_STATUS_CODE | WINHTTP_QUERY_FLAG_NUMBER,
+            PCWSTR::null(),
+            Some((&mut status as *mut u32).cast()),
+            &mut length,
+            std::ptr::null_mut(),
+        )
+        .map_err(|e| WinError::new("WinHttpQueryHeaders(STATUS_CODE)", &e))?;
+
+        let mut body = Vec::new();
+        let mut buffer = [0u8; 8192];
+        loop {
+            let mut read = 0u32;
+            WinHttpReadData(
+                handle.0,
+                buffer.as_mut_ptr().cast(),
+                buffer.len() as u32,
+                &mut read,
+            )
+            .map_err(|e| WinError::new("WinHttpReadData", &e))?;
+            if read == 0 {
+                break;
+            }
+            body.extend_from_slice(&buffer[..read as usize]);
+        }
+
+        Ok(Response {
+            status: status as u16,
+            body: String::from_utf8_lossy(&body).into_owned(),
+        })
+    }
+}
+
+/// The value of an `Authorization` header for HTTP basic authentication.
+pub fn basic_auth(username: &str, password: &str) -> String {
+    format!(
+        "Basic {}",
+        base64(format!("{}:{}", username, password).as_bytes())
+    )
+}
+
+/// `value` percent-encoded for an `application/x-www-form-urlencoded`
+/// body, keeping only the characters that never need it.
+pub fn form_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+fn base64(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut output = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let triple = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for index in 0..4 {
+            if index <= chunk.len() {
+                output.push(ALPHABET[(triple >> (18 - 6 * index) & 0x3F) as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
@@ -0,0 +1,225 @@
+//! Finds a packaged app's logo among the assets in its install directory.
+//! The manifest names a logo such as `Assets\Square44x44Logo.png`, but what's
+//! on disk are qualified variants of it, `Square44x44Logo.scale-200.png` or
+//! `Square44x44Logo.targetsize-256_altform-unplated.png`, one per scale,
+//! size and theme.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::package;
+
+/// The manifest every packaged app's install directory has.
+const MANIFEST: &str = "AppxManifest.xml";
+
+/// One variant of a logo the manifest declares.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogoAsset {
+    pub path: PathBuf,
+    /// Width and height in pixels, from its qualifiers
+    pub size: u32,
+    /// Drawn without the tile's background plate, the look taskbar and
+    /// Start menu icons have
+    pub unplated: bool,
+}
+
+/// The app's square logos in every size its package ships, unplated ones
+/// first, each largest first. High contrast variants are left out.
+pub fn logo_assets(aumid: &str) -> Vec<LogoAsset> {
+    let Some(install_path) = package::installed_path(aumid) else {
+        return Vec::new();
+    };
+    let app_id = aumid.split_once('!').map_or("", |(_, app_id)| app_id);
+    let Ok(manifest) = fs::read_to_string(install_path.join(MANIFEST)) else {
+        return Vec::new();
+    };
+
+    let mut assets = Vec::new();
+    for (relative, base_size) in declared_logos(&manifest, app_id) {
+        assets.extend(variants(&install_path.join(relative), base_size));
+    }
+    assets.sort_by_key(|asset| (!asset.unplated, std::cmp::Reverse(asset.size)));
+    assets.dedup_by(|a, b| a.path == b.path);
+    assets
+}
+
+/// The logo that works best as an icon: the largest unplated one, or the
+/// largest tile when the package has none.
+pub fn best_logo(aumid: &str) -> Option<PathBuf> {
+    logo_assets(aumid)
+        .into_iter()
+        .next()
+        .map(|asset| asset.path)
+}
+
+/// The logo closest to `size` pixels: the smallest at least that large, so
+/// frontends scale down rather than up, or the largest there is. Unplated
+/// logos win over tiles whenever one is large enough.
+pub fn logo_for_size(aumid: &str, size: u32) -> Option<LogoAsset> {
+    let assets = logo_assets(aumid);
+    let fits = |unplated: bool| {
+        assets
+            .iter()
+            .filter(|asset| asset.unplated == unplated && asset.size >= size)
+            .min_by_key(|asset| asset.size)
+    };
+    fits(true)
+        .or_else(|| fits(false))
+        .or_else(|| assets.iter().max_by_key(|asset| asset.size))
+        .cloned()
+}
+
+/// Writes `asset` to `out`, as an ICO wrapping the PNG when `out` ends in
+/// `.ico` and as the PNG itself otherwise. Returns the image's pixel size.
+pub fn write_logo(asset: &LogoAsset, out: &Path) -> std::io::Result<(u32, u32)> {
+    let png = fs::read(&asset.path)?;
+    let (width, height) = png_size(&png).ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("{} isn't a PNG", asset.path.display()),
+        )
+    })?;
+    let is_ico = out
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("ico"));
+    if is_ico {
+        fs::write(out, ico_wrapping(&png, width, height))?;
+    } else {
+        fs::write(out, &png)?;
+    }
+    Ok((width, height))
+}
+
+/// Width and height from a PNG's header chunk.
+fn png_size(png: &[u8]) -> Option<(u32, u32)> {
+    if png.get(..8)? != b"\x89PNG\r\n\x1a\n" || png.get(12..16)? != b"IHDR" {
+        return None;
+    }
+    let width = u32::from_be_bytes(png.get(16..20)?.try_into().ok()?);
+    let height = u32::from_be_bytes(png.get(20..24)?.try_into().ok()?);
+    Some((width, height))
+}
+
+/// A single-image ICO holding `png` as is, which Windows has read since
+/// Vista.
+fn ico_wrapping(png: &[u8], width: u32, height: u32) -> Vec<u8> {
+    // Sizes of 256 and up are stored as 0
+    let dimension = |pixels: u32| if pixels >= 256 { 0 } else { pixels as u8 };
+    let mut ico = Vec::with_capacity(22 + png.len());
+    // ICONDIR: reserved, type 1 (icon), one image
+    ico.extend_from_slice(&[0, 0, 1, 0, 1, 0]);
+    // ICONDIRENTRY: size, no palette, one plane, 32 bits per pixel
+    ico.extend_from_slice(&[dimension(width), dimension(height), 0, 0, 1, 0, 32, 0]);
+    ico.extend_from_slice(&(png.len() as u32).to_le_bytes());
+    ico.extend_from_slice(&22u32.to_le_bytes());
+    ico.extend_from_slice(png);
+    ico
+}
+
+/// The logos `app_id`'s `VisualElements` declare, with the size each is at
+/// 100% scale, then the package's store logo as a last resort.
+fn declared_logos(manifest: &str, app_id: &str) -> Vec<(String, u32)> {
+    let mut logos = Vec::new();
+    let application = manifest
+        .match_indices("<Application ")
+        .map(|(start, _)| &manifest[start..])
+        .find(|section| attribute(opening_tag(section), "Id") == Some(app_id));
+    if let Some(application) = application {
+        let section = application
+            .find("</Application>")
+            .map_or(application, |end| &application[..end]);
+        if let Some(start) = section.find("VisualElements ") {
+            let tag = opening_tag(&section[start..]);
+            for (name, size) in [("Square44x44Logo", 44), ("Square150x150Logo", 150)] {
+                if let Some(path) = attribute(tag, name) {
+                    logos.push((path.to_string(), size));
+                }
+            }
+        }
+    }
+    if let Some(start) = manifest.find("<Logo>")
+        && let Some(end) = manifest[start..].find("</Logo>")
+    {
+        // Store logos are 50x50 at 100% scale
+        logos.push((manifest[start + 6..start + end].trim().to_string(), 50));
+    }
+    logos
+}
+
+/// The element starting `text`, up to its closing `>`.
+fn opening_tag(text: &str) -> &str {
+    text.find('>').map_or(text, |end| &text[..end])
+}
+
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let needle = format!(" {}=\"", name);
+    let start = tag.find(&needle)? + needle.len();
+    let length = tag[start..].find('"')?;
+    Some(&tag[start..start + length])
+}
+
+/// The files on disk standing in for `declared`: itself if it exists, and
+/// every `<stem>.<qualifiers>.<extension>` next to it.
+fn variants(declared: &Path, base_size: u32) -> Vec<LogoAsset> {
+    let (Some(directory), Some(stem), Some(extension)) = (
+        declared.parent(),
+        declared.file_stem().and_then(|stem| stem.to_str()),
+        declared
+            .extension()
+            .and_then(|extension| extension.to_str()),
+    ) else {
+        return Vec::new();
+    };
+    let prefix = format!("{}.", stem.to_lowercase());
+    let suffix = format!(".{}", extension.to_lowercase());
+
+    let mut assets = Vec::new();
+    if declared.is_file() {
+        assets.push(LogoAsset {
+            path: declared.to_path_buf(),
+            size: base_size,
+            unplated: false,
+        });
+    }
+    let Ok(entries) = fs::read_dir(directory) else {
+        return assets;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_lowercase();
+        let Some(qualifiers) = name
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix(&suffix))
+        else {
+            continue;
+        };
+        if let Some((size, unplated)) = qualified_size(qualifiers, base_size) {
+            assets.push(LogoAsset {
+                path: entry.path(),
+                size,
+                unplated,
+            });
+        }
+    }
+    assets
+}
+
+/// The pixel size `qualifiers` (such as `scale-200` or
+/// `targetsize-48_altform-unplated`) describe, and whether the variant is
+/// unplated. `None` for high contrast variants, which look wrong anywhere
+/// else.
+fn qualified_size(qualifiers: &str, base_size: u32) -> Option<(u32, bool)> {
+    let mut size = base_size;
+    let mut unplated = false;
+    for qualifier in qualifiers.split('_') {
+        let (name, value) = qualifier.split_once('-').unwrap_or((qualifier, ""));
+        match name {
+            "contrast" => return None,
+            "targetsize" => size = value.parse().ok()?,
+            "scale" => size = base_size * value.parse::<u32>().ok()? / 100,
+            "altform" => unplated = value == "unplated",
+            // Language, theme and other qualifiers don't change the size
+            _ => {}
+        }
+    }
+    Some((size, unplated))
+}
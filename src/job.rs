@@ -0,0 +1,104 @@
+//! Follows the processes an app starts, wherever their executables live. The
+//! activated process is put in a job object, which every process it starts
+//! (and those start) joins automatically, so a launcher handing off to a
+//! game outside its install directory is still found.
+
+use windows::Win32::Foundation::{CloseHandle, HANDLE};
+use windows::Win32::System::JobObjects::{
+    AssignProcessToJobObject, CreateJobObjectW, JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+    JOBOBJECT_BASIC_PROCESS_ID_LIST, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+    JobObjectBasicProcessIdList, JobObjectExtendedLimitInformation, QueryInformationJobObject,
+    SetInformationJobObject, TerminateJobObject,
+};
+use windows::Win32::System::Threading::{OpenProcess, PROCESS_SET_QUOTA, PROCESS_TERMINATE};
+use windows::core::PCWSTR;
+
+use crate::error::WinError;
+
+/// How many processes [`ProcessJob::process_ids`] lists at most.
+const MAX_PROCESSES: usize = 256;
+
+/// A job holding an app's process tree. Closing it leaves the processes
+/// running, unless [`kill_on_close`](ProcessJob::kill_on_close) was called.
+#[derive(Debug)]
+pub struct ProcessJob {
+    handle: HANDLE,
+}
+
+// Job handles may be used from any thread
+unsafe impl Send for ProcessJob {}
+unsafe impl Sync for ProcessJob {}
+
+impl ProcessJob {
+    /// Puts `process_id` in a new job. Processes it already started stay
+    /// outside; ones started from now on join.
+    pub fn track(process_id: u32) -> Result<ProcessJob, WinError> {
+        unsafe {
+            let job = ProcessJob {
+                handle: CreateJobObjectW(None, PCWSTR::null())
+                    .map_err(|e| WinError::new("CreateJobObjectW", &e))?,
+            };
+            let process = OpenProcess(PROCESS_SET_QUOTA | PROCESS_TERMINATE, false, process_id)
+                .map_err(|e| WinError::new("OpenProcess", &e))?;
+            let result = AssignProcessToJobObject(job.handle, process)
+                .map_err(|e| WinError::new("AssignProcessToJobObject", &e));
+            let _ = CloseHandle(process);
+            result.map(|()| job)
+        }
+    }
+
+    /// Makes the job terminate its processes once it's closed, which Windows
+    /// does when this process exits, even if it's terminated or crashes.
+    pub fn kill_on_close(&self) -> Result<(), WinError> {
+        let mut limits = JOBOBJECT_EXTENDED_LIMIT_INFORMATION::default();
+        limits.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+        unsafe {
+            SetInformationJobObject(
+                self.handle,
+                JobObjectExtendedLimitInformation,
+                (&limits as *const JOBOBJECT_EXTENDED_LIMIT_INFORMATION).cast(),
+                size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+            )
+        }
+        .map_err(|e| WinError::new("SetInformationJobObject", &e))
+    }
+
+    /// Terminates every process in the job.
+    pub fn terminate(&self) -> Result<(), WinError> {
+        unsafe { TerminateJobObject(self.handle, 1) }
+            .map_err(|e| WinError::new("TerminateJobObject", &e))
+    }
+
+    /// The processes in the job that are still running, oldest first.
+    pub fn process_ids(&self) -> Vec<u32> {
+        let size =
+            size_of::<JOBOBJECT_BASIC_PROCESS_ID_LIST>() + (MAX_PROCESSES - 1) * size_of::<usize>();
+        // usizes keep the buffer aligned for the list
+        let mut buffer = vec![0usize; size.div_ceil(size_of::<usize>())];
+        let list = buffer.as_mut_ptr() as *mut JOBOBJECT_BASIC_PROCESS_ID_LIST;
+        unsafe {
+            if QueryInformationJobObject(
+                Some(self.handle),
+                JobObjectBasicProcessIdList,
+                list.cast(),
+                size as u32,
+                None,
+            )
+            .is_err()
+            {
+                return Vec::new();
+            }
+            let count = (*list).NumberOfProcessIdsInList as usize;
+            std::slice::from_raw_parts((*list).ProcessIdList.as_ptr(), count)
+                .iter()
+                .map(|&process_id| process_id as u32)
+                .collect()
+        }
+    }
+}
+
+impl Drop for ProcessJob {
+    fn drop(&mut self) {
+        let _ = unsafe { CloseHandle(self.handle) };
+    }
+}
@@ -0,0 +1,920 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use windows::Win32::Foundation::RPC_E_CHANGED_MODE;
+use windows::Win32::System::Com::{
+    CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED, CoCreateInstance, CoInitializeEx,
+    CoUninitialize,
+};
+use windows::Win32::UI::Shell::{
+    AO_NONE, ApplicationActivationManager, IApplicationActivationManager,
+};
+use windows::core::{HSTRING, PCWSTR};
+
+use crate::apps;
+use crate::capture;
+use crate::cleanup::CleanupHooks;
+use crate::compat::Capability;
+use crate::error::WinError;
+use crate::executable;
+use crate::launchers::LauncherSnapshot;
+use crate::matcher::{MonitorScope, MonitorTarget, ProcessFilters};
+use crate::monitor::{
+    self, EndReason, MonitorControl, MonitorOptions, MonitorState, StateTimeout, StateTimeouts,
+};
+use crate::package::{self, PackageUpdate, UpdateGuard};
+use crate::placement::WindowPlacement;
+use crate::poll::PollSettings;
+use crate::priority::{PriorityClass, ProcessTuning};
+use crate::process::{self, get_directory_from_path, get_process_info, spawn_process};
+use crate::protocol::{self, Handler};
+use crate::quit;
+use crate::report::{ChannelReporter, Event, Level, Reporter, debug};
+use crate::session::{SessionRecord, SessionState};
+use crate::system::{SystemSettings, SystemStateGuard};
+use crate::timings::{self, Phase};
+use crate::tree;
+#[cfg(feature = "wts")]
+use crate::wts::{self, SessionRequirement};
+
+/// How long `LaunchOptions::wait_for` waits for its executable to appear.
+const WAIT_FOR_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Keeps COM initialized on the current thread for as long as it lives.
+pub(crate) struct ComApartment {
+    initialized: bool,
+}
+
+impl ComApartment {
+    pub(crate) fn enter() -> Result<Self, WinError> {
+        let hr = timings::time(Phase::ComInit, || unsafe {
+            CoInitializeEx(None, COINIT_APARTMENTTHREADED)
+        });
+        // The host already put this thread in the multithreaded apartment;
+        // activation works from there too, and it isn't ours to uninitialize
+        if hr == RPC_E_CHANGED_MODE {
+            return Ok(ComApartment { initialized: false });
+        }
+        if hr.is_err() {
+            return Err(WinError::from_hresult("CoInitializeEx", hr));
+        }
+        Ok(ComApartment { initialized: true })
+    }
+}
+
+impl Drop for ComApartment {
+    fn drop(&mut self) {
+        if self.initialized {
+            unsafe { CoUninitialize() };
+        }
+    }
+}
+
+/// Activates a UWP app through `IApplicationActivationManager`, returning the
+/// process ID of the activated process.
+///
+/// Safe to call from any thread, including several at once. COM is
+/// initialized as a single-threaded apartment for the duration of the call
+/// unless the thread already belongs to an apartment, in which case that one
+/// is used and left as it was.
+pub fn activate(aumid: &str, arguments: Option<&str>) -> Result<u32, WinError> {
+    let _apartment = ComApartment::enter()?;
+    debug!(
+        "Activating {} with arguments {:?}",
+        aumid,
+        arguments.unwrap_or("")
+    );
+
+    timings::time(Phase::Activation, || unsafe {
+        // Create ApplicationActivationManager
+        let activation_manager: IApplicationActivationManager =
+            CoCreateInstance(&ApplicationActivationManager, None, CLSCTX_INPROC_SERVER)
+                .map_err(|e| WinError::new("CoCreateInstance(ApplicationActivationManager)", &e))?;
+
+        let aumid_hstring: HSTRING = HSTRING::from(aumid);
+        let arguments_hstring = arguments.map(HSTRING::from);
+        let arguments = arguments_hstring
+            .as_ref()
+            .map_or(PCWSTR::null(), |arguments| PCWSTR(arguments.as_ptr()));
+
+        // Launch the app and get the process ID (returned directly)
+        activation_manager
+            .ActivateApplication(&aumid_hstring, arguments, AO_NONE)
+            .map_err(|e| WinError::new("IApplicationActivationManager::ActivateApplication", &e))
+    })
+}
+
+/// The process [`launch_uwp`] activated.
+#[derive(Debug, Clone)]
+pub struct LaunchResult {
+    pub pid: u32,
+    /// Full path of the activated executable, when it could be queried
+    pub path: Option<String>,
+    pub package_family_name: Option<String>,
+}
+
+/// Activates an app without monitoring it, for tools that follow the process
+/// themselves or hand it to [`monitor::monitor`]. [`LaunchOptions`] does both
+/// and is usually what embedders want.
+pub fn launch_uwp(aumid: &str) -> Result<LaunchResult, WinError> {
+    let pid = activate(aumid, None)?;
+    Ok(LaunchResult {
+        pid,
+        path: get_process_info(pid).map(|info| info.path),
+        package_family_name: tree::get_package_family_name(pid),
+    })
+}
+
+/// What dropping a [`Session`] does when the app is still running. Cleanup
+/// hooks run at drop time either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DropBehavior {
+    /// Leave the app running. Monitoring continues in the background and the
+    /// session stays resumable with `librarylink resume`.
+    #[default]
+    Detach,
+    /// Terminate the tracked process and wait for monitoring to finish.
+    Kill,
+}
+
+type UpdateHook = Box<dyn FnOnce(&PackageUpdate) + Send>;
+
+/// Hooks run before activation when the package was updated since the app's
+/// previous session.
+#[derive(Default)]
+struct UpdateHooks(Vec<UpdateHook>);
+
+impl fmt::Debug for UpdateHooks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("UpdateHooks")
+            .field("count", &self.0.len())
+            .finish()
+    }
+}
+
+/// Describes an app launch for the library API.
+///
+/// ```no_run
+/// use librarylink::LaunchOptions;
+///
+/// let mut session = LaunchOptions::new("Microsoft.WindowsCalculator_8wekyb3d8bbwe!App")
+///     .wait_for("CalculatorApp.exe")
+///     .kill_on_drop(true)
+///     .launch()?;
+/// for event in session.events().try_iter() {
+///     println!("{:?}", event);
+/// }
+/// session.wait();
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct LaunchOptions {
+    /// The AUMID, or for a command the program, identifying the session
+    aumid: String,
+    /// Start `aumid` as a program instead of activating an app
+    command: bool,
+    /// A protocol URI to launch through its scheme's handler, which is
+    /// looked up at launch
+    uri: Option<String>,
+    args: Vec<String>,
+    /// A command's starting directory
+    working_dir: Option<PathBuf>,
+    wait_for: Option<String>,
+    on_drop: DropBehavior,
+    cleanup: CleanupHooks,
+    on_update: UpdateHooks,
+    scope: MonitorScope,
+    /// Searched for replacements, the first in place of the activated
+    /// process's directory
+    watch_dirs: Vec<PathBuf>,
+    filters: ProcessFilters,
+    #[cfg(feature = "wts")]
+    session: SessionRequirement,
+    polling: PollSettings,
+    close_launchers: bool,
+    kill_on_exit: bool,
+    system: SystemSettings,
+    exclusive: bool,
+    #[cfg(feature = "presentmon")]
+    presentmon: Option<PathBuf>,
+    if_updating: UpdateGuard,
+    /// Record a command's stdout and stderr, up to this many bytes
+    capture_output: Option<u64>,
+    timeouts: StateTimeouts,
+    placement: WindowPlacement,
+    auto_restore: bool,
+    restore_after: Option<Duration>,
+    tuning: ProcessTuning,
+    /// Follow the app once it's launched
+    monitor: bool,
+    debug_tree: bool,
+}
+
+impl LaunchOptions {
+    pub fn new(aumid: impl Into<String>) -> Self {
+        LaunchOptions {
+            aumid: aumid.into(),
+            command: false,
+            uri: None,
+            args: Vec::new(),
+            working_dir: None,
+            wait_for: None,
+            on_drop: DropBehavior::Detach,
+            cleanup: CleanupHooks::default(),
+            on_update: UpdateHooks::default(),
+            scope: MonitorScope::DirTree,
+            watch_dirs: Vec::new(),
+            filters: ProcessFilters::default(),
+            #[cfg(feature = "wts")]
+            session: SessionRequirement::Any,
+            polling: PollSettings::default(),
+            close_launchers: false,
+            kill_on_exit: false,
+            system: SystemSettings::default(),
+            exclusive: false,
+            #[cfg(feature = "presentmon")]
+            presentmon: None,
+            if_updating: UpdateGuard::Wait,
+            capture_output: None,
+            timeouts: StateTimeouts::default(),
+            placement: WindowPlacement::default(),
+            auto_restore: false,
+            restore_after: None,
+            tuning: ProcessTuning::default(),
+            monitor: true,
+            debug_tree: false,
+        }
+    }
+
+    /// Runs a program (a path, or a name searched for on the `PATH`) instead
+    /// of activating an app, with the same monitoring as an app gets. The
+    /// session records the program in place of an AUMID.
+    pub fn command(program: impl Into<String>) -> Self {
+        LaunchOptions {
+            command: true,
+            ..Self::new(program)
+        }
+    }
+
+    /// Launches a protocol URI, such as `ms-xbl-3d8b930f://` or
+    /// `discord://`, with the app registered for its scheme, with the same
+    /// monitoring as activating an app. A packaged handler is activated for
+    /// the protocol and the session records its AUMID; a desktop handler is
+    /// started by the shell and the session records the URI. Arguments
+    /// aren't passed to either.
+    pub fn uri(uri: impl Into<String>) -> Self {
+        let uri = uri.into();
+        LaunchOptions {
+            uri: Some(uri.clone()),
+            ..Self::new(uri)
+        }
+    }
+
+    /// Arguments passed to the app's activation, or the command's arguments.
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Starts a command in this directory instead of the current one. Apps
+    /// always start in their package's.
+    pub fn working_dir(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.working_dir = Some(directory.into());
+        self
+    }
+
+    /// Searches this directory for the game instead of the activated
+    /// process's, for launchers installed apart from the game they start.
+    /// Further calls add directories searched as well, for helpers that run
+    /// from elsewhere.
+    pub fn watch_dir(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.watch_dirs.push(directory.into());
+        self
+    }
+
+    /// Never tracks processes with this executable name, or matching a glob
+    /// such as `crashpad*.exe`, so a crash reporter or updater that outlives
+    /// the game doesn't keep the session going.
+    pub fn ignore_process(mut self, name: impl Into<String>) -> Self {
+        self.filters.ignore.push(name.into());
+        self
+    }
+
+    /// Tracks processes with this executable name or glob wherever they run
+    /// from, for helpers the game needs that are installed apart from it.
+    pub fn allow_process(mut self, name: impl Into<String>) -> Self {
+        self.filters.allow.push(name.into());
+        self
+    }
+
+    /// Tracks the first process with this executable name instead of the
+    /// activated one, for apps that start through a launcher. A pattern such
+    /// as `Binaries/Win64-*/Game.exe` or `**/Game.exe` is matched against the
+    /// install tree instead, for games whose executable moves between patches.
+    pub fn wait_for(mut self, executable: impl Into<String>) -> Self {
+        self.wait_for = Some(executable.into());
+        self
+    }
+
+    /// Kills the tracked process when the `Session` is dropped. Shorthand for
+    /// `on_drop(DropBehavior::Kill)`.
+    pub fn kill_on_drop(self, kill_on_drop: bool) -> Self {
+        self.on_drop(if kill_on_drop {
+            DropBehavior::Kill
+        } else {
+            DropBehavior::Detach
+        })
+    }
+
+    pub fn on_drop(mut self, behavior: DropBehavior) -> Self {
+        self.on_drop = behavior;
+        self
+    }
+
+    /// Adds a hook that runs exactly once when the session ends: after the app
+    /// exits, when the `Session` is dropped, or when the launch fails.
+    pub fn cleanup(mut self, hook: impl FnOnce() + Send + 'static) -> Self {
+        self.cleanup.push(hook);
+        self
+    }
+
+    /// Adds a hook that runs before activation when the app's package version
+    /// differs from the one recorded for its previous session, such as after
+    /// the Store updated the game in the background. The launch waits for it.
+    pub fn on_update(mut self, hook: impl FnOnce(&PackageUpdate) + Send + 'static) -> Self {
+        self.on_update.0.push(Box::new(hook));
+        self
+    }
+
+    pub fn scope(mut self, scope: MonitorScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    #[cfg(feature = "wts")]
+    pub fn session(mut self, requirement: SessionRequirement) -> Self {
+        self.session = requirement;
+        self
+    }
+
+    /// Polls at this interval instead of waiting on process handles.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.polling.interval = interval;
+        self.polling.forced = true;
+        self
+    }
+
+    /// Skips replacement candidates that exit before living this long, such
+    /// as updaters that run between a launcher and the game.
+    pub fn min_lifetime(mut self, lifetime: Duration) -> Self {
+        self.polling.min_lifetime = lifetime;
+        self
+    }
+
+    /// Closes store clients (EA app, Ubisoft Connect, Xbox app) that weren't
+    /// running before the launch once the session ends.
+    pub fn close_launchers(mut self, close_launchers: bool) -> Self {
+        self.close_launchers = close_launchers;
+        self
+    }
+
+    /// Terminates the game's process tree when this process exits, whether
+    /// on Ctrl+C, the console closing, or being terminated, so a host that
+    /// stops librarylink stops the game too. See [`quit::kill_on_exit`].
+    pub fn kill_on_exit(mut self, kill_on_exit: bool) -> Self {
+        self.kill_on_exit = kill_on_exit;
+        self
+    }
+
+    /// System settings to change for the session. The original values are
+    /// restored when it ends, however it ends; see [`SystemStateGuard`].
+    pub fn system(mut self, settings: SystemSettings) -> Self {
+        self.system = settings;
+        self
+    }
+
+    /// Marks the session as exclusive, so launches arbitrated with
+    /// [`concurrency::arbitrate`](crate::concurrency::arbitrate) wait for it
+    /// or terminate it.
+    pub fn exclusive(mut self, exclusive: bool) -> Self {
+        self.exclusive = exclusive;
+        self
+    }
+
+    /// Captures frame times with this PresentMon executable for the length of
+    /// the session; the statistics are attached to its [`SessionRecord`].
+    /// [`presentmon::locate`](crate::presentmon::locate) finds a bundled one.
+    #[cfg(feature = "presentmon")]
+    pub fn presentmon(mut self, presentmon: impl Into<PathBuf>) -> Self {
+        self.presentmon = Some(presentmon.into());
+        self
+    }
+
+    /// Records a command's stdout and stderr to a log in
+    /// [`capture::logs_dir`], keeping at most `limit` bytes. The log's path
+    /// is attached to the [`SessionRecord`]. Apps have no output to capture.
+    pub fn capture_output(mut self, limit: u64) -> Self {
+        self.capture_output = Some(limit);
+        self
+    }
+
+    /// What to do when the Store is installing or updating the app's package
+    /// at launch: wait for it (the default) or fail.
+    pub fn if_updating(mut self, guard: UpdateGuard) -> Self {
+        self.if_updating = guard;
+        self
+    }
+
+    /// Keeps the session going if a new process of the game appears within
+    /// `window` of the tracked one exiting, for games that restart themselves
+    /// to apply settings.
+    pub fn follow_restarts(mut self, window: Duration) -> Self {
+        self.polling.restart_window = window;
+        self
+    }
+
+    /// How long a replacement has to appear after the tracked process exits
+    /// before the app counts as exited (5 seconds by default), for loader
+    /// stubs that take a while to start the game.
+    pub fn grace_period(mut self, period: Duration) -> Self {
+        self.polling.grace_period = period;
+        self
+    }
+
+    /// Gives up with [`EndReason::ActivationTimeout`] if the `wait_for`
+    /// process hasn't appeared `timeout` after activation, instead of the
+    /// default 60 seconds.
+    pub fn activation_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.activation = Some(timeout);
+        self
+    }
+
+    /// Ends the session with [`EndReason::HandoffTimeout`] if the activated
+    /// process exits and no other process of the game appears within
+    /// `timeout`, for games that always start through a launcher. Without
+    /// it, that's an ordinary exit after the usual handoff window.
+    pub fn handoff_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.handoff = Some(timeout);
+        self
+    }
+
+    /// Moves the game's window to a monitor and resizes its client area once
+    /// it appears, in physical pixels whatever each monitor's scale.
+    pub fn placement(mut self, placement: WindowPlacement) -> Self {
+        self.placement = placement;
+        self
+    }
+
+    /// Restores the game's window whenever it becomes minimized, such as
+    /// after losing focus in exclusive fullscreen on a streaming host.
+    pub fn auto_restore(mut self, auto_restore: bool) -> Self {
+        self.auto_restore = auto_restore;
+        self
+    }
+
+    /// Restores the game's window and brings it back to the front once it
+    /// has been minimized or in the background for `after`, for streaming
+    /// hosts where nobody is at the machine to do it.
+    pub fn restore_minimized(mut self, after: Duration) -> Self {
+        self.restore_after = Some(after);
+        self
+    }
+
+    /// Runs the game at `priority` instead of the priority class it starts
+    /// with.
+    pub fn priority(mut self, priority: PriorityClass) -> Self {
+        self.tuning.priority = Some(priority);
+        self
+    }
+
+    /// Keeps the game on the CPUs set in `mask`, bit `n` being CPU `n`.
+    pub fn affinity(mut self, mask: u64) -> Self {
+        self.tuning.affinity = Some(mask);
+        self
+    }
+
+    /// Gives processes that take over from the launched one the priority
+    /// and affinity as well, for launchers that start the game themselves.
+    pub fn tune_replacements(mut self, tune_replacements: bool) -> Self {
+        self.tuning.replacements = tune_replacements;
+        self
+    }
+
+    /// Follows the app once it's launched, the default. Without monitoring
+    /// the session ends at activation: nothing is saved for `resume`, store
+    /// clients aren't closed and [`Session::wait`] returns straight away.
+    pub fn monitor(mut self, monitor: bool) -> Self {
+        self.monitor = monitor;
+        self
+    }
+
+    /// Prints the process tree whenever monitoring searches for a
+    /// replacement, for working out which process a launcher hands off to.
+    pub fn debug_tree(mut self, debug_tree: bool) -> Self {
+        self.debug_tree = debug_tree;
+        self
+    }
+
+    /// Activates the app and starts monitoring it on a background thread.
+    ///
+    /// May be called from any thread, and several sessions can run at once;
+    /// see [`activate`] for how COM is set up.
+    pub fn launch(mut self) -> Result<Session, Box<dyn std::error::Error>> {
+        let (sender, events) = mpsc::channel();
+        let reporter = ChannelReporter::new(sender);
+
+        // A packaged handler gets the checks an app does; a desktop one is
+        // started like a command
+        if let Some(uri) = &self.uri {
+            match timings::time(Phase::Lookup, || protocol::handler(uri))? {
+                Handler::App { aumid } => self.aumid = aumid,
+                Handler::Desktop { .. } => self.command = true,
+            }
+        }
+
+        reporter.event(&Event::LaunchRequested {
+            aumid: self.aumid.clone(),
+        });
+
+        #[cfg(feature = "wts")]
+        let launch_session = {
+            let launch_session = wts::check_launch_session(self.session)?;
+            reporter.event(&Event::SessionDetected {
+                session_id: launch_session.id,
+                kind: launch_session.kind.to_string(),
+                station_name: launch_session.station_name.clone(),
+            });
+            if launch_session.kind == wts::SessionKind::Remote {
+                reporter.message(
+                    Level::Warning,
+                    "⚠️ Running in a Remote Desktop session; the app will not appear on the console",
+                );
+            }
+            launch_session
+        };
+
+        // Mock apps are stand-ins started as commands
+        #[cfg(feature = "cli")]
+        if !self.command && self.uri.is_none() && crate::mock::is_enabled() {
+            let (program, args) = crate::mock::prepare(&self.aumid, &self.args)?;
+            self.aumid = program;
+            self.args = args;
+            self.command = true;
+        }
+
+        if !self.command {
+            package::guard_deployment(
+                &self.aumid,
+                self.if_updating,
+                package::UPDATE_TIMEOUT,
+                &reporter,
+            )?;
+        }
+
+        // Commands have no package to compare
+        let package_version = (!self.command)
+            .then(|| package::installed_version(&self.aumid))
+            .flatten();
+        // Activation's own error doesn't say what's wrong with the AUMID
+        if !self.command {
+            if !Capability::AppInfoLookup.is_supported() {
+                reporter.message(
+                    Level::Warning,
+                    &format!(
+                        "⚠️ {}; {}",
+                        Capability::AppInfoLookup.unsupported_message(),
+                        Capability::AppInfoLookup.fallback()
+                    ),
+                );
+            }
+            reporter.event(&apps::find_app(&self.aumid)?.event());
+        }
+        let update = package_version
+            .as_deref()
+            .and_then(|version| PackageUpdate::detect(&self.aumid, version));
+        if let Some(update) = &update {
+            reporter.event(&update.event());
+            for hook in std::mem::take(&mut self.on_update.0) {
+                hook(update);
+            }
+        }
+
+        if !self.system.is_empty() {
+            let guard = SystemStateGuard::new();
+            guard.apply(&self.system)?;
+            self.cleanup.push(move || guard.restore());
+        }
+        if self.close_launchers && self.monitor {
+            let launchers = LauncherSnapshot::take();
+            self.cleanup.push(move || launchers.close_new());
+        }
+
+        let mut output_log = None;
+        let activated_process_id = if let Some(uri) = &self.uri {
+            if self.command {
+                protocol::open(uri)?
+            } else {
+                protocol::activate(&self.aumid, uri)?
+            }
+        } else if let (true, Some(limit)) = (self.command, self.capture_output) {
+            let (process_id, log) = timings::time(Phase::Activation, || {
+                capture::spawn_captured(&self.aumid, &self.args, self.working_dir.as_deref(), limit)
+            })?;
+            reporter.event(&Event::OutputCaptured {
+                path: log.display().to_string(),
+            });
+            output_log = Some(log);
+            process_id
+        } else if self.command {
+            timings::time(Phase::Activation, || {
+                spawn_process(&self.aumid, &self.args, self.working_dir.as_deref(), false)
+            })?
+        } else {
+            let arguments = (!self.args.is_empty()).then(|| process::join_arguments(&self.args));
+            activate(&self.aumid, arguments.as_deref())?
+        };
+        reporter.event(&Event::Launched {
+            pid: activated_process_id,
+        });
+        #[cfg(feature = "wts")]
+        if !self.command
+            && let Some(session_id) = wts::session_id_for_process(activated_process_id)
+            && session_id != launch_session.id
+        {
+            reporter.message(
+                Level::Warning,
+                &format!(
+                    "⚠️ App was activated in session {} ({}), not the launching session {}",
+                    session_id,
+                    wts::describe_session(session_id).kind,
+                    launch_session.id
+                ),
+            );
+        }
+        let control = Arc::new(MonitorControl::default());
+        if !self.monitor {
+            return Ok(Session {
+                id: String::new(),
+                events,
+                control,
+                cleanup: Arc::new(self.cleanup),
+                monitor: None,
+                on_drop: DropBehavior::Detach,
+            });
+        }
+        control.enter(
+            MonitorState::Activated {
+                pid: activated_process_id,
+            },
+            &reporter,
+        );
+        if let Err(e) = control.follow_descendants(activated_process_id) {
+            reporter.message(
+                Level::Warning,
+                &format!(
+                    "⚠️ Only the install directory will be searched for the game: {}",
+                    e
+                ),
+            );
+        }
+        if self.kill_on_exit {
+            quit::kill_on_exit(&control);
+        }
+
+        // An activation stub that already handed off leaves the watch or
+        // install directory to find the game in
+        let activated = get_process_info(activated_process_id);
+        let process_dir = match (self.watch_dirs.first(), &activated) {
+            (Some(directory), _) => directory.display().to_string(),
+            (None, Some(activated)) => get_directory_from_path(&activated.path),
+            (None, None) => (!self.command)
+                .then(|| package::installed_path(&self.aumid))
+                .flatten()
+                .map(|path| path.display().to_string())
+                .ok_or_else(|| {
+                    format!(
+                        "Could not get process information for process {}",
+                        activated_process_id
+                    )
+                })?,
+        };
+        let package_family_name =
+            tree::get_package_family_name(activated_process_id).or_else(|| {
+                (!self.command)
+                    .then(|| package::family_name(&self.aumid).map(str::to_string))
+                    .flatten()
+            });
+        let target = MonitorTarget::new(&process_dir, package_family_name, self.scope)
+            .extra_directories(
+                self.watch_dirs
+                    .iter()
+                    .skip(1)
+                    .map(|directory| directory.display().to_string()),
+            )
+            .filters(self.filters.clone());
+        if self.scope == MonitorScope::Package && target.scope != MonitorScope::Package {
+            reporter.message(
+                Level::Warning,
+                "⚠️ Package family unknown; falling back to dir-tree scope",
+            );
+        }
+
+        let mut process_id = activated_process_id;
+        let mut path = match activated {
+            Some(activated) => activated.path,
+            None => {
+                process_id = match monitor::wait_for_first_process(
+                    activated_process_id,
+                    &target,
+                    self.timeouts.activation,
+                    &self.polling,
+                    &reporter,
+                ) {
+                    Some(process_id) => process_id,
+                    None => {
+                        let reason = EndReason::ActivationTimeout;
+                        control.enter(MonitorState::Ended { reason }, &reporter);
+                        return Err(StateTimeout {
+                            reason,
+                            limit: self
+                                .timeouts
+                                .activation
+                                .unwrap_or(monitor::FIRST_PROCESS_TIMEOUT),
+                        }
+                        .into());
+                    }
+                };
+                get_process_info(process_id)
+                    .map_or_else(|| "<Unknown>".to_string(), |info| info.path)
+            }
+        };
+        if let Some(executable) = &self.wait_for {
+            let resolved = executable::resolve(
+                executable,
+                &target.directory,
+                tree::get_package_full_name(activated_process_id).as_deref(),
+            );
+            let limit = self.timeouts.activation.unwrap_or(WAIT_FOR_TIMEOUT);
+            process_id =
+                match monitor::wait_for_process(&target, Some(&resolved), limit, &self.polling) {
+                    Some(process_id) => process_id,
+                    None => {
+                        let reason = EndReason::ActivationTimeout;
+                        control.enter(MonitorState::Ended { reason }, &reporter);
+                        return Err(StateTimeout { reason, limit }.into());
+                    }
+                };
+            if let Some(process_info) = get_process_info(process_id) {
+                path = process_info.path;
+            }
+        }
+
+        // Persist the session so the CLI can resume it if the host goes away
+        let mut state = SessionState::new(&self.aumid, process_id, target.clone());
+        state.exclusive = self.exclusive;
+        state.package_version = package_version;
+        state.package_updated = update.is_some();
+        state.output_log = output_log;
+        #[cfg(feature = "presentmon")]
+        if let Some(presentmon) = &self.presentmon {
+            state.record_frames(presentmon);
+        }
+        state.save()?;
+
+        reporter.event(&Event::MonitoringStarted {
+            session_id: state.id.clone(),
+            pid: process_id,
+            path,
+            directory: process_dir,
+            scope: target.scope,
+        });
+
+        let id = state.id.clone();
+        let monitor_control = Arc::clone(&control);
+        let cleanup = Arc::new(self.cleanup);
+        let monitor_cleanup = Arc::clone(&cleanup);
+        let options = MonitorOptions {
+            debug_tree: self.debug_tree,
+            polling: self.polling,
+            first_is_game: self.wait_for.is_some(),
+            timeouts: self.timeouts,
+            placement: self.placement,
+            auto_restore: self.auto_restore,
+            restore_after: self.restore_after,
+            tuning: self.tuning,
+        };
+        let monitor = thread::spawn(move || {
+            let record = monitor::monitor_process(
+                process_id,
+                &target,
+                &options,
+                &mut state,
+                &monitor_control,
+                &reporter,
+            );
+            monitor_cleanup.run();
+            record
+        });
+
+        Ok(Session {
+            id,
+            events,
+            control,
+            cleanup,
+            monitor: Some(monitor),
+            on_drop: self.on_drop,
+        })
+    }
+}
+
+/// A launched app being monitored in the background.
+///
+/// `Session` is `Send`, so it can be handed to another thread or task, but not
+/// `Sync`: share it behind a `Mutex` if several threads need it.
+pub struct Session {
+    id: String,
+    events: Receiver<Event>,
+    control: Arc<MonitorControl>,
+    cleanup: Arc<CleanupHooks>,
+    monitor: Option<JoinHandle<SessionRecord>>,
+    on_drop: DropBehavior,
+}
+
+impl Session {
+    /// The persisted session ID, as shown by `librarylink resume`; empty
+    /// for a launch without [`monitor`](LaunchOptions::monitor)ing.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The process currently being tracked. Changes when monitoring follows a
+    /// replacement process.
+    pub fn pid(&self) -> u32 {
+        self.control.tracked_pid()
+    }
+
+    /// Where monitoring is: following the launcher, searching for a
+    /// handoff, following the game, or ended.
+    pub fn state(&self) -> Option<MonitorState> {
+        self.control.state()
+    }
+
+    /// The exit code of the game's final process once the session has
+    /// ended, when it could be read.
+    pub fn exit_code(&self) -> Option<u32> {
+        self.control.exit_code()
+    }
+
+    /// Events reported while the session runs, ending with `SessionEnded`.
+    pub fn events(&self) -> &Receiver<Event> {
+        &self.events
+    }
+
+    /// Whether monitoring is still following the app.
+    pub fn is_running(&self) -> bool {
+        self.monitor
+            .as_ref()
+            .is_some_and(|monitor| !monitor.is_finished())
+    }
+
+    /// Blocks until nothing belonging to the app is left running. Returns the
+    /// finished session, or `None` if it was already waited on.
+    pub fn wait(&mut self) -> Option<SessionRecord> {
+        self.monitor.take()?.join().ok()
+    }
+
+    /// Terminates the tracked process and stops following replacements.
+    pub fn kill(&self) -> Result<(), WinError> {
+        self.control.kill()
+    }
+
+    /// A handle for killing the session or reading its tracked process from
+    /// other threads, while this thread consumes the events.
+    pub fn control(&self) -> Arc<MonitorControl> {
+        Arc::clone(&self.control)
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        if self.on_drop == DropBehavior::Kill && self.is_running() {
+            let _ = self.kill();
+            self.wait();
+        }
+        // No-op if the monitor already ran the hooks
+        self.cleanup.run();
+    }
+}
+
+// Hosts move sessions between threads; keep that from regressing silently
+const _: fn() = || {
+    fn assert_send<T: Send>() {}
+    assert_send::<Session>();
+};
@@ -0,0 +1,63 @@
+//! Store clients that games start on their own and that linger after the
+//! game exits.
+
+use crate::process::{find_processes_by_name, terminate_process};
+use crate::report::{self, Event, warning};
+
+/// Known clients and the processes that make them up.
+const KNOWN_LAUNCHERS: &[(&str, &[&str])] = &[
+    ("EA app", &["EADesktop.exe"]),
+    (
+        "Ubisoft Connect",
+        &["UbisoftConnect.exe", "upc.exe", "UplayWebCore.exe"],
+    ),
+    ("Xbox app", &["XboxPcApp.exe", "XboxPcAppFT.exe"]),
+];
+
+/// Which known launchers were running when a session started.
+#[derive(Debug, Clone)]
+pub struct LauncherSnapshot {
+    running: Vec<&'static str>,
+}
+
+fn is_running(processes: &[&str]) -> bool {
+    processes
+        .iter()
+        .any(|process| !find_processes_by_name(process).is_empty())
+}
+
+impl LauncherSnapshot {
+    pub fn take() -> Self {
+        LauncherSnapshot {
+            running: KNOWN_LAUNCHERS
+                .iter()
+                .filter(|(_, processes)| is_running(processes))
+                .map(|(name, _)| *name)
+                .collect(),
+        }
+    }
+
+    /// Terminates the known launchers that weren't running at the snapshot.
+    pub fn close_new(&self) {
+        for (name, processes) in KNOWN_LAUNCHERS {
+            if self.running.contains(name) || !is_running(processes) {
+                continue;
+            }
+            for process in *processes {
+                for process_id in find_processes_by_name(process) {
+                    if let Err(e) = terminate_process(process_id) {
+                        warning!(
+                            "⚠️ Could not close {} (process {}): {}",
+                            name,
+                            process_id,
+                            e
+                        );
+                    }
+                }
+            }
+            report::emit(Event::LauncherClosed {
+                name: name.to_string(),
+            });
+        }
+    }
+}
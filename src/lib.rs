@@ -0,0 +1,122 @@
+//! Launches UWP and Game Pass apps and follows them through launcher handoffs
+//! until the game exits.
+//!
+//! The `librarylink` CLI is built on this library; embedders usually start
+//! with [`LaunchOptions`]. [`list_apps`], [`launch_uwp`] and [`monitor()`] are
+//! the same steps taken one at a time.
+
+pub mod acl;
+pub mod apps;
+pub mod atomic_file;
+pub mod audio;
+#[cfg(feature = "json")]
+pub mod audit;
+#[cfg(all(feature = "json", feature = "presentmon"))]
+pub mod bench;
+pub mod capture;
+pub mod cleanup;
+pub mod client;
+pub mod clock;
+pub mod compat;
+pub mod concurrency;
+#[cfg(feature = "json")]
+pub mod config;
+#[cfg(feature = "json")]
+pub mod control;
+#[cfg(all(feature = "json", feature = "secret"))]
+pub mod daemon;
+pub mod dependency;
+#[cfg(feature = "json")]
+pub mod doctor;
+#[cfg(feature = "elevation")]
+pub mod elevation;
+pub mod error;
+pub mod eventlog;
+pub mod executable;
+pub mod exit;
+#[cfg(feature = "cli")]
+pub mod export;
+#[cfg(feature = "json")]
+pub mod features;
+pub mod games;
+pub mod gpu;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod icon;
+pub mod job;
+pub mod launch;
+pub mod launchers;
+pub mod matcher;
+#[cfg(all(feature = "json", feature = "http", feature = "secret"))]
+pub mod metadata;
+pub mod metrics;
+#[cfg(feature = "cli")]
+pub mod mock;
+pub mod monitor;
+pub mod named_mutex;
+pub mod package;
+pub mod patch;
+pub mod paths;
+#[cfg(feature = "picker")]
+pub mod picker;
+pub mod placement;
+pub mod poll;
+pub mod preflight;
+#[cfg(feature = "presentmon")]
+pub mod presentmon;
+pub mod priority;
+pub mod probe;
+pub mod process;
+#[cfg(feature = "json")]
+pub mod profile;
+pub mod protocol;
+pub mod quit;
+pub mod registry;
+#[cfg(feature = "json")]
+pub mod repair;
+pub mod report;
+pub mod resolve;
+pub mod retention;
+#[cfg(feature = "json")]
+pub mod rpc;
+pub mod search;
+#[cfg(feature = "secret")]
+pub mod secret;
+pub mod session;
+#[cfg(feature = "shortcut")]
+pub mod shortcut;
+#[cfg(feature = "json")]
+pub mod sink;
+#[cfg(feature = "json")]
+pub mod snapshot;
+#[cfg(feature = "splash")]
+pub mod splash;
+#[cfg(feature = "steam")]
+pub mod steam;
+#[cfg(all(feature = "json", feature = "http", feature = "secret"))]
+pub mod sunshine;
+pub mod system;
+pub mod template;
+#[cfg(test)]
+mod testing;
+pub mod timings;
+#[cfg(feature = "toast")]
+pub mod toast;
+#[cfg(all(feature = "json", feature = "secret"))]
+pub mod token;
+#[cfg(feature = "json")]
+pub mod toml;
+pub mod tree;
+#[cfg(feature = "steam")]
+pub mod vdf;
+#[cfg(all(feature = "json", feature = "http"))]
+pub mod webhook;
+pub mod window;
+pub mod window_state;
+pub mod winpath;
+#[cfg(feature = "wts")]
+pub mod wts;
+
+pub use apps::{AppFilter, list_apps, list_apps_matching};
+pub use launch::{DropBehavior, LaunchOptions, LaunchResult, Session, launch_uwp};
+pub use monitor::monitor;
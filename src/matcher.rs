@@ -0,0 +1,278 @@
+use std::fmt;
+
+use crate::executable;
+use crate::process::get_directory_from_path;
+use crate::{tree, winpath};
+
+/// Which processes may take over monitoring when the tracked one goes away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "json", serde(rename_all = "kebab-case"))]
+pub enum MonitorScope {
+    /// Only processes whose executable is directly in the target directory
+    ExactDir,
+    /// Processes anywhere under the target directory
+    DirTree,
+    /// Processes belonging to the same package, wherever they live
+    Package,
+}
+
+impl MonitorScope {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "exact-dir" => Some(MonitorScope::ExactDir),
+            "dir-tree" => Some(MonitorScope::DirTree),
+            "package" => Some(MonitorScope::Package),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for MonitorScope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            MonitorScope::ExactDir => "exact-dir",
+            MonitorScope::DirTree => "dir-tree",
+            MonitorScope::Package => "package",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Executable names, or globs such as `crashpad*.exe`, that override where
+/// a process runs from when deciding whether it belongs to the target.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProcessFilters {
+    /// Never tracked, such as crash reporters that outlive the game
+    #[cfg_attr(
+        feature = "json",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub ignore: Vec<String>,
+    /// Tracked wherever they are, such as an anti-cheat service installed
+    /// apart from the game. Ignoring wins over allowing.
+    #[cfg_attr(
+        feature = "json",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub allow: Vec<String>,
+}
+
+impl ProcessFilters {
+    pub fn is_empty(&self) -> bool {
+        self.ignore.is_empty() && self.allow.is_empty()
+    }
+
+    pub fn ignores(&self, name: &str) -> bool {
+        self.ignore.iter().any(|filter| name_matches(filter, name))
+    }
+
+    pub fn allows(&self, name: &str) -> bool {
+        self.allow.iter().any(|filter| name_matches(filter, name))
+    }
+}
+
+fn name_matches(filter: &str, name: &str) -> bool {
+    if filter.contains(['*', '?']) {
+        executable::glob_matches(filter, name)
+    } else {
+        filter.eq_ignore_ascii_case(name)
+    }
+}
+
+/// Describes which processes count as the monitored app.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+pub struct MonitorTarget {
+    pub directory: String,
+    /// Further directories searched as `directory` is, for games whose
+    /// helpers run from elsewhere
+    #[cfg_attr(
+        feature = "json",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub extra_directories: Vec<String>,
+    pub package_family_name: Option<String>,
+    pub scope: MonitorScope,
+    #[cfg_attr(
+        feature = "json",
+        serde(default, skip_serializing_if = "ProcessFilters::is_empty")
+    )]
+    pub filters: ProcessFilters,
+}
+
+impl MonitorTarget {
+    /// Builds a target, falling back to `dir-tree` when package scope was
+    /// requested but the package family isn't known.
+    pub fn new(directory: &str, package_family_name: Option<String>, scope: MonitorScope) -> Self {
+        let scope = if scope == MonitorScope::Package && package_family_name.is_none() {
+            MonitorScope::DirTree
+        } else {
+            scope
+        };
+
+        MonitorTarget {
+            directory: winpath::normalize_path(directory),
+            extra_directories: Vec::new(),
+            package_family_name,
+            scope,
+            filters: ProcessFilters::default(),
+        }
+    }
+
+    /// Also searches these directories. Under package scope, processes
+    /// anywhere beneath them qualify as well as the package's own.
+    pub fn extra_directories<I, S>(mut self, directories: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.extra_directories.extend(
+            directories
+                .into_iter()
+                .map(|directory| winpath::normalize_path(directory.as_ref())),
+        );
+        self
+    }
+
+    pub fn filters(mut self, filters: ProcessFilters) -> Self {
+        self.filters = filters;
+        self
+    }
+
+    /// Human-readable description of where replacements are searched.
+    pub fn describe(&self) -> String {
+        let described = match (&self.scope, &self.package_family_name) {
+            (MonitorScope::Package, Some(family)) => format!("package {}", family),
+            (MonitorScope::ExactDir, _) => format!("directory {} (exact)", self.directory),
+            _ => format!("directory {}", self.directory),
+        };
+        if self.extra_directories.is_empty() {
+            described
+        } else {
+            format!("{} and {}", described, self.extra_directories.join(", "))
+        }
+    }
+
+    /// Whether a process with this executable name and path belongs to the
+    /// target: [`matches_identity`](Self::matches_identity), as the filters
+    /// override it.
+    pub fn admits(
+        &self,
+        name: &str,
+        process_path: &str,
+        package_family_name: impl FnOnce() -> Option<String>,
+    ) -> bool {
+        !self.filters.ignores(name)
+            && (self.filters.allows(name)
+                || self.matches_identity(process_path, package_family_name))
+    }
+
+    /// Whether the process with the given ID and executable path belongs to the target.
+    pub fn matches(&self, process_id: u32, process_path: &str) -> bool {
+        self.matches_identity(process_path, || tree::get_package_family_name(process_id))
+    }
+
+    /// [`matches`](Self::matches) for a process known by its executable path
+    /// and package family, such as one recorded in a snapshot. The family is
+    /// only asked for under package scope. The filters aren't applied; see
+    /// [`admits`](Self::admits).
+    pub fn matches_identity(
+        &self,
+        process_path: &str,
+        package_family_name: impl FnOnce() -> Option<String>,
+    ) -> bool {
+        let mut directories = std::iter::once(&self.directory).chain(self.extra_directories.iter());
+        match self.scope {
+            MonitorScope::Package => {
+                package_family_name().is_some_and(|family| {
+                    self.package_family_name
+                        .as_ref()
+                        .is_some_and(|target| target.eq_ignore_ascii_case(&family))
+                }) || {
+                    let process_path = winpath::normalize_path(process_path);
+                    self.extra_directories
+                        .iter()
+                        .any(|directory| path_is_within(&process_path, directory))
+                }
+            }
+            MonitorScope::ExactDir => {
+                let process_dir = get_directory_from_path(&winpath::normalize_path(process_path));
+                directories.any(|directory| paths_equal(&process_dir, directory))
+            }
+            MonitorScope::DirTree => {
+                let process_path = winpath::normalize_path(process_path);
+                directories.any(|directory| path_is_within(&process_path, directory))
+            }
+        }
+    }
+}
+
+fn path_components(path: &str) -> Vec<String> {
+    // `\\?\C:\Game` names the same location as `C:\Game`
+    winpath::strip_prefixes(path)
+        .split(['\\', '/'])
+        .filter(|component| !component.is_empty())
+        .map(|component| component.to_lowercase())
+        .collect()
+}
+
+/// Whether two paths name the same location, compared component by component
+/// and case-insensitively, ignoring `\\?\` prefixes. Paths are otherwise
+/// compared as given; normalize them first with [`winpath::normalize_path`]
+/// to see through junctions and short names.
+pub fn paths_equal(a: &str, b: &str) -> bool {
+    path_components(a) == path_components(b)
+}
+
+/// Whether `path` is `directory` itself or lies beneath it. Whole components
+/// are compared, so `C:\Games\Foo` does not contain `C:\Games\Foobar\game.exe`.
+pub fn path_is_within(path: &str, directory: &str) -> bool {
+    let path = path_components(path);
+    let directory = path_components(directory);
+    directory.len() <= path.len() && path.iter().zip(&directory).all(|(a, b)| a == b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_equal_ignores_trailing_separators() {
+        assert!(paths_equal(r"C:\Game\", r"C:\Game"));
+        assert!(paths_equal(r"C:\Game", r"C:\Game\\"));
+        assert!(paths_equal("C:/Game/", r"C:\Game"));
+    }
+
+    #[test]
+    fn paths_equal_ignores_case() {
+        assert!(paths_equal(r"c:\game\GAME.EXE", r"C:\Game\game.exe"));
+        assert!(!paths_equal(r"C:\Game", r"C:\Games"));
+    }
+
+    #[test]
+    fn paths_equal_ignores_long_path_prefixes() {
+        assert!(paths_equal(r"\\?\C:\Game", r"C:\Game"));
+        assert!(paths_equal(
+            r"\\?\UNC\server\share\Game",
+            r"\\server\share\Game"
+        ));
+        assert!(!paths_equal(r"\\?\C:\Game", r"C:\Games"));
+    }
+
+    #[test]
+    fn path_is_within_compares_whole_components() {
+        assert!(path_is_within(r"C:\Game\game.exe", r"C:\Game"));
+        assert!(path_is_within(r"C:\Game", r"C:\Game\"));
+        assert!(!path_is_within(r"C:\Games\game.exe", r"C:\Game"));
+        assert!(!path_is_within(r"C:\Game", r"C:\Game\bin"));
+    }
+
+    #[test]
+    fn path_is_within_ignores_case_and_prefixes() {
+        assert!(path_is_within(r"\\?\c:\game\BIN\game.exe", r"C:\Game\bin\"));
+        assert!(path_is_within(r"C:\GAME\game.exe", r"\\?\C:\Game"));
+        assert!(!path_is_within(r"\\?\C:\Games\game.exe", r"C:\Game"));
+    }
+}
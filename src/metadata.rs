@@ -0,0 +1,327 @@
+//! Metadata for exported library entries from an online game database,
+//! since packages only carry a name and a publisher. Titles are looked up
+//! by name, and the answers, misses included, are cached under
+//! `%APPDATA%\librarylink\metadata` so an export only asks about games it
+//! hasn't seen recently.
+//!
+//! [IGDB](https://api-docs.igdb.com) is the one provider so far. It's
+//! configured through the environment: `LIBRARYLINK_IGDB_CLIENT_ID` and
+//! `LIBRARYLINK_IGDB_CLIENT_SECRET`, a Twitch application's credentials. The
+//! client secret can be stored with `secret set igdb_client_secret` instead.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+use crate::atomic_file;
+use crate::clock;
+use crate::http::{self, Request};
+use crate::paths;
+use crate::report::debug;
+use crate::search;
+use crate::secret;
+use crate::session::unix_now;
+
+pub const IGDB_CLIENT_ID_VARIABLE: &str = "LIBRARYLINK_IGDB_CLIENT_ID";
+pub const IGDB_CLIENT_SECRET_VARIABLE: &str = "LIBRARYLINK_IGDB_CLIENT_SECRET";
+
+/// The stored secret used when [`IGDB_CLIENT_SECRET_VARIABLE`] isn't set.
+pub const IGDB_CLIENT_SECRET_NAME: &str = "igdb_client_secret";
+
+const TWITCH_TOKEN_URL: &str = "https://id.twitch.tv/oauth2/token";
+const IGDB_GAMES_URL: &str = "https://api.igdb.com/v4/games";
+const IGDB_COVER_URL: &str = "https://images.igdb.com/igdb/image/upload/t_cover_big";
+
+/// IGDB allows four requests a second.
+const IGDB_REQUEST_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long a cached answer is trusted.
+const CACHE_LIFETIME_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// What a provider knows about a game.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct GameMetadata {
+    /// The provider's name for the game, which may differ from the package's
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub genres: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release_year: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cover_url: Option<String>,
+}
+
+/// A database games can be looked up in by name.
+pub trait MetadataProvider {
+    /// The provider's name, which its cache file is named after.
+    fn name(&self) -> &'static str;
+
+    /// Looks up the game called `title`. `None` if there's no good match.
+    fn lookup(&mut self, title: &str) -> Result<Option<GameMetadata>, Box<dyn std::error::Error>>;
+}
+
+/// Looks up each of `titles`, answering from the cache where it can, and
+/// saves each answer as it comes, so an export cut short keeps what it
+/// learned. A title that can't be looked up, such as when the provider is
+/// unreachable, is `None` and isn't cached.
+pub fn enrich(provider: &mut dyn MetadataProvider, titles: &[String]) -> Vec<Option<GameMetadata>> {
+    let mut cache = MetadataCache::load(provider.name());
+    let now = unix_now();
+    titles
+        .iter()
+        .map(|title| {
+            let key = cache_key(title);
+            if let Some(entry) = cache.entries.get(&key)
+                && now.saturating_sub(entry.fetched) < CACHE_LIFETIME_SECS
+            {
+                return entry.metadata.clone();
+            }
+            match provider.lookup(title) {
+                Ok(metadata) => {
+                    cache.entries.insert(
+                        key,
+                        CacheEntry {
+                            fetched: now,
+                            metadata: metadata.clone(),
+                        },
+                    );
+                    cache.save(provider.name());
+                    metadata
+                }
+                Err(e) => {
+                    debug!("Could not look up {} on {}: {}", title, provider.name(), e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+fn cache_key(title: &str) -> String {
+    title.trim().to_lowercase()
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct MetadataCache {
+    entries: BTreeMap<String, CacheEntry>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct CacheEntry {
+    /// Unix time of the lookup
+    fetched: u64,
+    /// `None` when the provider had no match
+    metadata: Option<GameMetadata>,
+}
+
+pub fn cache_dir() -> PathBuf {
+    paths::data_dir().join("metadata")
+}
+
+impl MetadataCache {
+    fn path(provider: &str) -> PathBuf {
+        cache_dir().join(format!("{}.json", provider))
+    }
+
+    /// The provider's cache; empty when missing or unreadable.
+    fn load(provider: &str) -> Self {
+        fs::read_to_string(Self::path(provider))
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, provider: &str) {
+        if let Ok(contents) = serde_json::to_string_pretty(self) {
+            let _ = atomic_file::write(&Self::path(provider), contents);
+        }
+    }
+}
+
+/// IGDB, reached with a Twitch application's client credentials.
+pub struct Igdb {
+    client_id: String,
+    client_secret: String,
+    /// The app access token and when it expires
+    token: Option<(String, Instant)>,
+    last_request: Option<Instant>,
+}
+
+impl Igdb {
+    /// The provider configured in the environment, or `None` when the
+    /// credentials aren't set.
+    pub fn from_env() -> Option<Self> {
+        let non_empty = |name| {
+            env::var(name)
+                .ok()
+                .filter(|value: &String| !value.is_empty())
+        };
+        Some(Igdb {
+            client_id: non_empty(IGDB_CLIENT_ID_VARIABLE)?,
+            client_secret: secret::setting(IGDB_CLIENT_SECRET_VARIABLE, IGDB_CLIENT_SECRET_NAME)?,
+            token: None,
+            last_request: None,
+        })
+    }
+
+    fn access_token(&mut self) -> Result<String, Box<dyn std::error::Error>> {
+        if let Some((token, expires)) = &self.token
+            && Instant::now() < *expires
+        {
+            return Ok(token.clone());
+        }
+        let body = format!(
+            "client_id={}&client_secret={}&grant_type=client_credentials",
+            http::form_encode(&self.client_id),
+            http::form_encode(&self.client_secret)
+        );
+        let response = http::send(
+            "POST",
+            TWITCH_TOKEN_URL,
+            &Request {
+                headers: vec![(
+                    "Content-Type",
+                    "application/x-www-form-urlencoded".to_string(),
+                )],
+                body: Some(body.as_bytes()),
+                ..Default::default()
+            },
+        )?;
+        if !response.is_success() {
+            return Err(format!(
+                "Twitch refused the IGDB credentials (HTTP {})",
+                response.status
+            )
+            .into());
+        }
+        let answer: Value = serde_json::from_str(&response.body)?;
+        let token = answer["access_token"]
+            .as_str()
+            .ok_or("Twitch's answer had no access token")?
+            .to_string();
+        // Renew a minute early rather than have a request refused
+        let lifetime = answer["expires_in"]
+            .as_u64()
+            .unwrap_or(0)
+            .saturating_sub(60);
+        self.token = Some((
+            token.clone(),
+            Instant::now() + Duration::from_secs(lifetime),
+        ));
+        Ok(token)
+    }
+
+    /// Sends an IGDB query, keeping to the rate limit and retrying once
+    /// when IGDB says to slow down.
+    fn query(&mut self, query: &str) -> Result<Value, Box<dyn std::error::Error>> {
+        let token = self.access_token()?;
+        for attempt in 0..2 {
+            if let Some(last) = self.last_request {
+                let wait = IGDB_REQUEST_INTERVAL.saturating_sub(last.elapsed());
+                thread::sleep(wait);
+            }
+            self.last_request = Some(Instant::now());
+            let response = http::send(
+                "POST",
+                IGDB_GAMES_URL,
+                &Request {
+                    headers: vec![
+                        ("Client-ID", self.client_id.clone()),
+                        ("Authorization", format!("Bearer {}", token)),
+                    ],
+                    body: Some(query.as_bytes()),
+                    ..Default::default()
+                },
+            )?;
+            match response.status {
+                429 if attempt == 0 => thread::sleep(Duration::from_secs(1)),
+                _ if response.is_success() => return Ok(serde_json::from_str(&response.body)?),
+                status => return Err(format!("IGDB answered HTTP {}", status).into()),
+            }
+        }
+        Err("IGDB kept refusing requests".into())
+    }
+}
+
+impl MetadataProvider for Igdb {
+    fn name(&self) -> &'static str {
+        "igdb"
+    }
+
+    fn lookup(&mut self, title: &str) -> Result<Option<GameMetadata>, Box<dyn std::error::Error>> {
+        let term = search_term(title);
+        if term.is_empty() {
+            return Ok(None);
+        }
+        let query = format!(
+            "search \"{}\"; fields name,first_release_date,genres.name,cover.image_id; limit 10;",
+            term.replace('\\', "\\\\").replace('"', "\\\"")
+        );
+        let results = self.query(&query)?;
+        let results = results.as_array().map(Vec::as_slice).unwrap_or_default();
+        // An exact match ignoring case and accents, else IGDB's best guess
+        // when it's at least a close one
+        let best = results
+            .iter()
+            .find(|game| {
+                game["name"]
+                    .as_str()
+                    .is_some_and(|name| search::equals(name, &term))
+            })
+            .or_else(|| {
+                results.first().filter(|game| {
+                    game["name"].as_str().is_some_and(|name| {
+                        search::contains(name, &term) || search::contains(&term, name)
+                    })
+                })
+            });
+        Ok(best.map(|game| GameMetadata {
+            name: game["name"].as_str().unwrap_or(title).to_string(),
+            genres: game["genres"]
+                .as_array()
+                .map(|genres| {
+                    genres
+                        .iter()
+                        .filter_map(|genre| genre["name"].as_str().map(str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            release_year: game["first_release_date"]
+                .as_u64()
+                .and_then(clock::utc_year),
+            cover_url: game["cover"]["image_id"]
+                .as_str()
+                .map(|image_id| format!("{}/{}.jpg", IGDB_COVER_URL, image_id)),
+        }))
+    }
+}
+
+/// A package's display name as a title to search for, without the
+/// trademark signs and edition suffixes stores add to it.
+fn search_term(title: &str) -> String {
+    let title: String = title
+        .chars()
+        .filter(|c| !matches!(c, '™' | '®' | '©'))
+        .collect();
+    let mut term = title.trim();
+    for suffix in [
+        "Standard Edition",
+        "Deluxe Edition",
+        "Premium Edition",
+        "Ultimate Edition",
+        "Game of the Year Edition",
+        "for Windows 10",
+        "(PC)",
+        "(Windows)",
+    ] {
+        if let Some(stripped) = term.strip_suffix(suffix) {
+            term = stripped.trim_end_matches([' ', '-', ':', '–']);
+        }
+    }
+    term.trim().to_string()
+}
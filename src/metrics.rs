@@ -0,0 +1,293 @@
+//! Resource usage samples for a running process: CPU from its process times,
+//! memory from its memory counters and GPU from the `GPU Engine` performance
+//! counters Task Manager reads.
+
+use std::collections::HashMap;
+use std::mem;
+use std::time::Instant;
+use windows::Win32::Foundation::{CloseHandle, FILETIME};
+use windows::Win32::System::Performance::{
+    PDH_FMT_COUNTERVALUE_ITEM_W, PDH_FMT_DOUBLE, PDH_HCOUNTER, PDH_HQUERY, PDH_MORE_DATA,
+    PdhAddEnglishCounterW, PdhCloseQuery, PdhCollectQueryData, PdhGetFormattedCounterArrayW,
+    PdhOpenQueryW,
+};
+use windows::Win32::System::ProcessStatus::{GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS_EX};
+use windows::Win32::System::Threading::{
+    GetProcessTimes, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION,
+};
+use windows::core::{PCWSTR, w};
+
+use crate::session::unix_now;
+
+/// Valid and new-data PDH counter statuses.
+const PDH_CSTATUS_VALID_DATA: u32 = 0;
+const PDH_CSTATUS_NEW_DATA: u32 = 1;
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct ProcessSample {
+    /// Unix time of the sample
+    pub at: u64,
+    pub pid: u32,
+    /// Share of the whole machine's CPU time since the previous sample
+    pub cpu_percent: Option<f64>,
+    /// Utilization of the busiest GPU engine type (3D, copy, video...)
+    pub gpu_percent: Option<f64>,
+    pub working_set_bytes: u64,
+    pub private_bytes: u64,
+}
+
+/// Samples one process at a time. CPU and GPU usage are rates, so the first
+/// sample of a process has none.
+pub struct ProcessSampler {
+    gpu: Option<GpuCounters>,
+    previous: Option<(u32, Instant, u64)>,
+    processors: f64,
+}
+
+impl Default for ProcessSampler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProcessSampler {
+    pub fn new() -> Self {
+        ProcessSampler {
+            gpu: GpuCounters::open(),
+            previous: None,
+            processors: std::thread::available_parallelism().map_or(1, |count| count.get()) as f64,
+        }
+    }
+
+    /// Samples `process_id`, or returns `None` once it can't be opened.
+    pub fn sample(&mut self, process_id: u32) -> Option<ProcessSample> {
+        let now = Instant::now();
+        let (cpu_time, working_set_bytes, private_bytes) = read_process(process_id)?;
+
+        let cpu_percent = match self.previous {
+            Some((previous_pid, previous_at, previous_cpu)) if previous_pid == process_id => {
+                let wall = now.duration_since(previous_at).as_secs_f64();
+                // Process times are in 100ns units
+                let busy = cpu_time.saturating_sub(previous_cpu) as f64 / 10_000_000.0;
+                (wall > 0.0).then(|| busy / wall / self.processors * 100.0)
+            }
+            _ => None,
+        };
+        self.previous = Some((process_id, now, cpu_time));
+
+        Some(ProcessSample {
+            at: unix_now(),
+            pid: process_id,
+            cpu_percent,
+            gpu_percent: self
+                .gpu
+                .as_ref()
+                .and_then(|gpu| gpu.utilization(process_id)),
+            working_set_bytes,
+            private_bytes,
+        })
+    }
+}
+
+/// Total CPU time in 100ns units, working set and private bytes.
+fn read_process(process_id: u32) -> Option<(u64, u64, u64)> {
+    unsafe {
+        let process_handle =
+            OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, process_id).ok()?;
+
+        let mut creation = FILETIME::default();
+        let mut exit = FILETIME::default();
+        let mut kernel = FILETIME::default();
+        let mut user = FILETIME::default();
+        let times = GetProcessTimes(
+            process_handle,
+            &mut creation,
+            &mut exit,
+            &mut kernel,
+            &mut user,
+        );
+
+        let mut counters = PROCESS_MEMORY_COUNTERS_EX {
+            cb: mem::size_of::<PROCESS_MEMORY_COUNTERS_EX>() as u32,
+            ..Default::default()
+        };
+        let memory = GetProcessMemoryInfo(
+            process_handle,
+            &mut counters as *mut PROCESS_MEMORY_COUNTERS_EX as *mut _,
+            counters.cb,
+        );
+        let _ = CloseHandle(process_handle);
+
+        times.ok()?;
+        memory.ok()?;
+        let ticks =
+            |time: FILETIME| ((time.dwHighDateTime as u64) << 32) | time.dwLowDateTime as u64;
+        Some((
+            ticks(kernel) + ticks(user),
+            counters.WorkingSetSize as u64,
+            counters.PrivateUsage as u64,
+        ))
+    }
+}
+
+/// A PDH query over every GPU engine instance. Instance names look like
+/// `pid_1234_luid_0x0_0x1_phys_0_eng_0_engtype_3D`.
+struct GpuCounters {
+    query: PDH_HQUERY,
+    counter: PDH_HCOUNTER,
+}
+
+// PDH query handles may be used from any thread, one at a time
+unsafe impl Send for GpuCounters {}
+
+impl GpuCounters {
+    fn open() -> Option<Self> {
+        let mut query = PDH_HQUERY::default();
+        let mut counter = PDH_HCOUNTER::default();
+        unsafe {
+            if PdhOpenQueryW(PCWSTR::null(), 0, &mut query) != 0 {
+                return None;
+            }
+            if PdhAddEnglishCounterW(
+                query,
+                w!(r"\GPU Engine(*)\Utilization Percentage"),
+                0,
+                &mut counter,
+            ) != 0
+            {
+                let _ = PdhCloseQuery(query);
+                return None;
+            }
+            // Rates need a baseline collection
+            let _ = PdhCollectQueryData(query);
+        }
+        Some(GpuCounters { query, counter })
+    }
+
+    /// The busiest engine type's utilization, summed over the process's
+    /// engines of that type.
+    fn utilization(&self, process_id: u32) -> Option<f64> {
+        let prefix = format!("pid_{}_", process_id);
+        let mut by_type: HashMap<String, f64> = HashMap::new();
+
+        unsafe {
+            if PdhCollectQueryData(self.query) != 0 {
+                return None;
+            }
+            let mut buffer_size = 0u32;
+            let mut item_count = 0u32;
+            if PdhGetFormattedCounterArrayW(
+                self.counter,
+                PDH_FMT_DOUBLE,
+                &mut buffer_size,
+                &mut item_count,
+                None,
+            ) != PDH_MORE_DATA
+            {
+                return None;
+            }
+
+            // The buffer holds the items followed by their names
+            let item_size = mem::size_of::<PDH_FMT_COUNTERVALUE_ITEM_W>();
+            let mut buffer: Vec<PDH_FMT_COUNTERVALUE_ITEM_W> = vec![
+                    PDH_FMT_COUNTERVALUE_ITEM_W::default();
+                    (buffer_size as usize).div_ceil(item_size)
+                ];
+            if PdhGetFormattedCounterArrayW(
+                self.counter,
+                PDH_FMT_DOUBLE,
+                &mut buffer_size,
+                &mut item_count,
+                Some(buffer.as_mut_ptr()),
+            ) != 0
+            {
+                return None;
+            }
+
+            for item in buffer.iter().take(item_count as usize) {
+                if !matches!(
+                    item.FmtValue.CStatus,
+                    PDH_CSTATUS_VALID_DATA | PDH_CSTATUS_NEW_DATA
+                ) {
+                    continue;
+                }
+                let Ok(name) = item.szName.to_string() else {
+                    continue;
+                };
+                if !name.starts_with(&prefix) {
+                    continue;
+                }
+                let engine_type = name
+                    .rsplit_once("engtype_")
+                    .map_or("", |(_, engine_type)| engine_type);
+                *by_type.entry(engine_type.to_string()).or_default() +=
+                    item.FmtValue.Anonymous.doubleValue;
+            }
+        }
+
+        Some(by_type.into_values().fold(0.0, f64::max).min(100.0))
+    }
+}
+
+impl Drop for GpuCounters {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = PdhCloseQuery(self.query);
+        }
+    }
+}
+
+/// Averages and peaks over a run of samples.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct UsageSummary {
+    pub samples: usize,
+    pub avg_cpu_percent: Option<f64>,
+    pub max_cpu_percent: Option<f64>,
+    pub avg_gpu_percent: Option<f64>,
+    pub max_gpu_percent: Option<f64>,
+    pub avg_working_set_bytes: u64,
+    pub peak_working_set_bytes: u64,
+    pub peak_private_bytes: u64,
+}
+
+impl UsageSummary {
+    pub fn from_samples(samples: &[ProcessSample]) -> Self {
+        fn average(values: &[f64]) -> Option<f64> {
+            (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+        }
+        fn maximum(values: &[f64]) -> Option<f64> {
+            values.iter().copied().reduce(f64::max)
+        }
+
+        let cpu: Vec<f64> = samples
+            .iter()
+            .filter_map(|sample| sample.cpu_percent)
+            .collect();
+        let gpu: Vec<f64> = samples
+            .iter()
+            .filter_map(|sample| sample.gpu_percent)
+            .collect();
+        let working_set: u64 = samples.iter().map(|sample| sample.working_set_bytes).sum();
+
+        UsageSummary {
+            samples: samples.len(),
+            avg_cpu_percent: average(&cpu),
+            max_cpu_percent: maximum(&cpu),
+            avg_gpu_percent: average(&gpu),
+            max_gpu_percent: maximum(&gpu),
+            avg_working_set_bytes: working_set.checked_div(samples.len() as u64).unwrap_or(0),
+            peak_working_set_bytes: samples
+                .iter()
+                .map(|sample| sample.working_set_bytes)
+                .max()
+                .unwrap_or(0),
+            peak_private_bytes: samples
+                .iter()
+                .map(|sample| sample.private_bytes)
+                .max()
+                .unwrap_or(0),
+        }
+    }
+}
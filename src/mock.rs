@@ -0,0 +1,185 @@
+//! A stand-in for the Store, selected with `--provider mock`, for developing
+//! frontends on machines without Game Pass or the games installed. The app
+//! list is fixed, and each mock game is a copy of librarylink under
+//! `%APPDATA%\librarylink\mock` running its hidden `mock-game` command, so it
+//! is launched, handed off and monitored like a real one.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use crate::package;
+use crate::paths;
+use crate::report::AppListing;
+use crate::search;
+
+/// How long a launcher waits after starting the next executable, as
+/// bootstrappers do before exiting.
+const HANDOFF_DELAY: Duration = Duration::from_secs(1);
+
+struct MockApp {
+    name: &'static str,
+    aumid: &'static str,
+    folder: &'static str,
+    /// Each executable starts the next and exits; the last one is the game
+    executables: &'static [&'static str],
+    /// How long the game runs before exiting
+    runtime_secs: u64,
+}
+
+const APPS: [MockApp; 3] = [
+    MockApp {
+        name: "Mock Racer",
+        aumid: "LibraryLink.MockRacer_librarylinkmock!App",
+        folder: "Mock Racer",
+        executables: &["RacerLauncher.exe", "Racer.exe"],
+        runtime_secs: 120,
+    },
+    MockApp {
+        name: "Mock Puzzle",
+        aumid: "LibraryLink.MockPuzzle_librarylinkmock!App",
+        folder: "Mock Puzzle",
+        executables: &["Puzzle.exe"],
+        runtime_secs: 60,
+    },
+    // Exits almost immediately, like a launcher that never starts its game
+    MockApp {
+        name: "Mock Broken Launcher",
+        aumid: "LibraryLink.MockBroken_librarylinkmock!App",
+        folder: "Mock Broken Launcher",
+        executables: &["BrokenLauncher.exe"],
+        runtime_secs: 2,
+    },
+];
+
+/// The version every mock app reports.
+const MOCK_VERSION: &str = "1.0.0.0";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Switches listing and launching to the mock apps for the rest of the
+/// process.
+pub fn enable() {
+    ENABLED.store(true, Ordering::SeqCst);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+}
+
+fn install_dir() -> PathBuf {
+    paths::data_dir().join("mock")
+}
+
+/// The mock apps, filtered like `list-apps --search`.
+pub fn apps(search_term: Option<&str>) -> Vec<AppListing> {
+    APPS.iter()
+        .filter(|app| search_term.is_none_or(|term| search::contains(app.name, term)))
+        .map(|app| AppListing {
+            name: app.name.to_string(),
+            aumid: app.aumid.to_string(),
+            package_family_name: package::family_name(app.aumid).map(str::to_string),
+            install_path: Some(install_dir().join(app.folder).display().to_string()),
+            version: Some(MOCK_VERSION.to_string()),
+            publisher: Some("librarylink".to_string()),
+            framework: false,
+            provisioned: false,
+        })
+        .collect()
+}
+
+/// Installs the mock app `aumid` and returns the program and arguments that
+/// start it, for launching as a command. `args` are passed on to the game,
+/// so `--run <seconds>` changes how long it runs.
+pub fn prepare(
+    aumid: &str,
+    args: &[String],
+) -> Result<(String, Vec<String>), Box<dyn std::error::Error>> {
+    let app = APPS
+        .iter()
+        .find(|app| app.aumid.eq_ignore_ascii_case(aumid))
+        .ok_or_else(|| format!("{} isn't a mock app; run list-apps to see them", aumid))?;
+    let directory = install(app)?;
+
+    let mut executables = app.executables.iter().map(|name| directory.join(name));
+    let program = executables
+        .next()
+        .ok_or("Mock app has no executables")?
+        .display()
+        .to_string();
+    let mut arguments = vec!["mock-game".to_string()];
+    for next in executables {
+        arguments.push("--then".to_string());
+        arguments.push(next.display().to_string());
+    }
+    arguments.push("--run".to_string());
+    arguments.push(app.runtime_secs.to_string());
+    arguments.extend(args.iter().cloned());
+    Ok((program, arguments))
+}
+
+/// Copies librarylink to each of the app's executable names.
+fn install(app: &MockApp) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let source = env::current_exe()?;
+    let size = fs::metadata(&source)?.len();
+    let directory = install_dir().join(app.folder);
+    fs::create_dir_all(&directory)?;
+    for name in app.executables {
+        let path = directory.join(name);
+        if is_installed(&path, size) {
+            continue;
+        }
+        // A copy still running from an earlier launch can't be replaced
+        if let Err(e) = fs::copy(&source, &path)
+            && !path.exists()
+        {
+            return Err(format!("Could not install {}: {}", path.display(), e).into());
+        }
+    }
+    Ok(directory)
+}
+
+fn is_installed(path: &Path, size: u64) -> bool {
+    fs::metadata(path).is_ok_and(|metadata| metadata.len() == size)
+}
+
+/// Runs as a mock game, for the hidden `mock-game` command:
+/// `mock-game [--then <exe>]... [--run <seconds>]`. With `--then`, starts the
+/// first of those executables with the rest and exits, like a launcher;
+/// otherwise runs for `--run` seconds (the last one given) and exits.
+pub fn run_game(args: &[String]) {
+    let mut then = Vec::new();
+    let mut runtime_secs = 60;
+    let mut i = 0;
+    while i < args.len() {
+        match (args[i].as_str(), args.get(i + 1)) {
+            ("--then", Some(next)) => {
+                then.push(next.clone());
+                i += 2;
+            }
+            ("--run", Some(seconds)) => {
+                runtime_secs = seconds.parse().unwrap_or(runtime_secs);
+                i += 2;
+            }
+            _ => i += 1,
+        }
+    }
+
+    if let Some((next, rest)) = then.split_first() {
+        let mut command = Command::new(next);
+        command.arg("mock-game");
+        for executable in rest {
+            command.args(["--then", executable]);
+        }
+        command.args(["--run", &runtime_secs.to_string()]);
+        if command.spawn().is_ok() {
+            thread::sleep(HANDOFF_DELAY);
+        }
+        return;
+    }
+    thread::sleep(Duration::from_secs(runtime_secs));
+}
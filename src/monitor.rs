@@ -0,0 +1,965 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use windows::Win32::Foundation::{
+    CloseHandle, LPARAM, STILL_ACTIVE, WAIT_FAILED, WAIT_OBJECT_0, WPARAM,
+};
+use windows::Win32::System::Threading::{
+    GetExitCodeProcess, INFINITE, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION,
+    PROCESS_SYNCHRONIZE, WaitForSingleObject,
+};
+use windows::Win32::UI::WindowsAndMessaging::{PostMessageW, WM_CLOSE};
+
+#[cfg(feature = "json")]
+use crate::control;
+use crate::error::WinError;
+use crate::executable;
+use crate::exit::ExitReason;
+use crate::job::ProcessJob;
+use crate::matcher::{MonitorScope, MonitorTarget};
+use crate::placement::{self, WindowPlacement};
+use crate::poll::PollSettings;
+use crate::priority::ProcessTuning;
+use crate::process::{
+    SuspendedThreads, get_directory_from_path, get_process_age, get_process_creation_time,
+    get_process_info, get_process_infos, is_same_process_running, list_process_ids,
+    suspend_processes, terminate_process,
+};
+use crate::report::{Event, LostReason, Reporter, debug, info, warning};
+use crate::session::{SessionRecord, SessionState};
+use crate::timings::{self, Phase};
+use crate::tree;
+use crate::window;
+use crate::window_state;
+
+/// Descendants that are never the game: console hosts and crash reporting.
+const HELPER_EXECUTABLES: [&str; 2] = ["conhost.exe", "WerFault.exe"];
+
+/// How long [`wait_for_first_process`] waits unless an activation timeout
+/// is set.
+pub const FIRST_PROCESS_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How often [`MonitorControl::quit`] checks whether the game has closed.
+const QUIT_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MonitorOptions {
+    /// Print the process tree during replacement scans
+    pub debug_tree: bool,
+    pub polling: PollSettings,
+    /// The first process is the game itself, found with `wait_for`, rather
+    /// than whatever activation started
+    pub first_is_game: bool,
+    pub timeouts: StateTimeouts,
+    /// Where to move the game's window once it shows one
+    pub placement: WindowPlacement,
+    /// Restore the game's window whenever it becomes minimized
+    pub auto_restore: bool,
+    /// Restore and refocus the game's window once it has been minimized or
+    /// in the background this long
+    pub restore_after: Option<Duration>,
+    /// Priority and affinity for the first process, and its replacements if
+    /// asked for
+    pub tuning: ProcessTuning,
+}
+
+/// Limits on how long monitoring may stay in a state before giving up, each
+/// ending the session with its own [`EndReason`]. Unset limits don't apply.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StateTimeouts {
+    /// From activation until the process named by `wait_for` appears, or
+    /// any process of the target when the activated one exited before it
+    /// could be followed. Defaults to 60 seconds.
+    pub activation: Option<Duration>,
+    /// How long the handoff search after the activated process exits may
+    /// last before the game has been tracked, in place of the usual handoff
+    /// window. For games that always start through a launcher.
+    pub handoff: Option<Duration>,
+}
+
+/// Where a monitored launch is. Every transition is reported as a
+/// `state_changed` event; the usual path is
+///
+/// ```text
+/// Activated -> TrackingStub -> HandoffSearch -> TrackingGame -> HandoffSearch -> Ended
+/// ```
+///
+/// with `HandoffSearch` and `TrackingGame` repeating for as long as the game
+/// keeps handing off to new processes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+#[cfg_attr(feature = "json", serde(tag = "state", rename_all = "snake_case"))]
+pub enum MonitorState {
+    /// The app was activated and `pid` is the process activation started
+    Activated { pid: u32 },
+    /// Tracking the activated process, which is often a launcher or
+    /// bootstrapper that hands off to the game
+    TrackingStub { pid: u32 },
+    /// The tracked process is gone and the monitor is looking for another
+    /// process belonging to the app
+    HandoffSearch { previous_pid: u32 },
+    /// Tracking a process that took over, or the one `wait_for` named
+    TrackingGame { pid: u32 },
+    /// Monitoring finished
+    Ended { reason: EndReason },
+}
+
+/// Why monitoring finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+#[cfg_attr(feature = "json", serde(rename_all = "snake_case"))]
+pub enum EndReason {
+    /// Nothing belonging to the app was left running
+    Exited,
+    /// The session was killed
+    Killed,
+    /// The game was closed because the user quit it, see
+    /// [`MonitorControl::quit`]
+    Quit,
+    /// The game didn't appear within [`StateTimeouts::activation`]
+    ActivationTimeout,
+    /// The activated process exited and nothing took over from it within
+    /// [`StateTimeouts::handoff`]
+    HandoffTimeout,
+}
+
+impl EndReason {
+    /// The CLI's exit code for a session that ended this way, as
+    /// [`ExitReason`] numbers them.
+    pub fn exit_code(self) -> i32 {
+        ExitReason::for_end(self).map_or(0, ExitReason::code)
+    }
+
+    /// The reason's name, as in JSON and `--porcelain` output.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EndReason::Exited => "exited",
+            EndReason::Killed => "killed",
+            EndReason::Quit => "quit",
+            EndReason::ActivationTimeout => "activation_timeout",
+            EndReason::HandoffTimeout => "handoff_timeout",
+        }
+    }
+}
+
+impl fmt::Display for EndReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            EndReason::Exited => "nothing belonging to the app is left running",
+            EndReason::Killed => "the session was killed",
+            EndReason::Quit => "the user quit the game",
+            EndReason::ActivationTimeout => "the game never started",
+            EndReason::HandoffTimeout => "the launcher exited without the game taking over",
+        })
+    }
+}
+
+/// A launch that gave up because a state outlasted its [`StateTimeouts`]
+/// limit before the session started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateTimeout {
+    pub reason: EndReason,
+    pub limit: Duration,
+}
+
+impl fmt::Display for StateTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} within {} seconds", self.reason, self.limit.as_secs())
+    }
+}
+
+impl std::error::Error for StateTimeout {}
+
+/// Shared between a running monitor and whoever controls it, such as a
+/// library `Session` on another thread.
+#[derive(Debug, Default)]
+pub struct MonitorControl {
+    tracked: AtomicU32,
+    killed: AtomicBool,
+    quitting: AtomicBool,
+    suspended: Mutex<Option<SuspendedThreads>>,
+    state: Mutex<Option<MonitorState>>,
+    job: OnceLock<ProcessJob>,
+    exit_code: Mutex<Option<u32>>,
+}
+
+impl MonitorControl {
+    /// The process currently being tracked, or 0 before monitoring starts.
+    pub fn tracked_pid(&self) -> u32 {
+        self.tracked.load(Ordering::SeqCst)
+    }
+
+    /// The state the monitor is in, or `None` before the app is activated.
+    pub fn state(&self) -> Option<MonitorState> {
+        *self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// The exit code of the last tracked process to exit, when it could be
+    /// read. Once monitoring has ended, that of the game's final process.
+    pub fn exit_code(&self) -> Option<u32> {
+        *self
+            .exit_code
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Moves to `state` and reports the transition.
+    pub fn enter(&self, state: MonitorState, reporter: &dyn Reporter) {
+        *self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(state);
+        reporter.event(&Event::StateChanged { state });
+    }
+
+    /// Puts the activated process in a job, so processes it starts are
+    /// considered for the handoff wherever their executables are, before the
+    /// target's. Has no effect after the first call.
+    pub fn follow_descendants(&self, process_id: u32) -> Result<(), WinError> {
+        if self.job.get().is_none() {
+            let _ = self.job.set(ProcessJob::track(process_id)?);
+        }
+        Ok(())
+    }
+
+    fn set_tracked(&self, process_id: u32) {
+        self.tracked.store(process_id, Ordering::SeqCst);
+    }
+
+    pub fn is_killed(&self) -> bool {
+        self.killed.load(Ordering::SeqCst)
+    }
+
+    /// Terminates the tracked process and stops the monitor from following
+    /// any replacement.
+    pub fn kill(&self) -> Result<(), WinError> {
+        self.killed.store(true, Ordering::SeqCst);
+        match self.tracked_pid() {
+            0 => Ok(()),
+            process_id => terminate_process(process_id),
+        }
+    }
+
+    /// Makes the processes in the launch's job, the game's tree, terminate
+    /// when this process exits. Has no effect before
+    /// [`follow_descendants`](Self::follow_descendants) succeeds.
+    pub fn kill_tree_on_close(&self) -> Result<(), WinError> {
+        self.job.get().map_or(Ok(()), ProcessJob::kill_on_close)
+    }
+
+    /// Terminates every process in the launch's job and the tracked process,
+    /// which may have been found outside it, and stops the monitor from
+    /// following any replacement.
+    pub fn kill_tree(&self) -> Result<(), WinError> {
+        // The tracked process first, while its ID is sure to still be its own
+        let tracked = self.kill();
+        let job = self.job.get().map_or(Ok(()), ProcessJob::terminate);
+        tracked.and(job)
+    }
+
+    /// Whether [`quit`](Self::quit) was called.
+    pub fn is_quitting(&self) -> bool {
+        self.quitting.load(Ordering::SeqCst)
+    }
+
+    /// Closes the game as the user quitting it would: asks the tracked
+    /// process's windows to close, and terminates it if it's still running
+    /// after `grace`. Nothing that takes over afterwards is followed, and the
+    /// session ends with [`EndReason::Quit`].
+    pub fn quit(&self, grace: Duration) -> Result<(), WinError> {
+        self.quitting.store(true, Ordering::SeqCst);
+        // A suspended game can't process the close request
+        self.resume()?;
+        let process_id = self.tracked_pid();
+        if process_id != 0 {
+            let created = get_process_creation_time(process_id).unwrap_or(0);
+            for window in window::visible_windows(Some(&[process_id])) {
+                unsafe {
+                    let _ = PostMessageW(Some(window.handle), WM_CLOSE, WPARAM(0), LPARAM(0));
+                }
+            }
+            let deadline = Instant::now() + grace;
+            while Instant::now() < deadline && is_same_process_running(process_id, created) {
+                thread::sleep(QUIT_CHECK_INTERVAL);
+            }
+            if !is_same_process_running(process_id, created) {
+                self.killed.store(true, Ordering::SeqCst);
+                return Ok(());
+            }
+        }
+        self.kill()
+    }
+
+    /// Whether [`suspend`](Self::suspend) was called without a
+    /// [`resume`](Self::resume) since.
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .is_some()
+    }
+
+    /// Pauses the game by suspending every thread of its process tree.
+    /// Returns how many processes were suspended, none if it already was.
+    pub fn suspend(&self) -> Result<usize, WinError> {
+        let mut suspended = self
+            .suspended
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if suspended.is_some() {
+            return Ok(0);
+        }
+        let threads = suspend_processes(&self.tree_process_ids())?;
+        let processes = threads.process_count();
+        *suspended = Some(threads);
+        Ok(processes)
+    }
+
+    /// Undoes [`suspend`](Self::suspend), resuming only the threads it
+    /// suspended. Returns how many processes were resumed, none if the game
+    /// wasn't suspended.
+    pub fn resume(&self) -> Result<usize, WinError> {
+        let threads = self
+            .suspended
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take();
+        Ok(threads.map(SuspendedThreads::resume).unwrap_or(0))
+    }
+
+    /// The game's process tree: the processes in the launch's job, and the
+    /// tracked process and its descendants, which may be outside it.
+    fn tree_process_ids(&self) -> Vec<u32> {
+        let mut process_ids: HashSet<u32> = self
+            .job
+            .get()
+            .map(ProcessJob::process_ids)
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+        let tracked = self.tracked_pid();
+        if tracked != 0 {
+            process_ids.insert(tracked);
+            let entries = tree::snapshot_processes().unwrap_or_default();
+            let mut parents = vec![tracked];
+            while let Some(parent) = parents.pop() {
+                // A process outliving its parent keeps the parent's ID, which
+                // may since have been reused
+                let parent_created = get_process_creation_time(parent).unwrap_or(0);
+                for entry in entries.iter().filter(|entry| entry.parent_pid == parent) {
+                    let created = get_process_creation_time(entry.pid).unwrap_or(0);
+                    if created >= parent_created && process_ids.insert(entry.pid) {
+                        parents.push(entry.pid);
+                    }
+                }
+            }
+        }
+        process_ids.into_iter().collect()
+    }
+}
+
+/// Looks for a process to take over from one that stopped being trackable,
+/// reporting the outcome. The scan repeats for a short handoff window, since
+/// launchers often exit before the process they start is visible. When
+/// `debug_tree` carries the root and tracked process IDs, the process tree is
+/// printed first.
+pub fn search_for_replacement(
+    target: &MonitorTarget,
+    debug_tree: Option<(u32, u32)>,
+    polling: &PollSettings,
+    reporter: &dyn Reporter,
+) -> Option<u32> {
+    search_within(
+        target,
+        None,
+        debug_tree,
+        polling,
+        polling.search_window(true),
+        reporter,
+    )
+}
+
+/// [`search_for_replacement`] for at most `window`.
+fn search_within(
+    target: &MonitorTarget,
+    job: Option<&ProcessJob>,
+    debug_tree: Option<(u32, u32)>,
+    polling: &PollSettings,
+    window: Duration,
+    reporter: &dyn Reporter,
+) -> Option<u32> {
+    reporter.event(&Event::ReplacementSearch {
+        target: target.clone(),
+    });
+    if let Some((root_process_id, tracked_process_id)) = debug_tree {
+        tree::print_process_tree(root_process_id, Some(tracked_process_id));
+    }
+
+    // Look for another process belonging to the target
+    match find_replacement(target, job, polling, window, reporter) {
+        Some(new_process_id) => {
+            let (name, path) = match get_process_info(new_process_id) {
+                Some(process_info) => (process_info.name, process_info.path),
+                None => ("<Unknown>".to_string(), "<Unknown>".to_string()),
+            };
+            reporter.event(&Event::ReplacementFound {
+                pid: new_process_id,
+                name,
+                path,
+            });
+            Some(new_process_id)
+        }
+        None => {
+            reporter.event(&Event::NoReplacement {
+                target: target.clone(),
+            });
+            None
+        }
+    }
+}
+
+/// Scans for a replacement for `window`, normally the handoff window, or the
+/// restart window if that is longer. A candidate younger than the minimum
+/// lifetime is watched until it reaches it. If it exits first, it's skipped
+/// and the window starts over, since whatever it was updating or reporting
+/// on often starts next.
+fn find_replacement(
+    target: &MonitorTarget,
+    job: Option<&ProcessJob>,
+    polling: &PollSettings,
+    window: Duration,
+    reporter: &dyn Reporter,
+) -> Option<u32> {
+    let mut skipped = HashSet::new();
+    let mut started = Instant::now();
+    let mut announced_restart_wait = false;
+    loop {
+        if let Some(process_id) = find_target_process(target, job, None, &skipped) {
+            // Read the name now; it can't be read once the process has exited
+            let name = get_process_info(process_id)
+                .map_or_else(|| "<Unknown>".to_string(), |info| info.name);
+            match observe_lifetime(process_id, polling) {
+                Ok(()) => return Some(process_id),
+                Err(lived) => {
+                    reporter.event(&Event::ReplacementSkipped {
+                        pid: process_id,
+                        name,
+                        lived_ms: lived.as_millis() as u64,
+                    });
+                    skipped.insert(process_id);
+                    started = Instant::now();
+                    continue;
+                }
+            }
+        }
+        if started.elapsed() >= window {
+            return None;
+        }
+        // Only a restart window extends the search that long; a handoff
+        // timeout is still waiting on the launcher
+        if !announced_restart_wait
+            && window <= polling.restart_window
+            && started.elapsed() >= polling.grace_period
+        {
+            reporter.event(&Event::WaitingForRestart {
+                window_secs: window.as_secs(),
+            });
+            announced_restart_wait = true;
+        }
+        polling.sleep();
+    }
+}
+
+/// Waits until a process is at least the minimum lifetime old. Returns how
+/// long it lived if it exits first.
+fn observe_lifetime(process_id: u32, polling: &PollSettings) -> Result<(), Duration> {
+    if polling.min_lifetime.is_zero() {
+        return Ok(());
+    }
+    let created = get_process_creation_time(process_id).unwrap_or(0);
+    let mut lived = Duration::ZERO;
+    loop {
+        if !is_same_process_running(process_id, created) {
+            return Err(lived);
+        }
+        lived = get_process_age(process_id).unwrap_or(lived);
+        if lived >= polling.min_lifetime {
+            return Ok(());
+        }
+        polling.sleep();
+    }
+}
+
+/// Scans for a process belonging to the target every poll interval until one
+/// appears or `timeout` elapses. When `executable` is given, only processes
+/// with that file name (case-insensitive) qualify, or whose path matches it
+/// when it's a pattern.
+pub fn wait_for_process(
+    target: &MonitorTarget,
+    executable: Option<&str>,
+    timeout: Duration,
+    polling: &PollSettings,
+) -> Option<u32> {
+    let started = Instant::now();
+    loop {
+        if let Some(process_id) = find_target_process(target, None, executable, &HashSet::new()) {
+            return Some(process_id);
+        }
+        if started.elapsed() >= timeout {
+            return None;
+        }
+        polling.sleep();
+    }
+}
+
+/// Waits for any process of the target to appear, for launches whose
+/// activated process exited before it could be followed, as activation
+/// stubs that hand off within a second do. `timeout` is the activation
+/// timeout, if one is set.
+pub fn wait_for_first_process(
+    activated_process_id: u32,
+    target: &MonitorTarget,
+    timeout: Option<Duration>,
+    polling: &PollSettings,
+    reporter: &dyn Reporter,
+) -> Option<u32> {
+    let timeout = timeout.unwrap_or(FIRST_PROCESS_TIMEOUT);
+    reporter.event(&Event::WaitingForFirstProcess {
+        activated_pid: activated_process_id,
+        directory: target.directory.clone(),
+        timeout_secs: timeout.as_secs(),
+    });
+    wait_for_process(target, None, timeout, polling)
+}
+
+/// Checks on a process every poll interval until it exits. Used when the
+/// process can't be opened for waiting or polling was requested explicitly.
+fn poll_until_exit(
+    process_id: u32,
+    polling: &PollSettings,
+    control: &MonitorControl,
+    reporter: &dyn Reporter,
+) {
+    reporter.event(&Event::Polling {
+        pid: process_id,
+        interval_ms: polling.interval.as_millis() as u64,
+    });
+
+    let created = get_process_creation_time(process_id).unwrap_or(0);
+    while !control.is_killed() && is_same_process_running(process_id, created) {
+        polling.sleep();
+    }
+}
+
+/// Waits until the tracked process stops being trackable and reports why.
+fn track_until_lost(
+    process_id: u32,
+    polling: &PollSettings,
+    control: &MonitorControl,
+    reporter: &dyn Reporter,
+) -> LostReason {
+    // Held throughout so the exit code can still be read once the process is
+    // gone, whichever way it's waited on
+    let exit_handle =
+        unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, process_id) }.ok();
+    let mut lost_reason = loop {
+        let process_handle = unsafe { OpenProcess(PROCESS_SYNCHRONIZE, false, process_id) };
+
+        break match process_handle {
+            Ok(process_handle) if polling.forced => {
+                unsafe {
+                    let _ = CloseHandle(process_handle);
+                };
+                poll_until_exit(process_id, polling, control, reporter);
+                LostReason::Exited
+            }
+            Ok(process_handle) => {
+                reporter.event(&Event::Waiting { pid: process_id });
+
+                // Wait for the process to terminate (handle becomes signaled)
+                let wait_result = unsafe { WaitForSingleObject(process_handle, INFINITE) };
+
+                // Close the handle after waiting
+                unsafe {
+                    let _ = CloseHandle(process_handle);
+                };
+
+                match wait_result {
+                    WAIT_OBJECT_0 => LostReason::Exited,
+                    WAIT_FAILED => {
+                        reporter.event(&Event::Error {
+                            context: "Waiting on the tracked process failed".to_string(),
+                            error: WinError::from_last_error("WaitForSingleObject"),
+                        });
+                        LostReason::WaitFailed
+                    }
+                    _ => {
+                        warning!(
+                            "⚠️ Unexpected wait result: {:?}. Continuing monitoring...",
+                            wait_result
+                        );
+                        continue;
+                    }
+                }
+            }
+            // Some processes deny SYNCHRONIZE but can still be queried, so poll them instead
+            Err(_) if is_same_process_running(process_id, 0) => {
+                poll_until_exit(process_id, polling, control, reporter);
+                LostReason::Exited
+            }
+            Err(_) => LostReason::OpenFailed,
+        };
+    };
+    if control.is_killed() {
+        lost_reason = LostReason::Killed;
+    }
+
+    let mut exit_code = None;
+    if let Some(exit_handle) = exit_handle {
+        unsafe {
+            let mut code = 0u32;
+            if lost_reason == LostReason::Exited
+                && GetExitCodeProcess(exit_handle, &mut code).is_ok()
+                && code != STILL_ACTIVE.0 as u32
+            {
+                exit_code = Some(code);
+            }
+            let _ = CloseHandle(exit_handle);
+        }
+    }
+    *control
+        .exit_code
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = exit_code;
+
+    reporter.event(&Event::TrackedProcessLost {
+        pid: process_id,
+        reason: lost_reason,
+        exit_code,
+    });
+    lost_reason
+}
+
+/// Follows a process (and any replacements) until nothing belonging to the
+/// target is left, then finishes the session. Runs the states of
+/// [`MonitorState`] from tracking the first process on, reporting each
+/// transition through `control`. Tracked windows are watched, and placed if
+/// there's a window placement, alongside.
+pub fn monitor_process(
+    process_id: u32,
+    target: &MonitorTarget,
+    options: &MonitorOptions,
+    session: &mut SessionState,
+    control: &MonitorControl,
+    reporter: &dyn Reporter,
+) -> SessionRecord {
+    let session_id = session.id.clone();
+    let aumid = session.aumid.clone();
+    #[cfg(feature = "json")]
+    let server = control::Server::default();
+    thread::scope(|scope| {
+        if !options.placement.is_empty() {
+            scope.spawn(|| placement::place_tracked_windows(&options.placement, control, reporter));
+        }
+        scope.spawn(|| {
+            window_state::watch_tracked_windows(options, &session_id, &aumid, control, reporter)
+        });
+        #[cfg(feature = "json")]
+        let serving = scope.spawn(|| server.serve(&session_id, control, reporter));
+        let record = run_states(process_id, target, options, session, control, reporter);
+        #[cfg(feature = "json")]
+        server.stop(&serving);
+        record
+    })
+}
+
+fn run_states(
+    process_id: u32,
+    target: &MonitorTarget,
+    options: &MonitorOptions,
+    session: &mut SessionState,
+    control: &MonitorControl,
+    reporter: &dyn Reporter,
+) -> SessionRecord {
+    let root_process_id = process_id;
+    let polling = &options.polling;
+    control.set_tracked(process_id);
+    tune(&options.tuning, process_id, reporter);
+
+    // Until then, a failed handoff means the game never took over
+    let mut game_tracked = options.first_is_game;
+    let mut state = if options.first_is_game {
+        MonitorState::TrackingGame { pid: process_id }
+    } else {
+        MonitorState::TrackingStub { pid: process_id }
+    };
+    let mut first_tracked = false;
+    loop {
+        if let MonitorState::Ended { .. } = state
+            && control.is_quitting()
+        {
+            state = MonitorState::Ended {
+                reason: EndReason::Quit,
+            };
+        }
+        control.enter(state, reporter);
+        if let MonitorState::TrackingStub { .. } | MonitorState::TrackingGame { .. } = state
+            && !first_tracked
+        {
+            first_tracked = true;
+            timings::milestone(Phase::FirstProcess);
+        }
+        state = match state {
+            MonitorState::Activated { pid }
+            | MonitorState::TrackingStub { pid }
+            | MonitorState::TrackingGame { pid } => {
+                match track_until_lost(pid, polling, control, reporter) {
+                    LostReason::Killed => MonitorState::Ended {
+                        reason: EndReason::Killed,
+                    },
+                    _ => MonitorState::HandoffSearch { previous_pid: pid },
+                }
+            }
+            MonitorState::HandoffSearch { previous_pid } => {
+                let debug_tree = options
+                    .debug_tree
+                    .then_some((root_process_id, previous_pid));
+                let handoff_limit = options.timeouts.handoff.filter(|_| !game_tracked);
+                let window = handoff_limit.unwrap_or_else(|| polling.search_window(!game_tracked));
+                match search_within(
+                    target,
+                    control.job.get(),
+                    debug_tree,
+                    polling,
+                    window,
+                    reporter,
+                ) {
+                    Some(new_process_id) => {
+                        game_tracked = true;
+                        control.set_tracked(new_process_id);
+                        session.track(new_process_id);
+                        if options.tuning.replacements {
+                            tune(&options.tuning, new_process_id, reporter);
+                        }
+
+                        // A kill or quit that raced with the handoff also applies to the new process
+                        if control.is_killed() || control.is_quitting() {
+                            let _ = terminate_process(new_process_id);
+                        }
+                        MonitorState::TrackingGame {
+                            pid: new_process_id,
+                        }
+                    }
+                    None if handoff_limit.is_some() => MonitorState::Ended {
+                        reason: EndReason::HandoffTimeout,
+                    },
+                    None => MonitorState::Ended {
+                        reason: EndReason::Exited,
+                    },
+                }
+            }
+            MonitorState::Ended { .. } => break,
+        };
+    }
+
+    end_session(session, reporter)
+}
+
+/// Gives `process_id` the priority and affinity asked for, if any.
+fn tune(tuning: &ProcessTuning, process_id: u32, reporter: &dyn Reporter) {
+    if tuning.is_empty() {
+        return;
+    }
+    reporter.event(&match tuning.apply(process_id) {
+        Ok(()) => Event::ProcessTuned {
+            pid: process_id,
+            priority: tuning.priority,
+            affinity: tuning.affinity,
+        },
+        Err(e) => Event::ProcessNotTuned {
+            pid: process_id,
+            reason: e.to_string(),
+        },
+    });
+}
+
+/// Follows an already running process, and whatever takes over from it in
+/// its directory tree, until the app exits. Blocks the calling thread and
+/// reports to [`report::reporter`](crate::report::reporter); use
+/// [`monitor_process`] to choose the scope, reporter or control.
+pub fn monitor(
+    process_id: u32,
+    options: &MonitorOptions,
+) -> Result<SessionRecord, Box<dyn std::error::Error>> {
+    let info = get_process_info(process_id).ok_or_else(|| {
+        format!(
+            "Could not get process information for process {}",
+            process_id
+        )
+    })?;
+    let target = MonitorTarget::new(
+        &get_directory_from_path(&info.path),
+        tree::get_package_family_name(process_id),
+        MonitorScope::DirTree,
+    );
+    let mut session = SessionState::new(&info.path, process_id, target.clone());
+    if let Err(e) = session.save() {
+        warning!("⚠️ Could not save session state: {}", e);
+    }
+    Ok(monitor_process(
+        process_id,
+        &target,
+        options,
+        &mut session,
+        &MonitorControl::default(),
+        crate::report::reporter(),
+    ))
+}
+
+/// Takes over a session whose librarylink process has gone away (see
+/// [`SessionState::owner_alive`]) and follows it to the end, looking for a
+/// process that's taken over when the tracked one has exited.
+pub fn resume(mut session: SessionState, reporter: &dyn Reporter) -> SessionRecord {
+    reporter.event(&Event::SessionResumed {
+        session_id: session.id.clone(),
+        aumid: session.aumid.clone(),
+        elapsed_secs: crate::session::unix_now().saturating_sub(session.started_at),
+    });
+    session.adopt();
+
+    let target = session.target.clone();
+    let process_id = if session.tracked_alive() {
+        info!(
+            "🔗 Tracked process {} is still running",
+            session.tracked_pid
+        );
+        info!("");
+        session.tracked_pid
+    } else {
+        reporter.event(&Event::TrackedProcessLost {
+            pid: session.tracked_pid,
+            reason: LostReason::Exited,
+            exit_code: None,
+        });
+        match search_for_replacement(&target, None, &PollSettings::default(), reporter) {
+            Some(process_id) => {
+                session.track(process_id);
+                process_id
+            }
+            None => return end_session(&session, reporter),
+        }
+    };
+    monitor_process(
+        process_id,
+        &target,
+        &MonitorOptions::default(),
+        &mut session,
+        &MonitorControl::default(),
+        reporter,
+    )
+}
+
+/// Finishes a session and reports how long it ran.
+pub fn end_session(session: &SessionState, reporter: &dyn Reporter) -> SessionRecord {
+    let record = session.finish();
+    reporter.event(&Event::SessionEnded {
+        session_id: record.id.clone(),
+        duration_secs: record.duration_secs,
+        frames: record.frames.clone(),
+    });
+    record
+}
+
+/// Finds a running process belonging to the target, optionally restricted to
+/// one executable name or pattern, other than the `skipped` ones. Processes
+/// in `job` are the app's own descendants and qualify wherever they are.
+fn find_target_process(
+    target: &MonitorTarget,
+    job: Option<&ProcessJob>,
+    executable: Option<&str>,
+    skipped: &HashSet<u32>,
+) -> Option<u32> {
+    for process_id in job.map(ProcessJob::process_ids).unwrap_or_default() {
+        if skipped.contains(&process_id) {
+            continue;
+        }
+        if let Some(process_info) = get_process_info(process_id)
+            && process_info.path != "<Unknown>"
+            && !HELPER_EXECUTABLES
+                .iter()
+                .any(|helper| helper.eq_ignore_ascii_case(&process_info.name))
+            && !target.filters.ignores(&process_info.name)
+            && matches_executable(target, executable, &process_info.name, &process_info.path)
+        {
+            debug!(
+                "Candidate {} (PID: {}) found in the launch's job",
+                process_info.name, process_id
+            );
+            return Some(process_id);
+        }
+    }
+
+    // Check each process to see if it belongs to the target, reading them
+    // all at once and matching in order
+    let process_ids: Vec<u32> = list_process_ids()
+        .into_iter()
+        .filter(|process_id| !skipped.contains(process_id))
+        .collect();
+    let infos = get_process_infos(&process_ids);
+    for (process_id, process_info) in process_ids.into_iter().zip(infos) {
+        if let Some(process_info) = process_info
+            && is_candidate(
+                target,
+                executable,
+                &process_info.name,
+                &process_info.path,
+                || tree::get_package_family_name(process_id),
+            )
+        {
+            debug!(
+                "Candidate {} (PID: {}) found at {}",
+                process_info.name, process_id, process_info.path
+            );
+            return Some(process_id);
+        }
+    }
+
+    None
+}
+
+/// Whether a process with this executable name and path, and the package
+/// family `package_family_name` returns, may be tracked for the target.
+/// Shared by live scans and snapshot replays, so both select alike.
+pub(crate) fn is_candidate(
+    target: &MonitorTarget,
+    executable: Option<&str>,
+    name: &str,
+    path: &str,
+    package_family_name: impl FnOnce() -> Option<String>,
+) -> bool {
+    path != "<Unknown>"
+        && matches_executable(target, executable, name, path)
+        && target.admits(name, path, package_family_name)
+}
+
+/// Whether the process is the `executable` name or pattern, when one is given.
+fn matches_executable(
+    target: &MonitorTarget,
+    executable: Option<&str>,
+    name: &str,
+    path: &str,
+) -> bool {
+    executable.is_none_or(|executable| {
+        if executable::is_pattern(executable) {
+            executable::matches(executable, &target.directory, path)
+        } else {
+            name.eq_ignore_ascii_case(executable)
+        }
+    })
+}
@@ -0,0 +1,61 @@
+//! Named mutexes, for work that librarylink processes running side by side
+//! (the CLI, the daemon, an elevated copy) must take turns at.
+
+use std::time::Duration;
+use windows::Win32::Foundation::{
+    CloseHandle, HANDLE, WAIT_ABANDONED, WAIT_OBJECT_0, WAIT_TIMEOUT,
+};
+use windows::Win32::System::Threading::{
+    CreateMutexW, INFINITE, ReleaseMutex, WaitForSingleObject,
+};
+use windows::core::HSTRING;
+
+use crate::error::WinError;
+
+/// Ownership of a named mutex, released when dropped. Windows ties it to
+/// the thread that acquired it, so it has to be dropped there too.
+#[derive(Debug)]
+pub struct NamedMutex {
+    handle: isize,
+}
+
+impl NamedMutex {
+    /// Waits for the mutex `name`, creating it if no process has; `None`
+    /// waits as long as it takes. Returns `Ok(None)` once `timeout` has
+    /// passed without getting it.
+    pub fn acquire(name: &str, timeout: Option<Duration>) -> Result<Option<Self>, WinError> {
+        let handle = unsafe { CreateMutexW(None, false, &HSTRING::from(name)) }
+            .map_err(|e| WinError::new("CreateMutexW", &e))?;
+        let millis = timeout.map_or(INFINITE, |timeout| {
+            u32::try_from(timeout.as_millis()).unwrap_or(INFINITE - 1)
+        });
+        let result = unsafe { WaitForSingleObject(handle, millis) };
+        // Abandoned by a process that exited holding it, which leaves
+        // nothing to wait for
+        if result == WAIT_OBJECT_0 || result == WAIT_ABANDONED {
+            return Ok(Some(NamedMutex {
+                handle: handle.0 as isize,
+            }));
+        }
+
+        let error =
+            (result != WAIT_TIMEOUT).then(|| WinError::from_last_error("WaitForSingleObject"));
+        unsafe {
+            let _ = CloseHandle(handle);
+        }
+        match error {
+            Some(error) => Err(error),
+            None => Ok(None),
+        }
+    }
+}
+
+impl Drop for NamedMutex {
+    fn drop(&mut self) {
+        let handle = HANDLE(self.handle as *mut _);
+        unsafe {
+            let _ = ReleaseMutex(handle);
+            let _ = CloseHandle(handle);
+        }
+    }
+}
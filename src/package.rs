@@ -0,0 +1,445 @@
+//! Installed packages, looked up by the package family in an AUMID.
+//!
+//! Lookups are for the current user, which is where packages are
+//! registered. A package can still be on the machine without being
+//! registered for them: provisioned for all users, which registers it at
+//! each user's next sign-in, or installed only under another account.
+//! [`registration_hint`] tells those apart for errors, and [`repair`]
+//! registers the package for the current user in either case.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use windows::ApplicationModel::{Package, PackageCatalog, PackageUpdatingEventArgs};
+use windows::Foundation::{TypedEventHandler, Uri};
+use windows::Management::Deployment::{DeploymentOptions, PackageManager};
+use windows::Win32::Foundation::{ERROR_INSUFFICIENT_BUFFER, ERROR_SUCCESS};
+use windows::Win32::Storage::Packaging::Appx::GetPackagesByPackageFamily;
+use windows::core::{HSTRING, PWSTR};
+
+use crate::error::WinError;
+use crate::process;
+use crate::registry;
+use crate::report::{Event, Reporter};
+use crate::session;
+use crate::timings::{self, Phase};
+
+/// The deployment stack's record of packages staged on the machine:
+/// provisioned ones under `Applications`, and each user's under their SID.
+const ALL_USER_STORE: &str =
+    r"HKLM\SOFTWARE\Microsoft\Windows\CurrentVersion\Appx\AppxAllUserStore";
+
+/// How long a launch waits for the Store to finish installing or updating
+/// the app's package.
+pub const UPDATE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// What a launch does when the app's package is being installed or updated,
+/// which otherwise makes activation fail with an unhelpful error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "json", serde(rename_all = "kebab-case"))]
+pub enum UpdateGuard {
+    /// Wait for the deployment to finish, reporting its progress
+    #[default]
+    Wait,
+    /// Fail the launch straight away
+    Fail,
+}
+
+impl UpdateGuard {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "wait" => Some(UpdateGuard::Wait),
+            "fail" => Some(UpdateGuard::Fail),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for UpdateGuard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            UpdateGuard::Wait => "wait",
+            UpdateGuard::Fail => "fail",
+        })
+    }
+}
+
+/// A package whose version differs from the one recorded for the app's
+/// previous session.
+#[derive(Debug, Clone)]
+pub struct PackageUpdate {
+    pub aumid: String,
+    pub previous_version: String,
+    pub version: String,
+}
+
+impl PackageUpdate {
+    /// Compares `version` with the version recorded for the app's previous
+    /// session. Apps without recorded versions aren't considered updated.
+    pub fn detect(aumid: &str, version: &str) -> Option<Self> {
+        let previous_version = session::last_package_version(aumid)?;
+        (previous_version != version).then(|| PackageUpdate {
+            aumid: aumid.to_string(),
+            previous_version,
+            version: version.to_string(),
+        })
+    }
+
+    pub fn event(&self) -> Event {
+        Event::PackageUpdated {
+            aumid: self.aumid.clone(),
+            previous_version: self.previous_version.clone(),
+            version: self.version.clone(),
+        }
+    }
+}
+
+/// The package family part of an AUMID (`<family>!<app id>`).
+pub fn family_name(aumid: &str) -> Option<&str> {
+    aumid
+        .split_once('!')
+        .map(|(family, _)| family)
+        .filter(|family| !family.is_empty())
+}
+
+/// The version part of a package full name
+/// (`<name>_<version>_<architecture>_<resource id>_<publisher id>`).
+pub fn version(full_name: &str) -> Option<&str> {
+    full_name
+        .split('_')
+        .nth(1)
+        .filter(|version| !version.is_empty())
+}
+
+/// Full names of the packages installed for the current user in a family.
+pub fn installed_full_names(family_name: &str) -> Vec<String> {
+    let family = HSTRING::from(family_name);
+    timings::time(Phase::Enumeration, || unsafe {
+        // First call reports the count and the buffer length needed
+        let mut count = 0u32;
+        let mut buffer_length = 0u32;
+        let result =
+            GetPackagesByPackageFamily(&family, &mut count, None, &mut buffer_length, None);
+        if result != ERROR_INSUFFICIENT_BUFFER || count == 0 {
+            return Vec::new();
+        }
+
+        let mut names = vec![PWSTR::null(); count as usize];
+        let mut buffer: Vec<u16> = vec![0; buffer_length as usize];
+        let result = GetPackagesByPackageFamily(
+            &family,
+            &mut count,
+            Some(names.as_mut_ptr()),
+            &mut buffer_length,
+            Some(PWSTR(buffer.as_mut_ptr())),
+        );
+        if result != ERROR_SUCCESS {
+            return Vec::new();
+        }
+
+        names
+            .iter()
+            .take(count as usize)
+            .filter_map(|name| name.to_string().ok())
+            .collect()
+    })
+}
+
+/// The family of a package full name: its name and publisher ID, the first
+/// and last parts.
+fn full_name_family(full_name: &str) -> Option<String> {
+    let (name, _) = full_name.split_once('_')?;
+    let (_, publisher_id) = full_name.rsplit_once('_')?;
+    Some(format!("{}_{}", name, publisher_id))
+}
+
+fn in_family(full_name: &str, family_name: &str) -> bool {
+    full_name_family(full_name).is_some_and(|family| family.eq_ignore_ascii_case(family_name))
+}
+
+/// Full names of the packages provisioned for all users.
+fn provisioned_full_names() -> Vec<String> {
+    registry::subkeys(&format!("{}\\Applications", ALL_USER_STORE)).unwrap_or_default()
+}
+
+/// The families provisioned for all users, lowercased, for checking many
+/// packages at once.
+pub fn provisioned_families() -> HashSet<String> {
+    provisioned_full_names()
+        .iter()
+        .filter_map(|full_name| full_name_family(full_name))
+        .map(|family| family.to_lowercase())
+        .collect()
+}
+
+/// Whether the family is provisioned for all users, so every account on the
+/// machine gets it, rather than installed for the current user alone.
+pub fn is_provisioned(family_name: &str) -> bool {
+    provisioned_full_names()
+        .iter()
+        .any(|full_name| in_family(full_name, family_name))
+}
+
+/// How a package is on the machine when it isn't registered for the
+/// current user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MachineInstall {
+    /// Provisioned for all users, but never registered for this one
+    Provisioned,
+    /// Installed for another account only
+    OtherUser,
+}
+
+/// How the family is on the machine, if it is there without being
+/// registered for the current user.
+pub fn machine_install(family_name: &str) -> Option<MachineInstall> {
+    if !installed_full_names(family_name).is_empty() {
+        return None;
+    }
+    if is_provisioned(family_name) {
+        return Some(MachineInstall::Provisioned);
+    }
+    // Other accounts' packages are listed under their SIDs; an elevated
+    // PackageManager sees them even where the registry doesn't let us
+    let in_user_store = registry::subkeys(ALL_USER_STORE)
+        .unwrap_or_default()
+        .iter()
+        .filter(|key| key.starts_with("S-1-"))
+        .any(|sid| {
+            registry::subkeys(&format!("{}\\{}", ALL_USER_STORE, sid))
+                .unwrap_or_default()
+                .iter()
+                .any(|full_name| in_family(full_name, family_name))
+        });
+    let for_any_user = || {
+        process::is_elevated()
+            && PackageManager::new()
+                .and_then(|manager| {
+                    manager.FindPackagesByPackageFamilyName(&HSTRING::from(family_name))
+                })
+                .is_ok_and(|packages| packages.into_iter().next().is_some())
+    };
+    (in_user_store || for_any_user()).then_some(MachineInstall::OtherUser)
+}
+
+/// Why `aumid` can't be found for the current user when its package is on
+/// the machine all the same, with the command that registers it. `None`
+/// when the package is registered or not on the machine at all.
+pub fn registration_hint(aumid: &str) -> Option<String> {
+    let family_name = family_name(aumid)?;
+    let how = match machine_install(family_name)? {
+        MachineInstall::Provisioned => "is provisioned for all users but isn't registered for",
+        MachineInstall::OtherUser => "is installed for another user but not for",
+    };
+    Some(format!(
+        "{} {} the current user. Register it with: librarylink package repair {}",
+        family_name, how, family_name
+    ))
+}
+
+/// What [`repair`] did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Repair {
+    /// The package registered for the current user was registered again
+    /// from its manifest
+    Reregistered { full_name: String },
+    /// The package on the machine was registered for the current user
+    Registered { install: MachineInstall },
+}
+
+/// Registers the family's package for the current user again, as
+/// `Add-AppxPackage -DisableDevelopmentMode -Register AppxManifest.xml`
+/// does: the usual fix for an app that's missing from Start or won't
+/// activate although it's installed. A package that's only provisioned or
+/// installed for another user is registered by family name instead. Running
+/// apps of the package are closed first.
+pub fn repair(family_name: &str) -> Result<Repair, Box<dyn std::error::Error>> {
+    let manager = PackageManager::new().map_err(|e| WinError::new("PackageManager::new", &e))?;
+
+    if let Some(package) = find_packages(family_name).into_iter().next() {
+        let full_name = package
+            .Id()
+            .and_then(|id| id.FullName())
+            .map_err(|e| WinError::new("Package::Id", &e))?
+            .to_string();
+        let folder = package
+            .InstalledPath()
+            .map_err(|e| WinError::new("Package::InstalledPath", &e))?;
+        let manifest = PathBuf::from(folder.to_os_string()).join("AppxManifest.xml");
+        let uri = Uri::CreateUri(&HSTRING::from(format!(
+            "file:///{}",
+            manifest.display().to_string().replace('\\', "/")
+        )))
+        .map_err(|e| WinError::new("Uri::CreateUri", &e))?;
+        manager
+            .RegisterPackageAsync(&uri, None, DeploymentOptions::ForceApplicationShutdown)
+            .and_then(|operation| operation.get())
+            .map_err(|e| WinError::new("PackageManager::RegisterPackageAsync", &e))?;
+        return Ok(Repair::Reregistered { full_name });
+    }
+
+    let install = machine_install(family_name).ok_or_else(|| {
+        format!(
+            "No package of the family {} is installed on this machine",
+            family_name
+        )
+    })?;
+    manager
+        .RegisterPackageByFamilyNameAndOptionalPackagesAsync(
+            &HSTRING::from(family_name),
+            None,
+            DeploymentOptions::ForceApplicationShutdown,
+            None,
+            None,
+        )
+        .and_then(|operation| operation.get())
+        .map_err(|e| {
+            WinError::new(
+                "PackageManager::RegisterPackageByFamilyNameAndOptionalPackagesAsync",
+                &e,
+            )
+        })?;
+    Ok(Repair::Registered { install })
+}
+
+/// The installed version of an app's package, or `None` if it isn't an
+/// installed packaged app.
+pub fn installed_version(aumid: &str) -> Option<String> {
+    installed_full_names(family_name(aumid)?)
+        .iter()
+        .find_map(|full_name| version(full_name).map(str::to_string))
+}
+
+/// The family's packages as the deployment stack sees them.
+pub(crate) fn find_packages(family_name: &str) -> Vec<Package> {
+    timings::time(Phase::Enumeration, || {
+        PackageManager::new()
+            .and_then(|manager| {
+                // An empty SID means the current user
+                manager.FindPackagesByUserSecurityIdPackageFamilyName(
+                    &HSTRING::new(),
+                    &HSTRING::from(family_name),
+                )
+            })
+            .map(|packages| packages.into_iter().collect())
+            .unwrap_or_default()
+    })
+}
+
+/// The app's installed package, found by the family name in its AUMID.
+pub fn installed_package(aumid: &str) -> Option<Package> {
+    find_packages(family_name(aumid)?).into_iter().next()
+}
+
+/// Where the app's package is installed.
+pub fn installed_path(aumid: &str) -> Option<PathBuf> {
+    find_packages(family_name(aumid)?)
+        .iter()
+        .find_map(|package| package.InstalledPath().ok())
+        .map(|path| PathBuf::from(path.to_os_string()))
+}
+
+/// Whether the Store is installing, updating or repairing a package of the
+/// family.
+pub fn is_deploying(family_name: &str) -> bool {
+    find_packages(family_name).iter().any(|package| {
+        package.Status().is_ok_and(|status| {
+            status.DeploymentInProgress().unwrap_or(false) || status.Servicing().unwrap_or(false)
+        })
+    })
+}
+
+/// Holds a launch until the app's package isn't being deployed, or fails it
+/// per `guard`. Progress comes from the package catalog's updating events,
+/// which report on deployments started by the Store.
+pub fn guard_deployment(
+    aumid: &str,
+    guard: UpdateGuard,
+    timeout: Duration,
+    reporter: &dyn Reporter,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(family_name) = family_name(aumid) else {
+        return Ok(());
+    };
+    if !is_deploying(family_name) {
+        return Ok(());
+    }
+    if guard == UpdateGuard::Fail {
+        return Err(format!(
+            "{} is being installed or updated by the Microsoft Store; launch it once the update finishes",
+            family_name
+        )
+        .into());
+    }
+
+    let progress: Arc<Mutex<Option<f64>>> = Arc::new(Mutex::new(None));
+    let catalog = PackageCatalog::OpenForCurrentUser().ok();
+    let token = catalog.as_ref().and_then(|catalog| {
+        let progress = Arc::clone(&progress);
+        let family_name = family_name.to_string();
+        let handler =
+            TypedEventHandler::<PackageCatalog, PackageUpdatingEventArgs>::new(move |_, args| {
+                if let Ok(args) = args.ok()
+                    && args
+                        .TargetPackage()
+                        .and_then(|package| package.Id())
+                        .and_then(|id| id.FamilyName())
+                        .is_ok_and(|name| name.to_string().eq_ignore_ascii_case(&family_name))
+                    && let Ok(percent) = args.Progress()
+                {
+                    *progress
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(percent);
+                }
+                Ok(())
+            });
+        catalog.PackageUpdating(&handler).ok()
+    });
+
+    reporter.event(&Event::PackageDeploying {
+        family_name: family_name.to_string(),
+        progress_percent: None,
+    });
+    let started = Instant::now();
+    let mut reported: Option<u32> = None;
+    let result = loop {
+        if !is_deploying(family_name) {
+            reporter.event(&Event::PackageDeployed {
+                family_name: family_name.to_string(),
+                waited_secs: started.elapsed().as_secs(),
+            });
+            break Ok(());
+        }
+        if started.elapsed() >= timeout {
+            break Err(format!(
+                "{} was still being updated after {} minutes",
+                family_name,
+                timeout.as_secs() / 60
+            )
+            .into());
+        }
+
+        // Report progress in whole-percent steps
+        let percent = progress
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .map(|percent| percent.clamp(0.0, 100.0) as u32);
+        if percent.is_some() && percent != reported {
+            reported = percent;
+            reporter.event(&Event::PackageDeploying {
+                family_name: family_name.to_string(),
+                progress_percent: percent,
+            });
+        }
+        thread::sleep(Duration::from_secs(1));
+    };
+
+    if let (Some(catalog), Some(token)) = (catalog, token) {
+        let _ = catalog.RemovePackageUpdating(token);
+    }
+    result
+}
@@ -0,0 +1,929 @@
+//! Edits to a game's own config files for the length of a session, for the
+//! resolution and fullscreen settings many games keep in an INI, JSON or XML
+//! file rather than the registry. Files are edited in place, keeping their
+//! layout, comments and encoding, and replaced [atomically](crate::atomic_file)
+//! so a crash mid-write can't leave the game a truncated config. The changed
+//! values are put back by [`SystemStateGuard`](crate::system::SystemStateGuard).
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use windows::Win32::System::Environment::ExpandEnvironmentStringsW;
+use windows::core::HSTRING;
+
+use crate::atomic_file;
+
+/// A file to edit, as an entry of a profile's `[[system.files]]` array.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "json", derive(serde::Deserialize))]
+#[cfg_attr(feature = "json", serde(deny_unknown_fields))]
+pub struct FilePatch {
+    /// The file, which may use `%VARIABLES%` such as `%LOCALAPPDATA%`
+    pub path: PathBuf,
+    /// Taken from the extension if unset: `.ini` and `.cfg` are INI,
+    /// `.json` is JSON and `.xml` and `.config` are XML
+    #[cfg_attr(feature = "json", serde(default))]
+    pub format: Option<FileFormat>,
+    /// Values to set by key: `[Section]Key` (or `Key` before any section)
+    /// for INI files, and `/`-separated paths for JSON and XML, where a last
+    /// step of `@name` is an attribute
+    pub set: BTreeMap<String, PatchValue>,
+    /// Whether the previous values are put back after the session
+    #[cfg_attr(feature = "json", serde(default = "default_revert"))]
+    pub revert: bool,
+}
+
+#[cfg(feature = "json")]
+fn default_revert() -> bool {
+    true
+}
+
+impl FilePatch {
+    /// The path with environment variables expanded.
+    pub fn resolved_path(&self) -> PathBuf {
+        expand_environment(&self.path)
+    }
+
+    pub fn file_format(&self) -> Result<FileFormat, String> {
+        if let Some(format) = self.format {
+            return Ok(format);
+        }
+        let extension = self
+            .path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        match extension.as_deref() {
+            Some("ini" | "cfg") => Ok(FileFormat::Ini),
+            Some("json") => Ok(FileFormat::Json),
+            Some("xml" | "config") => Ok(FileFormat::Xml),
+            _ => Err(format!(
+                "Can't tell what format {} is in; set `format` to ini, json or xml",
+                self.path.display()
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "json", serde(rename_all = "snake_case"))]
+pub enum FileFormat {
+    Ini,
+    Json,
+    Xml,
+}
+
+/// A value to write. INI and XML files get its text; JSON files get it as
+/// the matching JSON type.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "json", derive(serde::Deserialize))]
+#[cfg_attr(feature = "json", serde(untagged))]
+pub enum PatchValue {
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+}
+
+impl PatchValue {
+    /// The value as it's written into a file of `format`.
+    pub fn raw(&self, format: FileFormat) -> String {
+        let text = match self {
+            PatchValue::Boolean(value) => value.to_string(),
+            PatchValue::Integer(value) => value.to_string(),
+            PatchValue::Float(value) => value.to_string(),
+            PatchValue::String(text) => text.clone(),
+        };
+        match (format, self) {
+            (FileFormat::Json, PatchValue::String(text)) => json_string(text),
+            (FileFormat::Xml, _) => xml_escape(&text),
+            _ => text,
+        }
+    }
+}
+
+/// How a file's text was encoded, so it's written back the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+}
+
+/// A config file read into memory for editing.
+#[derive(Debug, Clone)]
+pub struct ConfigFile {
+    pub path: PathBuf,
+    pub format: FileFormat,
+    encoding: Encoding,
+    text: String,
+}
+
+impl ConfigFile {
+    pub fn open(path: &Path, format: FileFormat) -> Result<ConfigFile, String> {
+        let bytes =
+            fs::read(path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        let (encoding, text) = if let Some(rest) = bytes.strip_prefix(b"\xEF\xBB\xBF") {
+            (Encoding::Utf8Bom, String::from_utf8(rest.to_vec()).ok())
+        } else if let Some(rest) = bytes.strip_prefix(b"\xFF\xFE") {
+            let wide: Vec<u16> = rest
+                .chunks_exact(2)
+                .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                .collect();
+            (Encoding::Utf16Le, String::from_utf16(&wide).ok())
+        } else {
+            (Encoding::Utf8, String::from_utf8(bytes).ok())
+        };
+        let text = text.ok_or_else(|| format!("{} isn't UTF-8 or UTF-16 text", path.display()))?;
+        Ok(ConfigFile {
+            path: path.to_path_buf(),
+            format,
+            encoding,
+            text,
+        })
+    }
+
+    /// The value under `key` as it's written in the file, or `None` if the
+    /// file doesn't have one.
+    pub fn get(&self, key: &str) -> Result<Option<String>, String> {
+        let value = match self.format {
+            FileFormat::Ini => {
+                let (section, name) = ini_key(key)?;
+                ini_find(&self.text, section, name).value
+            }
+            FileFormat::Json => json_find(&self.text, &path_steps(key)?)?.value,
+            FileFormat::Xml => xml_find(&self.text, &path_steps(key)?)?.value,
+        };
+        Ok(value.map(|range| self.text[range].to_string()))
+    }
+
+    /// Sets `key` to `raw`, which is written as is. Missing INI keys and
+    /// sections are added, as are missing JSON members and XML attributes
+    /// of existing elements.
+    pub fn set(&mut self, key: &str, raw: &str) -> Result<(), String> {
+        match self.format {
+            FileFormat::Ini => {
+                let (section, name) = ini_key(key)?;
+                self.text = ini_set(&self.text, section, name, raw);
+            }
+            FileFormat::Json => self.text = json_set(&self.text, &path_steps(key)?, raw)?,
+            FileFormat::Xml => self.text = xml_set(&self.text, &path_steps(key)?, raw)?,
+        }
+        Ok(())
+    }
+
+    /// Removes `key`, for putting back a value that didn't exist. XML
+    /// element text can't be removed; it's emptied instead.
+    pub fn remove(&mut self, key: &str) -> Result<(), String> {
+        match self.format {
+            FileFormat::Ini => {
+                let (section, name) = ini_key(key)?;
+                if let Some(line) = ini_find(&self.text, section, name).line {
+                    self.text.replace_range(line, "");
+                }
+            }
+            FileFormat::Json => self.text = json_remove(&self.text, &path_steps(key)?)?,
+            FileFormat::Xml => self.text = xml_remove(&self.text, &path_steps(key)?)?,
+        }
+        Ok(())
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let bytes = match self.encoding {
+            Encoding::Utf8 => self.text.as_bytes().to_vec(),
+            Encoding::Utf8Bom => [b"\xEF\xBB\xBF".as_slice(), self.text.as_bytes()].concat(),
+            Encoding::Utf16Le => [0xFF, 0xFE]
+                .into_iter()
+                .chain(self.text.encode_utf16().flat_map(u16::to_le_bytes))
+                .collect(),
+        };
+        atomic_file::write(&self.path, bytes)
+            .map_err(|e| format!("Could not write {}: {}", self.path.display(), e))
+    }
+}
+
+fn expand_environment(path: &Path) -> PathBuf {
+    let source = HSTRING::from(path.as_os_str());
+    let length = unsafe { ExpandEnvironmentStringsW(&source, None) };
+    if length == 0 {
+        return path.to_path_buf();
+    }
+    let mut buffer = vec![0u16; length as usize];
+    let written = unsafe { ExpandEnvironmentStringsW(&source, Some(&mut buffer)) };
+    if written == 0 || written > length {
+        return path.to_path_buf();
+    }
+    // The count includes the terminating NUL
+    PathBuf::from(String::from_utf16_lossy(&buffer[..written as usize - 1]))
+}
+
+fn path_steps(key: &str) -> Result<Vec<&str>, String> {
+    let steps: Vec<&str> = key.trim_matches('/').split('/').collect();
+    if steps.iter().any(|step| step.is_empty()) {
+        return Err(format!("'{}' isn't a valid path", key));
+    }
+    Ok(steps)
+}
+
+fn line_ending(text: &str) -> &'static str {
+    if text.contains("\r\n") { "\r\n" } else { "\n" }
+}
+
+// INI files
+
+/// Splits `[Section]Key` into its section and key.
+fn ini_key(key: &str) -> Result<(&str, &str), String> {
+    let (section, name) = match key.strip_prefix('[') {
+        Some(rest) => rest
+            .split_once(']')
+            .ok_or_else(|| format!("'{}' has no closing ]", key))?,
+        None => ("", key),
+    };
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(format!("'{}' names no key", key));
+    }
+    Ok((section.trim(), name))
+}
+
+/// Where an INI key is, or where it would go.
+#[derive(Debug, Default)]
+struct IniLocation {
+    /// The key's whole line, line ending included
+    line: Option<Range<usize>>,
+    value: Option<Range<usize>>,
+    /// Where a line for the key would be inserted in its section, if the
+    /// section exists
+    insert_at: Option<usize>,
+}
+
+fn ini_find(text: &str, section: &str, key: &str) -> IniLocation {
+    let mut location = IniLocation::default();
+    let mut current = "";
+    let mut offset = 0;
+    if section.is_empty() {
+        location.insert_at = Some(0);
+    }
+    for line in text.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        let content = line.trim_end_matches(['\r', '\n']);
+        let trimmed = content.trim();
+        if let Some(header) = trimmed.strip_prefix('[')
+            && let Some((name, _)) = header.split_once(']')
+        {
+            current = name.trim();
+            if current.eq_ignore_ascii_case(section) {
+                location.insert_at = Some(offset);
+            }
+            continue;
+        }
+        if !current.eq_ignore_ascii_case(section) {
+            continue;
+        }
+        if trimmed.is_empty() || trimmed.starts_with([';', '#']) {
+            continue;
+        }
+        location.insert_at = Some(offset);
+        if let Some((name, value)) = content.split_once('=')
+            && name.trim().eq_ignore_ascii_case(key)
+        {
+            let value_start = start + name.len() + 1 + (value.len() - value.trim_start().len());
+            let value_end = start + content.trim_end().len();
+            location.line = Some(start..offset);
+            location.value = Some(value_start..value_end.max(value_start));
+            return location;
+        }
+    }
+    location
+}
+
+fn ini_set(text: &str, section: &str, key: &str, raw: &str) -> String {
+    let location = ini_find(text, section, key);
+    let mut text = text.to_string();
+    let newline = line_ending(&text);
+    if let Some(value) = location.value {
+        text.replace_range(value, raw);
+        return text;
+    }
+    let entry = format!("{}={}{}", key, raw, newline);
+    match location.insert_at {
+        Some(at) => {
+            // The section's last line may lack a line ending at the end of
+            // the file
+            let separator = if at > 0 && !text[..at].ends_with('\n') {
+                newline
+            } else {
+                ""
+            };
+            text.insert_str(at, &format!("{}{}", separator, entry));
+        }
+        None => {
+            if !text.is_empty() && !text.ends_with('\n') {
+                text.push_str(newline);
+            }
+            text.push_str(&format!("[{}]{}{}", section, newline, entry));
+        }
+    }
+    text
+}
+
+// JSON files
+
+/// Where a JSON member is, or the object it would go in.
+#[derive(Debug, Default)]
+struct JsonLocation {
+    value: Option<Range<usize>>,
+    /// The member from its key's opening quote to its value's end
+    member: Option<Range<usize>>,
+    /// The other members of its object, for removing it or inserting next
+    /// to them
+    siblings: Vec<Range<usize>>,
+    /// Just past the `{` of the object the member is or would be in
+    object_start: usize,
+}
+
+/// A member of a JSON object.
+struct JsonMember {
+    key: String,
+    /// From the key's opening quote to the value's end
+    span: Range<usize>,
+    value: Range<usize>,
+}
+
+struct JsonScanner<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl JsonScanner<'_> {
+    fn skip_whitespace(&mut self) {
+        while self
+            .bytes
+            .get(self.pos)
+            .is_some_and(|byte| byte.is_ascii_whitespace())
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("{} at offset {}", message, self.pos)
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.bytes.get(self.pos) != Some(&byte) {
+            return Err(self.error(&format!("expected '{}'", byte as char)));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    /// Reads a string, returning its contents with escapes resolved.
+    fn string(&mut self) -> Result<String, String> {
+        self.expect(b'"')?;
+        let mut value = String::new();
+        let mut start = self.pos;
+        loop {
+            match self.bytes.get(self.pos) {
+                None => return Err(self.error("unterminated string")),
+                Some(b'"') => {
+                    value.push_str(&String::from_utf8_lossy(&self.bytes[start..self.pos]));
+                    self.pos += 1;
+                    return Ok(value);
+                }
+                Some(b'\\') => {
+                    value.push_str(&String::from_utf8_lossy(&self.bytes[start..self.pos]));
+                    let escaped = *self.bytes.get(self.pos + 1).unwrap_or(&b'\\');
+                    self.pos += 2;
+                    match escaped {
+                        b'n' => value.push('\n'),
+                        b't' => value.push('\t'),
+                        b'r' => value.push('\r'),
+                        b'b' => value.push('\u{8}'),
+                        b'f' => value.push('\u{c}'),
+                        b'u' => {
+                            let code = std::str::from_utf8(
+                                self.bytes.get(self.pos..self.pos + 4).unwrap_or_default(),
+                            )
+                            .ok()
+                            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                            .ok_or_else(|| self.error("invalid \\u escape"))?;
+                            value.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                            self.pos += 4;
+                        }
+                        other => value.push(other as char),
+                    }
+                    start = self.pos;
+                }
+                Some(_) => self.pos += 1,
+            }
+        }
+    }
+
+    /// Skips over one value of any type.
+    fn skip_value(&mut self) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.bytes.get(self.pos) {
+            Some(b'"') => self.string().map(|_| ()),
+            Some(&open @ (b'{' | b'[')) => {
+                let close = if open == b'{' { b'}' } else { b']' };
+                self.pos += 1;
+                loop {
+                    self.skip_whitespace();
+                    if self.bytes.get(self.pos) == Some(&close) {
+                        self.pos += 1;
+                        return Ok(());
+                    }
+                    if open == b'{' {
+                        self.string()?;
+                        self.expect(b':')?;
+                    }
+                    self.skip_value()?;
+                    self.skip_whitespace();
+                    match self.bytes.get(self.pos) {
+                        Some(b',') => self.pos += 1,
+                        Some(&byte) if byte == close => {}
+                        _ => return Err(self.error("expected ',' or a closing bracket")),
+                    }
+                }
+            }
+            Some(_) => {
+                let start = self.pos;
+                while self
+                    .bytes
+                    .get(self.pos)
+                    .is_some_and(|byte| byte.is_ascii_alphanumeric() || b"+-.".contains(byte))
+                {
+                    self.pos += 1;
+                }
+                if self.pos == start {
+                    return Err(self.error("expected a value"));
+                }
+                Ok(())
+            }
+            None => Err(self.error("unexpected end of file")),
+        }
+    }
+
+    /// Reads the object starting here.
+    fn members(&mut self) -> Result<Vec<JsonMember>, String> {
+        self.expect(b'{')?;
+        let mut members = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.bytes.get(self.pos) == Some(&b'}') {
+                return Ok(members);
+            }
+            let member_start = self.pos;
+            let key = self.string()?;
+            self.expect(b':')?;
+            self.skip_whitespace();
+            let value_start = self.pos;
+            self.skip_value()?;
+            members.push(JsonMember {
+                key,
+                span: member_start..self.pos,
+                value: value_start..self.pos,
+            });
+            self.skip_whitespace();
+            match self.bytes.get(self.pos) {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {}
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+}
+
+fn json_find(text: &str, steps: &[&str]) -> Result<JsonLocation, String> {
+    let mut scanner = JsonScanner {
+        bytes: text.as_bytes(),
+        pos: 0,
+    };
+    let mut location = JsonLocation::default();
+    // Skip a UTF-8 BOM left in the text
+    if text.starts_with('\u{feff}') {
+        scanner.pos = '\u{feff}'.len_utf8();
+    }
+    for (depth, step) in steps.iter().enumerate() {
+        scanner.skip_whitespace();
+        if scanner.bytes.get(scanner.pos) != Some(&b'{') {
+            return Err(format!("{} isn't an object", steps[..depth].join("/")));
+        }
+        location.object_start = scanner.pos + 1;
+        let members = scanner.members()?;
+        let found = members.iter().position(|member| member.key == *step);
+        let Some(index) = found else {
+            if depth + 1 < steps.len() {
+                return Err(format!("There's no {}", steps[..=depth].join("/")));
+            }
+            location.siblings = members.into_iter().map(|member| member.span).collect();
+            return Ok(location);
+        };
+        let value = members[index].value.clone();
+        if depth + 1 == steps.len() {
+            location.member = Some(members[index].span.clone());
+            location.value = Some(value);
+            location.siblings = members
+                .into_iter()
+                .enumerate()
+                .filter(|(other, _)| *other != index)
+                .map(|(_, member)| member.span)
+                .collect();
+            return Ok(location);
+        }
+        scanner.pos = value.start;
+    }
+    Ok(location)
+}
+
+fn json_set(text: &str, steps: &[&str], raw: &str) -> Result<String, String> {
+    let location = json_find(text, steps)?;
+    let mut text = text.to_string();
+    if let Some(value) = location.value {
+        text.replace_range(value, raw);
+        return Ok(text);
+    }
+    let member = format!("{}: {}", json_string(steps[steps.len() - 1]), raw);
+    match location.siblings.last() {
+        Some(last) => {
+            // Indent like the member before it
+            let indent = text[..last.start]
+                .rfind(|c: char| !c.is_whitespace())
+                .map_or("", |end| &text[end + 1..last.start]);
+            let insert = format!(
+                ",{}{}",
+                if indent.is_empty() { " " } else { indent },
+                member
+            );
+            text.insert_str(last.end, &insert);
+        }
+        None => text.insert_str(location.object_start, &member),
+    }
+    Ok(text)
+}
+
+fn json_remove(text: &str, steps: &[&str]) -> Result<String, String> {
+    let location = json_find(text, steps)?;
+    let mut text = text.to_string();
+    let Some(member) = location.member else {
+        return Ok(text);
+    };
+    let before = location
+        .siblings
+        .iter()
+        .rfind(|sibling| sibling.end <= member.start);
+    let after = location
+        .siblings
+        .iter()
+        .find(|sibling| sibling.start >= member.end);
+    let removed = match (before, after) {
+        // Take the comma before it along
+        (Some(before), _) => before.end..member.end,
+        (None, Some(after)) => member.start..after.start,
+        (None, None) => member,
+    };
+    text.replace_range(removed, "");
+    Ok(text)
+}
+
+fn json_string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// XML files
+
+/// Where an XML element's text or attribute is.
+#[derive(Debug)]
+struct XmlLocation {
+    /// The element's start tag, `<` to `>`
+    tag: Range<usize>,
+    name: String,
+    self_closing: bool,
+    /// The element's text, or the attribute's value inside its quotes
+    value: Option<Range<usize>>,
+    /// The attribute with its leading space, name and quotes
+    attribute: Option<Range<usize>>,
+}
+
+/// Finds the first element at `steps`, a path from the root element, and
+/// the attribute a last `@name` step names.
+fn xml_find(text: &str, steps: &[&str]) -> Result<XmlLocation, String> {
+    let (elements, attribute) = match steps.split_last() {
+        Some((last, rest)) if last.starts_with('@') => (rest, Some(&last[1..])),
+        _ => (steps, None),
+    };
+    if elements.is_empty() {
+        return Err("An XML path needs an element".to_string());
+    }
+    let mut stack: Vec<&str> = Vec::new();
+    let mut found: Option<XmlLocation> = None;
+    let mut pos = 0;
+    while let Some(offset) = text[pos..].find('<') {
+        let start = pos + offset;
+        let rest = &text[start..];
+        let skip_to = |terminator: &str| {
+            rest.find(terminator)
+                .map(|end| start + end + terminator.len())
+                .ok_or_else(|| format!("unterminated markup at offset {}", start))
+        };
+        if rest.starts_with("<!--") {
+            pos = skip_to("-->")?;
+        } else if rest.starts_with("<![CDATA[") {
+            pos = skip_to("]]>")?;
+        } else if rest.starts_with("<?") {
+            pos = skip_to("?>")?;
+        } else if rest.starts_with("<!") {
+            pos = skip_to(">")?;
+        } else if rest.starts_with("</") {
+            pos = skip_to(">")?;
+            if stack.len() == elements.len()
+                && let Some(mut location) = found.take()
+            {
+                let content = location.tag.end..start;
+                if text[content.clone()].contains('<') {
+                    return Err(format!("{} has child elements", elements.join("/")));
+                }
+                location.value = Some(content);
+                return Ok(location);
+            }
+            stack.pop();
+        } else {
+            let end = tag_end(text, start)
+                .ok_or_else(|| format!("unterminated tag at offset {}", start))?;
+            pos = end;
+            let tag = &text[start..end];
+            let name_end = tag[1..]
+                .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+                .map_or(tag.len(), |end| end + 1);
+            let name = &tag[1..name_end];
+            let self_closing = tag.ends_with("/>");
+            stack.push(name);
+            if found.is_none() && stack == elements {
+                let mut location = XmlLocation {
+                    tag: start..end,
+                    name: name.to_string(),
+                    self_closing,
+                    value: None,
+                    attribute: None,
+                };
+                if let Some(attribute) = attribute {
+                    if let Some((whole, value)) = xml_attribute(tag, attribute) {
+                        location.attribute = Some(start + whole.start..start + whole.end);
+                        location.value = Some(start + value.start..start + value.end);
+                    }
+                    return Ok(location);
+                }
+                if self_closing {
+                    // Empty, with no text to point at
+                    return Ok(location);
+                }
+                found = Some(location);
+            }
+            if self_closing {
+                stack.pop();
+            }
+        }
+    }
+    Err(format!("There's no element {}", elements.join("/")))
+}
+
+/// The end of the tag starting at `start`, past its `>`, skipping any `>`
+/// inside quoted attribute values.
+fn tag_end(text: &str, start: usize) -> Option<usize> {
+    let mut quote = None;
+    for (offset, c) in text[start..].char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), c) if c == open => quote = None,
+            (None, '>') => return Some(start + offset + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The span of `name="value"` in `tag`, from the space before it, and of
+/// the value inside its quotes.
+fn xml_attribute(tag: &str, name: &str) -> Option<(Range<usize>, Range<usize>)> {
+    let mut search = 0;
+    while let Some(offset) = tag[search..].find(name) {
+        let start = search + offset;
+        search = start + name.len();
+        let preceded = tag[..start].ends_with(char::is_whitespace);
+        let after = tag[search..].trim_start();
+        if !preceded || !after.starts_with('=') {
+            continue;
+        }
+        let after_equals = after[1..].trim_start();
+        let quote = after_equals.chars().next()?;
+        if quote != '"' && quote != '\'' {
+            return None;
+        }
+        let value_start = tag.len() - after_equals.len() + 1;
+        let value_end = value_start + tag[value_start..].find(quote)?;
+        let whole_start = tag[..start].trim_end().len();
+        return Some((whole_start..value_end + 1, value_start..value_end));
+    }
+    None
+}
+
+fn xml_set(text: &str, steps: &[&str], raw: &str) -> Result<String, String> {
+    let location = xml_find(text, steps)?;
+    let mut text = text.to_string();
+    if let Some(value) = location.value {
+        text.replace_range(value, raw);
+    } else if let Some(attribute) = steps.last().and_then(|step| step.strip_prefix('@')) {
+        let close = if location.self_closing { 2 } else { 1 };
+        let at = text[..location.tag.end - close].trim_end().len();
+        text.insert_str(at, &format!(" {}=\"{}\"", attribute, raw));
+    } else {
+        // A self-closing element gets an end tag to hold the text
+        let tag = &text[location.tag.clone()];
+        let opening = tag[..tag.len() - 2].trim_end().to_string();
+        text.replace_range(
+            location.tag,
+            &format!("{}>{}</{}>", opening, raw, location.name),
+        );
+    }
+    Ok(text)
+}
+
+fn xml_remove(text: &str, steps: &[&str]) -> Result<String, String> {
+    let location = xml_find(text, steps)?;
+    let mut text = text.to_string();
+    if let Some(attribute) = location.attribute {
+        text.replace_range(attribute, "");
+    } else if let Some(value) = location.value {
+        text.replace_range(value, "");
+    }
+    Ok(text)
+}
+
+pub(crate) fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_path;
+
+    /// Writes `bytes` to a fresh file and opens it for editing.
+    fn open(name: &str, bytes: &[u8], format: FileFormat) -> ConfigFile {
+        let path = temp_path(name);
+        fs::write(&path, bytes).unwrap();
+        ConfigFile::open(&path, format).unwrap()
+    }
+
+    #[test]
+    fn ini_set_and_remove_round_trip() {
+        let original = "; comment\r\n[Display]\r\nWidth=1280\r\nHeight = 720\r\n";
+        let mut file = open("patch-ini.ini", original.as_bytes(), FileFormat::Ini);
+        file.set("[Display]Width", "1920").unwrap();
+        file.set("[display]Height", "1080").unwrap();
+        file.set("[Display]Fullscreen", "1").unwrap();
+        file.set("[Audio]Volume", "50").unwrap();
+        assert_eq!(
+            file.text,
+            "; comment\r\n[Display]\r\nWidth=1920\r\nHeight = 1080\r\nFullscreen=1\r\n\
+             [Audio]\r\nVolume=50\r\n"
+        );
+        file.save().unwrap();
+
+        let mut file = ConfigFile::open(&file.path, FileFormat::Ini).unwrap();
+        assert_eq!(
+            file.get("[Display]Height").unwrap().as_deref(),
+            Some("1080")
+        );
+        file.set("[Display]Width", "1280").unwrap();
+        file.set("[Display]Height", "720").unwrap();
+        file.remove("[Display]Fullscreen").unwrap();
+        file.remove("[Audio]Volume").unwrap();
+        assert_eq!(file.text, format!("{}[Audio]\r\n", original));
+        let _ = fs::remove_file(&file.path);
+    }
+
+    #[test]
+    fn json_set_and_remove_round_trip() {
+        let original = "{\n  \"width\": 1280,\n  \"window\": {\"mode\": \"windowed\"}\n}\n";
+        let mut file = open("patch-json.json", original.as_bytes(), FileFormat::Json);
+        file.set("width", "1920").unwrap();
+        file.set("window/mode", &json_string("fullscreen")).unwrap();
+        file.set("vsync", "true").unwrap();
+        assert_eq!(
+            file.text,
+            "{\n  \"width\": 1920,\n  \"window\": {\"mode\": \"fullscreen\"},\n  \"vsync\": true\n}\n"
+        );
+        file.save().unwrap();
+
+        let mut file = ConfigFile::open(&file.path, FileFormat::Json).unwrap();
+        assert_eq!(
+            file.get("window/mode").unwrap().as_deref(),
+            Some("\"fullscreen\"")
+        );
+        file.set("width", "1280").unwrap();
+        file.set("window/mode", &json_string("windowed")).unwrap();
+        file.remove("vsync").unwrap();
+        assert_eq!(file.text, original);
+        let _ = fs::remove_file(&file.path);
+    }
+
+    #[test]
+    fn xml_set_and_remove_round_trip() {
+        let original = "<?xml version=\"1.0\"?>\n<Settings>\n  <Width>1280</Width>\n  \
+                        <Window mode=\"windowed\"/>\n</Settings>\n";
+        let mut file = open("patch-xml.xml", original.as_bytes(), FileFormat::Xml);
+        file.set("Settings/Width", "1920").unwrap();
+        file.set("Settings/Window/@mode", "fullscreen").unwrap();
+        file.set("Settings/Window/@vsync", "1").unwrap();
+        assert_eq!(
+            file.text,
+            "<?xml version=\"1.0\"?>\n<Settings>\n  <Width>1920</Width>\n  \
+             <Window mode=\"fullscreen\" vsync=\"1\"/>\n</Settings>\n"
+        );
+        file.save().unwrap();
+
+        let mut file = ConfigFile::open(&file.path, FileFormat::Xml).unwrap();
+        assert_eq!(
+            file.get("Settings/Window/@mode").unwrap().as_deref(),
+            Some("fullscreen")
+        );
+        file.set("Settings/Width", "1280").unwrap();
+        file.set("Settings/Window/@mode", "windowed").unwrap();
+        file.remove("Settings/Window/@vsync").unwrap();
+        assert_eq!(file.text, original);
+        let _ = fs::remove_file(&file.path);
+    }
+
+    #[test]
+    fn save_keeps_a_utf8_bom() {
+        let mut file = open(
+            "patch-bom.ini",
+            b"\xEF\xBB\xBF[Display]\nWidth=1280\n",
+            FileFormat::Ini,
+        );
+        file.set("[Display]Width", "1920").unwrap();
+        file.save().unwrap();
+        assert_eq!(
+            fs::read(&file.path).unwrap(),
+            b"\xEF\xBB\xBF[Display]\nWidth=1920\n"
+        );
+        let _ = fs::remove_file(&file.path);
+    }
+
+    #[test]
+    fn save_keeps_utf16() {
+        let utf16 = |text: &str| -> Vec<u8> {
+            [0xFF, 0xFE]
+                .into_iter()
+                .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+                .collect()
+        };
+        let mut file = open(
+            "patch-utf16.ini",
+            &utf16("[Display]\r\nWidth=1280\r\n"),
+            FileFormat::Ini,
+        );
+        assert_eq!(file.get("[Display]Width").unwrap().as_deref(), Some("1280"));
+        file.set("[Display]Width", "1920").unwrap();
+        file.save().unwrap();
+        assert_eq!(
+            fs::read(&file.path).unwrap(),
+            utf16("[Display]\r\nWidth=1920\r\n")
+        );
+        let _ = fs::remove_file(&file.path);
+    }
+
+    #[test]
+    fn save_keeps_plain_utf8() {
+        let mut file = open(
+            "patch-utf8.json",
+            b"{\"name\": \"Caf\xC3\xA9\"}",
+            FileFormat::Json,
+        );
+        file.set("name", &json_string("Bistro")).unwrap();
+        file.save().unwrap();
+        assert_eq!(fs::read(&file.path).unwrap(), b"{\"name\": \"Bistro\"}");
+        let _ = fs::remove_file(&file.path);
+    }
+}
@@ -0,0 +1,52 @@
+use std::env;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// A file beside the executable that makes every run portable, as if
+/// `--portable` was passed: shortcuts, scheduled tasks and the daemon
+/// included.
+pub const PORTABLE_FLAG_FILE: &str = "portable.flag";
+
+/// The data directory beside the executable in portable mode.
+const PORTABLE_DATA_DIR: &str = "librarylink-data";
+
+static PORTABLE: AtomicBool = AtomicBool::new(false);
+
+/// Keeps librarylink's state beside the executable rather than in
+/// `%APPDATA%` for the rest of the process, as for `--portable`.
+pub fn enable_portable() {
+    PORTABLE.store(true, Ordering::SeqCst);
+}
+
+/// Whether state is kept beside the executable, through `--portable` or
+/// [`PORTABLE_FLAG_FILE`].
+pub fn is_portable() -> bool {
+    static FLAG_FILE: OnceLock<bool> = OnceLock::new();
+    PORTABLE.load(Ordering::SeqCst)
+        || *FLAG_FILE.get_or_init(|| {
+            executable_dir().is_some_and(|dir| dir.join(PORTABLE_FLAG_FILE).is_file())
+        })
+}
+
+fn executable_dir() -> Option<PathBuf> {
+    env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(PathBuf::from))
+}
+
+/// Directory holding librarylink's persisted state (config, sessions,
+/// history): `%APPDATA%\librarylink`, or `librarylink-data` beside the
+/// executable in portable mode.
+pub fn data_dir() -> PathBuf {
+    if is_portable()
+        && let Some(dir) = executable_dir()
+    {
+        return dir.join(PORTABLE_DATA_DIR);
+    }
+    match env::var_os("APPDATA") {
+        Some(app_data) => PathBuf::from(app_data).join("librarylink"),
+        // Fall back to the current directory if the profile isn't available
+        None => PathBuf::from("librarylink"),
+    }
+}
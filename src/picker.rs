@@ -0,0 +1,316 @@
+//! The list `pick` shows: apps in the console, narrowed as a search is typed
+//! and chosen with the arrow keys. It reads and draws on the console itself
+//! (`CONIN$` and `CONOUT$`), so the choice can go to a redirected stdout, as
+//! in `$aumid = librarylink pick`.
+
+use windows::Win32::Foundation::{CloseHandle, GENERIC_READ, GENERIC_WRITE, HANDLE};
+use windows::Win32::Storage::FileSystem::{
+    CreateFileW, FILE_FLAGS_AND_ATTRIBUTES, FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING,
+};
+use windows::Win32::System::Console::{
+    CONSOLE_MODE, CONSOLE_SCREEN_BUFFER_INFO, ENABLE_EXTENDED_FLAGS, ENABLE_PROCESSED_OUTPUT,
+    ENABLE_VIRTUAL_TERMINAL_PROCESSING, ENABLE_WINDOW_INPUT, GetConsoleMode,
+    GetConsoleScreenBufferInfo, INPUT_RECORD, KEY_EVENT, ReadConsoleInputW, SetConsoleMode,
+    WINDOW_BUFFER_SIZE_EVENT, WriteConsoleW,
+};
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    VIRTUAL_KEY, VK_BACK, VK_DOWN, VK_END, VK_ESCAPE, VK_HOME, VK_NEXT, VK_PRIOR, VK_RETURN, VK_UP,
+};
+use windows::core::HSTRING;
+
+use crate::error::WinError;
+use crate::report::AppListing;
+use crate::search;
+
+/// Lines the list leaves for the search and status lines.
+const CHROME_LINES: usize = 2;
+
+/// The console, switched to raw key input and an alternate screen until
+/// dropped.
+struct Console {
+    input: HANDLE,
+    output: HANDLE,
+    input_mode: CONSOLE_MODE,
+    output_mode: CONSOLE_MODE,
+    alternate_screen: bool,
+}
+
+impl Console {
+    fn open() -> Result<Console, String> {
+        let open = |name: &str| unsafe {
+            CreateFileW(
+                &HSTRING::from(name),
+                (GENERIC_READ | GENERIC_WRITE).0,
+                FILE_SHARE_READ | FILE_SHARE_WRITE,
+                None,
+                OPEN_EXISTING,
+                FILE_FLAGS_AND_ATTRIBUTES(0),
+                None,
+            )
+            .map_err(|_| "pick needs an interactive console".to_string())
+        };
+        let input = open("CONIN$")?;
+        let output = open("CONOUT$");
+        let mut input_mode = CONSOLE_MODE::default();
+        let mut output_mode = CONSOLE_MODE::default();
+        let modes = output.clone().and_then(|output| unsafe {
+            GetConsoleMode(input, &mut input_mode)
+                .and_then(|_| GetConsoleMode(output, &mut output_mode))
+                .map_err(|e| WinError::new("GetConsoleMode", &e).to_string())
+        });
+        if let Err(e) = modes {
+            unsafe {
+                let _ = CloseHandle(input);
+                if let Ok(output) = output {
+                    let _ = CloseHandle(output);
+                }
+            }
+            return Err(e);
+        }
+        // From here on, dropping the console puts the modes back
+        let mut console = Console {
+            input,
+            output: output?,
+            input_mode,
+            output_mode,
+            alternate_screen: false,
+        };
+        unsafe {
+            // Keys one at a time, unechoed, with Ctrl+C read as a key
+            SetConsoleMode(input, ENABLE_WINDOW_INPUT | ENABLE_EXTENDED_FLAGS)
+                .map_err(|e| WinError::new("SetConsoleMode", &e).to_string())?;
+            SetConsoleMode(
+                console.output,
+                output_mode | ENABLE_PROCESSED_OUTPUT | ENABLE_VIRTUAL_TERMINAL_PROCESSING,
+            )
+            .map_err(|_| "This console can't show the picker; use list-apps instead".to_string())?;
+        }
+        console.write("\x1b[?1049h");
+        console.alternate_screen = true;
+        Ok(console)
+    }
+
+    fn write(&self, text: &str) {
+        let wide: Vec<u16> = text.encode_utf16().collect();
+        unsafe {
+            let _ = WriteConsoleW(self.output, &wide, None, None);
+        }
+    }
+
+    /// The visible window's rows and columns.
+    fn size(&self) -> (usize, usize) {
+        let mut info = CONSOLE_SCREEN_BUFFER_INFO::default();
+        if unsafe { GetConsoleScreenBufferInfo(self.output, &mut info) }.is_err() {
+            return (25, 80);
+        }
+        let window = info.srWindow;
+        (
+            (window.Bottom - window.Top + 1).max(1) as usize,
+            (window.Right - window.Left + 1).max(1) as usize,
+        )
+    }
+
+    fn next_key(&self) -> Result<Key, String> {
+        loop {
+            let mut records = [INPUT_RECORD::default()];
+            let mut read = 0;
+            unsafe { ReadConsoleInputW(self.input, &mut records, &mut read) }
+                .map_err(|e| WinError::new("ReadConsoleInputW", &e).to_string())?;
+            let record = records[0];
+            if read == 0 {
+                continue;
+            }
+            if record.EventType as u32 == WINDOW_BUFFER_SIZE_EVENT {
+                return Ok(Key::Resize);
+            }
+            if record.EventType as u32 != KEY_EVENT {
+                continue;
+            }
+            let event = unsafe { record.Event.KeyEvent };
+            if !event.bKeyDown.as_bool() {
+                continue;
+            }
+            let key = match VIRTUAL_KEY(event.wVirtualKeyCode) {
+                VK_UP => Key::Up,
+                VK_DOWN => Key::Down,
+                VK_PRIOR => Key::PageUp,
+                VK_NEXT => Key::PageDown,
+                VK_HOME => Key::Home,
+                VK_END => Key::End,
+                VK_RETURN => Key::Enter,
+                VK_ESCAPE => Key::Escape,
+                VK_BACK => Key::Backspace,
+                _ => match char::from_u32(unsafe { event.uChar.UnicodeChar } as u32) {
+                    // Ctrl+C
+                    Some('\u{3}') => Key::Escape,
+                    Some(c) if !c.is_control() => Key::Char(c),
+                    _ => continue,
+                },
+            };
+            return Ok(key);
+        }
+    }
+}
+
+impl Drop for Console {
+    fn drop(&mut self) {
+        if self.alternate_screen {
+            self.write("\x1b[?1049l");
+        }
+        unsafe {
+            let _ = SetConsoleMode(self.input, self.input_mode);
+            let _ = SetConsoleMode(self.output, self.output_mode);
+            let _ = CloseHandle(self.input);
+            let _ = CloseHandle(self.output);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Key {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Enter,
+    Escape,
+    Backspace,
+    Char(char),
+    Resize,
+}
+
+/// Shows `apps` with the search `query` typed in, returning the index of
+/// the one picked, or `None` if the picker is left with Escape or Ctrl+C.
+/// `action` says what Enter does, for the status line.
+pub fn pick_app(apps: &[AppListing], query: &str, action: &str) -> Result<Option<usize>, String> {
+    let console = Console::open()?;
+    let mut query = query.to_string();
+    let mut selected = 0;
+    let mut top = 0;
+    loop {
+        let matches = matching(apps, &query);
+        let (rows, columns) = console.size();
+        let visible = rows.saturating_sub(CHROME_LINES).max(1);
+        selected = selected.min(matches.len().saturating_sub(1));
+        if selected < top {
+            top = selected;
+        } else if selected >= top + visible {
+            top = selected + 1 - visible;
+        }
+        let view = View {
+            apps,
+            matches: &matches,
+            query: &query,
+            selected,
+            top,
+        };
+        console.write(&view.render(visible, columns, action));
+
+        match console.next_key()? {
+            Key::Up => selected = selected.saturating_sub(1),
+            Key::Down => selected += 1,
+            Key::PageUp => selected = selected.saturating_sub(visible),
+            Key::PageDown => selected += visible,
+            Key::Home => selected = 0,
+            Key::End => selected = matches.len(),
+            Key::Enter => {
+                if let Some(&index) = matches.get(selected) {
+                    return Ok(Some(index));
+                }
+            }
+            // The first Escape clears the search, the next one leaves
+            Key::Escape if !query.is_empty() => {
+                query.clear();
+                selected = 0;
+            }
+            Key::Escape => return Ok(None),
+            Key::Backspace => {
+                query.pop();
+                selected = 0;
+            }
+            Key::Char(c) => {
+                query.push(c);
+                selected = 0;
+            }
+            Key::Resize => {}
+        }
+    }
+}
+
+/// The indices of the apps whose name or AUMID contains every word of
+/// `query`, ignoring case and diacritics.
+fn matching(apps: &[AppListing], query: &str) -> Vec<usize> {
+    apps.iter()
+        .enumerate()
+        .filter(|(_, app)| search::contains_words(&format!("{} {}", app.name, app.aumid), query))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// One frame of the picker.
+struct View<'a> {
+    apps: &'a [AppListing],
+    matches: &'a [usize],
+    query: &'a str,
+    selected: usize,
+    /// The first match shown
+    top: usize,
+}
+
+impl View<'_> {
+    /// The frame as console output, `visible` matches at most and each line
+    /// cut to fit `columns`.
+    fn render(&self, visible: usize, columns: usize, action: &str) -> String {
+        // Lines stop short of the last column so they never wrap
+        let fit = |line: String| {
+            line.chars()
+                .take(columns.saturating_sub(1))
+                .collect::<String>()
+        };
+        let name_width = self
+            .matches
+            .iter()
+            .map(|&index| self.apps[index].name.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        let mut screen = String::from("\x1b[H");
+        screen.push_str(&fit(format!("Search: {}", self.query)));
+        screen.push_str("\x1b[K\r\n");
+        for (row, &index) in self.matches.iter().enumerate().skip(self.top).take(visible) {
+            let app = &self.apps[index];
+            let selected = row == self.selected;
+            let line = fit(format!(
+                "{} {:<width$}  {}",
+                if selected { ">" } else { " " },
+                app.name,
+                app.aumid,
+                width = name_width
+            ));
+            if selected {
+                screen.push_str(&format!("\x1b[7m{}\x1b[0m", line));
+            } else {
+                screen.push_str(&line);
+            }
+            screen.push_str("\x1b[K\r\n");
+        }
+        if self.matches.is_empty() {
+            screen.push_str("  No apps match.\x1b[K\r\n");
+        }
+        screen.push_str(&fit(format!(
+            "{} of {} apps · ↑/↓ to move · Enter to {} · Esc to cancel",
+            self.matches.len(),
+            self.apps.len(),
+            action
+        )));
+        // Clear what's left of the last frame, then put the cursor after the
+        // search text
+        screen.push_str(&format!(
+            "\x1b[K\x1b[J\x1b[1;{}H",
+            "Search: ".len() + self.query.chars().count() + 1
+        ));
+        screen
+    }
+}
@@ -0,0 +1,499 @@
+//! Moves the game's window to a chosen monitor and size once it appears.
+//! Coordinates are computed per-monitor DPI aware, in physical pixels, so a
+//! `1920x1080` window lands where it should on a 4K monitor at 150% next to a
+//! 1080p one at 100%.
+
+use std::collections::HashSet;
+use std::thread;
+use std::time::{Duration, Instant};
+use windows::Win32::Devices::Display::{
+    DISPLAYCONFIG_DEVICE_INFO_GET_ADVANCED_COLOR_INFO, DISPLAYCONFIG_DEVICE_INFO_GET_SOURCE_NAME,
+    DISPLAYCONFIG_DEVICE_INFO_GET_TARGET_NAME, DISPLAYCONFIG_DEVICE_INFO_HEADER,
+    DISPLAYCONFIG_DEVICE_INFO_SET_ADVANCED_COLOR_STATE, DISPLAYCONFIG_DEVICE_INFO_TYPE,
+    DISPLAYCONFIG_GET_ADVANCED_COLOR_INFO, DISPLAYCONFIG_MODE_INFO, DISPLAYCONFIG_PATH_INFO,
+    DISPLAYCONFIG_SET_ADVANCED_COLOR_STATE, DISPLAYCONFIG_SOURCE_DEVICE_NAME,
+    DISPLAYCONFIG_TARGET_DEVICE_NAME, DisplayConfigGetDeviceInfo, DisplayConfigSetDeviceInfo,
+    GetDisplayConfigBufferSizes, QDC_ONLY_ACTIVE_PATHS, QueryDisplayConfig,
+};
+use windows::Win32::Foundation::{HWND, LPARAM, LUID, RECT};
+use windows::Win32::Graphics::Gdi::{
+    EnumDisplayMonitors, GetMonitorInfoW, HDC, HMONITOR, MONITOR_DEFAULTTONEAREST, MONITORINFO,
+    MONITORINFOEXW, MonitorFromWindow,
+};
+use windows::Win32::UI::HiDpi::{
+    AdjustWindowRectExForDpi, DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2, GetDpiForMonitor,
+    MDT_EFFECTIVE_DPI, SetThreadDpiAwarenessContext,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GWL_EXSTYLE, GWL_STYLE, GetWindowLongW, GetWindowRect, MONITORINFOF_PRIMARY, SWP_NOACTIVATE,
+    SWP_NOZORDER, SetWindowPos, WINDOW_EX_STYLE, WINDOW_STYLE,
+};
+use windows::core::{BOOL, HRESULT};
+
+use crate::compat::Capability;
+use crate::error::WinError;
+use crate::monitor::{MonitorControl, MonitorState};
+use crate::report::{Event, Reporter};
+use crate::window;
+
+/// How long to wait for a tracked process to show a window before giving up
+/// on it.
+const WINDOW_TIMEOUT: Duration = Duration::from_secs(60);
+
+const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The DPI Windows treats as 100% scale.
+const DEFAULT_DPI: u32 = 96;
+
+/// Where to put the game's window. Nothing is moved when both are unset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WindowPlacement {
+    /// 1-based index into [`monitors`]; the window's current monitor if unset
+    pub monitor: Option<usize>,
+    /// Client area size in physical pixels; the current size if unset
+    pub size: Option<(u32, u32)>,
+}
+
+impl WindowPlacement {
+    pub fn is_empty(&self) -> bool {
+        self.monitor.is_none() && self.size.is_none()
+    }
+
+    /// Parses a `--window-size` value such as `1920x1080`.
+    pub fn parse_size(value: &str) -> Option<(u32, u32)> {
+        let (width, height) = value.split_once(['x', 'X'])?;
+        let size = (width.trim().parse().ok()?, height.trim().parse().ok()?);
+        (size.0 > 0 && size.1 > 0).then_some(size)
+    }
+}
+
+/// A display, in physical pixels.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct MonitorInfo {
+    /// 1-based, as `--monitor` takes it: the primary monitor first, then
+    /// left to right
+    pub index: usize,
+    /// Device name, such as `\\.\DISPLAY2`
+    pub name: String,
+    pub primary: bool,
+    pub left: i32,
+    pub top: i32,
+    pub width: u32,
+    pub height: u32,
+    /// The monitor's own name, such as `DELL U2720Q`, when it reports one
+    #[cfg_attr(feature = "json", serde(skip_serializing_if = "Option::is_none"))]
+    pub friendly_name: Option<String>,
+    /// In Hz, such as 59.94 or 144
+    #[cfg_attr(feature = "json", serde(skip_serializing_if = "Option::is_none"))]
+    pub refresh_rate: Option<f64>,
+    /// The display can show HDR
+    pub hdr_supported: bool,
+    /// HDR is turned on in Windows' display settings
+    pub hdr_enabled: bool,
+    /// The area not covered by the taskbar
+    #[cfg_attr(feature = "json", serde(skip))]
+    pub work_area: RECT,
+    /// Effective DPI; 96 is 100% scale
+    pub dpi: u32,
+}
+
+impl MonitorInfo {
+    /// The scale factor as a percentage.
+    pub fn scale_percent(&self) -> u32 {
+        self.dpi * 100 / DEFAULT_DPI
+    }
+}
+
+unsafe extern "system" fn collect_monitor(
+    monitor: HMONITOR,
+    _hdc: HDC,
+    _rect: *mut RECT,
+    lparam: LPARAM,
+) -> BOOL {
+    let monitors = unsafe { &mut *(lparam.0 as *mut Vec<MonitorInfo>) };
+    unsafe {
+        if let Some(info) = monitor_info(monitor) {
+            // Effective DPI is the same on both axes
+            let (mut dpi, mut dpi_y) = (DEFAULT_DPI, DEFAULT_DPI);
+            let _ = GetDpiForMonitor(monitor, MDT_EFFECTIVE_DPI, &mut dpi, &mut dpi_y);
+            let bounds = info.monitorInfo.rcMonitor;
+            monitors.push(MonitorInfo {
+                index: 0,
+                name: wide_to_string(&info.szDevice),
+                primary: info.monitorInfo.dwFlags & MONITORINFOF_PRIMARY != 0,
+                left: bounds.left,
+                top: bounds.top,
+                width: (bounds.right - bounds.left) as u32,
+                height: (bounds.bottom - bounds.top) as u32,
+                friendly_name: None,
+                refresh_rate: None,
+                hdr_supported: false,
+                hdr_enabled: false,
+                work_area: info.monitorInfo.rcWork,
+                dpi,
+            });
+        }
+    }
+    // Keep enumerating
+    BOOL(1)
+}
+
+pub(crate) fn monitor_info(monitor: HMONITOR) -> Option<MONITORINFOEXW> {
+    let mut info = MONITORINFOEXW::default();
+    info.monitorInfo.cbSize = size_of::<MONITORINFOEXW>() as u32;
+    unsafe {
+        GetMonitorInfoW(
+            monitor,
+            &mut info as *mut MONITORINFOEXW as *mut MONITORINFO,
+        )
+    }
+    .as_bool()
+    .then_some(info)
+}
+
+/// The connected monitors in `--monitor` order. Must be called from a
+/// per-monitor DPI aware thread for the sizes to be physical; this one makes
+/// the calling thread aware for the duration.
+pub fn monitors() -> Vec<MonitorInfo> {
+    let _awareness = DpiAwareness::enter();
+    let mut monitors: Vec<MonitorInfo> = Vec::new();
+    unsafe {
+        let _ = EnumDisplayMonitors(
+            None,
+            None,
+            Some(collect_monitor),
+            LPARAM(&mut monitors as *mut Vec<MonitorInfo> as isize),
+        );
+    }
+    monitors.sort_by_key(|monitor| (!monitor.primary, monitor.left, monitor.top));
+    let details = display_details();
+    for (position, monitor) in monitors.iter_mut().enumerate() {
+        monitor.index = position + 1;
+        if let Some(detail) = details.iter().find(|detail| detail.device == monitor.name) {
+            monitor.friendly_name = detail.friendly_name.clone();
+            monitor.refresh_rate = detail.refresh_rate;
+            monitor.hdr_supported = detail.hdr_supported;
+            monitor.hdr_enabled = detail.hdr_enabled;
+        }
+    }
+    monitors
+}
+
+/// What the display configuration knows about the monitor behind a GDI
+/// device name.
+struct DisplayDetails {
+    device: String,
+    /// The monitor's adapter and target ID in the display configuration
+    target: (LUID, u32),
+    friendly_name: Option<String>,
+    refresh_rate: Option<f64>,
+    hdr_supported: bool,
+    hdr_enabled: bool,
+}
+
+/// Reads the active display paths, which carry the refresh rate, the
+/// monitor's name and its advanced color (HDR) state that `GetMonitorInfoW`
+/// doesn't. Empty if the configuration can't be read.
+fn display_details() -> Vec<DisplayDetails> {
+    let mut path_count = 0u32;
+    let mut mode_count = 0u32;
+    unsafe {
+        if GetDisplayConfigBufferSizes(QDC_ONLY_ACTIVE_PATHS, &mut path_count, &mut mode_count)
+            .is_err()
+        {
+            return Vec::new();
+        }
+        let mut paths = vec![DISPLAYCONFIG_PATH_INFO::default(); path_count as usize];
+        let mut modes = vec![DISPLAYCONFIG_MODE_INFO::default(); mode_count as usize];
+        if QueryDisplayConfig(
+            QDC_ONLY_ACTIVE_PATHS,
+            &mut path_count,
+            paths.as_mut_ptr(),
+            &mut mode_count,
+            modes.as_mut_ptr(),
+            None,
+        )
+        .is_err()
+        {
+            return Vec::new();
+        }
+        paths.truncate(path_count as usize);
+
+        let mut details = Vec::new();
+        for path in &paths {
+            let mut source = DISPLAYCONFIG_SOURCE_DEVICE_NAME {
+                header: device_info_header::<DISPLAYCONFIG_SOURCE_DEVICE_NAME>(
+                    DISPLAYCONFIG_DEVICE_INFO_GET_SOURCE_NAME,
+                    path.sourceInfo.adapterId,
+                    path.sourceInfo.id,
+                ),
+                ..Default::default()
+            };
+            if DisplayConfigGetDeviceInfo(&mut source.header) != 0 {
+                continue;
+            }
+
+            let target_id = (path.targetInfo.adapterId, path.targetInfo.id);
+            let mut target = DISPLAYCONFIG_TARGET_DEVICE_NAME {
+                header: device_info_header::<DISPLAYCONFIG_TARGET_DEVICE_NAME>(
+                    DISPLAYCONFIG_DEVICE_INFO_GET_TARGET_NAME,
+                    target_id.0,
+                    target_id.1,
+                ),
+                ..Default::default()
+            };
+            let friendly_name = (DisplayConfigGetDeviceInfo(&mut target.header) == 0)
+                .then(|| wide_to_string(&target.monitorFriendlyDeviceName))
+                .filter(|name| !name.is_empty());
+
+            let mut color = DISPLAYCONFIG_GET_ADVANCED_COLOR_INFO {
+                header: device_info_header::<DISPLAYCONFIG_GET_ADVANCED_COLOR_INFO>(
+                    DISPLAYCONFIG_DEVICE_INFO_GET_ADVANCED_COLOR_INFO,
+                    target_id.0,
+                    target_id.1,
+                ),
+                ..Default::default()
+            };
+            // Bit 0 is advancedColorSupported, bit 1 advancedColorEnabled
+            let color_flags = if Capability::HdrState.is_supported()
+                && DisplayConfigGetDeviceInfo(&mut color.header) == 0
+            {
+                color.Anonymous.value
+            } else {
+                0
+            };
+
+            let rate = path.targetInfo.refreshRate;
+            details.push(DisplayDetails {
+                device: wide_to_string(&source.viewGdiDeviceName),
+                target: target_id,
+                friendly_name,
+                refresh_rate: (rate.Denominator != 0).then(|| {
+                    // Two decimals, so 59.94 doesn't print as 59.94005994
+                    (rate.Numerator as f64 / rate.Denominator as f64 * 100.0).round() / 100.0
+                }),
+                hdr_supported: color_flags & 0x1 != 0,
+                hdr_enabled: color_flags & 0x2 != 0,
+            });
+        }
+        details
+    }
+}
+
+/// Whether HDR is on for the display `device`, such as `\\.\DISPLAY2`, or
+/// the primary display.
+pub(crate) fn hdr_enabled(device: Option<&str>) -> Result<bool, String> {
+    Ok(hdr_display(device)?.hdr_enabled)
+}
+
+/// Turns HDR on or off for the display `device`, or the primary display, as
+/// the switch in Windows' display settings does.
+pub(crate) fn set_hdr(device: Option<&str>, enabled: bool) -> Result<(), String> {
+    let display = hdr_display(device)?;
+    let mut state = DISPLAYCONFIG_SET_ADVANCED_COLOR_STATE {
+        header: device_info_header::<DISPLAYCONFIG_SET_ADVANCED_COLOR_STATE>(
+            DISPLAYCONFIG_DEVICE_INFO_SET_ADVANCED_COLOR_STATE,
+            display.target.0,
+            display.target.1,
+        ),
+        ..Default::default()
+    };
+    // Bit 0 is enableAdvancedColor
+    state.Anonymous.value = enabled as u32;
+    let status = unsafe { DisplayConfigSetDeviceInfo(&state.header) };
+    if status != 0 {
+        return Err(WinError::from_hresult(
+            "DisplayConfigSetDeviceInfo",
+            HRESULT::from_win32(status as u32),
+        )
+        .to_string());
+    }
+    Ok(())
+}
+
+/// The display `device`, or the primary display, if it can show HDR.
+fn hdr_display(device: Option<&str>) -> Result<DisplayDetails, String> {
+    let device = match device {
+        Some(device) => device.to_string(),
+        None => monitors()
+            .into_iter()
+            .find(|monitor| monitor.primary)
+            .map(|monitor| monitor.name)
+            .ok_or("No display is connected")?,
+    };
+    let display = display_details()
+        .into_iter()
+        .find(|display| display.device.eq_ignore_ascii_case(&device))
+        .ok_or_else(|| format!("No active display is named '{}'", device))?;
+    if !display.hdr_supported {
+        return Err(format!("{} can't show HDR", device));
+    }
+    Ok(display)
+}
+
+fn device_info_header<T>(
+    kind: DISPLAYCONFIG_DEVICE_INFO_TYPE,
+    adapter: LUID,
+    id: u32,
+) -> DISPLAYCONFIG_DEVICE_INFO_HEADER {
+    DISPLAYCONFIG_DEVICE_INFO_HEADER {
+        r#type: kind,
+        size: size_of::<T>() as u32,
+        adapterId: adapter,
+        id,
+    }
+}
+
+/// A NUL-terminated UTF-16 buffer as a string.
+fn wide_to_string(buffer: &[u16]) -> String {
+    let length = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+    String::from_utf16_lossy(&buffer[..length])
+}
+
+/// Makes the current thread per-monitor DPI aware until dropped, so window
+/// and monitor coordinates are physical pixels whatever the host process is.
+pub(crate) struct DpiAwareness {
+    previous: windows::Win32::UI::HiDpi::DPI_AWARENESS_CONTEXT,
+}
+
+impl DpiAwareness {
+    pub(crate) fn enter() -> Self {
+        DpiAwareness {
+            previous: unsafe {
+                SetThreadDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2)
+            },
+        }
+    }
+}
+
+impl Drop for DpiAwareness {
+    fn drop(&mut self) {
+        if !self.previous.is_invalid() {
+            unsafe { SetThreadDpiAwarenessContext(self.previous) };
+        }
+    }
+}
+
+/// Places each process the monitor tracks once it shows a window, until the
+/// game's window has been placed or the session ends. Run alongside the
+/// monitor, which it only reads.
+pub(crate) fn place_tracked_windows(
+    placement: &WindowPlacement,
+    control: &MonitorControl,
+    reporter: &dyn Reporter,
+) {
+    let _awareness = DpiAwareness::enter();
+    let mut placed = HashSet::new();
+    let mut waiting_since = (0, Instant::now());
+    loop {
+        let (process_id, is_game) = match control.state() {
+            Some(MonitorState::Ended { .. }) => return,
+            Some(MonitorState::TrackingGame { pid }) => (pid, true),
+            Some(MonitorState::TrackingStub { pid }) => (pid, false),
+            _ => (0, false),
+        };
+        if process_id != 0 && !placed.contains(&process_id) {
+            if waiting_since.0 != process_id {
+                waiting_since = (process_id, Instant::now());
+            }
+            if let Some(hwnd) = window::main_window(process_id) {
+                placed.insert(process_id);
+                match place(process_id, hwnd, placement) {
+                    Ok(event) => reporter.event(&event),
+                    Err(reason) => reporter.event(&Event::WindowNotPlaced {
+                        pid: process_id,
+                        reason,
+                    }),
+                }
+                if is_game {
+                    return;
+                }
+            } else if waiting_since.1.elapsed() >= WINDOW_TIMEOUT {
+                placed.insert(process_id);
+                reporter.event(&Event::WindowNotPlaced {
+                    pid: process_id,
+                    reason: format!("no window appeared within {}s", WINDOW_TIMEOUT.as_secs()),
+                });
+            }
+        }
+        thread::sleep(CHECK_INTERVAL);
+    }
+}
+
+/// Moves and resizes `process_id`'s window `hwnd`, centered in the monitor's
+/// work area.
+fn place(process_id: u32, hwnd: HWND, placement: &WindowPlacement) -> Result<Event, String> {
+    if !Capability::PerMonitorDpi.is_supported() {
+        return Err(Capability::PerMonitorDpi.unsupported_message());
+    }
+    let monitors = monitors();
+    let monitor = match placement.monitor {
+        Some(index) => monitors
+            .iter()
+            .find(|monitor| monitor.index == index)
+            .ok_or_else(|| {
+                format!(
+                    "there is no monitor {} ({} connected)",
+                    index,
+                    monitors.len()
+                )
+            })?,
+        None => {
+            let current =
+                monitor_info(unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST) })
+                    .map(|info| wide_to_string(&info.szDevice));
+            monitors
+                .iter()
+                .find(|monitor| Some(&monitor.name) == current.as_ref())
+                .or_else(|| monitors.first())
+                .ok_or("no monitors found")?
+        }
+    };
+
+    unsafe {
+        let mut bounds = RECT::default();
+        GetWindowRect(hwnd, &mut bounds)
+            .map_err(|e| WinError::new("GetWindowRect", &e).to_string())?;
+        if let Some((width, height)) = placement.size {
+            // The size is the client area; add the frame as drawn at the
+            // target monitor's scale
+            bounds = RECT {
+                left: 0,
+                top: 0,
+                right: width as i32,
+                bottom: height as i32,
+            };
+            let style = WINDOW_STYLE(GetWindowLongW(hwnd, GWL_STYLE) as u32);
+            let extended = WINDOW_EX_STYLE(GetWindowLongW(hwnd, GWL_EXSTYLE) as u32);
+            AdjustWindowRectExForDpi(&mut bounds, style, false, extended, monitor.dpi)
+                .map_err(|e| WinError::new("AdjustWindowRectExForDpi", &e).to_string())?;
+        }
+        let width = bounds.right - bounds.left;
+        let height = bounds.bottom - bounds.top;
+
+        let work = monitor.work_area;
+        let x = work.left + ((work.right - work.left - width) / 2).max(0);
+        let y = work.top + ((work.bottom - work.top - height) / 2).max(0);
+        SetWindowPos(
+            hwnd,
+            None,
+            x,
+            y,
+            width,
+            height,
+            SWP_NOZORDER | SWP_NOACTIVATE,
+        )
+        .map_err(|e| WinError::new("SetWindowPos", &e).to_string())?;
+
+        Ok(Event::WindowPlaced {
+            pid: process_id,
+            monitor: monitor.index,
+            x,
+            y,
+            width: width as u32,
+            height: height as u32,
+            dpi: monitor.dpi,
+        })
+    }
+}
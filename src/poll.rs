@@ -0,0 +1,79 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Interval between scans when no explicit `--poll-interval` is given.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long a replacement process has to appear after a launcher stub exits,
+/// unless a grace period says otherwise. Launcher stubs often exit a moment
+/// before the game process shows up.
+pub const HANDOFF_WINDOW: Duration = Duration::from_secs(5);
+
+/// How the monitor checks on processes when it can't wait on a handle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PollSettings {
+    pub interval: Duration,
+    /// Set by `--poll-interval`: always poll, even when a wait handle is available
+    pub forced: bool,
+    /// Replacement candidates younger than this are watched until they reach
+    /// it, and skipped if they exit first (transient updaters, crash reporters)
+    pub min_lifetime: Duration,
+    /// How long to keep looking for a replacement when the game may be
+    /// restarting itself, when longer than the handoff window
+    pub restart_window: Duration,
+    /// How long a replacement has to appear before the app counts as exited,
+    /// for loaders that take a while to start the game
+    pub grace_period: Duration,
+}
+
+impl Default for PollSettings {
+    fn default() -> Self {
+        PollSettings {
+            interval: DEFAULT_INTERVAL,
+            forced: false,
+            min_lifetime: Duration::ZERO,
+            restart_window: Duration::ZERO,
+            grace_period: HANDOFF_WINDOW,
+        }
+    }
+}
+
+impl PollSettings {
+    /// Parses a `--poll-interval` value in milliseconds.
+    pub fn parse(value: &str) -> Option<Self> {
+        let millis = value.parse::<u64>().ok().filter(|&millis| millis > 0)?;
+        Some(PollSettings {
+            interval: Duration::from_millis(millis),
+            forced: true,
+            ..Default::default()
+        })
+    }
+
+    /// How long a replacement search lasts: the grace period or the restart
+    /// window after a launcher stub exits, and only the restart window once
+    /// the game has, so a game that quits ends its session right away.
+    pub fn search_window(&self, after_stub: bool) -> Duration {
+        if after_stub {
+            self.grace_period.max(self.restart_window)
+        } else {
+            self.restart_window
+        }
+    }
+
+    /// The interval with up to ±25% jitter, so several instances don't scan in lockstep.
+    pub fn jittered_interval(&self) -> Duration {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+        // Mix in the PID so instances started in the same instant still differ
+        let seed = nanos ^ std::process::id().wrapping_mul(2_654_435_761);
+        let percent = 75 + (seed % 51) as u64;
+
+        let millis = self.interval.as_millis() as u64;
+        Duration::from_millis((millis * percent / 100).max(1))
+    }
+
+    pub fn sleep(&self) {
+        std::thread::sleep(self.jittered_interval());
+    }
+}
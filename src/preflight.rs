@@ -0,0 +1,185 @@
+//! Checks run before a profile's game is activated, so a missing
+//! prerequisite is reported up front instead of as a black screen on the
+//! stream.
+
+use std::mem;
+use windows::Win32::Graphics::Gdi::{DISPLAY_DEVICEW, EnumDisplayDevicesW};
+use windows::Win32::Media::Multimedia::{
+    JOY_RETURNBUTTONS, JOYERR_NOERROR, JOYINFOEX, joyGetNumDevs, joyGetPosEx,
+};
+use windows::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+use windows::Win32::UI::Input::XboxController::{XINPUT_STATE, XInputGetState};
+use windows::core::{HSTRING, PCWSTR};
+
+use crate::package;
+use crate::probe;
+
+/// XInput supports four controllers.
+const XUSER_MAX_COUNT: u32 = 4;
+
+/// The checks a profile asks for, under `[preflight]`. All are off unless set.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "json", derive(serde::Deserialize))]
+#[cfg_attr(feature = "json", serde(default, deny_unknown_fields))]
+pub struct PreflightChecks {
+    /// Gigabytes that must be free on the volume the game is installed on
+    pub min_free_gb: Option<f64>,
+    /// Windows services, by service name, that must be running
+    pub services: Vec<String>,
+    /// A display adapter with a vendor driver must be present, rather than
+    /// only Microsoft's basic display driver
+    pub gpu_driver: bool,
+    /// A game controller must be connected
+    pub controller: bool,
+}
+
+impl PreflightChecks {
+    pub fn is_empty(&self) -> bool {
+        self.min_free_gb.is_none()
+            && self.services.is_empty()
+            && !self.gpu_driver
+            && !self.controller
+    }
+}
+
+/// The outcome of one check.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct PreflightResult {
+    pub check: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+/// Runs the checks for the app `aumid`.
+pub fn run(aumid: &str, checks: &PreflightChecks) -> Vec<PreflightResult> {
+    let mut results = Vec::new();
+    if let Some(min_free_gb) = checks.min_free_gb {
+        results.push(check_free_space(aumid, min_free_gb));
+    }
+    for service in &checks.services {
+        let passed = probe::is_service_running(service);
+        results.push(PreflightResult {
+            check: format!("{} service", service),
+            passed,
+            detail: if passed { "running" } else { "not running" }.to_string(),
+        });
+    }
+    if checks.gpu_driver {
+        results.push(check_gpu_driver());
+    }
+    if checks.controller {
+        results.push(check_controller());
+    }
+    results
+}
+
+fn check_free_space(aumid: &str, min_free_gb: f64) -> PreflightResult {
+    let Some(install_path) = package::installed_path(aumid) else {
+        return PreflightResult {
+            check: "free space".to_string(),
+            passed: false,
+            detail: "the game's install location is unknown".to_string(),
+        };
+    };
+    let volume = install_path
+        .ancestors()
+        .last()
+        .unwrap_or(&install_path)
+        .display()
+        .to_string();
+    let check = format!("free space on {}", volume);
+
+    let mut free_bytes = 0u64;
+    let result = unsafe {
+        GetDiskFreeSpaceExW(
+            &HSTRING::from(install_path.as_os_str()),
+            Some(&mut free_bytes),
+            None,
+            None,
+        )
+    };
+    match result {
+        Ok(()) => {
+            let free_gb = free_bytes as f64 / 1_073_741_824.0;
+            PreflightResult {
+                check,
+                passed: free_gb >= min_free_gb,
+                detail: format!("{:.1} GB free, {:.1} GB required", free_gb, min_free_gb),
+            }
+        }
+        Err(e) => PreflightResult {
+            check,
+            passed: false,
+            detail: format!("could not query free space: {}", e.message()),
+        },
+    }
+}
+
+/// Display adapter names, once each.
+fn display_adapters() -> Vec<String> {
+    let mut adapters: Vec<String> = Vec::new();
+    for index in 0.. {
+        let mut device = DISPLAY_DEVICEW {
+            cb: mem::size_of::<DISPLAY_DEVICEW>() as u32,
+            ..Default::default()
+        };
+        if !unsafe { EnumDisplayDevicesW(PCWSTR::null(), index, &mut device, 0) }.as_bool() {
+            break;
+        }
+        let length = device
+            .DeviceString
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(device.DeviceString.len());
+        let name = String::from_utf16_lossy(&device.DeviceString[..length]);
+        if !name.is_empty() && !adapters.contains(&name) {
+            adapters.push(name);
+        }
+    }
+    adapters
+}
+
+fn check_gpu_driver() -> PreflightResult {
+    let adapters = display_adapters();
+    // Microsoft Basic Display Adapter and Remote Display Adapter stand in
+    // when no vendor driver is installed
+    let vendor: Vec<&String> = adapters
+        .iter()
+        .filter(|name| !name.starts_with("Microsoft "))
+        .collect();
+    PreflightResult {
+        check: "GPU driver".to_string(),
+        passed: !vendor.is_empty(),
+        detail: if adapters.is_empty() {
+            "no display adapters".to_string()
+        } else {
+            adapters.join(", ")
+        },
+    }
+}
+
+fn check_controller() -> PreflightResult {
+    let xinput = (0..XUSER_MAX_COUNT)
+        .filter(|&index| {
+            let mut state = XINPUT_STATE::default();
+            unsafe { XInputGetState(index, &mut state) == 0 }
+        })
+        .count();
+    // Controllers without XInput support are only visible as joysticks
+    let joysticks = (0..unsafe { joyGetNumDevs() })
+        .filter(|&id| {
+            let mut info = JOYINFOEX {
+                dwSize: mem::size_of::<JOYINFOEX>() as u32,
+                dwFlags: JOY_RETURNBUTTONS as u32,
+                ..Default::default()
+            };
+            unsafe { joyGetPosEx(id, &mut info) == JOYERR_NOERROR }
+        })
+        .count();
+    PreflightResult {
+        check: "controller".to_string(),
+        passed: xinput > 0 || joysticks > 0,
+        detail: format!("{} XInput, {} joystick", xinput, joysticks),
+    }
+}
@@ -0,0 +1,246 @@
+//! Frame-time capture with PresentMon (https://github.com/GameTechDev/PresentMon),
+//! run as a separate process scoped to one game process.
+//!
+//! PresentMon traces ETW events, so it needs administrator rights or
+//! membership in the Performance Log Users group.
+
+use std::env;
+use std::fs;
+use std::os::windows::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+use windows::Win32::System::Threading::CREATE_NO_WINDOW;
+
+use crate::paths;
+use crate::report::warning;
+use crate::session::FrameStats;
+
+/// How long PresentMon gets to flush its output after the game exits.
+const EXIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A running PresentMon capture.
+#[derive(Debug)]
+pub struct FrameCapture {
+    child: Child,
+    output: PathBuf,
+}
+
+impl FrameCapture {
+    /// Starts `presentmon` tracing `process_id`, writing per-frame CSV to
+    /// `output`. PresentMon stops by itself when the process exits.
+    pub fn start(
+        presentmon: &Path,
+        process_id: u32,
+        output: &Path,
+    ) -> Result<FrameCapture, Box<dyn std::error::Error>> {
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let child = Command::new(presentmon)
+            .arg("--process_id")
+            .arg(process_id.to_string())
+            .arg("--output_file")
+            .arg(output)
+            .args(["--no_console_stats", "--terminate_on_proc_exit"])
+            // A capture interrupted earlier leaves its trace session behind
+            .args(["--stop_existing_session", "--session_name"])
+            .arg(format!("librarylink_{}", process_id))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .creation_flags(CREATE_NO_WINDOW.0)
+            .spawn()
+            .map_err(|e| format!("Could not start {}: {}", presentmon.display(), e))?;
+        Ok(FrameCapture {
+            child,
+            output: output.to_path_buf(),
+        })
+    }
+
+    /// The per-frame CSV being written.
+    pub fn output(&self) -> &Path {
+        &self.output
+    }
+
+    /// Waits briefly for PresentMon to finish after the game exited,
+    /// terminating it otherwise. Returns the CSV it wrote.
+    pub fn stop(mut self) -> PathBuf {
+        let started = Instant::now();
+        while matches!(self.child.try_wait(), Ok(None)) {
+            if started.elapsed() >= EXIT_TIMEOUT {
+                let _ = self.child.kill();
+                let _ = self.child.wait();
+                break;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        self.output
+    }
+
+    /// Stops the capture and summarizes what it captured.
+    pub fn finish(self) -> Option<FrameStats> {
+        summarize(&[self.stop()])
+    }
+}
+
+/// Captures frame times for a session, following it to each process it
+/// tracks. Each process gets its own PresentMon run and CSV under
+/// `%APPDATA%\librarylink\frames`; the statistics cover all of them.
+#[derive(Debug)]
+pub struct FrameRecorder {
+    presentmon: PathBuf,
+    dir: PathBuf,
+    name: String,
+    state: Mutex<RecorderState>,
+}
+
+#[derive(Debug, Default)]
+struct RecorderState {
+    current: Option<(u32, FrameCapture)>,
+    outputs: Vec<PathBuf>,
+}
+
+pub fn frames_dir() -> PathBuf {
+    paths::data_dir().join("frames")
+}
+
+impl FrameRecorder {
+    /// A recorder writing `<name>.csv`, `<name>-2.csv` and so on to
+    /// [`frames_dir`].
+    pub fn new(presentmon: &Path, name: &str) -> Self {
+        Self::in_dir(presentmon, &frames_dir(), name)
+    }
+
+    /// A recorder writing its CSVs to `dir` instead.
+    pub fn in_dir(presentmon: &Path, dir: &Path, name: &str) -> Self {
+        FrameRecorder {
+            presentmon: presentmon.to_path_buf(),
+            dir: dir.to_path_buf(),
+            name: name.to_string(),
+            state: Mutex::new(RecorderState::default()),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, RecorderState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Starts capturing `process_id`, ending the capture of the previously
+    /// tracked process.
+    pub fn follow(&self, process_id: u32) {
+        let mut state = self.lock();
+        if state
+            .current
+            .as_ref()
+            .is_some_and(|(current, _)| *current == process_id)
+        {
+            return;
+        }
+        if let Some((_, capture)) = state.current.take() {
+            let output = capture.stop();
+            state.outputs.push(output);
+        }
+
+        let output = match state.outputs.len() {
+            0 => self.dir.join(format!("{}.csv", self.name)),
+            count => self.dir.join(format!("{}-{}.csv", self.name, count + 1)),
+        };
+        match FrameCapture::start(&self.presentmon, process_id, &output) {
+            Ok(capture) => state.current = Some((process_id, capture)),
+            Err(e) => warning!(
+                "⚠️ Frame times won't be captured for process {}: {}",
+                process_id,
+                e
+            ),
+        }
+    }
+
+    /// Ends the current capture and summarizes every process captured.
+    pub fn finish(&self) -> Option<FrameStats> {
+        let mut state = self.lock();
+        if let Some((_, capture)) = state.current.take() {
+            let output = capture.stop();
+            state.outputs.push(output);
+        }
+        summarize(&state.outputs)
+    }
+}
+
+/// Finds a PresentMon to use when none is configured: `PresentMon*.exe` next
+/// to librarylink (as bundled by the installer), then `PresentMon.exe` on the
+/// `PATH`.
+pub fn locate() -> Option<PathBuf> {
+    let bundled = env::current_exe().ok().and_then(|exe| {
+        let dir = exe.parent()?.to_path_buf();
+        fs::read_dir(dir)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .find(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| {
+                        let name = name.to_ascii_lowercase();
+                        name.starts_with("presentmon") && name.ends_with(".exe")
+                    })
+            })
+    });
+    bundled.or_else(|| {
+        env::split_paths(&env::var_os("PATH")?)
+            .map(|dir| dir.join("PresentMon.exe"))
+            .find(|path| path.is_file())
+    })
+}
+
+/// Summarizes PresentMon CSVs together. Reads the frame time from
+/// `MsBetweenPresents` (PresentMon 1.x and 2.0) or `FrameTime` (later 2.x).
+pub fn summarize(outputs: &[PathBuf]) -> Option<FrameStats> {
+    let mut frame_times: Vec<f64> = outputs
+        .iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .flat_map(|contents| read_frame_times(&contents))
+        .collect();
+    if frame_times.is_empty() {
+        return None;
+    }
+    frame_times.sort_by(f64::total_cmp);
+
+    let percentile = |fraction: f64| {
+        let index = ((frame_times.len() as f64 * fraction).ceil() as usize).max(1) - 1;
+        frame_times[index.min(frame_times.len() - 1)]
+    };
+    let avg_frame_time_ms = frame_times.iter().sum::<f64>() / frame_times.len() as f64;
+    Some(FrameStats {
+        frames: frame_times.len(),
+        avg_fps: 1000.0 / avg_frame_time_ms,
+        low_1_percent_fps: 1000.0 / percentile(0.99),
+        low_0_1_percent_fps: 1000.0 / percentile(0.999),
+        avg_frame_time_ms,
+        max_frame_time_ms: frame_times[frame_times.len() - 1],
+    })
+}
+
+fn read_frame_times(contents: &str) -> Vec<f64> {
+    let mut lines = contents.lines();
+    let Some(header) = lines.next() else {
+        return Vec::new();
+    };
+    let header: Vec<&str> = header.split(',').collect();
+    let Some(column) = header
+        .iter()
+        .position(|name| *name == "MsBetweenPresents")
+        .or_else(|| header.iter().position(|name| *name == "FrameTime"))
+    else {
+        return Vec::new();
+    };
+
+    lines
+        .filter_map(|line| line.split(',').nth(column)?.parse::<f64>().ok())
+        .filter(|&frame_time| frame_time > 0.0)
+        .collect()
+}
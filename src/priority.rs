@@ -0,0 +1,121 @@
+//! `--priority` and `--affinity`: the scheduling priority and CPUs the
+//! launched process runs with, for games that stutter at the default
+//! priority or behave better kept off some cores. Applied to the process
+//! activation or the command started, and with `--tune-replacements` to
+//! every process that takes over from it.
+
+use std::fmt;
+use windows::Win32::Foundation::CloseHandle;
+use windows::Win32::System::Threading::{
+    ABOVE_NORMAL_PRIORITY_CLASS, BELOW_NORMAL_PRIORITY_CLASS, HIGH_PRIORITY_CLASS,
+    IDLE_PRIORITY_CLASS, NORMAL_PRIORITY_CLASS, OpenProcess, PROCESS_CREATION_FLAGS,
+    PROCESS_SET_INFORMATION, SetPriorityClass, SetProcessAffinityMask,
+};
+
+use crate::error::WinError;
+
+/// A Windows priority class. Real-time isn't offered: a game spinning at it
+/// can starve input and the desktop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+#[cfg_attr(feature = "json", serde(rename_all = "lowercase"))]
+pub enum PriorityClass {
+    Idle,
+    BelowNormal,
+    Normal,
+    AboveNormal,
+    High,
+}
+
+impl PriorityClass {
+    pub const NAMES: &'static str = "idle, belownormal, normal, abovenormal or high";
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "idle" => Some(PriorityClass::Idle),
+            "belownormal" => Some(PriorityClass::BelowNormal),
+            "normal" => Some(PriorityClass::Normal),
+            "abovenormal" => Some(PriorityClass::AboveNormal),
+            "high" => Some(PriorityClass::High),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PriorityClass::Idle => "idle",
+            PriorityClass::BelowNormal => "belownormal",
+            PriorityClass::Normal => "normal",
+            PriorityClass::AboveNormal => "abovenormal",
+            PriorityClass::High => "high",
+        }
+    }
+
+    fn flag(self) -> PROCESS_CREATION_FLAGS {
+        match self {
+            PriorityClass::Idle => IDLE_PRIORITY_CLASS,
+            PriorityClass::BelowNormal => BELOW_NORMAL_PRIORITY_CLASS,
+            PriorityClass::Normal => NORMAL_PRIORITY_CLASS,
+            PriorityClass::AboveNormal => ABOVE_NORMAL_PRIORITY_CLASS,
+            PriorityClass::High => HIGH_PRIORITY_CLASS,
+        }
+    }
+}
+
+impl fmt::Display for PriorityClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The priority and affinity to give the game's processes. Unset fields
+/// leave what Windows chose alone.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProcessTuning {
+    pub priority: Option<PriorityClass>,
+    /// Bit `n` allows CPU `n`
+    pub affinity: Option<u64>,
+    /// Tune processes that take over from the first one as well
+    pub replacements: bool,
+}
+
+impl ProcessTuning {
+    pub fn is_empty(&self) -> bool {
+        self.priority.is_none() && self.affinity.is_none()
+    }
+
+    /// Parses an affinity mask, in hex with `0x` or in decimal. A mask
+    /// without any CPU is refused.
+    pub fn parse_affinity(value: &str) -> Option<u64> {
+        let mask = match value
+            .strip_prefix("0x")
+            .or_else(|| value.strip_prefix("0X"))
+        {
+            Some(hex) => u64::from_str_radix(hex, 16).ok()?,
+            None => value.parse().ok()?,
+        };
+        (mask != 0).then_some(mask)
+    }
+
+    /// Gives `process_id` the priority and affinity. Both are attempted
+    /// even if the first fails.
+    pub fn apply(&self, process_id: u32) -> Result<(), WinError> {
+        unsafe {
+            let process = OpenProcess(PROCESS_SET_INFORMATION, false, process_id)
+                .map_err(|e| WinError::new("OpenProcess", &e))?;
+            let mut result = Ok(());
+            if let Some(priority) = self.priority
+                && let Err(e) = SetPriorityClass(process, priority.flag())
+            {
+                result = Err(WinError::new("SetPriorityClass", &e));
+            }
+            if let Some(mask) = self.affinity
+                && let Err(e) = SetProcessAffinityMask(process, mask as usize)
+            {
+                result = Err(WinError::new("SetProcessAffinityMask", &e));
+            }
+            let _ = CloseHandle(process);
+            result
+        }
+    }
+}
@@ -0,0 +1,105 @@
+//! Readiness probes for dependencies: conditions that show a store client has
+//! finished starting, checked repeatedly instead of sleeping a fixed time.
+
+use std::fmt;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use windows::Win32::System::Services::{
+    CloseServiceHandle, OpenSCManagerW, OpenServiceW, QueryServiceStatus, SC_MANAGER_CONNECT,
+    SERVICE_QUERY_STATUS, SERVICE_RUNNING, SERVICE_STATUS,
+};
+use windows::core::{HSTRING, PCWSTR};
+
+use crate::process::find_processes_by_name;
+use crate::search;
+use crate::window;
+
+/// How long a port probe waits for a connection before trying again later.
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "json", derive(serde::Deserialize))]
+#[cfg_attr(
+    feature = "json",
+    serde(tag = "type", rename_all = "kebab-case", deny_unknown_fields)
+)]
+pub enum Probe {
+    /// The dependency's process shows a visible window, optionally one whose
+    /// title contains `title` (case-insensitive)
+    Window {
+        #[cfg_attr(feature = "json", serde(default))]
+        title: Option<String>,
+    },
+    /// A Windows service, by its service name, is running
+    Service { name: String },
+    /// Something accepts TCP connections on a port, on localhost by default
+    Port {
+        port: u16,
+        #[cfg_attr(feature = "json", serde(default = "default_host"))]
+        host: String,
+    },
+}
+
+#[cfg(feature = "json")]
+fn default_host() -> String {
+    "127.0.0.1".to_string()
+}
+
+impl fmt::Display for Probe {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Probe::Window { title: None } => write!(f, "a window"),
+            Probe::Window { title: Some(title) } => write!(f, "a window titled '{}'", title),
+            Probe::Service { name } => write!(f, "the {} service", name),
+            Probe::Port { port, host } => write!(f, "a listener on {}:{}", host, port),
+        }
+    }
+}
+
+impl Probe {
+    /// Checks the probe once. `process` is the dependency's executable name,
+    /// whose processes window probes look at.
+    pub fn check(&self, process: &str) -> bool {
+        match self {
+            Probe::Window { title } => {
+                let process_ids = find_processes_by_name(process);
+                window::visible_windows(Some(&process_ids))
+                    .iter()
+                    .any(|window| {
+                        title.as_ref().map_or(!window.title.is_empty(), |title| {
+                            search::contains(&window.title, title)
+                        })
+                    })
+            }
+            Probe::Service { name } => is_service_running(name),
+            Probe::Port { port, host } => {
+                (host.as_str(), *port)
+                    .to_socket_addrs()
+                    .is_ok_and(|mut addresses| {
+                        addresses.any(|address| {
+                            TcpStream::connect_timeout(&address, CONNECT_TIMEOUT).is_ok()
+                        })
+                    })
+            }
+        }
+    }
+}
+
+pub(crate) fn is_service_running(name: &str) -> bool {
+    unsafe {
+        let Ok(manager) = OpenSCManagerW(PCWSTR::null(), PCWSTR::null(), SC_MANAGER_CONNECT) else {
+            return false;
+        };
+        let running = match OpenServiceW(manager, &HSTRING::from(name), SERVICE_QUERY_STATUS) {
+            Ok(service) => {
+                let mut status = SERVICE_STATUS::default();
+                let result = QueryServiceStatus(service, &mut status);
+                let _ = CloseServiceHandle(service);
+                result.is_ok() && status.dwCurrentState == SERVICE_RUNNING
+            }
+            Err(_) => false,
+        };
+        let _ = CloseServiceHandle(manager);
+        running
+    }
+}
@@ -0,0 +1,481 @@
+use std::collections::{HashMap, HashSet};
+use std::mem;
+use std::path::Path;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use windows::Win32::Foundation::{
+    CloseHandle, ERROR_INSUFFICIENT_BUFFER, FILETIME, HANDLE, MAX_PATH, STILL_ACTIVE,
+};
+use windows::Win32::Security::{GetTokenInformation, TOKEN_ELEVATION, TOKEN_QUERY, TokenElevation};
+use windows::Win32::System::Diagnostics::ToolHelp::{
+    CreateToolhelp32Snapshot, TH32CS_SNAPTHREAD, THREADENTRY32, Thread32First, Thread32Next,
+};
+use windows::Win32::System::ProcessStatus::EnumProcesses;
+use windows::Win32::System::Threading::{
+    CreateProcessW, GetCurrentProcess, GetExitCodeProcess, GetProcessTimes, OpenProcess,
+    OpenProcessToken, OpenThread, PROCESS_CREATION_FLAGS, PROCESS_INFORMATION, PROCESS_NAME_WIN32,
+    PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_TERMINATE, QueryFullProcessImageNameW, ResumeThread,
+    STARTF_USESHOWWINDOW, STARTUPINFOW, SuspendThread, THREAD_SUSPEND_RESUME, TerminateProcess,
+};
+use windows::Win32::UI::WindowsAndMessaging::SW_SHOWMINNOACTIVE;
+use windows::core::{HSTRING, PCWSTR, PWSTR};
+
+use crate::error::WinError;
+use crate::winpath;
+
+/// The longest path Windows allows, in UTF-16 units, terminator included.
+const MAX_LONG_PATH: usize = 32_768;
+
+/// How many threads [`get_process_infos`] queries processes on at most.
+const MAX_QUERY_THREADS: usize = 8;
+
+/// Paths of processes seen so far, by PID, with the creation time that
+/// tells a reused PID apart. Entries for processes that have exited are
+/// dropped whenever every process is listed.
+static INFO_CACHE: Mutex<Option<HashMap<u32, (u64, ProcessInfo)>>> = Mutex::new(None);
+
+#[derive(Debug, Clone)]
+pub struct ProcessInfo {
+    pub name: String,
+    pub path: String,
+}
+
+/// Whether this process runs with an elevated token.
+pub fn is_elevated() -> bool {
+    unsafe {
+        let mut token = HANDLE::default();
+        if OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token).is_err() {
+            return false;
+        }
+        let mut elevation = TOKEN_ELEVATION::default();
+        let mut length = 0u32;
+        let queried = GetTokenInformation(
+            token,
+            TokenElevation,
+            Some(&mut elevation as *mut TOKEN_ELEVATION as *mut _),
+            size_of::<TOKEN_ELEVATION>() as u32,
+            &mut length,
+        );
+        let _ = CloseHandle(token);
+        queried.is_ok() && elevation.TokenIsElevated != 0
+    }
+}
+
+pub fn get_process_info(process_id: u32) -> Option<ProcessInfo> {
+    unsafe {
+        // Open the process
+        let process_handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, process_id);
+
+        let process_handle = match process_handle {
+            Ok(handle) => handle,
+            Err(_) => return None,
+        };
+
+        // A process's path never changes, so scans reuse the last answer
+        // unless the PID now belongs to a process created since
+        let created = creation_time(process_handle);
+        if let Some(created) = created
+            && let Some(info) = cached_info(process_id, created)
+        {
+            let _ = CloseHandle(process_handle);
+            return Some(info);
+        }
+
+        // Get the process image name, growing the buffer for games installed
+        // under long paths
+        let path = image_path(process_handle).map(|path| winpath::strip_prefixes(&path));
+
+        // Close the process handle
+        let _ = CloseHandle(process_handle);
+
+        let known = path.is_some();
+        let path = path.unwrap_or_else(|| "<Unknown>".to_string());
+        // Extract just the filename from the full path
+        let name = path.split('\\').next_back().unwrap_or(&path).to_string();
+        let info = ProcessInfo { name, path };
+        if let (true, Some(created)) = (known, created) {
+            cache_info(process_id, created, &info);
+        }
+        Some(info)
+    }
+}
+
+fn cached_info(process_id: u32, created: u64) -> Option<ProcessInfo> {
+    let cache = INFO_CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    cache
+        .as_ref()?
+        .get(&process_id)
+        .filter(|(cached, _)| *cached == created)
+        .map(|(_, info)| info.clone())
+}
+
+fn cache_info(process_id: u32, created: u64, info: &ProcessInfo) {
+    let mut cache = INFO_CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    cache
+        .get_or_insert_with(HashMap::new)
+        .insert(process_id, (created, info.clone()));
+}
+
+/// [`get_process_info`] for each of `process_ids`, in the same order.
+/// Scans of every running process spend most of their time opening and
+/// querying each one, so the processes are split between a few threads.
+pub fn get_process_infos(process_ids: &[u32]) -> Vec<Option<ProcessInfo>> {
+    let threads = thread::available_parallelism()
+        .map_or(1, |count| count.get())
+        .min(MAX_QUERY_THREADS);
+    // Not worth the threads for a handful of processes
+    if threads == 1 || process_ids.len() < threads * 4 {
+        return process_ids.iter().map(|&id| get_process_info(id)).collect();
+    }
+    let chunk_size = process_ids.len().div_ceil(threads);
+    thread::scope(|scope| {
+        let workers: Vec<_> = process_ids
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let worker = scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|&id| get_process_info(id))
+                        .collect::<Vec<_>>()
+                });
+                (chunk.len(), worker)
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|(length, worker)| {
+                worker
+                    .join()
+                    .unwrap_or_else(|_| (0..length).map(|_| None).collect())
+            })
+            .collect()
+    })
+}
+
+/// The full path of the process's executable. Starts at `MAX_PATH` and
+/// doubles the buffer while it's too small, up to the longest path Windows
+/// allows.
+unsafe fn image_path(process_handle: HANDLE) -> Option<String> {
+    let mut buffer: Vec<u16> = vec![0; MAX_PATH as usize];
+    loop {
+        let mut size = buffer.len() as u32;
+        let result = unsafe {
+            QueryFullProcessImageNameW(
+                process_handle,
+                PROCESS_NAME_WIN32,
+                PWSTR(buffer.as_mut_ptr()),
+                &mut size,
+            )
+        };
+        match result {
+            Ok(()) if size > 0 => return Some(String::from_utf16_lossy(&buffer[..size as usize])),
+            Err(e)
+                if e.code() == ERROR_INSUFFICIENT_BUFFER.to_hresult()
+                    && buffer.len() < MAX_LONG_PATH =>
+            {
+                let length = (buffer.len() * 2).min(MAX_LONG_PATH);
+                buffer.resize(length, 0);
+            }
+            _ => return None,
+        }
+    }
+}
+
+pub fn get_process_creation_time(process_id: u32) -> Option<u64> {
+    unsafe {
+        let process_handle =
+            OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, process_id).ok()?;
+        let created = creation_time(process_handle);
+        let _ = CloseHandle(process_handle);
+        created
+    }
+}
+
+/// The process's creation time as a `FILETIME` count.
+unsafe fn creation_time(process_handle: HANDLE) -> Option<u64> {
+    unsafe {
+        let mut creation = FILETIME::default();
+        let mut exit = FILETIME::default();
+        let mut kernel = FILETIME::default();
+        let mut user = FILETIME::default();
+        let result = GetProcessTimes(
+            process_handle,
+            &mut creation,
+            &mut exit,
+            &mut kernel,
+            &mut user,
+        );
+        result.ok()?;
+        Some(((creation.dwHighDateTime as u64) << 32) | creation.dwLowDateTime as u64)
+    }
+}
+
+/// How long ago a process was created.
+pub fn get_process_age(process_id: u32) -> Option<Duration> {
+    // FILETIME counts 100ns intervals since 1601
+    const UNIX_EPOCH_TICKS: u64 = 116_444_736_000_000_000;
+    let created = get_process_creation_time(process_id)?.checked_sub(UNIX_EPOCH_TICKS)?;
+    let created = UNIX_EPOCH + Duration::from_nanos(created * 100);
+    Some(
+        SystemTime::now()
+            .duration_since(created)
+            .unwrap_or_default(),
+    )
+}
+
+/// Checks that a process is still running and, when a creation time is known,
+/// that the PID hasn't been reused by a different process.
+pub fn is_same_process_running(process_id: u32, created: u64) -> bool {
+    unsafe {
+        let Ok(process_handle) = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, process_id)
+        else {
+            return false;
+        };
+
+        let mut exit_code: u32 = 0;
+        let result = GetExitCodeProcess(process_handle, &mut exit_code);
+        let _ = CloseHandle(process_handle);
+
+        if result.is_err() || exit_code != STILL_ACTIVE.0 as u32 {
+            return false;
+        }
+    }
+
+    created == 0 || get_process_creation_time(process_id) == Some(created)
+}
+
+pub fn get_directory_from_path(path: &str) -> String {
+    // Extract directory from full path
+    if let Some(last_slash) = path.rfind('\\') {
+        path[..last_slash].to_string()
+    } else {
+        path.to_string()
+    }
+}
+
+/// Terminates a process, as `Session::kill` does for the tracked game.
+pub fn terminate_process(process_id: u32) -> Result<(), WinError> {
+    unsafe {
+        let process_handle = OpenProcess(PROCESS_TERMINATE, false, process_id)
+            .map_err(|e| WinError::new("OpenProcess", &e))?;
+        let result = TerminateProcess(process_handle, 1);
+        let _ = CloseHandle(process_handle);
+        result.map_err(|e| WinError::new("TerminateProcess", &e))
+    }
+}
+
+/// Threads suspended by [`suspend_processes`]. Their handles stay open so
+/// that [`resume`](Self::resume) resumes exactly these, and not threads
+/// started since or ones the game keeps suspended itself.
+#[derive(Debug, Default)]
+pub struct SuspendedThreads {
+    /// Each thread's raw handle, with the ID of its process
+    threads: Vec<(isize, u32)>,
+}
+
+impl SuspendedThreads {
+    /// How many processes had a thread suspended.
+    pub fn process_count(&self) -> usize {
+        let process_ids: HashSet<u32> = self.threads.iter().map(|&(_, pid)| pid).collect();
+        process_ids.len()
+    }
+
+    /// Resumes the threads. Returns how many processes had a thread resumed.
+    pub fn resume(self) -> usize {
+        let mut resumed = HashSet::new();
+        for &(thread, process_id) in &self.threads {
+            if unsafe { ResumeThread(HANDLE(thread as *mut _)) } != u32::MAX {
+                resumed.insert(process_id);
+            }
+        }
+        resumed.len()
+    }
+}
+
+impl Drop for SuspendedThreads {
+    fn drop(&mut self) {
+        for &(thread, _) in &self.threads {
+            unsafe {
+                let _ = CloseHandle(HANDLE(thread as *mut _));
+            }
+        }
+    }
+}
+
+/// Suspends every thread of the processes in `process_ids`. Suspending
+/// counts, so a thread that was already suspended stays so after the
+/// returned threads are resumed.
+pub fn suspend_processes(process_ids: &[u32]) -> Result<SuspendedThreads, WinError> {
+    let mut suspended = SuspendedThreads::default();
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0)
+            .map_err(|e| WinError::new("CreateToolhelp32Snapshot", &e))?;
+        let mut entry = THREADENTRY32 {
+            dwSize: mem::size_of::<THREADENTRY32>() as u32,
+            ..Default::default()
+        };
+        let mut result = Thread32First(snapshot, &mut entry);
+        while result.is_ok() {
+            if process_ids.contains(&entry.th32OwnerProcessID)
+                && let Ok(thread) = OpenThread(THREAD_SUSPEND_RESUME, false, entry.th32ThreadID)
+            {
+                if SuspendThread(thread) != u32::MAX {
+                    suspended
+                        .threads
+                        .push((thread.0 as isize, entry.th32OwnerProcessID));
+                } else {
+                    let _ = CloseHandle(thread);
+                }
+            }
+            result = Thread32Next(snapshot, &mut entry);
+        }
+        let _ = CloseHandle(snapshot);
+    }
+    Ok(suspended)
+}
+
+/// IDs of every running process, or an empty list if they can't be enumerated.
+pub fn list_process_ids() -> Vec<u32> {
+    let mut process_ids: Vec<u32> = vec![0; 1024];
+    let mut bytes_returned: u32 = 0;
+
+    // A full buffer may have left processes out, so grow it until one fits
+    loop {
+        let buffer_bytes = (process_ids.len() * mem::size_of::<u32>()) as u32;
+        let result =
+            unsafe { EnumProcesses(process_ids.as_mut_ptr(), buffer_bytes, &mut bytes_returned) };
+        if result.is_err() {
+            return Vec::new();
+        }
+        if bytes_returned < buffer_bytes {
+            break;
+        }
+        process_ids.resize(process_ids.len() * 2, 0);
+    }
+
+    let process_count = bytes_returned as usize / mem::size_of::<u32>();
+    let process_ids: Vec<u32> = process_ids
+        .iter()
+        .take(process_count)
+        .copied()
+        .filter(|&process_id| process_id != 0)
+        .collect();
+
+    // Processes that aren't running anymore have no use for their cache entries
+    let running: HashSet<u32> = process_ids.iter().copied().collect();
+    let mut cache = INFO_CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(cache) = cache.as_mut() {
+        cache.retain(|process_id, _| running.contains(process_id));
+    }
+    process_ids
+}
+
+/// Running processes whose executable file name matches (case-insensitive).
+pub fn find_processes_by_name(name: &str) -> Vec<u32> {
+    let process_ids = list_process_ids();
+    let infos = get_process_infos(&process_ids);
+    process_ids
+        .into_iter()
+        .zip(infos)
+        .filter(|(_, info)| {
+            info.as_ref()
+                .is_some_and(|info| info.name.eq_ignore_ascii_case(name))
+        })
+        .map(|(process_id, _)| process_id)
+        .collect()
+}
+
+/// Quotes a single argument following the MSVCRT command-line parsing rules.
+pub(crate) fn quote_argument(argument: &str) -> String {
+    if !argument.is_empty() && !argument.contains([' ', '\t', '"']) {
+        return argument.to_string();
+    }
+
+    let mut quoted = String::from("\"");
+    let mut backslashes = 0;
+    for c in argument.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                quoted.push_str(&"\\".repeat(backslashes * 2 + 1));
+                quoted.push('"');
+                backslashes = 0;
+            }
+            _ => {
+                quoted.push_str(&"\\".repeat(backslashes));
+                quoted.push(c);
+                backslashes = 0;
+            }
+        }
+    }
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+/// Builds a command line from arguments, quoting each as needed.
+pub fn join_arguments(arguments: &[String]) -> String {
+    arguments
+        .iter()
+        .map(|argument| quote_argument(argument))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Starts a desktop program, optionally minimized without taking focus and
+/// in `working_dir` rather than the current directory, and returns its
+/// process ID without waiting for it.
+pub fn spawn_process(
+    path: &str,
+    arguments: &[String],
+    working_dir: Option<&Path>,
+    minimized: bool,
+) -> Result<u32, WinError> {
+    let mut command_line = vec![path.to_string()];
+    command_line.extend_from_slice(arguments);
+    let mut command_line: Vec<u16> = join_arguments(&command_line)
+        .encode_utf16()
+        .chain(Some(0))
+        .collect();
+
+    let mut startup_info = STARTUPINFOW {
+        cb: mem::size_of::<STARTUPINFOW>() as u32,
+        ..Default::default()
+    };
+    if minimized {
+        startup_info.dwFlags = STARTF_USESHOWWINDOW;
+        startup_info.wShowWindow = SW_SHOWMINNOACTIVE.0 as u16;
+    }
+    let mut process_info = PROCESS_INFORMATION::default();
+    let working_dir = working_dir.map(|directory| HSTRING::from(directory.as_os_str()));
+
+    unsafe {
+        CreateProcessW(
+            None,
+            Some(PWSTR(command_line.as_mut_ptr())),
+            None,
+            None,
+            false,
+            PROCESS_CREATION_FLAGS(0),
+            None,
+            working_dir
+                .as_ref()
+                .map_or(PCWSTR::null(), |directory| PCWSTR(directory.as_ptr())),
+            &startup_info,
+            &mut process_info,
+        )
+        .map_err(|e| WinError::new("CreateProcessW", &e))?;
+
+        let _ = CloseHandle(process_info.hThread);
+        let _ = CloseHandle(process_info.hProcess);
+    }
+
+    Ok(process_info.dwProcessId)
+}
@@ -0,0 +1,434 @@
+//! Named launch profiles, stored as TOML in
+//! `%APPDATA%\librarylink\profiles\<name>.toml`:
+//!
+//! ```toml
+//! aumid = "EA.BattlefieldV_8wekyb3d8bbwe!App"
+//! wait_for = "bfv.exe"
+//!
+//! [[dependency]]
+//! name = "EA app"
+//! path = 'C:\Program Files\Electronic Arts\EA Desktop\EA Desktop\EALauncher.exe'
+//! process = "EADesktop.exe"
+//! ```
+
+use std::fs;
+use std::os::windows::process::CommandExt;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::concurrency::{self, ConcurrencyPolicy, ConflictAction};
+use crate::config;
+use crate::dependency::{self, Dependency};
+use crate::launch::{LaunchOptions, Session};
+use crate::matcher::MonitorScope;
+use crate::package::{PackageUpdate, UpdateGuard};
+use crate::patch::PatchValue;
+use crate::paths;
+use crate::placement::WindowPlacement;
+use crate::preflight::{self, PreflightChecks, PreflightResult};
+#[cfg(feature = "presentmon")]
+use crate::presentmon;
+use crate::priority::{PriorityClass, ProcessTuning};
+use crate::registry::RegistryData;
+use crate::report::{self, Event, info, warning};
+use crate::system::{DisplayMode, SystemSettings};
+use crate::template::Variables;
+use crate::toml;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// File name of the profile, without the extension
+    #[serde(skip)]
+    pub name: String,
+    /// Set by `--no-preflight`: launch without running the preflight checks
+    #[serde(skip)]
+    pub skip_preflight: bool,
+    pub aumid: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub wait_for: Option<String>,
+    #[serde(default)]
+    pub scope: Option<MonitorScope>,
+    /// Milliseconds; polls instead of waiting on process handles
+    #[serde(default)]
+    pub poll_interval: Option<u64>,
+    /// Seconds; replacement processes that exit sooner are skipped
+    #[serde(default)]
+    pub min_lifetime: Option<f64>,
+    /// Seconds to wait for the game to come back after it exits, for games
+    /// that restart themselves
+    #[serde(default)]
+    pub follow_restarts: Option<f64>,
+    /// Seconds to wait for the `wait_for` process after activation
+    #[serde(default)]
+    pub activation_timeout: Option<f64>,
+    /// Seconds to wait for the game to take over once the activated process
+    /// exits, for games that always start through a launcher
+    #[serde(default)]
+    pub handoff_timeout: Option<f64>,
+    /// Seconds a replacement has to appear before the app counts as exited
+    #[serde(default)]
+    pub grace_period: Option<f64>,
+    /// Directory searched for the game instead of the activated process's
+    #[serde(default)]
+    pub watch_dir: Option<PathBuf>,
+    /// Further directories searched after `watch_dir`
+    #[serde(default)]
+    pub watch_dirs: Vec<PathBuf>,
+    /// Executable names or globs never tracked, as `--ignore-process`
+    #[serde(default)]
+    pub ignore_processes: Vec<String>,
+    /// Executable names or globs tracked wherever they run from, as
+    /// `--allow-process`
+    #[serde(default)]
+    pub allow_processes: Vec<String>,
+    /// Monitor to move the game's window to, 1 being the primary
+    #[serde(default)]
+    pub monitor: Option<usize>,
+    /// Client area size for the game's window, such as `1920x1080`
+    #[serde(default)]
+    pub window_size: Option<String>,
+    /// Display mode for the session, such as `1920x1080@60` or `client`;
+    /// `[system] display` takes precedence
+    #[serde(default)]
+    pub resolution: Option<String>,
+    /// Restore the game's window whenever it becomes minimized
+    #[serde(default)]
+    pub auto_restore: bool,
+    /// Seconds the game's window may stay minimized or in the background
+    /// before it's brought back
+    #[serde(default)]
+    pub restore_minimized: Option<f64>,
+    /// Priority class for the game, such as `high`
+    #[serde(default)]
+    pub priority: Option<String>,
+    /// CPU affinity mask for the game, such as `"0xFF"`
+    #[serde(default)]
+    pub affinity: Option<String>,
+    /// Give processes that take over the priority and affinity as well
+    #[serde(default)]
+    pub tune_replacements: bool,
+    /// Don't run alongside any other session
+    #[serde(default)]
+    pub exclusive: bool,
+    /// Allow launching the profile's app while it already has a session
+    #[serde(default = "default_allow_multiple")]
+    pub allow_multiple: bool,
+    /// Whether to wait for or terminate sessions that conflict with an
+    /// exclusive launch
+    #[serde(default)]
+    pub on_conflict: ConflictAction,
+    /// Seconds a queued launch waits for conflicting sessions to end
+    #[serde(default)]
+    pub queue_timeout: Option<f64>,
+    /// Close store clients that start during the session once it ends
+    #[serde(default)]
+    pub close_launchers: bool,
+    /// Terminate the game's process tree if librarylink is stopped
+    #[serde(default)]
+    pub kill_on_exit: bool,
+    /// Capture frame times with PresentMon: `presentmon` if set, otherwise
+    /// one bundled with librarylink or on the `PATH`
+    #[serde(default)]
+    pub frame_times: bool,
+    #[serde(default)]
+    pub presentmon: Option<PathBuf>,
+    /// Whether to wait for or fail on a Store update in progress at launch
+    #[serde(default)]
+    pub if_updating: UpdateGuard,
+    /// Command line run through `cmd /C` before launching when the package
+    /// was updated since the last session, to re-apply mods or config tweaks
+    #[serde(default)]
+    pub on_update: Option<String>,
+    /// Prerequisites checked before the game is activated
+    #[serde(default)]
+    pub preflight: PreflightChecks,
+    /// Settings changed for the session and restored afterwards
+    #[serde(default)]
+    pub system: SystemSettings,
+    /// Started in order before the game
+    #[serde(default, rename = "dependency")]
+    pub dependencies: Vec<Dependency>,
+}
+
+fn default_allow_multiple() -> bool {
+    true
+}
+
+pub fn profiles_dir() -> PathBuf {
+    paths::data_dir().join("profiles")
+}
+
+impl Profile {
+    /// Reads `<name>.toml` from the profiles directory.
+    pub fn load(name: &str) -> Result<Profile, Box<dyn std::error::Error>> {
+        let path = profiles_dir().join(format!("{}.toml", name));
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| format!("Could not read profile {}: {}", path.display(), e))?;
+        let document = toml::parse(&contents).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut profile: Profile =
+            serde_json::from_value(document).map_err(|e| format!("{}: {}", path.display(), e))?;
+        profile.name = name.to_string();
+        Ok(profile)
+    }
+
+    /// A copy with the `{variable}`s in its paths, arguments, commands and
+    /// `[system]` values expanded for launching now.
+    pub fn resolved(&self) -> Result<Profile, Box<dyn std::error::Error>> {
+        let variables = Variables::for_launch(&self.aumid, &self.name);
+        let expand = |text: &str| variables.expand(text);
+        let expand_path = |path: &PathBuf| -> Result<PathBuf, String> {
+            expand(&path.to_string_lossy()).map(PathBuf::from)
+        };
+        let expand_all = |texts: &[String]| -> Result<Vec<String>, String> {
+            texts.iter().map(|text| expand(text)).collect()
+        };
+
+        let mut profile = self.clone();
+        profile.args = expand_all(&self.args)?;
+        profile.wait_for = self.wait_for.as_deref().map(expand).transpose()?;
+        profile.watch_dir = self.watch_dir.as_ref().map(expand_path).transpose()?;
+        profile.watch_dirs = self
+            .watch_dirs
+            .iter()
+            .map(expand_path)
+            .collect::<Result<_, _>>()?;
+        profile.window_size = self.window_size.as_deref().map(expand).transpose()?;
+        profile.resolution = self.resolution.as_deref().map(expand).transpose()?;
+        profile.presentmon = self.presentmon.as_ref().map(expand_path).transpose()?;
+        profile.on_update = self.on_update.as_deref().map(expand).transpose()?;
+        for dependency in &mut profile.dependencies {
+            dependency.path = dependency.path.as_deref().map(expand).transpose()?;
+            dependency.args = expand_all(&dependency.args)?;
+        }
+        for value in &mut profile.system.registry {
+            if let RegistryData::String(text) | RegistryData::ExpandString(text) = &mut value.data {
+                *text = expand(text)?;
+            }
+        }
+        for patch in &mut profile.system.files {
+            patch.path = expand_path(&patch.path)?;
+            for value in patch.set.values_mut() {
+                if let PatchValue::String(text) = value {
+                    *text = expand(text)?;
+                }
+            }
+        }
+        Ok(profile)
+    }
+
+    /// Launch options for the game itself, without the dependencies, over
+    /// the config file's defaults for its app.
+    pub fn launch_options(&self) -> LaunchOptions {
+        let mut options = config::current()
+            .defaults_for(&self.aumid)
+            .apply(LaunchOptions::new(&self.aumid))
+            .args(&self.args);
+        if let Some(executable) = &self.wait_for {
+            options = options.wait_for(executable);
+        }
+        if let Some(scope) = self.scope {
+            options = options.scope(scope);
+        }
+        if let Some(interval) = self.poll_interval.filter(|&millis| millis > 0) {
+            options = options.poll_interval(Duration::from_millis(interval));
+        }
+        if let Some(window) = self
+            .follow_restarts
+            .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        {
+            options = options.follow_restarts(window);
+        }
+        if let Some(timeout) = self
+            .activation_timeout
+            .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        {
+            options = options.activation_timeout(timeout);
+        }
+        if let Some(timeout) = self
+            .handoff_timeout
+            .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        {
+            options = options.handoff_timeout(timeout);
+        }
+        if let Some(period) = self
+            .grace_period
+            .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        {
+            options = options.grace_period(period);
+        }
+        for directory in self.watch_dir.iter().chain(&self.watch_dirs) {
+            options = options.watch_dir(directory);
+        }
+        for name in &self.ignore_processes {
+            options = options.ignore_process(name);
+        }
+        for name in &self.allow_processes {
+            options = options.allow_process(name);
+        }
+        let mut placement = WindowPlacement {
+            monitor: self.monitor.filter(|&index| index > 0),
+            size: None,
+        };
+        if let Some(size) = &self.window_size {
+            placement.size = WindowPlacement::parse_size(size);
+            if placement.size.is_none() {
+                warning!(
+                    "⚠️ Ignoring window_size '{}'; expected a size such as 1920x1080",
+                    size
+                );
+            }
+        }
+        options = options.placement(placement).auto_restore(self.auto_restore);
+        if let Some(after) = self
+            .restore_minimized
+            .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        {
+            options = options.restore_minimized(after);
+        }
+        if let Some(priority) = &self.priority {
+            match PriorityClass::parse(priority) {
+                Some(priority) => options = options.priority(priority),
+                None => warning!(
+                    "⚠️ Ignoring priority '{}'; expected {}",
+                    priority,
+                    PriorityClass::NAMES
+                ),
+            }
+        }
+        if let Some(affinity) = &self.affinity {
+            match ProcessTuning::parse_affinity(affinity) {
+                Some(mask) => options = options.affinity(mask),
+                None => warning!(
+                    "⚠️ Ignoring affinity '{}'; expected a CPU mask such as 0xFF",
+                    affinity
+                ),
+            }
+        }
+        options = options.tune_replacements(self.tune_replacements);
+        #[cfg(feature = "presentmon")]
+        if let Some(presentmon) = self
+            .presentmon
+            .clone()
+            .or_else(|| self.frame_times.then(presentmon::locate).flatten())
+        {
+            options = options.presentmon(presentmon);
+        } else if self.frame_times {
+            warning!(
+                "⚠️ No PresentMon found next to librarylink or on the PATH; frame times won't be captured"
+            );
+        }
+        if let Some(command) = self.on_update.clone() {
+            options = options.on_update(move |update| run_update_hook(&command, update));
+        }
+        let mut system = self.system.clone();
+        if let Some(resolution) = self
+            .resolution
+            .as_deref()
+            .filter(|_| system.display.is_none())
+        {
+            match DisplayMode::parse(resolution) {
+                Ok(mode) => system.display = Some(mode),
+                Err(message) => warning!("⚠️ Ignoring resolution: {}", message),
+            }
+        }
+        options
+            .exclusive(self.exclusive)
+            .if_updating(self.if_updating)
+            .close_launchers(self.close_launchers)
+            .kill_on_exit(self.kill_on_exit)
+            .system(system)
+    }
+
+    pub fn concurrency(&self) -> ConcurrencyPolicy {
+        ConcurrencyPolicy {
+            exclusive: self.exclusive,
+            allow_multiple: self.allow_multiple,
+            on_conflict: self.on_conflict,
+            queue_timeout: self
+                .queue_timeout
+                .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                .unwrap_or(concurrency::QUEUE_TIMEOUT),
+        }
+    }
+
+    /// Runs the profile's preflight checks.
+    pub fn preflight(&self) -> Vec<PreflightResult> {
+        preflight::run(&self.aumid, &self.preflight)
+    }
+
+    /// Runs the preflight checks, settles conflicts with active sessions,
+    /// then starts the dependencies and the game, with the profile's
+    /// variables [resolved](Profile::resolved). Dependencies librarylink
+    /// started are stopped in reverse order when the session ends, or right
+    /// away if a later step fails.
+    pub fn launch(&self) -> Result<Session, Box<dyn std::error::Error>> {
+        let profile = self.resolved()?;
+        profile.launch_resolved()
+    }
+
+    /// [`launch`](Self::launch) for a profile already [`resolved`](Self::resolved).
+    pub(crate) fn launch_resolved(&self) -> Result<Session, Box<dyn std::error::Error>> {
+        if !self.skip_preflight && !self.preflight.is_empty() {
+            let results = self.preflight();
+            let failed: Vec<String> = results
+                .iter()
+                .filter(|result| !result.passed)
+                .map(|result| result.check.clone())
+                .collect();
+            report::emit(Event::PreflightCompleted { results });
+            if !failed.is_empty() {
+                return Err(format!(
+                    "Preflight failed: {}; pass --no-preflight to launch anyway",
+                    failed.join(", ")
+                )
+                .into());
+            }
+        }
+
+        // Held until `launch` has saved the session
+        let _lock = concurrency::arbitrate(&self.aumid, &self.concurrency())?;
+
+        let mut started = Vec::new();
+        for dependency in &self.dependencies {
+            match dependency::start(dependency) {
+                Ok(Some(dependency)) => started.push(dependency),
+                Ok(None) => {}
+                Err(e) => {
+                    started
+                        .iter()
+                        .rev()
+                        .for_each(|dependency| dependency.stop());
+                    return Err(e);
+                }
+            }
+        }
+
+        let mut options = self.launch_options();
+        for dependency in started.into_iter().rev() {
+            options = options.cleanup(move || dependency.stop());
+        }
+        options.launch()
+    }
+}
+
+/// Runs a profile's `on_update` command and waits for it. The versions are
+/// passed in `LIBRARYLINK_PREVIOUS_VERSION` and `LIBRARYLINK_VERSION`.
+fn run_update_hook(command: &str, update: &PackageUpdate) {
+    info!("🔧 Running on_update: {}", command);
+    let status = Command::new("cmd")
+        .arg("/C")
+        .raw_arg(command)
+        .env("LIBRARYLINK_PREVIOUS_VERSION", &update.previous_version)
+        .env("LIBRARYLINK_VERSION", &update.version)
+        .stdin(Stdio::null())
+        .status();
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => warning!("⚠️ on_update exited with {}", status),
+        Err(e) => warning!("⚠️ Could not run on_update: {}", e),
+    }
+}
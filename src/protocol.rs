@@ -0,0 +1,147 @@
+//! Launches through protocol URIs such as `ms-xbl-3d8b930f://` or
+//! `discord://`, for apps that are best started that way. The URI goes to
+//! the app registered for its scheme, and the process it starts is returned
+//! as AUMID activation returns it, so it can be monitored the same way.
+
+use std::path::Path;
+use windows::Win32::Foundation::CloseHandle;
+use windows::Win32::System::Com::{CLSCTX_INPROC_SERVER, CoCreateInstance};
+use windows::Win32::System::Threading::GetProcessId;
+use windows::Win32::UI::Shell::{
+    ASSOCF_IS_PROTOCOL, ASSOCF_NOTRUNCATE, ASSOCSTR, ASSOCSTR_APPID, ASSOCSTR_EXECUTABLE,
+    ApplicationActivationManager, AssocQueryStringW, IApplicationActivationManager, IShellItem,
+    IShellItemArray, SEE_MASK_FLAG_NO_UI, SEE_MASK_NOCLOSEPROCESS, SHCreateItemFromParsingName,
+    SHCreateShellItemArrayFromShellItem, SHELLEXECUTEINFOW, ShellExecuteExW,
+};
+use windows::Win32::UI::WindowsAndMessaging::SW_SHOWNORMAL;
+use windows::core::{HSTRING, PCWSTR, PWSTR};
+
+use crate::error::WinError;
+use crate::launch::ComApartment;
+use crate::report::debug;
+use crate::timings::{self, Phase};
+
+/// The app registered for a URI's scheme.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Handler {
+    /// A packaged app, activated for the protocol
+    App { aumid: String },
+    /// A desktop program, which the shell starts with the URI
+    Desktop { executable: String },
+}
+
+impl Handler {
+    /// The name a launch is shown under, such as on a stream.
+    pub fn display_name(&self) -> Option<String> {
+        match self {
+            Handler::App { aumid } => crate::apps::display_name(aumid),
+            Handler::Desktop { executable } => Path::new(executable)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned()),
+        }
+    }
+}
+
+/// The URI's scheme, such as `discord` in `discord://`. Drive letters, as
+/// in `C:\`, aren't schemes.
+pub fn scheme(uri: &str) -> Option<&str> {
+    let (scheme, _) = uri.split_once(':')?;
+    let mut chars = scheme.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    (valid && scheme.len() > 1).then_some(scheme)
+}
+
+/// Looks up the app registered for the URI's scheme.
+pub fn handler(uri: &str) -> Result<Handler, String> {
+    let scheme = scheme(uri).ok_or_else(|| format!("'{}' isn't a protocol URI", uri))?;
+    // Packaged apps' AUMIDs are their family name and app ID joined with !;
+    // desktop programs have their own kinds of AppID, or none
+    if let Some(aumid) = query(scheme, ASSOCSTR_APPID).filter(|aumid| aumid.contains('!')) {
+        debug!("{}: is handled by the packaged app {}", scheme, aumid);
+        return Ok(Handler::App { aumid });
+    }
+    match query(scheme, ASSOCSTR_EXECUTABLE) {
+        Some(executable) => {
+            debug!("{}: is handled by {}", scheme, executable);
+            Ok(Handler::Desktop { executable })
+        }
+        None => Err(format!("No app is registered for {}: URIs", scheme)),
+    }
+}
+
+fn query(scheme: &str, kind: ASSOCSTR) -> Option<String> {
+    let scheme = HSTRING::from(scheme);
+    let flags = ASSOCF_IS_PROTOCOL | ASSOCF_NOTRUNCATE;
+    unsafe {
+        let mut length = 0u32;
+        // The first call reports the length needed, terminator included
+        let _ = AssocQueryStringW(flags, kind, &scheme, PCWSTR::null(), None, &mut length);
+        if length == 0 {
+            return None;
+        }
+        let mut buffer = vec![0u16; length as usize];
+        AssocQueryStringW(
+            flags,
+            kind,
+            &scheme,
+            PCWSTR::null(),
+            Some(PWSTR(buffer.as_mut_ptr())),
+            &mut length,
+        )
+        .ok()
+        .ok()?;
+        let value = String::from_utf16_lossy(&buffer[..(length as usize).saturating_sub(1)]);
+        (!value.is_empty()).then_some(value)
+    }
+}
+
+/// Activates the packaged app `aumid` for `uri` through
+/// `IApplicationActivationManager`, returning the activated process's ID.
+/// COM is set up as for [`activate`](crate::launch::activate).
+pub fn activate(aumid: &str, uri: &str) -> Result<u32, WinError> {
+    let _apartment = ComApartment::enter()?;
+    debug!("Activating {} for {}", aumid, uri);
+    timings::time(Phase::Activation, || unsafe {
+        let item: IShellItem = SHCreateItemFromParsingName(&HSTRING::from(uri), None)
+            .map_err(|e| WinError::new("SHCreateItemFromParsingName", &e))?;
+        let items: IShellItemArray = SHCreateShellItemArrayFromShellItem(&item)
+            .map_err(|e| WinError::new("SHCreateShellItemArrayFromShellItem", &e))?;
+        let activation_manager: IApplicationActivationManager =
+            CoCreateInstance(&ApplicationActivationManager, None, CLSCTX_INPROC_SERVER)
+                .map_err(|e| WinError::new("CoCreateInstance(ApplicationActivationManager)", &e))?;
+        activation_manager
+            .ActivateForProtocol(&HSTRING::from(aumid), &items)
+            .map_err(|e| WinError::new("IApplicationActivationManager::ActivateForProtocol", &e))
+    })
+}
+
+/// Opens `uri` with its scheme's desktop handler, returning the ID of the
+/// process the shell started. Fails when the handler passes the URI to a
+/// copy of itself that's already running instead of starting one.
+pub fn open(uri: &str) -> Result<u32, WinError> {
+    let file = HSTRING::from(uri);
+    let mut info = SHELLEXECUTEINFOW {
+        cbSize: size_of::<SHELLEXECUTEINFOW>() as u32,
+        fMask: SEE_MASK_NOCLOSEPROCESS | SEE_MASK_FLAG_NO_UI,
+        lpFile: PCWSTR(file.as_ptr()),
+        nShow: SW_SHOWNORMAL.0,
+        ..Default::default()
+    };
+    timings::time(Phase::Activation, || unsafe {
+        ShellExecuteExW(&mut info).map_err(|e| WinError::new("ShellExecuteExW", &e))?;
+        if info.hProcess.is_invalid() {
+            return Err(WinError {
+                call: "ShellExecuteExW".to_string(),
+                hresult: "0x00000000".to_string(),
+                message: "The handler didn't start a new process; it may have passed the URI to a copy that's already running".to_string(),
+            });
+        }
+        let process_id = GetProcessId(info.hProcess);
+        let _ = CloseHandle(info.hProcess);
+        if process_id == 0 {
+            return Err(WinError::from_last_error("GetProcessId"));
+        }
+        Ok(process_id)
+    })
+}
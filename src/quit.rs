@@ -0,0 +1,167 @@
+//! Tells a user quitting the game apart from a stream merely ending. Hosts
+//! like Sunshine end the stream by terminating librarylink, which leaves the
+//! game running for the next connection; quitting is asked for explicitly,
+//! with Ctrl+Break or by creating the file named in `LIBRARYLINK_QUIT_FILE`,
+//! and closes the game with [`MonitorControl::quit`]. Hosts that expect
+//! stopping librarylink to stop the game, like Steam's Stop button, opt in
+//! with [`kill_on_exit`].
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Once, Weak};
+use std::thread;
+use std::time::Duration;
+use windows::Win32::System::Console::{
+    CTRL_BREAK_EVENT, CTRL_C_EVENT, CTRL_CLOSE_EVENT, CTRL_LOGOFF_EVENT, CTRL_SHUTDOWN_EVENT,
+    SetConsoleCtrlHandler,
+};
+use windows::core::BOOL;
+
+use crate::error::WinError;
+use crate::monitor::{MonitorControl, MonitorState};
+use crate::report::{Event, Reporter, warning};
+
+/// Environment variable naming a file whose appearance asks for the game to
+/// be closed.
+pub const QUIT_FILE_VARIABLE: &str = "LIBRARYLINK_QUIT_FILE";
+
+/// How long the game gets to close its windows before it's terminated.
+pub const GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+/// How often the watcher checks for a quit request.
+const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+static BREAK_RECEIVED: AtomicBool = AtomicBool::new(false);
+static HANDLER: Once = Once::new();
+static EXIT_HANDLER: Once = Once::new();
+/// Sessions whose game goes down with librarylink.
+static KILL_ON_EXIT: Mutex<Vec<Weak<MonitorControl>>> = Mutex::new(Vec::new());
+
+/// How a quit was asked for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+#[cfg_attr(feature = "json", serde(rename_all = "snake_case"))]
+pub enum QuitSource {
+    /// Ctrl+Break was sent to librarylink
+    Break,
+    /// The file in `LIBRARYLINK_QUIT_FILE` appeared
+    File,
+}
+
+impl fmt::Display for QuitSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            QuitSource::Break => "Ctrl+Break",
+            QuitSource::File => "the quit file",
+        })
+    }
+}
+
+unsafe extern "system" fn break_handler(control_type: u32) -> BOOL {
+    if control_type == CTRL_BREAK_EVENT {
+        BREAK_RECEIVED.store(true, Ordering::SeqCst);
+        // Handled: keep running so the game is closed and cleanup happens
+        return BOOL(1);
+    }
+    BOOL(0)
+}
+
+/// Closes the game once a quit is requested, on a background thread that
+/// stops when the session ends. Ctrl+Break is taken over from the exit
+/// handler for the rest of the process, since while a session runs it means
+/// quit rather than exit.
+pub fn watch(control: Arc<MonitorControl>, reporter: &'static dyn Reporter) {
+    HANDLER.call_once(|| unsafe {
+        if let Err(e) = SetConsoleCtrlHandler(Some(break_handler), true) {
+            warning!(
+                "⚠️ Could not handle Ctrl+Break; use {} to quit the game instead: {}",
+                QUIT_FILE_VARIABLE,
+                WinError::new("SetConsoleCtrlHandler", &e)
+            );
+        }
+    });
+
+    let quit_file = env::var_os(QUIT_FILE_VARIABLE).map(PathBuf::from);
+    // One left over from an earlier session would quit this one at once
+    if let Some(path) = &quit_file {
+        let _ = fs::remove_file(path);
+    }
+
+    thread::spawn(move || {
+        while !matches!(control.state(), Some(MonitorState::Ended { .. })) {
+            let source = if BREAK_RECEIVED.swap(false, Ordering::SeqCst) {
+                Some(QuitSource::Break)
+            } else if let Some(path) = quit_file.as_ref().filter(|path| path.exists()) {
+                let _ = fs::remove_file(path);
+                Some(QuitSource::File)
+            } else {
+                None
+            };
+
+            if let Some(source) = source {
+                reporter.event(&Event::QuitRequested { source });
+                if let Err(e) = control.quit(GRACE_PERIOD) {
+                    reporter.event(&Event::Error {
+                        context: "Could not close the game".to_string(),
+                        error: e,
+                    });
+                }
+                return;
+            }
+            thread::sleep(CHECK_INTERVAL);
+        }
+    });
+}
+
+unsafe extern "system" fn exit_handler(control_type: u32) -> BOOL {
+    if matches!(
+        control_type,
+        CTRL_C_EVENT | CTRL_CLOSE_EVENT | CTRL_LOGOFF_EVENT | CTRL_SHUTDOWN_EVENT
+    ) {
+        let sessions = std::mem::take(
+            &mut *KILL_ON_EXIT
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        );
+        for control in sessions.iter().filter_map(Weak::upgrade) {
+            if matches!(control.state(), Some(MonitorState::Ended { .. })) {
+                continue;
+            }
+            warning!("⏹️ librarylink is exiting; terminating the game (--kill-on-exit)");
+            if let Err(e) = control.kill_tree() {
+                warning!("⚠️ Could not terminate the game: {}", e);
+            }
+        }
+    }
+    // Let the other handlers and the default handling (exiting) proceed
+    BOOL(0)
+}
+
+/// Terminates the game's process tree when librarylink exits: on Ctrl+C, the
+/// console closing, logoff and shutdown, and, through the launch's job, when
+/// librarylink is terminated or crashes. Ctrl+Break still quits the game
+/// gracefully (see [`watch`]).
+pub fn kill_on_exit(control: &Arc<MonitorControl>) {
+    if let Err(e) = control.kill_tree_on_close() {
+        warning!(
+            "⚠️ The game may keep running if librarylink is terminated: {}",
+            e
+        );
+    }
+    EXIT_HANDLER.call_once(|| unsafe {
+        if let Err(e) = SetConsoleCtrlHandler(Some(exit_handler), true) {
+            warning!(
+                "⚠️ Could not handle Ctrl+C; the game may keep running after it: {}",
+                WinError::new("SetConsoleCtrlHandler", &e)
+            );
+        }
+    });
+    let mut sessions = KILL_ON_EXIT
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    sessions.retain(|control| control.strong_count() > 0);
+    sessions.push(Arc::downgrade(control));
+}
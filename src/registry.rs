@@ -0,0 +1,226 @@
+//! Registry values a profile sets for the length of a session, such as a
+//! game's windowed mode flag or its skip-intro setting. Changes are applied
+//! and rolled back by [`SystemStateGuard`](crate::system::SystemStateGuard).
+//! Keys can also be listed, for reading what other software registered.
+
+use std::fmt;
+use windows::Win32::Foundation::{ERROR_FILE_NOT_FOUND, ERROR_SUCCESS};
+use windows::Win32::System::Registry::{
+    HKEY, HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE, KEY_READ, REG_DWORD, REG_EXPAND_SZ, REG_QWORD,
+    REG_SZ, REG_VALUE_TYPE, RRF_NOEXPAND, RRF_RT_ANY, RegCloseKey, RegDeleteKeyValueW,
+    RegEnumKeyExW, RegGetValueW, RegOpenKeyExW, RegSetKeyValueW,
+};
+use windows::core::{HSTRING, PWSTR};
+
+use crate::error::WinError;
+
+/// A value to set, as an entry of a profile's `[[system.registry]]` array.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegistryValue {
+    /// The key, starting with `HKCU\` or `HKLM\` (or their long forms); keys
+    /// that don't exist are created
+    pub key: String,
+    /// The value's name; empty for the key's default value
+    #[cfg_attr(feature = "json", serde(default))]
+    pub name: String,
+    #[cfg_attr(feature = "json", serde(flatten))]
+    pub data: RegistryData,
+}
+
+impl fmt::Display for RegistryValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "registry value {}\\{}", self.key, self.name)
+    }
+}
+
+/// A value's type and contents: `dword = 1`, `qword = 1` or
+/// `string = "..."` in profiles.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "json", serde(rename_all = "snake_case"))]
+pub enum RegistryData {
+    Dword(u32),
+    Qword(u64),
+    String(String),
+    /// A string with `%VARIABLES%` expanded when it's read
+    ExpandString(String),
+}
+
+/// Splits `HKCU\Software\...` into its root and subkey.
+fn split_key(key: &str) -> Result<(HKEY, &str), String> {
+    let (root, subkey) = key.split_once('\\').unwrap_or((key, ""));
+    let root = match root.to_ascii_uppercase().as_str() {
+        "HKCU" | "HKEY_CURRENT_USER" => HKEY_CURRENT_USER,
+        "HKLM" | "HKEY_LOCAL_MACHINE" => HKEY_LOCAL_MACHINE,
+        _ => {
+            return Err(format!(
+                "Registry key '{}' must start with HKCU\\ or HKLM\\",
+                key
+            ));
+        }
+    };
+    if subkey.is_empty() {
+        return Err(format!("Registry key '{}' names no subkey", key));
+    }
+    Ok((root, subkey))
+}
+
+/// Checks that `key` names a key librarylink can change.
+pub fn validate_key(key: &str) -> Result<(), String> {
+    split_key(key).map(|_| ())
+}
+
+/// The value's current contents, or `None` if it doesn't exist. Values of
+/// other types than [`RegistryData`] covers are an error, since they
+/// couldn't be put back.
+pub fn read(key: &str, name: &str) -> Result<Option<RegistryData>, Box<dyn std::error::Error>> {
+    let (root, subkey) = split_key(key)?;
+    let (subkey, name) = (HSTRING::from(subkey), HSTRING::from(name));
+    let mut kind = REG_VALUE_TYPE::default();
+    let mut length = 0u32;
+    let result = unsafe {
+        RegGetValueW(
+            root,
+            &subkey,
+            &name,
+            RRF_RT_ANY | RRF_NOEXPAND,
+            Some(&mut kind),
+            None,
+            Some(&mut length),
+        )
+    };
+    if result == ERROR_FILE_NOT_FOUND {
+        return Ok(None);
+    }
+    result.ok().map_err(|e| WinError::new("RegGetValueW", &e))?;
+
+    let mut buffer = vec![0u8; length as usize];
+    unsafe {
+        RegGetValueW(
+            root,
+            &subkey,
+            &name,
+            RRF_RT_ANY | RRF_NOEXPAND,
+            Some(&mut kind),
+            Some(buffer.as_mut_ptr().cast()),
+            Some(&mut length),
+        )
+    }
+    .ok()
+    .map_err(|e| WinError::new("RegGetValueW", &e))?;
+    buffer.truncate(length as usize);
+
+    let data = match kind {
+        REG_DWORD if buffer.len() >= 4 => {
+            RegistryData::Dword(u32::from_le_bytes(buffer[..4].try_into()?))
+        }
+        REG_QWORD if buffer.len() >= 8 => {
+            RegistryData::Qword(u64::from_le_bytes(buffer[..8].try_into()?))
+        }
+        REG_SZ => RegistryData::String(utf16_string(&buffer)),
+        REG_EXPAND_SZ => RegistryData::ExpandString(utf16_string(&buffer)),
+        _ => {
+            return Err(format!(
+                "{}\\{} has a type librarylink can't restore ({})",
+                key, name, kind.0
+            )
+            .into());
+        }
+    };
+    Ok(Some(data))
+}
+
+fn utf16_string(bytes: &[u8]) -> String {
+    let wide: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .take_while(|&c| c != 0)
+        .collect();
+    String::from_utf16_lossy(&wide)
+}
+
+/// Sets the value, creating its key if needed.
+pub fn write(key: &str, name: &str, data: &RegistryData) -> Result<(), Box<dyn std::error::Error>> {
+    let (root, subkey) = split_key(key)?;
+    let (kind, bytes) = match data {
+        RegistryData::Dword(value) => (REG_DWORD, value.to_le_bytes().to_vec()),
+        RegistryData::Qword(value) => (REG_QWORD, value.to_le_bytes().to_vec()),
+        RegistryData::String(text) => (REG_SZ, wide_bytes(text)),
+        RegistryData::ExpandString(text) => (REG_EXPAND_SZ, wide_bytes(text)),
+    };
+    unsafe {
+        RegSetKeyValueW(
+            root,
+            &HSTRING::from(subkey),
+            &HSTRING::from(name),
+            kind.0,
+            Some(bytes.as_ptr().cast()),
+            bytes.len() as u32,
+        )
+    }
+    .ok()
+    .map_err(|e| WinError::new("RegSetKeyValueW", &e).into())
+}
+
+fn wide_bytes(text: &str) -> Vec<u8> {
+    text.encode_utf16()
+        .chain(std::iter::once(0))
+        .flat_map(u16::to_le_bytes)
+        .collect()
+}
+
+/// Deletes the value. One that's already gone isn't an error.
+pub fn delete(key: &str, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let (root, subkey) = split_key(key)?;
+    let result = unsafe { RegDeleteKeyValueW(root, &HSTRING::from(subkey), &HSTRING::from(name)) };
+    if result == ERROR_FILE_NOT_FOUND {
+        return Ok(());
+    }
+    result
+        .ok()
+        .map_err(|e| WinError::new("RegDeleteKeyValueW", &e).into())
+}
+
+/// The names of the key's subkeys, or none if the key doesn't exist.
+pub fn subkeys(key: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let (root, subkey) = split_key(key)?;
+    let mut handle = HKEY::default();
+    let result =
+        unsafe { RegOpenKeyExW(root, &HSTRING::from(subkey), None, KEY_READ, &mut handle) };
+    if result == ERROR_FILE_NOT_FOUND {
+        return Ok(Vec::new());
+    }
+    result
+        .ok()
+        .map_err(|e| WinError::new("RegOpenKeyExW", &e))?;
+
+    let mut names = Vec::new();
+    // Key names are at most 255 characters
+    let mut buffer = [0u16; 256];
+    let mut index = 0;
+    loop {
+        let mut length = buffer.len() as u32;
+        let result = unsafe {
+            RegEnumKeyExW(
+                handle,
+                index,
+                Some(PWSTR(buffer.as_mut_ptr())),
+                &mut length,
+                None,
+                None,
+                None,
+                None,
+            )
+        };
+        if result != ERROR_SUCCESS {
+            break;
+        }
+        names.push(String::from_utf16_lossy(&buffer[..length as usize]));
+        index += 1;
+    }
+    unsafe {
+        let _ = RegCloseKey(handle);
+    }
+    Ok(names)
+}
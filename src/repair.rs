@@ -0,0 +1,218 @@
+//! `config repair`: checks the files librarylink keeps in its data directory
+//! and recovers the ones a crash or power cut left truncated. Files are
+//! written with [`atomic_file`] so this shouldn't happen, but files written
+//! by older versions, or edited by hand, can still be broken.
+//!
+//! Broken files worth keeping are renamed to `<file>.corrupt` rather than
+//! deleted, so nothing is lost that could be salvaged by hand. Broken caches
+//! and session state are just removed.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::atomic_file;
+use crate::config::{self, Config};
+#[cfg(feature = "secret")]
+use crate::daemon;
+use crate::executable;
+#[cfg(all(feature = "http", feature = "secret"))]
+use crate::metadata;
+use crate::session::{self, SessionRecord};
+#[cfg(all(feature = "http", feature = "secret"))]
+use crate::sunshine;
+use crate::system;
+use crate::toml;
+
+/// What `config repair` did about one file.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum RepairAction {
+    /// The file reads fine
+    Healthy,
+    /// The file was broken, and its backup took its place
+    RestoredBackup,
+    /// The history had lines that don't read, which were left out
+    DroppedLines { count: usize },
+    /// The file was broken, and can be done without: a cache, or state
+    /// that's no use once it can't be read
+    Removed,
+    /// The file is broken and has no usable backup; it's left in place
+    Unrecoverable { reason: String },
+}
+
+/// One file `config repair` checked.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FileRepair {
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub action: RepairAction,
+}
+
+impl fmt::Display for RepairAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RepairAction::Healthy => f.write_str("healthy"),
+            RepairAction::RestoredBackup => f.write_str("restored from its backup"),
+            RepairAction::DroppedLines { count } => {
+                write!(f, "dropped {} unreadable line(s)", count)
+            }
+            RepairAction::Removed => f.write_str("unreadable; removed"),
+            RepairAction::Unrecoverable { reason } => write!(f, "unrecoverable: {}", reason),
+        }
+    }
+}
+
+/// How a file's contents are checked, and what's done when they fail.
+#[derive(Clone, Copy)]
+enum Kind {
+    /// The config file, which must load as a [`Config`]
+    Config,
+    /// JSON worth keeping, restored from its backup when broken. Only the
+    /// daemon's queue is, and there's no daemon without `secret`.
+    #[cfg_attr(not(feature = "secret"), allow(dead_code))]
+    Kept,
+    /// JSON that's removed when broken
+    Disposable,
+}
+
+/// Checks every file in the data directory, repairing what it can.
+pub fn repair() -> Vec<FileRepair> {
+    let mut files = vec![
+        (config::config_path(), Kind::Config),
+        #[cfg(feature = "secret")]
+        (daemon::queue_path(), Kind::Kept),
+        #[cfg(all(feature = "http", feature = "secret"))]
+        (sunshine::pending_path(), Kind::Disposable),
+        (executable::cache_path(), Kind::Disposable),
+    ];
+    for directory in [
+        session::sessions_dir(),
+        system::snapshots_dir(),
+        #[cfg(all(feature = "http", feature = "secret"))]
+        metadata::cache_dir(),
+    ] {
+        files.extend(json_files(&directory).map(|path| (path, Kind::Disposable)));
+    }
+
+    let mut repairs: Vec<FileRepair> = files
+        .into_iter()
+        .filter(|(path, _)| path.exists())
+        .map(|(path, kind)| {
+            let action = repair_file(&path, kind);
+            FileRepair { path, action }
+        })
+        .collect();
+    let history = session::history_path();
+    if history.exists() {
+        let action = repair_history(&history);
+        repairs.push(FileRepair {
+            path: history,
+            action,
+        });
+    }
+    repairs
+}
+
+fn json_files(directory: &Path) -> impl Iterator<Item = PathBuf> {
+    fs::read_dir(directory)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("json"))
+        })
+}
+
+/// Why `contents` don't read as `kind`, or `None` if they do.
+fn problem(contents: &str, kind: Kind) -> Option<String> {
+    match kind {
+        Kind::Config => match toml::parse(contents) {
+            Ok(document) => serde_json::from_value::<Config>(document)
+                .err()
+                .map(|e| e.to_string()),
+            Err(e) => Some(e.to_string()),
+        },
+        Kind::Kept | Kind::Disposable => serde_json::from_str::<serde_json::Value>(contents)
+            .err()
+            .map(|e| e.to_string()),
+    }
+}
+
+fn repair_file(path: &Path, kind: Kind) -> RepairAction {
+    let reason = match fs::read(path) {
+        Ok(bytes) => match String::from_utf8(bytes) {
+            Ok(contents) => match problem(&contents, kind) {
+                None => return RepairAction::Healthy,
+                Some(reason) => reason,
+            },
+            Err(_) => "not UTF-8 text".to_string(),
+        },
+        Err(e) => {
+            return RepairAction::Unrecoverable {
+                reason: e.to_string(),
+            };
+        }
+    };
+
+    if let Kind::Disposable = kind {
+        return match fs::remove_file(path) {
+            Ok(()) => RepairAction::Removed,
+            Err(e) => RepairAction::Unrecoverable {
+                reason: e.to_string(),
+            },
+        };
+    }
+    let Some(backup) = fs::read_to_string(atomic_file::backup_path(path))
+        .ok()
+        .filter(|contents| problem(contents, kind).is_none())
+    else {
+        return RepairAction::Unrecoverable { reason };
+    };
+    let restored = fs::copy(path, atomic_file::with_suffix(path, "corrupt"))
+        .and_then(|_| atomic_file::write(path, backup));
+    match restored {
+        Ok(()) => RepairAction::RestoredBackup,
+        Err(e) => RepairAction::Unrecoverable {
+            reason: e.to_string(),
+        },
+    }
+}
+
+/// Keeps the history's readable records, setting the rest aside in
+/// `history.jsonl.corrupt`.
+fn repair_history(path: &Path) -> RepairAction {
+    let contents = match fs::read(path) {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        Err(e) => {
+            return RepairAction::Unrecoverable {
+                reason: e.to_string(),
+            };
+        }
+    };
+    let (kept, dropped): (Vec<&str>, Vec<&str>) = contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .partition(|line| serde_json::from_str::<SessionRecord>(line).is_ok());
+    if dropped.is_empty() {
+        return RepairAction::Healthy;
+    }
+
+    let lines =
+        |lines: &[&str]| -> String { lines.iter().map(|line| format!("{}\n", line)).collect() };
+    let corrupt = atomic_file::with_suffix(path, "corrupt");
+    let mut set_aside = fs::read_to_string(&corrupt).unwrap_or_default();
+    set_aside.push_str(&lines(&dropped));
+    let written = atomic_file::write(&corrupt, set_aside)
+        .and_then(|_| atomic_file::write_with_backup(path, lines(&kept)));
+    match written {
+        Ok(()) => RepairAction::DroppedLines {
+            count: dropped.len(),
+        },
+        Err(e) => RepairAction::Unrecoverable {
+            reason: e.to_string(),
+        },
+    }
+}
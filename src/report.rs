@@ -0,0 +1,1197 @@
+use std::sync::OnceLock;
+use std::sync::mpsc::Sender;
+
+use crate::audio::AudioDevice;
+use crate::concurrency::ConflictAction;
+use crate::error::WinError;
+use crate::exit::ExitReason;
+use crate::games::GameListing;
+use crate::matcher::{MonitorScope, MonitorTarget};
+use crate::metrics::UsageSummary;
+use crate::monitor::MonitorState;
+use crate::placement::MonitorInfo;
+use crate::preflight::PreflightResult;
+use crate::priority::PriorityClass;
+use crate::quit::QuitSource;
+use crate::session::{self, FrameStats};
+use crate::timings::PhaseTiming;
+use crate::window_state::{RestoreReason, WindowMode};
+
+/// How much a message matters, least first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+#[cfg_attr(feature = "json", serde(rename_all = "lowercase"))]
+pub enum Level {
+    /// Detail for tracking down problems, shown with `--verbose`
+    Debug,
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct AppListing {
+    pub name: String,
+    pub aumid: String,
+    #[cfg_attr(feature = "json", serde(skip_serializing_if = "Option::is_none"))]
+    pub package_family_name: Option<String>,
+    #[cfg_attr(feature = "json", serde(skip_serializing_if = "Option::is_none"))]
+    pub install_path: Option<String>,
+    #[cfg_attr(feature = "json", serde(skip_serializing_if = "Option::is_none"))]
+    pub version: Option<String>,
+    #[cfg_attr(feature = "json", serde(skip_serializing_if = "Option::is_none"))]
+    pub publisher: Option<String>,
+    /// A framework package, listed with `--include-frameworks`; it has no
+    /// AUMID since it can't be launched
+    #[cfg_attr(feature = "json", serde(skip_serializing_if = "is_false"))]
+    pub framework: bool,
+    /// The package is provisioned for all users rather than installed for
+    /// the current user alone
+    #[cfg_attr(feature = "json", serde(skip_serializing_if = "is_false"))]
+    pub provisioned: bool,
+}
+
+#[cfg(feature = "json")]
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+/// Why the tracked process stopped being tracked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+#[cfg_attr(feature = "json", serde(rename_all = "snake_case"))]
+pub enum LostReason {
+    /// The process exited
+    Exited,
+    /// The process could not be opened for monitoring
+    OpenFailed,
+    /// Waiting on the process handle failed
+    WaitFailed,
+    /// The process was terminated through `Session::kill`
+    Killed,
+}
+
+/// Structured milestones reported while listing, launching and monitoring apps.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+#[cfg_attr(feature = "json", serde(tag = "event", rename_all = "snake_case"))]
+pub enum Event {
+    LaunchRequested {
+        aumid: String,
+    },
+    /// Active sessions conflict with an exclusive launch, or are exclusive
+    /// themselves
+    SessionConflict {
+        sessions: Vec<String>,
+        action: ConflictAction,
+    },
+    /// A profile dependency is being started before the game
+    DependencyStarting {
+        name: String,
+    },
+    /// A profile dependency was already running, so it's left alone
+    DependencyRunning {
+        name: String,
+        pid: u32,
+    },
+    /// A started dependency is being checked with readiness probes
+    DependencyWaiting {
+        name: String,
+        probes: Vec<String>,
+    },
+    DependencyReady {
+        name: String,
+        pid: u32,
+    },
+    DependencyStopped {
+        name: String,
+    },
+    /// A system setting was changed for the session
+    SettingApplied {
+        setting: String,
+    },
+    /// A system setting changed for a session was put back
+    SettingRestored {
+        setting: String,
+    },
+    /// A store client that started during the session was closed
+    LauncherClosed {
+        name: String,
+    },
+    SessionDetected {
+        session_id: u32,
+        kind: String,
+        station_name: String,
+    },
+    AppFound {
+        display_name: Option<String>,
+        package_display_name: Option<String>,
+        install_path: Option<String>,
+        package_full_name: Option<String>,
+        package_family_name: Option<String>,
+    },
+    /// The app's package version differs from its previous session's
+    PackageUpdated {
+        aumid: String,
+        previous_version: String,
+        version: String,
+    },
+    /// A profile's preflight checks ran
+    PreflightCompleted {
+        results: Vec<PreflightResult>,
+    },
+    /// The Store is installing or updating the app's package, so the launch
+    /// waits; repeated as the progress changes
+    PackageDeploying {
+        family_name: String,
+        progress_percent: Option<u32>,
+    },
+    /// The deployment the launch was waiting for finished
+    PackageDeployed {
+        family_name: String,
+        waited_secs: u64,
+    },
+    Launched {
+        pid: u32,
+    },
+    /// A command's stdout and stderr are being recorded to a log
+    OutputCaptured {
+        path: String,
+    },
+    /// The activated process exited before it could be followed; any
+    /// process appearing in the directory is taken for the game
+    WaitingForFirstProcess {
+        activated_pid: u32,
+        directory: String,
+        timeout_secs: u64,
+    },
+    MonitoringStarted {
+        session_id: String,
+        pid: u32,
+        path: String,
+        directory: String,
+        scope: MonitorScope,
+    },
+    /// The monitor moved to another state
+    StateChanged {
+        #[cfg_attr(feature = "json", serde(flatten))]
+        state: MonitorState,
+    },
+    /// The game is up: a tracked process, the launcher or the game, shows a
+    /// visible window. Reported once per session.
+    GameReady {
+        session_id: String,
+        aumid: String,
+        pid: u32,
+        #[cfg_attr(feature = "json", serde(skip_serializing_if = "Option::is_none"))]
+        path: Option<String>,
+    },
+    /// A tracked process's window was moved to the requested monitor and
+    /// size. Coordinates are physical pixels.
+    WindowPlaced {
+        pid: u32,
+        monitor: usize,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+        dpi: u32,
+    },
+    WindowNotPlaced {
+        pid: u32,
+        reason: String,
+    },
+    /// A game process was given the priority class and CPU affinity mask
+    /// asked for
+    ProcessTuned {
+        pid: u32,
+        #[cfg_attr(feature = "json", serde(skip_serializing_if = "Option::is_none"))]
+        priority: Option<PriorityClass>,
+        #[cfg_attr(feature = "json", serde(skip_serializing_if = "Option::is_none"))]
+        affinity: Option<u64>,
+    },
+    ProcessNotTuned {
+        pid: u32,
+        reason: String,
+    },
+    /// The tracked process's window changed between fullscreen, borderless,
+    /// windowed and minimized. `previous` is unset for its first window.
+    WindowModeChanged {
+        pid: u32,
+        mode: WindowMode,
+        #[cfg_attr(feature = "json", serde(skip_serializing_if = "Option::is_none"))]
+        previous: Option<WindowMode>,
+    },
+    /// The game's window was restored after it minimized or lost focus, as
+    /// asked for with auto-restore or the restore watchdog
+    WindowRestored {
+        pid: u32,
+        reason: RestoreReason,
+    },
+    /// The restore watchdog couldn't bring the game's window back to the
+    /// front. Reported once until the window is back in front.
+    WindowNotRestored {
+        pid: u32,
+        reason: RestoreReason,
+    },
+    /// The streaming host or the user asked for the game to be closed
+    QuitRequested {
+        source: QuitSource,
+    },
+    /// The game's process tree was suspended through `librarylink control`
+    Suspended {
+        processes: usize,
+    },
+    Resumed {
+        processes: usize,
+    },
+    Waiting {
+        pid: u32,
+    },
+    /// The tracked process is checked periodically instead of waited on
+    Polling {
+        pid: u32,
+        interval_ms: u64,
+    },
+    TrackedProcessLost {
+        pid: u32,
+        reason: LostReason,
+        /// The process's exit code, when it could be read
+        #[cfg_attr(feature = "json", serde(skip_serializing_if = "Option::is_none"))]
+        exit_code: Option<u32>,
+    },
+    ReplacementSearch {
+        target: MonitorTarget,
+    },
+    ReplacementFound {
+        pid: u32,
+        name: String,
+        path: String,
+    },
+    /// A replacement candidate exited before reaching the minimum lifetime
+    ReplacementSkipped {
+        pid: u32,
+        name: String,
+        lived_ms: u64,
+    },
+    /// No replacement appeared during the handoff window; the search goes on
+    /// in case the game is restarting itself
+    WaitingForRestart {
+        window_secs: u64,
+    },
+    NoReplacement {
+        target: MonitorTarget,
+    },
+    SessionResumed {
+        session_id: String,
+        aumid: String,
+        elapsed_secs: u64,
+    },
+    SessionEnded {
+        session_id: String,
+        duration_secs: u64,
+        /// Frame pacing, when the session was captured with PresentMon
+        #[cfg_attr(feature = "json", serde(skip_serializing_if = "Option::is_none"))]
+        frames: Option<FrameStats>,
+    },
+    /// A benchmark run finished and its results were written
+    BenchmarkCompleted {
+        profile: String,
+        results: String,
+        duration_secs: u64,
+        ended_early: bool,
+        summary: UsageSummary,
+        frames: Option<FrameStats>,
+    },
+    /// How long each phase took, with `--timings`. Reported once, when the
+    /// launch ends.
+    Timings {
+        phases: Vec<PhaseTiming>,
+    },
+    AppList {
+        apps: Vec<AppListing>,
+    },
+    GameList {
+        games: Vec<GameListing>,
+    },
+    MonitorList {
+        monitors: Vec<MonitorInfo>,
+    },
+    AudioDeviceList {
+        devices: Vec<AudioDevice>,
+    },
+    /// A Windows API failure that ended the current operation
+    Error {
+        context: String,
+        #[cfg_attr(feature = "json", serde(flatten))]
+        error: WinError,
+    },
+    /// The CLI is giving up, with `code` as its exit code; the error that
+    /// caused it was reported just before
+    Failed {
+        reason: ExitReason,
+        code: i32,
+        /// The game's own exit code, for [`ExitReason::GameFailed`]
+        #[cfg_attr(feature = "json", serde(skip_serializing_if = "Option::is_none"))]
+        game_exit_code: Option<u32>,
+    },
+}
+
+/// Destination for everything librarylink reports. Embedders and output modes
+/// pick an implementation instead of librarylink writing to stdout directly.
+pub trait Reporter: Send + Sync {
+    /// Free-form progress or diagnostic text.
+    fn message(&self, level: Level, text: &str);
+
+    /// A structured milestone.
+    fn event(&self, event: &Event);
+}
+
+impl Event {
+    /// How much the event matters, for `--quiet` and `--verbose`.
+    pub fn level(&self) -> Level {
+        match self {
+            Event::Error { .. } | Event::Failed { .. } => Level::Error,
+            Event::WindowNotPlaced { .. }
+            | Event::WindowNotRestored { .. }
+            | Event::ProcessNotTuned { .. } => Level::Warning,
+            _ => Level::Info,
+        }
+    }
+}
+
+/// Console output for people, with the familiar emoji progress lines.
+pub struct HumanReporter;
+
+/// One JSON object per line for scripts and frontends.
+#[cfg(feature = "json")]
+pub struct JsonReporter;
+
+/// Discards all output.
+pub struct SilentReporter;
+
+/// Tab-separated lines for scripts that don't parse JSON, with a fixed set
+/// of keywords: `launched <pid>`, `monitoring <pid> <session id>`,
+/// `ready <pid>`, `ended <reason>`, `error <call> <hresult>` and
+/// `failed <reason> <code>`. Messages go to stderr as they are.
+pub struct PorcelainReporter;
+
+/// Sends events to a channel, as the event receiver of a library `Session`.
+/// Messages go to the process-wide reporter.
+pub struct ChannelReporter {
+    sender: Sender<Event>,
+}
+
+impl ChannelReporter {
+    pub fn new(sender: Sender<Event>) -> Self {
+        ChannelReporter { sender }
+    }
+}
+
+impl Reporter for HumanReporter {
+    fn message(&self, _level: Level, text: &str) {
+        println!("{}", text);
+    }
+
+    fn event(&self, event: &Event) {
+        match event {
+            Event::LaunchRequested { aumid } => {
+                println!("=== UWP App Launch ===");
+                println!("Looking up and launching app with AUMID: {}", aumid);
+                println!();
+            }
+            Event::SessionConflict { sessions, action } => match action {
+                ConflictAction::Queue => println!(
+                    "⏸️ Waiting for {} to end before launching...",
+                    sessions.join(", ")
+                ),
+                ConflictAction::Terminate => {
+                    println!("🛑 Terminating {} before launching...", sessions.join(", "))
+                }
+            },
+            Event::DependencyStarting { name } => {
+                println!("🧩 Starting {}...", name);
+            }
+            Event::DependencyRunning { name, pid } => {
+                println!("🧩 {} is already running (process {})", name, pid);
+            }
+            Event::DependencyWaiting { name, probes } => {
+                println!("⏳ Waiting for {}: {}", name, probes.join(", "));
+            }
+            Event::DependencyReady { name, pid } => {
+                println!("✅ {} is ready (process {})", name, pid);
+                println!();
+            }
+            Event::DependencyStopped { name } => {
+                println!("🧹 Stopped {}", name);
+            }
+            Event::SettingApplied { setting } => {
+                println!("⚙️ Set {}", setting);
+            }
+            Event::SettingRestored { setting } => {
+                println!("↩️ Restored {}", setting);
+            }
+            Event::LauncherClosed { name } => {
+                println!("🧹 Closed {}, which started during the session", name);
+            }
+            Event::SessionDetected {
+                session_id,
+                kind,
+                station_name,
+            } => {
+                if station_name.is_empty() {
+                    println!("Windows Session: {} ({})", session_id, kind);
+                } else {
+                    println!(
+                        "Windows Session: {} ({}, {})",
+                        session_id, kind, station_name
+                    );
+                }
+            }
+            Event::AppFound {
+                display_name,
+                package_display_name,
+                install_path,
+                package_full_name,
+                package_family_name,
+            } => {
+                println!("Successfully found app information!");
+                if let Some(display_name) = display_name {
+                    println!("App Display Name: {}", display_name);
+                }
+                if let Some(package_name) = package_display_name {
+                    println!("Package Display Name: {}", package_name);
+                }
+                if let Some(install_path) = install_path {
+                    println!("*** INSTALL DIRECTORY FOUND ***");
+                    println!("Installed Path: {}", install_path);
+                }
+                if let Some(full_name) = package_full_name {
+                    println!("Package Full Name: {}", full_name);
+                }
+                if let Some(family_name) = package_family_name {
+                    println!("Package Family Name: {}", family_name);
+                }
+            }
+            Event::PackageUpdated {
+                previous_version,
+                version,
+                ..
+            } => {
+                println!(
+                    "📦 Package updated from {} to {} since the last session",
+                    previous_version, version
+                );
+            }
+            Event::PreflightCompleted { results } => {
+                println!("🛫 Preflight checks:");
+                for result in results {
+                    println!(
+                        "   {} {}: {}",
+                        if result.passed { "✅" } else { "❌" },
+                        result.check,
+                        result.detail
+                    );
+                }
+                println!();
+            }
+            Event::PackageDeploying {
+                family_name,
+                progress_percent,
+            } => match progress_percent {
+                Some(percent) => println!("⏳ Updating {}: {}%", family_name, percent),
+                None => println!(
+                    "⏳ {} is being updated by the Microsoft Store; waiting for it to finish...",
+                    family_name
+                ),
+            },
+            Event::PackageDeployed {
+                family_name,
+                waited_secs,
+            } => {
+                println!(
+                    "✅ {} finished updating after {}",
+                    family_name,
+                    session::format_duration(*waited_secs)
+                );
+                println!();
+            }
+            Event::Launched { pid } => {
+                println!("✅ Successfully launched app!");
+                println!("🚀 Process ID: {}", pid);
+            }
+            Event::OutputCaptured { path } => {
+                println!("📝 Recording output to {}", path);
+            }
+            Event::WaitingForFirstProcess {
+                activated_pid,
+                directory,
+                timeout_secs,
+            } => {
+                println!(
+                    "⏳ Process {} exited right away; waiting up to {}s for the game to start in {}...",
+                    activated_pid, timeout_secs, directory
+                );
+            }
+            Event::MonitoringStarted {
+                session_id,
+                pid,
+                path,
+                directory,
+                scope,
+            } => {
+                println!("📋 Launched Process Details:");
+                println!("   Process Path: {}", path);
+                println!();
+                println!("🔍 Starting process monitoring...");
+                println!("   Monitoring directory: {}", directory);
+                println!("   Monitoring scope: {}", scope);
+                println!("   Initial process ID: {}", pid);
+                println!("   Session ID: {}", session_id);
+                println!();
+            }
+            // The events around each transition already describe it
+            Event::StateChanged { .. } => {}
+            Event::GameReady { pid, .. } => {
+                println!("🎮 The game is up (process {})", pid);
+            }
+            Event::WindowPlaced {
+                monitor,
+                x,
+                y,
+                width,
+                height,
+                dpi,
+                ..
+            } => {
+                println!(
+                    "🪟 Moved the game window to monitor {} ({}x{} at {},{}, {}% scale)",
+                    monitor,
+                    width,
+                    height,
+                    x,
+                    y,
+                    dpi * 100 / 96
+                );
+            }
+            Event::WindowNotPlaced { pid, reason } => {
+                println!(
+                    "⚠️ Could not place the window of process {}: {}",
+                    pid, reason
+                );
+            }
+            Event::ProcessTuned {
+                pid,
+                priority,
+                affinity,
+            } => {
+                let mut changes = Vec::new();
+                if let Some(priority) = priority {
+                    changes.push(format!("{} priority", priority));
+                }
+                if let Some(mask) = affinity {
+                    changes.push(format!("CPU affinity 0x{:X}", mask));
+                }
+                println!("⚙️ Gave process {} {}", pid, changes.join(" and "));
+            }
+            Event::ProcessNotTuned { pid, reason } => {
+                println!(
+                    "⚠️ Could not set the priority or affinity of process {}: {}",
+                    pid, reason
+                );
+            }
+            Event::WindowModeChanged {
+                pid,
+                mode,
+                previous,
+            } => match previous {
+                Some(previous) => println!(
+                    "🪟 The window of process {} went from {} to {}",
+                    pid, previous, mode
+                ),
+                None => println!("🪟 The window of process {} is {}", pid, mode),
+            },
+            Event::WindowRestored { pid, reason } => {
+                println!("🪟 Restored the window of process {} ({})", pid, reason);
+            }
+            Event::WindowNotRestored { pid, reason } => {
+                println!(
+                    "⚠️ Could not bring back the window of process {} ({})",
+                    pid, reason
+                );
+            }
+            Event::QuitRequested { source } => {
+                println!("🛑 Quit requested through {}; closing the game...", source);
+            }
+            Event::Suspended { processes } => {
+                println!("⏸️ Suspended the game ({} process(es))", processes);
+            }
+            Event::Resumed { processes } => {
+                println!("▶️ Resumed the game ({} process(es))", processes);
+            }
+            Event::Waiting { pid } => {
+                println!("⏳ Waiting for process {} to terminate...", pid);
+            }
+            Event::Polling { pid, interval_ms } => {
+                println!(
+                    "⏳ Polling process {} every {}ms until it terminates...",
+                    pid, interval_ms
+                );
+            }
+            Event::TrackedProcessLost {
+                pid,
+                reason,
+                exit_code,
+            } => match reason {
+                LostReason::Exited => match exit_code {
+                    Some(code) if *code != 0 => {
+                        println!("❌ Process {} has terminated with exit code {}", pid, code)
+                    }
+                    _ => println!("❌ Process {} has terminated", pid),
+                },
+                LostReason::OpenFailed => {
+                    println!("❌ Failed to open process {} for monitoring", pid)
+                }
+                LostReason::WaitFailed => {
+                    println!("❌ Waiting on process {} failed", pid)
+                }
+                LostReason::Killed => println!("🛑 Process {} was killed", pid),
+            },
+            Event::ReplacementSearch { target } => {
+                println!(
+                    "🔍 Searching for replacement process in {}",
+                    target.describe()
+                );
+            }
+            Event::ReplacementFound { pid, name, path } => {
+                println!("🔄 Found replacement process: {}", pid);
+                println!("   Process Name: {}", name);
+                println!("   Process Path: {}", path);
+                println!("📍 Now monitoring process {}", pid);
+                println!();
+            }
+            Event::ReplacementSkipped {
+                pid,
+                name,
+                lived_ms,
+            } => {
+                println!(
+                    "⏭️ Skipping process {} ({}), which exited after {:.1}s",
+                    pid,
+                    name,
+                    *lived_ms as f64 / 1000.0
+                );
+            }
+            Event::WaitingForRestart { window_secs } => {
+                println!(
+                    "⏳ Waiting up to {}s in case the game is restarting itself...",
+                    window_secs
+                );
+            }
+            Event::NoReplacement { target } => {
+                println!("💀 No replacement process found in {}", target.describe());
+                println!("🚪 Exiting monitoring...");
+            }
+            Event::SessionResumed {
+                session_id,
+                aumid,
+                elapsed_secs,
+            } => {
+                println!("=== Resuming Session {} ===", session_id);
+                println!("AUMID: {}", aumid);
+                println!("Started {} ago", session::format_duration(*elapsed_secs));
+                println!();
+            }
+            Event::SessionEnded {
+                duration_secs,
+                frames,
+                ..
+            } => {
+                println!(
+                    "⏱️ Session played for {}",
+                    session::format_duration(*duration_secs)
+                );
+                if let Some(frames) = frames {
+                    println!(
+                        "🎞️ {} frames at avg {:.1} FPS, 1% low {:.1} FPS, 0.1% low {:.1} FPS",
+                        frames.frames,
+                        frames.avg_fps,
+                        frames.low_1_percent_fps,
+                        frames.low_0_1_percent_fps
+                    );
+                }
+            }
+            Event::BenchmarkCompleted {
+                profile,
+                results,
+                duration_secs,
+                ended_early,
+                summary,
+                frames,
+            } => {
+                println!();
+                println!("=== Benchmark: {} ===", profile);
+                if *ended_early {
+                    println!(
+                        "⚠️ The game exited after {}",
+                        session::format_duration(*duration_secs)
+                    );
+                } else {
+                    println!("Duration: {}", session::format_duration(*duration_secs));
+                }
+                let percent = |value: Option<f64>| {
+                    value.map_or("n/a".to_string(), |value| format!("{:.1}%", value))
+                };
+                println!(
+                    "CPU: avg {}, max {}",
+                    percent(summary.avg_cpu_percent),
+                    percent(summary.max_cpu_percent)
+                );
+                println!(
+                    "GPU: avg {}, max {}",
+                    percent(summary.avg_gpu_percent),
+                    percent(summary.max_gpu_percent)
+                );
+                println!(
+                    "Memory: avg {} MB, peak {} MB working set, peak {} MB private",
+                    summary.avg_working_set_bytes / 1_048_576,
+                    summary.peak_working_set_bytes / 1_048_576,
+                    summary.peak_private_bytes / 1_048_576
+                );
+                if let Some(frames) = frames {
+                    println!(
+                        "Frames: {} at avg {:.1} FPS, 1% low {:.1} FPS, 0.1% low {:.1} FPS",
+                        frames.frames,
+                        frames.avg_fps,
+                        frames.low_1_percent_fps,
+                        frames.low_0_1_percent_fps
+                    );
+                }
+                println!("📄 Results written to {}", results);
+            }
+            Event::Timings { phases } => {
+                println!("⏱️ Timings:");
+                if phases.is_empty() {
+                    println!("   nothing was timed");
+                }
+                for timing in phases {
+                    if timing.phase.is_milestone() {
+                        println!("   {}: {} ms after activation", timing.phase, timing.millis);
+                    } else if timing.count > 1 {
+                        println!(
+                            "   {}: {} ms ({} times)",
+                            timing.phase, timing.millis, timing.count
+                        );
+                    } else {
+                        println!("   {}: {} ms", timing.phase, timing.millis);
+                    }
+                }
+            }
+            Event::AppList { apps } => print_apps_table(apps),
+            Event::GameList { games } => print_games_table(games),
+            Event::MonitorList { monitors } => print_monitors_table(monitors),
+            Event::AudioDeviceList { devices } => print_audio_devices(devices),
+            Event::Error { context, error } => println!("❌ {}: {}", context, error),
+            // The error before it said what went wrong
+            Event::Failed { .. } => {}
+        }
+    }
+}
+
+fn print_apps_table(apps: &[AppListing]) {
+    if apps.is_empty() {
+        println!("No applications found.");
+        return;
+    }
+
+    println!("=== Apps with AUMIDs (UWP/Store Apps) ===");
+    println!("Found {} applications:\n", apps.len());
+
+    // Calculate column widths
+    let max_name_width = apps
+        .iter()
+        .map(|app| app.name.len())
+        .max()
+        .unwrap_or(12)
+        .max(12);
+
+    println!(
+        "{:<width$} AUMID",
+        "Application Name",
+        width = max_name_width
+    );
+    println!(
+        "{:<width$} {}",
+        "-".repeat(max_name_width),
+        "-".repeat(50),
+        width = max_name_width
+    );
+
+    for app in apps {
+        if app.framework {
+            let family = app.package_family_name.as_deref().unwrap_or_default();
+            println!(
+                "{:<width$} (framework) {}",
+                app.name,
+                family,
+                width = max_name_width
+            );
+        } else if app.provisioned {
+            println!(
+                "{:<width$} {} (all users)",
+                app.name,
+                app.aumid,
+                width = max_name_width
+            );
+        } else {
+            println!("{:<width$} {}", app.name, app.aumid, width = max_name_width);
+        }
+    }
+}
+
+fn print_games_table(games: &[GameListing]) {
+    if games.is_empty() {
+        println!("No games found.");
+        return;
+    }
+
+    println!("=== Games (Xbox / Game Pass) ===");
+    println!("Found {} games:\n", games.len());
+
+    let max_name_width = games
+        .iter()
+        .map(|game| game.app.name.len())
+        .max()
+        .unwrap_or(12)
+        .max(12);
+
+    println!("{:<width$} AUMID", "Game Name", width = max_name_width);
+    println!(
+        "{:<width$} {}",
+        "-".repeat(max_name_width),
+        "-".repeat(50),
+        width = max_name_width
+    );
+
+    for game in games {
+        println!(
+            "{:<width$} {}",
+            game.app.name,
+            game.app.aumid,
+            width = max_name_width
+        );
+    }
+}
+
+fn print_monitors_table(monitors: &[MonitorInfo]) {
+    if monitors.is_empty() {
+        println!("No monitors found.");
+        return;
+    }
+
+    println!("=== Monitors (for --monitor) ===\n");
+    for monitor in monitors {
+        let name = monitor.friendly_name.as_deref().unwrap_or(&monitor.name);
+        let refresh = monitor
+            .refresh_rate
+            .map(|rate| format!(" @ {}Hz", rate))
+            .unwrap_or_default();
+        let hdr = match (monitor.hdr_supported, monitor.hdr_enabled) {
+            (_, true) => ", HDR on",
+            (true, false) => ", HDR off",
+            (false, false) => "",
+        };
+        println!(
+            "{}. {}{} - {}x{}{}, {}% scale{}",
+            monitor.index,
+            name,
+            if monitor.primary { " (primary)" } else { "" },
+            monitor.width,
+            monitor.height,
+            refresh,
+            monitor.scale_percent(),
+            hdr
+        );
+    }
+}
+
+fn print_audio_devices(devices: &[AudioDevice]) {
+    if devices.is_empty() {
+        println!("No audio output devices found.");
+        return;
+    }
+
+    println!("=== Audio Output Devices ===\n");
+    for device in devices {
+        let roles = match (device.default, device.default_communications) {
+            (true, true) => " (default)",
+            (true, false) => " (default, not for communications)",
+            (false, true) => " (default for communications)",
+            (false, false) => "",
+        };
+        println!("{}{}", device.name, roles);
+        println!("   {}", device.id);
+    }
+}
+
+/// A message as the JSON line `--output json` prints, or `None` for blank
+/// spacer lines, which only make sense for human output.
+#[cfg(feature = "json")]
+pub(crate) fn message_line(level: Level, text: &str) -> Option<String> {
+    if text.is_empty() {
+        return None;
+    }
+    let message = serde_json::json!({
+        "event": "message",
+        "level": level,
+        "text": text,
+    });
+    Some(message.to_string())
+}
+
+#[cfg(feature = "json")]
+pub(crate) fn event_line(event: &Event) -> Option<String> {
+    serde_json::to_string(event)
+        .map_err(|e| eprintln!("Could not serialize event: {}", e))
+        .ok()
+}
+
+#[cfg(feature = "json")]
+impl Reporter for JsonReporter {
+    fn message(&self, level: Level, text: &str) {
+        if let Some(line) = message_line(level, text) {
+            println!("{}", line);
+        }
+    }
+
+    fn event(&self, event: &Event) {
+        if let Some(line) = event_line(event) {
+            println!("{}", line);
+        }
+    }
+}
+
+impl Reporter for PorcelainReporter {
+    fn message(&self, level: Level, text: &str) {
+        if level >= Level::Warning {
+            eprintln!("{}", text);
+        }
+    }
+
+    fn event(&self, event: &Event) {
+        match event {
+            Event::Launched { pid } => println!("launched\t{}", pid),
+            Event::MonitoringStarted {
+                session_id, pid, ..
+            } => println!("monitoring\t{}\t{}", pid, session_id),
+            Event::GameReady { pid, .. } => println!("ready\t{}", pid),
+            Event::StateChanged {
+                state: MonitorState::Ended { reason },
+            } => println!("ended\t{}", reason.as_str()),
+            Event::Error { error, .. } => println!("error\t{}\t{}", error.call, error.hresult),
+            Event::Failed {
+                reason,
+                code,
+                game_exit_code: Some(game_exit_code),
+            } => println!("failed\t{}\t{}\t{}", reason, code, game_exit_code),
+            Event::Failed { reason, code, .. } => println!("failed\t{}\t{}", reason, code),
+            _ => {}
+        }
+    }
+}
+
+impl Reporter for SilentReporter {
+    fn message(&self, _level: Level, _text: &str) {}
+
+    fn event(&self, _event: &Event) {}
+}
+
+impl Reporter for ChannelReporter {
+    fn message(&self, level: Level, text: &str) {
+        reporter().message(level, text);
+    }
+
+    fn event(&self, event: &Event) {
+        // The receiver may have been dropped by an embedder that ignores events
+        let _ = self.sender.send(event.clone());
+    }
+}
+
+/// How much of the output `--quiet` and `--verbose` let through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Verbosity {
+    /// Warnings and errors only
+    Quiet,
+    /// Everything but debug detail
+    #[default]
+    Normal,
+    /// Everything
+    Verbose,
+}
+
+impl Verbosity {
+    fn lowest(self) -> Level {
+        match self {
+            Verbosity::Quiet => Level::Warning,
+            Verbosity::Normal => Level::Info,
+            Verbosity::Verbose => Level::Debug,
+        }
+    }
+}
+
+/// Passes on what the verbosity lets through to `inner`.
+pub struct FilteredReporter {
+    inner: Box<dyn Reporter>,
+    lowest: Level,
+}
+
+impl FilteredReporter {
+    pub fn new(inner: Box<dyn Reporter>, verbosity: Verbosity) -> Self {
+        FilteredReporter {
+            inner,
+            lowest: verbosity.lowest(),
+        }
+    }
+}
+
+impl Reporter for FilteredReporter {
+    fn message(&self, level: Level, text: &str) {
+        if level >= self.lowest {
+            self.inner.message(level, text);
+        }
+    }
+
+    fn event(&self, event: &Event) {
+        if event.level() >= self.lowest {
+            self.inner.event(event);
+        }
+    }
+}
+
+/// Parses an `--output` value into a reporter.
+pub fn reporter_for_mode(mode: &str) -> Option<Box<dyn Reporter>> {
+    match mode {
+        "human" => Some(Box::new(HumanReporter)),
+        #[cfg(feature = "json")]
+        "json" => Some(Box::new(JsonReporter)),
+        "silent" => Some(Box::new(SilentReporter)),
+        "porcelain" => Some(Box::new(PorcelainReporter)),
+        _ => None,
+    }
+}
+
+static REPORTER: OnceLock<Box<dyn Reporter>> = OnceLock::new();
+
+/// Installs the process-wide reporter. Only the first call has an effect.
+pub fn set_reporter(reporter: Box<dyn Reporter>) {
+    let _ = REPORTER.set(reporter);
+}
+
+pub fn reporter() -> &'static dyn Reporter {
+    REPORTER
+        .get_or_init(|| {
+            Box::new(FilteredReporter::new(
+                Box::new(HumanReporter),
+                Verbosity::Normal,
+            ))
+        })
+        .as_ref()
+}
+
+pub fn emit(event: Event) {
+    reporter().event(&event);
+}
+
+impl From<log::Level> for Level {
+    fn from(level: log::Level) -> Self {
+        match level {
+            log::Level::Error => Level::Error,
+            log::Level::Warn => Level::Warning,
+            log::Level::Info => Level::Info,
+            log::Level::Debug | log::Level::Trace => Level::Debug,
+        }
+    }
+}
+
+/// A `log` backend that passes records, librarylink's own diagnostics among
+/// them, to the installed [`reporter`] as messages. The CLI installs it with
+/// [`log_to_reporter`]; embedders can install any other `log` backend
+/// instead, and still get events from the reporter.
+pub struct ReporterLogger;
+
+impl log::Log for ReporterLogger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        reporter().message(record.level().into(), &record.args().to_string());
+    }
+
+    fn flush(&self) {}
+}
+
+/// Makes [`ReporterLogger`] the process's `log` backend, letting every level
+/// through so the reporter decides what to show. Fails if another backend
+/// was installed first.
+pub fn log_to_reporter() -> Result<(), log::SetLoggerError> {
+    log::set_logger(&ReporterLogger)?;
+    log::set_max_level(log::LevelFilter::Debug);
+    Ok(())
+}
+
+// For the macros below, so callers needn't depend on `log` themselves
+#[doc(hidden)]
+pub use log;
+
+/// Reports a Windows API failure with its HRESULT and failing call.
+pub fn failure(context: impl Into<String>, error: WinError) {
+    emit(Event::Error {
+        context: context.into(),
+        error,
+    });
+}
+
+// `#[macro_export]` puts macros at the crate root, where the short names
+// would clash with `log`'s; they're exported from here instead
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __report_debug {
+    ($($arg:tt)*) => {
+        $crate::report::log::debug!($($arg)*)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __report_info {
+    ($($arg:tt)*) => {
+        $crate::report::log::info!($($arg)*)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __report_warning {
+    ($($arg:tt)*) => {
+        $crate::report::log::warn!($($arg)*)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __report_error {
+    ($($arg:tt)*) => {
+        $crate::report::log::error!($($arg)*)
+    };
+}
+
+/// Logs a diagnostic through `log`, at debug level.
+pub use crate::__report_debug as debug;
+/// Logs a failure through `log`, at error level.
+pub use crate::__report_error as error;
+/// Logs progress through `log`, at info level.
+pub use crate::__report_info as info;
+/// Logs a problem that doesn't stop the launch through `log`, at warn level.
+pub use crate::__report_warning as warning;
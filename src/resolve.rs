@@ -0,0 +1,131 @@
+//! Finding an installed app by its display name, for shortcuts that should
+//! keep working when an update changes the app's AUMID. Names are scored
+//! rather than matched outright, so `forza 5` and a misspelt `Forza Horizn`
+//! still find the game, and the best scoring app wins.
+
+use crate::apps::{self, AppFilter};
+use crate::report::AppListing;
+use crate::search;
+
+/// The lowest score an app is taken for a match with.
+pub const MIN_SCORE: u32 = 50;
+
+/// An app whose name matched, and how well: 100 for the same name, less the
+/// more it had to be stretched.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct NameMatch {
+    #[cfg_attr(feature = "json", serde(flatten))]
+    pub app: AppListing,
+    pub score: u32,
+}
+
+/// The installed apps whose names match `name`, best first.
+pub fn candidates(name: &str) -> Result<Vec<NameMatch>, Box<dyn std::error::Error>> {
+    let mut matches: Vec<NameMatch> = apps::list_apps_matching(&AppFilter::default())?
+        .into_iter()
+        .filter_map(|app| {
+            let family = app.package_family_name.as_deref();
+            let package_name = family.map(|family| family.split('_').next().unwrap_or(family));
+            let score = score(&app.name, package_name, name);
+            (score >= MIN_SCORE).then_some(NameMatch { app, score })
+        })
+        .collect();
+    // Of equal scores, the shortest name has the least left unmatched
+    matches.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then(a.app.name.chars().count().cmp(&b.app.name.chars().count()))
+            .then_with(|| a.app.name.cmp(&b.app.name))
+    });
+    Ok(matches)
+}
+
+/// The installed app `name` best matches, if any is close enough.
+pub fn resolve(name: &str) -> Result<Option<NameMatch>, Box<dyn std::error::Error>> {
+    Ok(candidates(name)?.into_iter().next())
+}
+
+/// How well an app called `app_name`, in the package `package_name`, matches
+/// `query`, from 0 to 100.
+pub fn score(app_name: &str, package_name: Option<&str>, query: &str) -> u32 {
+    let name = without_marks(app_name);
+    let query = without_marks(query);
+    if query.is_empty() || name.is_empty() {
+        return 0;
+    }
+    if search::equals(&name, &query) {
+        return 100;
+    }
+    // The more of the name the query leaves out, the lower it scores
+    let unmatched = |most: u32| {
+        let name_length = name.chars().count() as u32;
+        let query_length = query.chars().count().min(name_length as usize) as u32;
+        most * (name_length - query_length) / name_length
+    };
+    if search::contains(&name, &query) {
+        return 90 - unmatched(20);
+    }
+    if search::contains_words(&name, &query) {
+        return 75 - unmatched(15);
+    }
+    if package_name.is_some_and(|package_name| search::matches_package_name(package_name, &query)) {
+        return 60;
+    }
+    (word_similarity(&name, &query) * 65.0).round() as u32
+}
+
+/// How closely each word of `query` resembles one of `name`'s, from 0 to 1,
+/// for names that are misspelt rather than shortened.
+fn word_similarity(name: &str, query: &str) -> f64 {
+    let name_words: Vec<Vec<char>> = name
+        .split_whitespace()
+        .map(|word| word.to_lowercase().chars().collect())
+        .collect();
+    let query_words: Vec<Vec<char>> = query
+        .split_whitespace()
+        .map(|word| word.to_lowercase().chars().collect())
+        .collect();
+    if query_words.is_empty() {
+        return 0.0;
+    }
+    let total: f64 = query_words
+        .iter()
+        .map(|query_word| {
+            name_words
+                .iter()
+                .map(|name_word| {
+                    let longest = name_word.len().max(query_word.len());
+                    1.0 - edit_distance(name_word, query_word) as f64 / longest as f64
+                })
+                .fold(0.0, f64::max)
+        })
+        .sum();
+    let similarity = total / query_words.len() as f64;
+    // Below this, words only share a few letters by chance
+    if similarity < 0.75 { 0.0 } else { similarity }
+}
+
+/// The Levenshtein distance between `a` and `b`.
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// `name` without the trademark signs stores put in titles, which nobody
+/// types.
+fn without_marks(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, '™' | '®' | '©'))
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
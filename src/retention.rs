@@ -0,0 +1,157 @@
+//! Retention for the data librarylink accumulates on a long-lived host:
+//! captured output logs, frame-time traces and session history.
+//! Enforced when librarylink starts and by `librarylink maintenance clean`.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::capture;
+#[cfg(feature = "presentmon")]
+use crate::presentmon;
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+const MEGABYTE: u64 = 1024 * 1024;
+
+/// Limits for one kind of data. Whatever is older than `max_age` goes first,
+/// then the oldest of the rest until it fits in `max_bytes`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RetentionPolicy {
+    pub max_age: Option<Duration>,
+    pub max_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataKind {
+    /// Captured stdout and stderr of wrapped commands
+    Logs,
+    /// PresentMon frame-time CSVs
+    Traces,
+    /// Finished sessions in `history.jsonl`
+    History,
+}
+
+impl DataKind {
+    pub const ALL: [DataKind; 3] = [DataKind::Logs, DataKind::Traces, DataKind::History];
+
+    pub fn default_policy(self) -> RetentionPolicy {
+        let (days, megabytes) = match self {
+            DataKind::Logs => (30, 100),
+            DataKind::Traces => (30, 500),
+            DataKind::History => (365, 10),
+        };
+        RetentionPolicy {
+            max_age: Some(DAY * days),
+            max_bytes: Some(megabytes * MEGABYTE),
+        }
+    }
+
+    fn directory(self) -> Option<PathBuf> {
+        match self {
+            DataKind::Logs => Some(capture::logs_dir()),
+            #[cfg(feature = "presentmon")]
+            DataKind::Traces => Some(presentmon::frames_dir()),
+            // Nothing writes traces without PresentMon
+            #[cfg(not(feature = "presentmon"))]
+            DataKind::Traces => None,
+            DataKind::History => None,
+        }
+    }
+}
+
+impl fmt::Display for DataKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            DataKind::Logs => "logs",
+            DataKind::Traces => "traces",
+            DataKind::History => "history",
+        })
+    }
+}
+
+/// What enforcing a policy removed: files, or history records.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Cleaned {
+    pub removed: usize,
+    pub freed_bytes: u64,
+}
+
+/// Applies `policy` to one kind of data. Files that can't be removed, such as
+/// a log still being written, are left for next time.
+pub fn enforce(kind: DataKind, policy: &RetentionPolicy) -> std::io::Result<Cleaned> {
+    match kind.directory() {
+        Some(directory) => Ok(clean_directory(&directory, policy)),
+        None => trim_history(policy),
+    }
+}
+
+#[cfg(feature = "json")]
+fn trim_history(policy: &RetentionPolicy) -> std::io::Result<Cleaned> {
+    let (removed, freed_bytes) = crate::session::trim_history(policy.max_age, policy.max_bytes)?;
+    Ok(Cleaned {
+        removed,
+        freed_bytes,
+    })
+}
+
+/// Without the `json` feature there is no history to trim.
+#[cfg(not(feature = "json"))]
+fn trim_history(_policy: &RetentionPolicy) -> std::io::Result<Cleaned> {
+    Ok(Cleaned::default())
+}
+
+fn clean_directory(directory: &Path, policy: &RetentionPolicy) -> Cleaned {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return Cleaned::default();
+    };
+    let mut files: Vec<(PathBuf, SystemTime, u64)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let metadata = entry
+                .metadata()
+                .ok()
+                .filter(|metadata| metadata.is_file())?;
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            Some((entry.path(), modified, metadata.len()))
+        })
+        .collect();
+    // Oldest first
+    files.sort_by_key(|(_, modified, _)| *modified);
+
+    let now = SystemTime::now();
+    let mut total: u64 = files.iter().map(|(_, _, size)| size).sum();
+    let mut cleaned = Cleaned::default();
+    for (path, modified, size) in &files {
+        let expired = policy
+            .max_age
+            .is_some_and(|max_age| now.duration_since(*modified).is_ok_and(|age| age > max_age));
+        let over_quota = policy.max_bytes.is_some_and(|max_bytes| total > max_bytes);
+        if !expired && !over_quota {
+            // The rest are newer still, and already fit
+            break;
+        }
+        if fs::remove_file(path).is_ok() {
+            total -= size;
+            cleaned.removed += 1;
+            cleaned.freed_bytes += size;
+        }
+    }
+    cleaned
+}
+
+/// Applies every kind's policy, `overrides` taking the place of the
+/// defaults where set.
+pub fn enforce_all(overrides: &RetentionPolicy) -> Vec<(DataKind, std::io::Result<Cleaned>)> {
+    DataKind::ALL
+        .into_iter()
+        .map(|kind| {
+            let defaults = kind.default_policy();
+            let policy = RetentionPolicy {
+                max_age: overrides.max_age.or(defaults.max_age),
+                max_bytes: overrides.max_bytes.or(defaults.max_bytes),
+            };
+            (kind, enforce(kind, &policy))
+        })
+        .collect()
+}
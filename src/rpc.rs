@@ -0,0 +1,440 @@
+//! JSON-RPC 2.0 over stdio, for frontends that drive librarylink as a
+//! long-lived sidecar instead of running a CLI command per operation.
+//!
+//! Requests and responses are one JSON object per line. Session events and
+//! log messages arrive as `event` and `log` notifications. Launches, kills
+//! and shutdowns are recorded in the `audit` log under the parent process.
+
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+use crate::apps;
+use crate::audit::{self, Actor, Outcome};
+use crate::config;
+use crate::features;
+use crate::launch::LaunchOptions;
+use crate::matcher::MonitorScope;
+use crate::monitor::MonitorControl;
+use crate::report::{self, Event, Level, Reporter};
+use crate::session::SessionRecord;
+#[cfg(feature = "wts")]
+use crate::wts::SessionRequirement;
+
+pub(crate) const PARSE_ERROR: i64 = -32700;
+pub(crate) const INVALID_REQUEST: i64 = -32600;
+pub(crate) const METHOD_NOT_FOUND: i64 = -32601;
+pub(crate) const INVALID_PARAMS: i64 = -32602;
+/// Server-defined: the requested operation itself failed
+pub(crate) const OPERATION_FAILED: i64 = -32000;
+/// Server-defined: the client's token doesn't allow the method
+#[cfg(feature = "secret")]
+pub(crate) const FORBIDDEN: i64 = -32001;
+
+pub(crate) struct RpcError {
+    pub(crate) code: i64,
+    pub(crate) message: String,
+    pub(crate) data: Option<Value>,
+}
+
+impl RpcError {
+    pub(crate) fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    pub(crate) fn invalid_params(message: impl Into<String>) -> Self {
+        RpcError::new(INVALID_PARAMS, message)
+    }
+}
+
+/// Writes whole lines to stdout, so responses and notifications sent from
+/// different threads never interleave.
+#[derive(Clone)]
+struct Output {
+    stdout: Arc<Mutex<io::Stdout>>,
+}
+
+impl Output {
+    fn new() -> Self {
+        Output {
+            stdout: Arc::new(Mutex::new(io::stdout())),
+        }
+    }
+
+    fn send(&self, message: &Value) {
+        let mut stdout = self
+            .stdout
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let _ = writeln!(stdout, "{}", message);
+        let _ = stdout.flush();
+    }
+
+    fn notify(&self, method: &str, params: Value) {
+        self.send(&json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    /// Answers a request. Notifications (requests without an ID) get no reply.
+    fn respond(&self, id: Option<&Value>, result: Result<Value, RpcError>) {
+        let Some(id) = id else {
+            return;
+        };
+
+        self.send(&response(id, result));
+    }
+}
+
+/// Builds the response object answering the request with `id`.
+pub(crate) fn response(id: &Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => {
+            let mut error_object = json!({ "code": error.code, "message": error.message });
+            if let Some(data) = error.data {
+                error_object["data"] = data;
+            }
+            json!({ "jsonrpc": "2.0", "id": id, "error": error_object })
+        }
+    }
+}
+
+/// Turns output from code that uses the process-wide reporter into
+/// notifications, keeping stdout valid JSON-RPC.
+struct RpcReporter {
+    output: Output,
+}
+
+impl Reporter for RpcReporter {
+    fn message(&self, level: Level, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        self.output
+            .notify("log", json!({ "level": level, "text": text }));
+    }
+
+    fn event(&self, event: &Event) {
+        self.output
+            .notify("event", json!({ "sessionId": null, "event": event }));
+    }
+}
+
+/// A session launched over RPC. The `Session` itself lives on the thread
+/// forwarding its events; this keeps what other requests need.
+struct TrackedSession {
+    control: Arc<MonitorControl>,
+    kill_on_exit: bool,
+    running: bool,
+    record: Option<SessionRecord>,
+    /// IDs of `wait` requests to answer when the session ends
+    waiters: Vec<Value>,
+}
+
+#[derive(Clone, Default)]
+struct Sessions {
+    inner: Arc<Mutex<HashMap<String, TrackedSession>>>,
+}
+
+impl Sessions {
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, TrackedSession>> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Serves requests from stdin until it closes or `shutdown` is called.
+/// Sessions launched with `killOnExit` are killed on the way out.
+pub fn serve() {
+    let output = Output::new();
+    report::set_reporter(Box::new(RpcReporter {
+        output: output.clone(),
+    }));
+    let sessions = Sessions::default();
+    let actor = Actor::parent();
+
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let request: Value = match serde_json::from_str(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                output.respond(
+                    Some(&Value::Null),
+                    Err(RpcError::new(PARSE_ERROR, e.to_string())),
+                );
+                continue;
+            }
+        };
+
+        if !handle(&request, &output, &sessions, &actor) {
+            break;
+        }
+    }
+
+    for session in sessions.lock().values() {
+        if session.kill_on_exit && session.running {
+            let _ = session.control.kill();
+        }
+    }
+}
+
+/// Dispatches one request. Returns false when the server should stop.
+fn handle(request: &Value, output: &Output, sessions: &Sessions, actor: &Actor) -> bool {
+    let id = request.get("id");
+    let Some(method) = request.get("method").and_then(Value::as_str) else {
+        let id = id.or(Some(&Value::Null));
+        output.respond(
+            id,
+            Err(RpcError::new(
+                INVALID_REQUEST,
+                "Expected a request object with a method (batches are not supported)",
+            )),
+        );
+        return true;
+    };
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+    let result = match method {
+        "listApps" => list_apps(&params),
+        "launch" => {
+            // Launching can take a while (waitFor), so reply from a thread
+            let (id, output, sessions) = (id.cloned(), output.clone(), sessions.clone());
+            let actor = actor.clone();
+            thread::spawn(move || {
+                let result = launch(&params, &output, &sessions);
+                let aumid = params.get("aumid").and_then(Value::as_str);
+                audit_result(&actor, "launch", aumid.unwrap_or(""), &result);
+                output.respond(id.as_ref(), result);
+            });
+            return true;
+        }
+        "wait" => {
+            if let Err(error) = wait(id, &params, output, sessions) {
+                output.respond(id, Err(error));
+            }
+            return true;
+        }
+        "kill" => {
+            let result = kill(&params, sessions);
+            let session_id = params.get("sessionId").and_then(Value::as_str);
+            audit_result(actor, "kill", session_id.unwrap_or(""), &result);
+            result
+        }
+        "sessions" => Ok(list_sessions(sessions)),
+        "capabilities" => Ok(json!(features::detect())),
+        "shutdown" => {
+            audit::record("rpc", actor, "shutdown", "", Outcome::Ok);
+            output.respond(id, Ok(Value::Null));
+            return false;
+        }
+        _ => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("Unknown method '{}'", method),
+        )),
+    };
+
+    output.respond(id, result);
+    true
+}
+
+fn audit_result(actor: &Actor, action: &str, target: &str, result: &Result<Value, RpcError>) {
+    let outcome = match result {
+        Ok(_) => Outcome::Ok,
+        Err(error) => Outcome::Failed(&error.message),
+    };
+    audit::record("rpc", actor, action, target, outcome);
+}
+
+pub(crate) fn string_param<'a>(params: &'a Value, name: &str) -> Result<Option<&'a str>, RpcError> {
+    match params.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(value)) => Ok(Some(value)),
+        Some(_) => Err(RpcError::invalid_params(format!(
+            "'{}' must be a string",
+            name
+        ))),
+    }
+}
+
+fn session_id_param(params: &Value) -> Result<&str, RpcError> {
+    string_param(params, "sessionId")?
+        .ok_or_else(|| RpcError::invalid_params("'sessionId' is required"))
+}
+
+fn list_apps(params: &Value) -> Result<Value, RpcError> {
+    let include_frameworks = match params.get("includeFrameworks") {
+        None | Some(Value::Null) => false,
+        Some(value) => value
+            .as_bool()
+            .ok_or_else(|| RpcError::invalid_params("'includeFrameworks' must be a boolean"))?,
+    };
+    let filter = apps::AppFilter {
+        search: string_param(params, "search")?.map(str::to_string),
+        publisher: string_param(params, "publisher")?.map(str::to_string),
+        family: string_param(params, "family")?.map(str::to_string),
+        include_frameworks,
+    };
+    let apps = apps::list_apps_matching(&filter)
+        .map_err(|e| RpcError::new(OPERATION_FAILED, e.to_string()))?;
+    Ok(json!(apps))
+}
+
+fn launch(params: &Value, output: &Output, sessions: &Sessions) -> Result<Value, RpcError> {
+    let aumid = string_param(params, "aumid")?
+        .ok_or_else(|| RpcError::invalid_params("'aumid' is required"))?;
+    let mut options = config::current()
+        .defaults_for(aumid)
+        .apply(LaunchOptions::new(aumid));
+
+    if let Some(args) = params.get("args") {
+        let args = args
+            .as_array()
+            .and_then(|args| args.iter().map(Value::as_str).collect::<Option<Vec<_>>>())
+            .ok_or_else(|| RpcError::invalid_params("'args' must be an array of strings"))?;
+        options = options.args(args);
+    }
+    if let Some(executable) = string_param(params, "waitFor")? {
+        options = options.wait_for(executable);
+    }
+    if let Some(scope) = string_param(params, "scope")? {
+        let scope = MonitorScope::parse(scope).ok_or_else(|| {
+            RpcError::invalid_params("'scope' must be 'exact-dir', 'dir-tree' or 'package'")
+        })?;
+        options = options.scope(scope);
+    }
+    #[cfg(feature = "wts")]
+    if let Some(requirement) = string_param(params, "session")? {
+        let requirement = SessionRequirement::parse(requirement)
+            .ok_or_else(|| RpcError::invalid_params("'session' must be 'any' or 'console'"))?;
+        options = options.session(requirement);
+    }
+    if let Some(interval) = params.get("pollInterval") {
+        let interval = interval
+            .as_u64()
+            .filter(|&millis| millis > 0)
+            .ok_or_else(|| RpcError::invalid_params("'pollInterval' must be a positive number"))?;
+        options = options.poll_interval(Duration::from_millis(interval));
+    }
+    if let Some(close_launchers) = params.get("closeLaunchers") {
+        let close_launchers = close_launchers
+            .as_bool()
+            .ok_or_else(|| RpcError::invalid_params("'closeLaunchers' must be a boolean"))?;
+        options = options.close_launchers(close_launchers);
+    }
+    #[cfg(feature = "presentmon")]
+    if let Some(presentmon) = string_param(params, "presentmon")? {
+        options = options.presentmon(presentmon);
+    }
+    let kill_on_exit = params
+        .get("killOnExit")
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+
+    let mut session = options
+        .launch()
+        .map_err(|e| RpcError::new(OPERATION_FAILED, e.to_string()))?;
+    let session_id = session.id().to_string();
+    let pid = session.pid();
+
+    sessions.lock().insert(
+        session_id.clone(),
+        TrackedSession {
+            control: session.control(),
+            kill_on_exit,
+            running: true,
+            record: None,
+            waiters: Vec::new(),
+        },
+    );
+
+    // Forward the session's events until it ends, then answer pending waits
+    let (forward_id, output, sessions) = (session_id.clone(), output.clone(), sessions.clone());
+    thread::spawn(move || {
+        for event in session.events() {
+            output.notify("event", json!({ "sessionId": forward_id, "event": event }));
+        }
+        let record = session.wait();
+
+        let waiters = match sessions.lock().get_mut(&forward_id) {
+            Some(tracked) => {
+                tracked.running = false;
+                tracked.record = record.clone();
+                std::mem::take(&mut tracked.waiters)
+            }
+            None => Vec::new(),
+        };
+        for waiter in waiters {
+            output.respond(Some(&waiter), Ok(json!(record)));
+        }
+    });
+
+    Ok(json!({ "sessionId": session_id, "pid": pid }))
+}
+
+/// Answers immediately if the session already ended, otherwise when it does.
+fn wait(
+    id: Option<&Value>,
+    params: &Value,
+    output: &Output,
+    sessions: &Sessions,
+) -> Result<(), RpcError> {
+    let session_id = session_id_param(params)?;
+    let mut sessions = sessions.lock();
+    let tracked = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| RpcError::invalid_params(format!("Unknown session '{}'", session_id)))?;
+
+    if tracked.running {
+        if let Some(id) = id {
+            tracked.waiters.push(id.clone());
+        }
+    } else {
+        output.respond(id, Ok(json!(tracked.record)));
+    }
+    Ok(())
+}
+
+fn kill(params: &Value, sessions: &Sessions) -> Result<Value, RpcError> {
+    let session_id = session_id_param(params)?;
+    let control = sessions
+        .lock()
+        .get(session_id)
+        .map(|tracked| Arc::clone(&tracked.control))
+        .ok_or_else(|| RpcError::invalid_params(format!("Unknown session '{}'", session_id)))?;
+
+    control.kill().map_err(|e| RpcError {
+        code: OPERATION_FAILED,
+        message: e.to_string(),
+        data: serde_json::to_value(&e).ok(),
+    })?;
+    Ok(Value::Null)
+}
+
+fn list_sessions(sessions: &Sessions) -> Value {
+    let sessions: Vec<Value> = sessions
+        .lock()
+        .iter()
+        .map(|(session_id, tracked)| {
+            json!({
+                "sessionId": session_id,
+                "pid": tracked.control.tracked_pid(),
+                "state": tracked.control.state(),
+                "running": tracked.running,
+            })
+        })
+        .collect();
+    json!(sessions)
+}
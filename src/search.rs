@@ -0,0 +1,88 @@
+//! Matching what people type against app names, which are often not ASCII:
+//! case, diacritics, full- and half-width forms and hiragana against
+//! katakana are all ignored, so `pokemon` finds `Pokémon` and `ＦＦ` finds
+//! `FF`. Windows' own linguistic comparison does the folding.
+
+use windows::Win32::Foundation::{
+    ERROR_INVALID_FLAGS, ERROR_INVALID_PARAMETER, GetLastError, LPARAM,
+};
+use windows::Win32::Globalization::{
+    COMPARE_STRING_FLAGS, CSTR_EQUAL, CompareStringEx, FIND_FROMSTART, FindNLSStringEx,
+    LOCALE_NAME_INVARIANT, NORM_IGNORECASE, NORM_IGNOREKANATYPE, NORM_IGNORENONSPACE,
+    NORM_IGNOREWIDTH,
+};
+
+/// What [`contains`] and [`equals`] ignore.
+const FOLDING: u32 =
+    NORM_IGNORECASE.0 | NORM_IGNORENONSPACE.0 | NORM_IGNOREWIDTH.0 | NORM_IGNOREKANATYPE.0;
+
+/// Whether `text` contains `term`, ignoring case, diacritics, width and kana
+/// type. An empty term is in every text.
+pub fn contains(text: &str, term: &str) -> bool {
+    if term.is_empty() {
+        return true;
+    }
+    if text.is_empty() {
+        return false;
+    }
+    let source: Vec<u16> = text.encode_utf16().collect();
+    let value: Vec<u16> = term.encode_utf16().collect();
+    let found = unsafe {
+        FindNLSStringEx(
+            LOCALE_NAME_INVARIANT,
+            FIND_FROMSTART | FOLDING,
+            &source,
+            &value,
+            None,
+            None,
+            None,
+            LPARAM(0),
+        )
+    };
+    if found >= 0 {
+        return true;
+    }
+    // Not finding the term leaves no error; a string Windows can't compare
+    // falls back to comparing by case alone
+    match unsafe { GetLastError() } {
+        ERROR_INVALID_FLAGS | ERROR_INVALID_PARAMETER => {
+            text.to_lowercase().contains(&term.to_lowercase())
+        }
+        _ => false,
+    }
+}
+
+/// Whether `a` and `b` are the same name, ignoring what [`contains`] does.
+pub fn equals(a: &str, b: &str) -> bool {
+    let a: Vec<u16> = a.encode_utf16().collect();
+    let b: Vec<u16> = b.encode_utf16().collect();
+    let result = unsafe {
+        CompareStringEx(
+            LOCALE_NAME_INVARIANT,
+            COMPARE_STRING_FLAGS(FOLDING),
+            &a,
+            &b,
+            None,
+            None,
+            None,
+        )
+    };
+    if result.0 != 0 {
+        return result == CSTR_EQUAL;
+    }
+    String::from_utf16_lossy(&a).to_lowercase() == String::from_utf16_lossy(&b).to_lowercase()
+}
+
+/// Whether `text` contains every whitespace-separated word of `query`, as
+/// [`contains`] matches them.
+pub fn contains_words(text: &str, query: &str) -> bool {
+    query.split_whitespace().all(|word| contains(text, word))
+}
+
+/// Whether a package identity name such as `BandaiNamcoEntertainment.TalesofArise`
+/// matches `term` with its spaces left out, for titles whose display name is
+/// in another script than the query.
+pub fn matches_package_name(package_name: &str, term: &str) -> bool {
+    let term: String = term.split_whitespace().collect();
+    !term.is_empty() && contains(package_name, &term)
+}
@@ -0,0 +1,155 @@
+//! Credentials for integrations, kept out of the config file and the
+//! environment. Each secret is encrypted with DPAPI for the current Windows
+//! user and stored as `%APPDATA%\librarylink\secrets\<name>`, so a copy of
+//! the data directory, on a NAS or in a backup, is useless on any other
+//! account or machine.
+//!
+//! Integrations look a secret up with [`setting`] by the name they document,
+//! after their environment variable.
+
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use windows::Win32::Foundation::{HLOCAL, LocalFree};
+use windows::Win32::Security::Cryptography::{
+    CRYPT_INTEGER_BLOB, CRYPTPROTECT_UI_FORBIDDEN, CryptProtectData, CryptUnprotectData,
+};
+use windows::core::w;
+
+use crate::atomic_file;
+use crate::error::WinError;
+use crate::paths;
+
+/// Mixed into every encryption, so other software running as the user
+/// can't decrypt the files with a bare `CryptUnprotectData`.
+const ENTROPY: &[u8] = b"librarylink secret";
+
+fn secrets_dir() -> PathBuf {
+    paths::data_dir().join("secrets")
+}
+
+/// Checks that `name` can be a file name: letters, digits, `_`, `-` and `.`.
+pub fn validate_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Secret name '{}' may only use letters, digits, '_', '-' and '.'",
+            name
+        ))
+    }
+}
+
+/// Encrypts and stores `value` as `name`, replacing what it held.
+pub fn set(name: &str, value: &str) -> Result<(), Box<dyn Error>> {
+    validate_name(name)?;
+    let encrypted = protect(value.as_bytes())?;
+    atomic_file::write(&secrets_dir().join(name), encrypted)?;
+    Ok(())
+}
+
+/// The secret stored as `name`, or `None` if there isn't one.
+pub fn get(name: &str) -> Result<Option<String>, Box<dyn Error>> {
+    validate_name(name)?;
+    let encrypted = match fs::read(secrets_dir().join(name)) {
+        Ok(encrypted) => encrypted,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let value = unprotect(&encrypted)?;
+    Ok(Some(
+        String::from_utf8(value).map_err(|_| format!("Secret '{}' isn't text", name))?,
+    ))
+}
+
+/// Deletes the secret stored as `name`. Returns whether there was one.
+pub fn remove(name: &str) -> Result<bool, Box<dyn Error>> {
+    validate_name(name)?;
+    match fs::remove_file(secrets_dir().join(name)) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// The names of every stored secret, sorted.
+pub fn names() -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(secrets_dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| validate_name(name).is_ok() && !name.ends_with(".tmp"))
+        .collect();
+    names.sort();
+    names
+}
+
+/// A credential from the environment variable `variable` when it's set,
+/// or else the secret `name`. A secret that can't be decrypted, such as one
+/// copied from another machine, counts as unset.
+pub fn setting(variable: &str, name: &str) -> Option<String> {
+    env::var(variable)
+        .ok()
+        .filter(|value| !value.is_empty())
+        .or_else(|| get(name).ok().flatten().filter(|value| !value.is_empty()))
+}
+
+fn blob(data: &[u8]) -> CRYPT_INTEGER_BLOB {
+    CRYPT_INTEGER_BLOB {
+        cbData: data.len() as u32,
+        pbData: data.as_ptr() as *mut u8,
+    }
+}
+
+/// Copies DPAPI's output and frees it.
+unsafe fn take(output: CRYPT_INTEGER_BLOB) -> Vec<u8> {
+    unsafe {
+        let data = std::slice::from_raw_parts(output.pbData, output.cbData as usize).to_vec();
+        let _ = LocalFree(Some(HLOCAL(output.pbData.cast())));
+        data
+    }
+}
+
+fn protect(data: &[u8]) -> Result<Vec<u8>, WinError> {
+    let entropy = blob(ENTROPY);
+    let mut output = CRYPT_INTEGER_BLOB::default();
+    unsafe {
+        CryptProtectData(
+            &blob(data),
+            w!("librarylink secret"),
+            Some(&entropy),
+            None,
+            None,
+            CRYPTPROTECT_UI_FORBIDDEN,
+            &mut output,
+        )
+        .map_err(|e| WinError::new("CryptProtectData", &e))?;
+        Ok(take(output))
+    }
+}
+
+fn unprotect(data: &[u8]) -> Result<Vec<u8>, WinError> {
+    let entropy = blob(ENTROPY);
+    let mut output = CRYPT_INTEGER_BLOB::default();
+    unsafe {
+        CryptUnprotectData(
+            &blob(data),
+            None,
+            Some(&entropy),
+            None,
+            None,
+            CRYPTPROTECT_UI_FORBIDDEN,
+            &mut output,
+        )
+        .map_err(|e| WinError::new("CryptUnprotectData", &e))?;
+        Ok(take(output))
+    }
+}
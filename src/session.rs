@@ -0,0 +1,434 @@
+use std::fs;
+#[cfg(feature = "presentmon")]
+use std::path::Path;
+use std::path::PathBuf;
+#[cfg(feature = "presentmon")]
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "json")]
+use crate::atomic_file;
+use crate::compat::WindowsVersion;
+use crate::gpu::{self, GpuAdapter};
+use crate::matcher::MonitorTarget;
+#[cfg(feature = "json")]
+use crate::named_mutex::NamedMutex;
+use crate::paths;
+#[cfg(feature = "presentmon")]
+use crate::presentmon::FrameRecorder;
+use crate::process;
+use crate::report::warning;
+
+/// State of a monitoring session, persisted so a restarted librarylink can
+/// re-attach to a game that is still running.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+pub struct SessionState {
+    pub id: String,
+    pub aumid: String,
+    /// PID of the librarylink process that owns the session
+    pub owner_pid: u32,
+    pub owner_created: u64,
+    pub tracked_pid: u32,
+    pub tracked_created: u64,
+    pub target: MonitorTarget,
+    /// Unix timestamp (seconds) of the original launch
+    pub started_at: u64,
+    /// Other launches wait for (or terminate) this session
+    #[cfg_attr(feature = "json", serde(default))]
+    pub exclusive: bool,
+    /// Captures frame times of whichever process is tracked. Not persisted,
+    /// so resumed sessions aren't captured.
+    #[cfg(feature = "presentmon")]
+    #[cfg_attr(feature = "json", serde(skip))]
+    pub frames: Option<Arc<FrameRecorder>>,
+    /// Version of the app's package at launch
+    #[cfg_attr(feature = "json", serde(default))]
+    pub package_version: Option<String>,
+    /// The package version differs from the app's previous session
+    #[cfg_attr(feature = "json", serde(default))]
+    pub package_updated: bool,
+    /// Log holding a command's captured stdout and stderr
+    #[cfg_attr(feature = "json", serde(default))]
+    pub output_log: Option<PathBuf>,
+    /// What the session was launched on. Captured at launch, so a resumed
+    /// session keeps its own.
+    #[cfg_attr(feature = "json", serde(default))]
+    pub environment: Option<SessionEnvironment>,
+}
+
+/// The software a session ran on besides the game's package, so that when a
+/// game stops working, comparing sessions says what changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+pub struct SessionEnvironment {
+    pub librarylink_version: String,
+    #[cfg_attr(
+        feature = "json",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub windows: Option<WindowsVersion>,
+    #[cfg_attr(
+        feature = "json",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub gpus: Vec<GpuAdapter>,
+}
+
+impl SessionEnvironment {
+    /// The environment of the running process.
+    pub fn capture() -> Self {
+        SessionEnvironment {
+            librarylink_version: env!("CARGO_PKG_VERSION").to_string(),
+            windows: WindowsVersion::current().cloned(),
+            gpus: gpu::adapters(),
+        }
+    }
+}
+
+/// Frame pacing over a capture. The lows are the frame rates at the 99th and
+/// 99.9th percentile frame times.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrameStats {
+    pub frames: usize,
+    pub avg_fps: f64,
+    pub low_1_percent_fps: f64,
+    pub low_0_1_percent_fps: f64,
+    pub avg_frame_time_ms: f64,
+    pub max_frame_time_ms: f64,
+}
+
+/// A finished session, appended to the history file.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+pub struct SessionRecord {
+    pub id: String,
+    pub aumid: String,
+    pub started_at: u64,
+    pub ended_at: u64,
+    pub duration_secs: u64,
+    /// Frame pacing, for sessions captured with PresentMon
+    #[cfg_attr(
+        feature = "json",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub frames: Option<FrameStats>,
+    #[cfg_attr(
+        feature = "json",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub package_version: Option<String>,
+    #[cfg_attr(feature = "json", serde(default, skip_serializing_if = "is_false"))]
+    pub package_updated: bool,
+    #[cfg_attr(
+        feature = "json",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub output_log: Option<PathBuf>,
+    #[cfg_attr(
+        feature = "json",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub environment: Option<SessionEnvironment>,
+}
+
+#[cfg(feature = "json")]
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// Distinguishes sessions started by the same process within one second.
+static NEXT_SEQUENCE: AtomicU32 = AtomicU32::new(0);
+
+/// Held while the history is appended to or trimmed, by every librarylink
+/// process, so a trim can't drop a record appended while it ran.
+#[cfg(feature = "json")]
+const HISTORY_MUTEX: &str = r"Local\librarylink-history";
+
+pub(crate) fn sessions_dir() -> PathBuf {
+    paths::data_dir().join("sessions")
+}
+
+#[cfg(feature = "json")]
+pub(crate) fn history_path() -> PathBuf {
+    paths::data_dir().join("history.jsonl")
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl SessionState {
+    pub fn new(aumid: &str, tracked_pid: u32, target: MonitorTarget) -> Self {
+        let owner_pid = std::process::id();
+        let started_at = unix_now();
+        let id = match NEXT_SEQUENCE.fetch_add(1, Ordering::Relaxed) {
+            0 => format!("{}-{}", started_at, owner_pid),
+            sequence => format!("{}-{}-{}", started_at, owner_pid, sequence),
+        };
+
+        SessionState {
+            id,
+            aumid: aumid.to_string(),
+            owner_pid,
+            owner_created: process::get_process_creation_time(owner_pid).unwrap_or(0),
+            tracked_pid,
+            tracked_created: process::get_process_creation_time(tracked_pid).unwrap_or(0),
+            target,
+            started_at,
+            exclusive: false,
+            #[cfg(feature = "presentmon")]
+            frames: None,
+            package_version: None,
+            package_updated: false,
+            output_log: None,
+            environment: Some(SessionEnvironment::capture()),
+        }
+    }
+
+    fn path(&self) -> PathBuf {
+        sessions_dir().join(format!("{}.json", self.id))
+    }
+
+    /// Persists the session. Without the `json` feature sessions aren't
+    /// persisted and can't be resumed.
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        #[cfg(feature = "json")]
+        {
+            atomic_file::write(&self.path(), serde_json::to_string_pretty(self)?)?;
+        }
+        Ok(())
+    }
+
+    /// Records a new tracked process and persists the change.
+    pub fn track(&mut self, process_id: u32) {
+        self.tracked_pid = process_id;
+        self.tracked_created = process::get_process_creation_time(process_id).unwrap_or(0);
+        #[cfg(feature = "presentmon")]
+        if let Some(frames) = &self.frames {
+            frames.follow(process_id);
+        }
+        if let Err(e) = self.save() {
+            warning!("⚠️ Could not save session state: {}", e);
+        }
+    }
+
+    /// Captures frame times with `presentmon` from the tracked process on,
+    /// following each replacement until the session finishes.
+    #[cfg(feature = "presentmon")]
+    pub fn record_frames(&mut self, presentmon: &Path) {
+        let recorder = FrameRecorder::new(presentmon, &self.id);
+        recorder.follow(self.tracked_pid);
+        self.frames = Some(Arc::new(recorder));
+    }
+
+    /// Takes ownership of an orphaned session for the current process.
+    pub fn adopt(&mut self) {
+        self.owner_pid = std::process::id();
+        self.owner_created = process::get_process_creation_time(self.owner_pid).unwrap_or(0);
+        if let Err(e) = self.save() {
+            warning!("⚠️ Could not save session state: {}", e);
+        }
+    }
+
+    /// Whether the librarylink process that owns this session is still running.
+    pub fn owner_alive(&self) -> bool {
+        process::is_same_process_running(self.owner_pid, self.owner_created)
+    }
+
+    /// Whether the tracked game process is still the one that was recorded.
+    pub fn tracked_alive(&self) -> bool {
+        process::is_same_process_running(self.tracked_pid, self.tracked_created)
+    }
+
+    /// Removes the persisted state and appends the session to the history file.
+    pub fn finish(&self) -> SessionRecord {
+        let _ = fs::remove_file(self.path());
+
+        let ended_at = unix_now();
+        let record = SessionRecord {
+            id: self.id.clone(),
+            aumid: self.aumid.clone(),
+            started_at: self.started_at,
+            ended_at,
+            duration_secs: ended_at.saturating_sub(self.started_at),
+            #[cfg(feature = "presentmon")]
+            frames: self.frames.as_ref().and_then(|frames| frames.finish()),
+            #[cfg(not(feature = "presentmon"))]
+            frames: None,
+            package_version: self.package_version.clone(),
+            package_updated: self.package_updated,
+            output_log: self.output_log.clone(),
+            environment: self.environment.clone(),
+        };
+
+        if let Err(e) = append_history(&record) {
+            warning!("⚠️ Could not write session history: {}", e);
+        }
+
+        record
+    }
+}
+
+#[cfg(not(feature = "json"))]
+fn append_history(_record: &SessionRecord) -> Result<(), Box<dyn std::error::Error>> {
+    Ok(())
+}
+
+#[cfg(feature = "json")]
+fn append_history(record: &SessionRecord) -> Result<(), Box<dyn std::error::Error>> {
+    let line = format!("{}\n", serde_json::to_string(record)?);
+
+    let _guard = NamedMutex::acquire(HISTORY_MUTEX, None)?;
+    fs::create_dir_all(paths::data_dir())?;
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(history_path())?;
+    // A line cut short by a crash would swallow this one as well
+    let length = file.metadata()?.len();
+    let mut line = line;
+    if length > 0 {
+        let mut last = [0u8];
+        std::io::Seek::seek(&mut file, std::io::SeekFrom::Start(length - 1))?;
+        std::io::Read::read_exact(&mut file, &mut last)?;
+        if last[0] != b'\n' {
+            line.insert(0, '\n');
+        }
+    }
+    std::io::Write::write_all(&mut file, line.as_bytes())?;
+    file.sync_data()?;
+    Ok(())
+}
+
+/// Drops history records of sessions that ended more than `max_age` ago,
+/// then the oldest records until the file fits in `max_bytes`. Returns the
+/// number of records dropped and the bytes freed.
+#[cfg(feature = "json")]
+pub fn trim_history(
+    max_age: Option<std::time::Duration>,
+    max_bytes: Option<u64>,
+) -> std::io::Result<(usize, u64)> {
+    let _guard = NamedMutex::acquire(HISTORY_MUTEX, None).map_err(std::io::Error::other)?;
+    let Ok(contents) = fs::read_to_string(history_path()) else {
+        return Ok((0, 0));
+    };
+
+    let cutoff = max_age.map(|age| unix_now().saturating_sub(age.as_secs()));
+    let lines: Vec<&str> = contents.lines().filter(|line| !line.is_empty()).collect();
+    let mut kept: Vec<&str> = lines
+        .iter()
+        .copied()
+        .filter(
+            |line| match (cutoff, serde_json::from_str::<SessionRecord>(line)) {
+                (Some(cutoff), Ok(record)) => record.ended_at >= cutoff,
+                // Lines that don't parse aren't ours to judge
+                _ => true,
+            },
+        )
+        .collect();
+    if let Some(max_bytes) = max_bytes {
+        let mut size: u64 = kept.iter().map(|line| line.len() as u64 + 1).sum();
+        let mut dropped = 0;
+        while size > max_bytes && dropped < kept.len() {
+            size -= kept[dropped].len() as u64 + 1;
+            dropped += 1;
+        }
+        kept.drain(..dropped);
+    }
+
+    let removed = lines.len() - kept.len();
+    if removed == 0 {
+        return Ok((0, 0));
+    }
+    let trimmed: String = kept.iter().map(|line| format!("{}\n", line)).collect();
+    atomic_file::write_with_backup(&history_path(), &trimmed)?;
+    Ok((
+        removed,
+        (contents.len() as u64).saturating_sub(trimmed.len() as u64),
+    ))
+}
+
+/// Loads the finished sessions in the history file, oldest first, skipping
+/// lines that fail to parse.
+#[cfg(feature = "json")]
+pub fn load_history() -> Vec<SessionRecord> {
+    let Ok(contents) = fs::read_to_string(history_path()) else {
+        return Vec::new();
+    };
+    contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+#[cfg(not(feature = "json"))]
+pub fn load_history() -> Vec<SessionRecord> {
+    Vec::new()
+}
+
+/// The package version recorded for the most recent session of `aumid`.
+pub fn last_package_version(aumid: &str) -> Option<String> {
+    load_history()
+        .into_iter()
+        .rev()
+        .filter(|record| record.aumid == aumid)
+        .find_map(|record| record.package_version)
+}
+
+/// Loads every persisted session state, skipping files that fail to parse.
+#[cfg(feature = "json")]
+pub fn load_sessions() -> Vec<SessionState> {
+    let Ok(entries) = fs::read_dir(sessions_dir()) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+        .filter_map(|contents| serde_json::from_str(&contents).ok())
+        .collect()
+}
+
+#[cfg(not(feature = "json"))]
+pub fn load_sessions() -> Vec<SessionState> {
+    Vec::new()
+}
+
+/// The persisted sessions, oldest first, leaving out orphaned ones (whose
+/// librarylink process has gone away) unless `orphaned`.
+pub fn list_sessions(orphaned: bool) -> Vec<SessionState> {
+    let mut sessions: Vec<SessionState> = load_sessions()
+        .into_iter()
+        .filter(|state| orphaned || state.owner_alive())
+        .collect();
+    sessions.sort_by_key(|state| state.started_at);
+    sessions
+}
+
+/// The orphaned sessions `resume` can take over, or only the one with `id`.
+pub fn orphaned_sessions(id: Option<&str>) -> Vec<SessionState> {
+    load_sessions()
+        .into_iter()
+        .filter(|state| !state.owner_alive())
+        .filter(|state| id.is_none_or(|id| state.id == id))
+        .collect()
+}
+
+pub fn format_duration(total_secs: u64) -> String {
+    let hours = total_secs / 3600;
+    let minutes = (total_secs % 3600) / 60;
+    let seconds = total_secs % 60;
+    if hours > 0 {
+        format!("{}h {:02}m {:02}s", hours, minutes, seconds)
+    } else {
+        format!("{}m {:02}s", minutes, seconds)
+    }
+}
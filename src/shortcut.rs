@@ -0,0 +1,197 @@
+//! `make-shortcut`: Windows shortcuts to packaged apps, which otherwise can
+//! only be made by dragging them out of `shell:AppsFolder`. A `.lnk` opens
+//! the app itself, or runs `librarylink uwp-launch` so the launch is
+//! monitored; a `.url` opens a `librarylink://launch/` link.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use windows::Win32::System::Com::{CLSCTX_INPROC_SERVER, CoCreateInstance, IPersistFile};
+use windows::Win32::UI::Shell::Common::ITEMIDLIST;
+use windows::Win32::UI::Shell::{ILFree, IShellLinkW, SHParseDisplayName, ShellLink};
+use windows::core::{HSTRING, Interface};
+
+use crate::apps;
+use crate::error::WinError;
+use crate::icon;
+use crate::launch::ComApartment;
+use crate::paths;
+use crate::report::warning;
+
+/// The icon size kept for shortcuts, the largest Explorer shows.
+const ICON_SIZE: u32 = 256;
+
+/// What a shortcut runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShortcutTarget {
+    /// The app's entry in `shell:AppsFolder`, as the Start menu starts it
+    AppsFolder,
+    /// `librarylink uwp-launch <AUMID>`, monitoring the app
+    Wrapper,
+}
+
+/// The kind of shortcut file, by its extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShortcutKind {
+    /// A shell link, `.lnk`
+    Link,
+    /// An Internet shortcut, `.url`, which opens a `librarylink://` link
+    Url,
+}
+
+impl ShortcutKind {
+    /// The kind `path`'s extension names, if it's one of them.
+    pub fn of(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        match extension.as_str() {
+            "lnk" => Some(ShortcutKind::Link),
+            "url" => Some(ShortcutKind::Url),
+            _ => None,
+        }
+    }
+}
+
+/// A shortcut to write.
+#[derive(Debug, Clone)]
+pub struct Shortcut<'a> {
+    pub aumid: &'a str,
+    /// Shown as the `.lnk`'s comment
+    pub description: &'a str,
+    pub target: ShortcutTarget,
+    /// An `.ico` to show, or the target's own icon when `None`
+    pub icon: Option<PathBuf>,
+}
+
+/// Copies the app's logo to `%APPDATA%\librarylink\icons` as an ICO, where
+/// it survives package updates, for shortcuts to show. `None` if the package
+/// has no logo.
+pub fn keep_icon(aumid: &str) -> std::io::Result<Option<PathBuf>> {
+    let Some(asset) = icon::logo_for_size(aumid, ICON_SIZE) else {
+        return Ok(None);
+    };
+    let directory = paths::data_dir().join("icons");
+    fs::create_dir_all(&directory)?;
+    let kept = directory.join(format!("{}.ico", aumid.replace('!', "_")));
+    icon::write_logo(&asset, &kept)?;
+    Ok(Some(kept))
+}
+
+/// Writes `shortcut` to `out`, as the kind its extension names.
+pub fn write(shortcut: &Shortcut, out: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    // IPersistFile wants the full path
+    let out = std::path::absolute(out)?;
+    match ShortcutKind::of(&out) {
+        Some(ShortcutKind::Link) => write_link(shortcut, &out),
+        Some(ShortcutKind::Url) => write_url(shortcut, &out),
+        None => Err(format!("{} isn't a .lnk or .url file", out.display()).into()),
+    }
+}
+
+/// Writes a shortcut to `aumid` at `out` with the app's logo as its icon,
+/// named `name` or after the app inside `out` when it's a directory, and
+/// returns where it went.
+pub fn create(
+    aumid: &str,
+    out: PathBuf,
+    name: Option<String>,
+    target: ShortcutTarget,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let display_name = name.or_else(|| apps::display_name(aumid));
+    let out = if out.is_dir() {
+        let display_name = display_name.as_ref().ok_or_else(|| {
+            format!(
+                "Could not find an app named {}; pass --name, or a file name in --out",
+                aumid
+            )
+        })?;
+        // Characters file names can't hold
+        let file_name: String = display_name
+            .chars()
+            .map(|c| if r#"\/:*?"<>|"#.contains(c) { '_' } else { c })
+            .collect();
+        out.join(format!("{}.lnk", file_name.trim()))
+    } else if out.extension().is_none() {
+        out.with_extension("lnk")
+    } else {
+        out
+    };
+    if ShortcutKind::of(&out).is_none() {
+        return Err(format!("{} isn't a .lnk or .url file", out.display()).into());
+    }
+
+    let icon = match keep_icon(aumid) {
+        Ok(Some(icon)) => Some(icon),
+        Ok(None) => {
+            warning!("⚠️ No icon found in the app's package; the shortcut shows its target's");
+            None
+        }
+        Err(e) => {
+            warning!("⚠️ Could not copy the app's icon: {}", e);
+            None
+        }
+    };
+    let description = display_name.unwrap_or_else(|| aumid.to_string());
+    let shortcut = Shortcut {
+        aumid,
+        description: &description,
+        target,
+        icon,
+    };
+    write(&shortcut, &out).map_err(|e| format!("Could not create {}: {}", out.display(), e))?;
+    Ok(out)
+}
+
+fn write_link(shortcut: &Shortcut, out: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let _apartment = ComApartment::enter()?;
+    unsafe {
+        let link: IShellLinkW = CoCreateInstance(&ShellLink, None, CLSCTX_INPROC_SERVER)
+            .map_err(|e| WinError::new("CoCreateInstance(ShellLink)", &e))?;
+        match shortcut.target {
+            ShortcutTarget::AppsFolder => {
+                let name = HSTRING::from(format!(r"shell:AppsFolder\{}", shortcut.aumid));
+                let mut pidl: *mut ITEMIDLIST = std::ptr::null_mut();
+                SHParseDisplayName(&name, None, &mut pidl, 0, None)
+                    .map_err(|e| WinError::new("SHParseDisplayName", &e))?;
+                let set = link.SetIDList(pidl);
+                ILFree(Some(pidl));
+                set.map_err(|e| WinError::new("IShellLink::SetIDList", &e))?;
+            }
+            ShortcutTarget::Wrapper => {
+                let executable = std::env::current_exe()?;
+                link.SetPath(&HSTRING::from(executable.as_os_str()))
+                    .map_err(|e| WinError::new("IShellLink::SetPath", &e))?;
+                link.SetArguments(&HSTRING::from(format!("uwp-launch {}", shortcut.aumid)))
+                    .map_err(|e| WinError::new("IShellLink::SetArguments", &e))?;
+                if let Some(directory) = executable.parent() {
+                    link.SetWorkingDirectory(&HSTRING::from(directory.as_os_str()))
+                        .map_err(|e| WinError::new("IShellLink::SetWorkingDirectory", &e))?;
+                }
+            }
+        }
+        link.SetDescription(&HSTRING::from(shortcut.description))
+            .map_err(|e| WinError::new("IShellLink::SetDescription", &e))?;
+        if let Some(icon) = &shortcut.icon {
+            link.SetIconLocation(&HSTRING::from(icon.as_os_str()), 0)
+                .map_err(|e| WinError::new("IShellLink::SetIconLocation", &e))?;
+        }
+        let file: IPersistFile = link
+            .cast()
+            .map_err(|e| WinError::new("IShellLink::QueryInterface(IPersistFile)", &e))?;
+        file.Save(&HSTRING::from(out.as_os_str()), true)
+            .map_err(|e| WinError::new("IPersistFile::Save", &e))?;
+    }
+    Ok(())
+}
+
+/// An Internet shortcut holds a URL, so it always goes through librarylink's
+/// protocol handler, which the installer registers.
+fn write_url(shortcut: &Shortcut, out: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut contents = format!(
+        "[InternetShortcut]\r\nURL=librarylink://launch/{}\r\n",
+        shortcut.aumid
+    );
+    if let Some(icon) = &shortcut.icon {
+        contents.push_str(&format!("IconFile={}\r\nIconIndex=0\r\n", icon.display()));
+    }
+    fs::write(out, contents)?;
+    Ok(())
+}
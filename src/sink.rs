@@ -0,0 +1,300 @@
+//! Extra destinations that receive every event and message as JSON lines,
+//! alongside the console output, so several consumers (a streaming host, a
+//! logger, a dashboard) can follow the same launch at once.
+//!
+//! Pipe sinks are written from a thread of their own through a queue of
+//! [`PIPE_QUEUE_LINES`], so a reader that stops reading can't stall the
+//! launch; lines that don't fit are dropped.
+
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::net::UdpSocket;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::clock;
+use crate::report::{self, Event, Level, Reporter};
+
+/// Where [`SinkSpec::File`] rotates the log by default.
+pub const DEFAULT_ROTATE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// How many rotated files (`<path>.1` through `<path>.3`) are kept.
+const ROTATED_FILES: u32 = 3;
+
+/// Environment variable with sinks to add to every invocation, separated by
+/// `;`.
+pub const SINKS_VARIABLE: &str = "LIBRARYLINK_SINKS";
+
+/// How many lines a pipe sink holds for a reader that has fallen behind.
+pub const PIPE_QUEUE_LINES: usize = 1024;
+
+/// How long [`flush`] waits for a reader that has fallen behind.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Lines queued for pipe sinks and not yet written, for [`flush`].
+static PIPE_PENDING: (Mutex<usize>, Condvar) = (Mutex::new(0), Condvar::new());
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SinkSpec {
+    /// JSON lines on stdout, whatever the output mode
+    Stdout,
+    /// Appended to a file, rotated once it reaches the size
+    File { path: PathBuf, rotate_bytes: u64 },
+    /// `--log-file`: a file sink with every line stamped with the local time,
+    /// and debug messages included whatever the verbosity
+    Log(PathBuf),
+    /// Written to a named pipe another program created, such as
+    /// `\\.\pipe\dashboard`; reconnected whenever it goes away
+    Pipe(String),
+    /// One datagram per line to a `host:port`
+    Udp(String),
+}
+
+impl SinkSpec {
+    /// Parses a `--sink` value: `stdout`, `file:<path>`, `pipe:<name>` or
+    /// `udp:<host>:<port>`. A bare pipe name gets the `\\.\pipe\` prefix.
+    pub fn parse(value: &str) -> Option<Self> {
+        let (kind, target) = value.split_once(':').unwrap_or((value, ""));
+        match (kind, target) {
+            ("stdout", "") => Some(SinkSpec::Stdout),
+            ("file", path) if !path.is_empty() => Some(SinkSpec::File {
+                path: PathBuf::from(path),
+                rotate_bytes: DEFAULT_ROTATE_BYTES,
+            }),
+            ("pipe", name) if !name.is_empty() => {
+                Some(SinkSpec::Pipe(if name.starts_with(r"\\") {
+                    name.to_string()
+                } else {
+                    format!(r"\\.\pipe\{}", name)
+                }))
+            }
+            ("udp", address) if address.contains(':') => Some(SinkSpec::Udp(address.to_string())),
+            _ => None,
+        }
+    }
+
+    /// The sinks listed in `LIBRARYLINK_SINKS`, skipping ones that don't
+    /// parse.
+    pub fn from_env() -> Vec<SinkSpec> {
+        std::env::var(SINKS_VARIABLE)
+            .map(|value| {
+                value
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|spec| !spec.is_empty())
+                    .filter_map(SinkSpec::parse)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Display for SinkSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SinkSpec::Stdout => f.write_str("stdout"),
+            SinkSpec::File { path, .. } => write!(f, "file {}", path.display()),
+            SinkSpec::Log(path) => write!(f, "log file {}", path.display()),
+            SinkSpec::Pipe(name) => write!(f, "pipe {}", name),
+            SinkSpec::Udp(address) => write!(f, "udp {}", address),
+        }
+    }
+}
+
+enum Sink {
+    Stdout,
+    File {
+        path: PathBuf,
+        rotate_bytes: u64,
+        file: Option<File>,
+        written: u64,
+        timestamps: bool,
+    },
+    /// Lines for the thread that writes to the pipe
+    Pipe(SyncSender<String>),
+    Udp {
+        socket: UdpSocket,
+        address: String,
+    },
+}
+
+impl Sink {
+    fn open(spec: &SinkSpec) -> io::Result<Sink> {
+        Ok(match spec {
+            SinkSpec::Stdout => Sink::Stdout,
+            SinkSpec::File { path, rotate_bytes } => Sink::open_file(path, *rotate_bytes, false)?,
+            SinkSpec::Log(path) => Sink::open_file(path, DEFAULT_ROTATE_BYTES, true)?,
+            SinkSpec::Pipe(name) => Sink::open_pipe(name)?,
+            SinkSpec::Udp(address) => Sink::Udp {
+                socket: UdpSocket::bind("0.0.0.0:0")?,
+                address: address.clone(),
+            },
+        })
+    }
+
+    fn open_file(path: &std::path::Path, rotate_bytes: u64, timestamps: bool) -> io::Result<Sink> {
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let written = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+        Ok(Sink::File {
+            path: path.to_path_buf(),
+            rotate_bytes,
+            file: Some(file),
+            written,
+            timestamps,
+        })
+    }
+
+    fn open_pipe(name: &str) -> io::Result<Sink> {
+        let (lines, queue) = mpsc::sync_channel::<String>(PIPE_QUEUE_LINES);
+        let name = name.to_string();
+        thread::Builder::new()
+            .name("pipe sink".to_string())
+            .spawn(move || {
+                // The reader may not be listening yet
+                let mut connection: Option<File> = None;
+                for line in queue {
+                    if connection.is_none() {
+                        connection = OpenOptions::new().write(true).open(&name).ok();
+                    }
+                    if let Some(pipe) = &mut connection
+                        && writeln!(pipe, "{}", line).is_err()
+                    {
+                        // The reader went away; reconnect on the next line
+                        connection = None;
+                    }
+                    let (pending, written) = &PIPE_PENDING;
+                    *pending.lock().unwrap_or_else(|p| p.into_inner()) -= 1;
+                    written.notify_all();
+                }
+            })?;
+        Ok(Sink::Pipe(lines))
+    }
+
+    fn write_line(&mut self, line: &str) {
+        match self {
+            Sink::Stdout => println!("{}", line),
+            Sink::File {
+                path,
+                rotate_bytes,
+                file,
+                written,
+                timestamps,
+            } => {
+                let stamped;
+                let line = if *timestamps {
+                    stamped = format!("{} {}", clock::local_timestamp(), line);
+                    stamped.as_str()
+                } else {
+                    line
+                };
+                if *written + line.len() as u64 + 1 > *rotate_bytes && *written > 0 {
+                    // Close the log before renaming it
+                    *file = None;
+                    rotate(path);
+                    *written = 0;
+                }
+                if file.is_none() {
+                    *file = OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(&*path)
+                        .ok();
+                }
+                if let Some(handle) = file
+                    && writeln!(handle, "{}", line).is_ok()
+                {
+                    *written += line.len() as u64 + 1;
+                }
+            }
+            Sink::Pipe(lines) => {
+                let mut pending = PIPE_PENDING.0.lock().unwrap_or_else(|p| p.into_inner());
+                // A full queue drops the line rather than waiting on the reader
+                if lines.try_send(line.to_string()).is_ok() {
+                    *pending += 1;
+                }
+            }
+            Sink::Udp { socket, address } => {
+                let _ = socket.send_to(line.as_bytes(), &*address);
+            }
+        }
+    }
+}
+
+/// Shifts `<path>.1` to `<path>.2` and so on, dropping the oldest, and
+/// moves the log to `<path>.1`.
+fn rotate(path: &std::path::Path) {
+    let rotated = |index: u32| {
+        let mut name = path.as_os_str().to_owned();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    };
+    let _ = fs::remove_file(rotated(ROTATED_FILES));
+    for index in (1..ROTATED_FILES).rev() {
+        let _ = fs::rename(rotated(index), rotated(index + 1));
+    }
+    let _ = fs::rename(path, rotated(1));
+}
+
+/// Waits up to [`FLUSH_TIMEOUT`] for pipe sinks to write the lines they
+/// hold, so the last events aren't lost when the process exits.
+pub fn flush() {
+    let (pending, written) = &PIPE_PENDING;
+    let pending = pending.lock().unwrap_or_else(|p| p.into_inner());
+    let _ = written.wait_timeout_while(pending, FLUSH_TIMEOUT, |pending| *pending > 0);
+}
+
+/// Reports to `primary` and mirrors everything to the sinks as JSON lines.
+pub struct MirrorReporter {
+    primary: Box<dyn Reporter>,
+    sinks: Vec<Mutex<Sink>>,
+}
+
+impl MirrorReporter {
+    /// Opens the sinks. One that can't be opened fails the whole setup, so
+    /// a mistyped path isn't silently ignored.
+    pub fn new(primary: Box<dyn Reporter>, specs: &[SinkSpec]) -> Result<Self, String> {
+        let sinks = specs
+            .iter()
+            .map(|spec| {
+                Sink::open(spec)
+                    .map(Mutex::new)
+                    .map_err(|e| format!("Could not open sink {}: {}", spec, e))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(MirrorReporter { primary, sinks })
+    }
+
+    fn mirror(&self, line: &str) {
+        for sink in &self.sinks {
+            sink.lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .write_line(line);
+        }
+    }
+}
+
+impl Reporter for MirrorReporter {
+    fn message(&self, level: Level, text: &str) {
+        self.primary.message(level, text);
+        if let Some(line) = report::message_line(level, text) {
+            self.mirror(&line);
+        }
+    }
+
+    fn event(&self, event: &Event) {
+        self.primary.event(event);
+        if let Some(line) = report::event_line(event) {
+            self.mirror(&line);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+//! Snapshots of the process list for reproducing selection problems offline.
+//! `debug snapshot` records every process with its path and package identity,
+//! and `debug select` replays a snapshot through the same candidate checks the
+//! replacement search uses, so a user's snapshot attached to an issue shows a
+//! maintainer exactly what monitoring saw.
+
+use std::fs;
+use std::path::Path;
+
+use crate::matcher::MonitorTarget;
+use crate::monitor;
+use crate::process::{get_process_creation_time, get_process_info};
+use crate::session::unix_now;
+use crate::tree;
+
+/// One process as the monitor sees it.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ProcessRecord {
+    pub pid: u32,
+    pub parent_pid: u32,
+    pub name: String,
+    /// Full executable path, or `<Unknown>` when the process can't be queried
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package_full_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package_family_name: Option<String>,
+    /// Creation time as a FILETIME tick count
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<u64>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Snapshot {
+    /// Unix timestamp (seconds) of when the snapshot was taken
+    pub taken_at: u64,
+    pub processes: Vec<ProcessRecord>,
+}
+
+impl Snapshot {
+    /// Records every running process.
+    pub fn take() -> Result<Snapshot, Box<dyn std::error::Error>> {
+        let processes = tree::snapshot_processes()?
+            .into_iter()
+            .map(|entry| {
+                let path = get_process_info(entry.pid)
+                    .map_or_else(|| "<Unknown>".to_string(), |info| info.path);
+                ProcessRecord {
+                    pid: entry.pid,
+                    parent_pid: entry.parent_pid,
+                    name: entry.exe_name,
+                    path,
+                    package_full_name: tree::get_package_full_name(entry.pid),
+                    package_family_name: tree::get_package_family_name(entry.pid),
+                    created: get_process_creation_time(entry.pid),
+                }
+            })
+            .collect();
+        Ok(Snapshot {
+            taken_at: unix_now(),
+            processes,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Snapshot, Box<dyn std::error::Error>> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        Ok(serde_json::from_str(&contents).map_err(|e| format!("{}: {}", path.display(), e))?)
+    }
+
+    /// The processes that qualify for `target`, optionally restricted to a
+    /// `wait_for` executable name or pattern, in scan order: the first is
+    /// the one a replacement search would track.
+    pub fn select(&self, target: &MonitorTarget, executable: Option<&str>) -> Vec<&ProcessRecord> {
+        self.processes
+            .iter()
+            .filter(|process| {
+                monitor::is_candidate(target, executable, &process.name, &process.path, || {
+                    process.package_family_name.clone()
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,458 @@
+//! `--splash`: a full-screen window saying what librarylink is doing, for
+//! launches from Steam Big Picture or a streaming client, where the console
+//! can't be seen and a failed launch is otherwise just a black screen. It
+//! shows "Launching <app>…" with the current step, closes once the game
+//! shows a window, and keeps an error up until a key, a click or a gamepad
+//! button dismisses it.
+
+use std::sync::mpsc;
+use std::sync::{Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use windows::Win32::Foundation::{COLORREF, HWND, LPARAM, LRESULT, POINT, RECT, WPARAM};
+use windows::Win32::Graphics::Gdi::{
+    BeginPaint, CLEARTYPE_QUALITY, CLIP_DEFAULT_PRECIS, CreateFontW, CreateSolidBrush,
+    DEFAULT_CHARSET, DT_CENTER, DT_WORDBREAK, DeleteObject, DrawTextW, EndPaint, FW_NORMAL,
+    FW_SEMIBOLD, FillRect, HDC, InvalidateRect, MONITOR_DEFAULTTOPRIMARY, MonitorFromPoint,
+    OUT_DEFAULT_PRECIS, PAINTSTRUCT, SelectObject, SetBkMode, SetTextColor, TRANSPARENT,
+};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::UI::Input::XboxController::{XINPUT_STATE, XInputGetState, XUSER_MAX_COUNT};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW, GetClientRect, GetMessageW,
+    IDC_ARROW, LoadCursorW, MSG, PostMessageW, PostQuitMessage, RegisterClassW, SW_SHOW,
+    SetForegroundWindow, SetTimer, ShowWindow, TranslateMessage, WM_CLOSE, WM_DESTROY, WM_KEYDOWN,
+    WM_LBUTTONDOWN, WM_PAINT, WM_TIMER, WNDCLASSW, WS_EX_TOOLWINDOW, WS_POPUP,
+};
+use windows::core::{HSTRING, w};
+
+use crate::placement::{self, DpiAwareness};
+use crate::report::{Event, Level, Reporter};
+use crate::window;
+
+/// How long an error stays up when nobody dismisses it.
+const ERROR_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long the splash waits for the next step before closing anyway,
+/// such as when the game was started without a process to follow.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How often the window checks for the game's window and gamepad buttons.
+const TICK: Duration = Duration::from_millis(100);
+
+/// What the window shows. There's only ever one splash, so its window
+/// procedure reads this directly.
+struct Contents {
+    title: String,
+    detail: String,
+    error: bool,
+    /// The process whose window closes the splash
+    tracked_pid: Option<u32>,
+    /// When the splash last changed
+    updated: Option<Instant>,
+    /// Gamepad buttons held when the error appeared, which don't dismiss it
+    held_buttons: u16,
+}
+
+static CONTENTS: Mutex<Contents> = Mutex::new(Contents {
+    title: String::new(),
+    detail: String::new(),
+    error: false,
+    tracked_pid: None,
+    updated: None,
+    held_buttons: 0,
+});
+
+fn contents() -> MutexGuard<'static, Contents> {
+    CONTENTS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// The splash window, run on a thread of its own.
+struct Splash {
+    /// The window's handle as an integer, since `HWND` doesn't cross threads
+    hwnd: isize,
+    thread: JoinHandle<()>,
+}
+
+impl Splash {
+    fn open() -> Option<Splash> {
+        let (sender, receiver) = mpsc::channel();
+        let thread = thread::spawn(move || run_window(sender));
+        let hwnd = receiver.recv().ok().flatten()?;
+        Some(Splash { hwnd, thread })
+    }
+
+    fn redraw(&self) {
+        let _ = unsafe { InvalidateRect(Some(HWND(self.hwnd as *mut _)), None, true) };
+    }
+
+    fn close(self) {
+        let _ = unsafe {
+            PostMessageW(
+                Some(HWND(self.hwnd as *mut _)),
+                WM_CLOSE,
+                WPARAM(0),
+                LPARAM(0),
+            )
+        };
+        let _ = self.thread.join();
+    }
+
+    /// Waits for the error on screen to be dismissed or time out.
+    fn wait(self) {
+        let _ = self.thread.join();
+    }
+}
+
+fn run_window(sender: mpsc::Sender<Option<isize>>) {
+    // Physical pixels, so the window covers the whole monitor
+    let _dpi = DpiAwareness::enter();
+    let hwnd = unsafe { create_window() };
+    let _ = sender.send(hwnd.map(|hwnd| hwnd.0 as isize));
+    let Some(hwnd) = hwnd else {
+        return;
+    };
+    unsafe {
+        let _ = ShowWindow(hwnd, SW_SHOW);
+        // Launched from Steam, librarylink may take the foreground over Big Picture
+        let _ = SetForegroundWindow(hwnd);
+        SetTimer(Some(hwnd), 1, TICK.as_millis() as u32, None);
+        let mut message = MSG::default();
+        while GetMessageW(&mut message, None, 0, 0).as_bool() {
+            let _ = TranslateMessage(&message);
+            DispatchMessageW(&message);
+        }
+    }
+}
+
+unsafe fn create_window() -> Option<HWND> {
+    unsafe {
+        let instance = GetModuleHandleW(None).ok()?;
+        let class = WNDCLASSW {
+            lpfnWndProc: Some(window_procedure),
+            hInstance: instance.into(),
+            hCursor: LoadCursorW(None, IDC_ARROW).unwrap_or_default(),
+            lpszClassName: w!("LibraryLinkSplash"),
+            ..Default::default()
+        };
+        // Fails harmlessly when a previous splash already registered it
+        RegisterClassW(&class);
+
+        let monitor = MonitorFromPoint(POINT { x: 0, y: 0 }, MONITOR_DEFAULTTOPRIMARY);
+        let bounds = placement::monitor_info(monitor)?.monitorInfo.rcMonitor;
+        CreateWindowExW(
+            WS_EX_TOOLWINDOW,
+            w!("LibraryLinkSplash"),
+            w!("librarylink"),
+            WS_POPUP,
+            bounds.left,
+            bounds.top,
+            bounds.right - bounds.left,
+            bounds.bottom - bounds.top,
+            None,
+            None,
+            Some(instance.into()),
+            None,
+        )
+        .ok()
+    }
+}
+
+unsafe extern "system" fn window_procedure(
+    hwnd: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match message {
+            WM_PAINT => {
+                paint(hwnd);
+                LRESULT(0)
+            }
+            WM_TIMER => {
+                if should_close() {
+                    let _ = DestroyWindow(hwnd);
+                }
+                LRESULT(0)
+            }
+            WM_KEYDOWN | WM_LBUTTONDOWN if contents().error => {
+                let _ = DestroyWindow(hwnd);
+                LRESULT(0)
+            }
+            WM_CLOSE => {
+                let _ = DestroyWindow(hwnd);
+                LRESULT(0)
+            }
+            WM_DESTROY => {
+                PostQuitMessage(0);
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(hwnd, message, wparam, lparam),
+        }
+    }
+}
+
+/// Whether the splash has done its job: the game showed a window, an error
+/// was dismissed with a gamepad or timed out, or nothing has happened for a
+/// while.
+fn should_close() -> bool {
+    let mut contents = contents();
+    let elapsed = contents
+        .updated
+        .map_or(Duration::ZERO, |updated| updated.elapsed());
+    if contents.error {
+        let buttons = gamepad_buttons();
+        // Only a press dismisses, not a button still held from before
+        let pressed = buttons & !contents.held_buttons != 0;
+        contents.held_buttons = buttons;
+        return pressed || elapsed >= ERROR_TIMEOUT;
+    }
+    elapsed >= IDLE_TIMEOUT
+        || contents
+            .tracked_pid
+            .is_some_and(|pid| window::main_window(pid).is_some())
+}
+
+/// The buttons held on every connected controller.
+fn gamepad_buttons() -> u16 {
+    (0..XUSER_MAX_COUNT)
+        .filter_map(|index| {
+            let mut state = XINPUT_STATE::default();
+            (unsafe { XInputGetState(index, &mut state) } == 0).then_some(state.Gamepad.wButtons.0)
+        })
+        .fold(0, |held, buttons| held | buttons)
+}
+
+unsafe fn paint(hwnd: HWND) {
+    unsafe {
+        let mut paint = PAINTSTRUCT::default();
+        let hdc = BeginPaint(hwnd, &mut paint);
+        let mut bounds = RECT::default();
+        let _ = GetClientRect(hwnd, &mut bounds);
+        let background = CreateSolidBrush(rgb(16, 16, 16));
+        FillRect(hdc, &bounds, background);
+        let _ = DeleteObject(background.into());
+
+        let (title, detail, error) = {
+            let contents = contents();
+            (
+                contents.title.clone(),
+                contents.detail.clone(),
+                contents.error,
+            )
+        };
+        let height = bounds.bottom - bounds.top;
+        let width = bounds.right - bounds.left;
+        // The title a little above the middle, the detail under it
+        let mut title_area = RECT {
+            left: bounds.left + width / 10,
+            top: bounds.top + height * 2 / 5,
+            right: bounds.right - width / 10,
+            bottom: bounds.top + height / 2,
+        };
+        draw_text(
+            hdc,
+            &title,
+            &mut title_area,
+            height / 14,
+            FW_SEMIBOLD.0 as i32,
+            rgb(240, 240, 240),
+        );
+        let mut detail_area = RECT {
+            top: title_area.bottom,
+            bottom: bounds.bottom - height / 10,
+            ..title_area
+        };
+        let color = if error {
+            rgb(255, 120, 110)
+        } else {
+            rgb(170, 170, 170)
+        };
+        draw_text(
+            hdc,
+            &detail,
+            &mut detail_area,
+            height / 30,
+            FW_NORMAL.0 as i32,
+            color,
+        );
+        let _ = EndPaint(hwnd, &paint);
+    }
+}
+
+unsafe fn draw_text(
+    hdc: HDC,
+    text: &str,
+    area: &mut RECT,
+    size: i32,
+    weight: i32,
+    color: COLORREF,
+) {
+    unsafe {
+        let font = CreateFontW(
+            size,
+            0,
+            0,
+            0,
+            weight,
+            0,
+            0,
+            0,
+            DEFAULT_CHARSET,
+            OUT_DEFAULT_PRECIS,
+            CLIP_DEFAULT_PRECIS,
+            CLEARTYPE_QUALITY,
+            0,
+            &HSTRING::from("Segoe UI"),
+        );
+        let previous = SelectObject(hdc, font.into());
+        SetTextColor(hdc, color);
+        SetBkMode(hdc, TRANSPARENT);
+        let mut text: Vec<u16> = text.encode_utf16().collect();
+        DrawTextW(hdc, &mut text, area, DT_CENTER | DT_WORDBREAK);
+        SelectObject(hdc, previous);
+        let _ = DeleteObject(font.into());
+    }
+}
+
+fn rgb(red: u8, green: u8, blue: u8) -> COLORREF {
+    COLORREF(red as u32 | (green as u32) << 8 | (blue as u32) << 16)
+}
+
+/// Reports to `primary`, and shows launch progress and errors in the splash
+/// window.
+pub struct SplashReporter {
+    primary: Box<dyn Reporter>,
+    splash: Mutex<Option<Splash>>,
+}
+
+impl SplashReporter {
+    pub fn new(primary: Box<dyn Reporter>) -> Self {
+        SplashReporter {
+            primary,
+            splash: Mutex::new(None),
+        }
+    }
+
+    /// Changes what the splash shows, opening it if `open` and it isn't
+    /// already. Returns whether it's showing.
+    fn show(&self, open: bool, update: impl FnOnce(&mut Contents)) -> bool {
+        let mut splash = self.splash.lock().unwrap_or_else(|p| p.into_inner());
+        {
+            let mut contents = contents();
+            if splash.is_none() {
+                if !open {
+                    return false;
+                }
+                contents.title = "librarylink".to_string();
+                contents.detail.clear();
+                contents.error = false;
+                contents.tracked_pid = None;
+            }
+            update(&mut contents);
+            contents.updated = Some(Instant::now());
+        }
+        if splash.is_none() {
+            *splash = Splash::open();
+        }
+        match &*splash {
+            Some(splash) => {
+                splash.redraw();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn close(&self) {
+        let splash = self.splash.lock().unwrap_or_else(|p| p.into_inner()).take();
+        if let Some(splash) = splash {
+            splash.close();
+        }
+    }
+
+    /// Shows `message` as an error and waits until it's dismissed, since
+    /// librarylink usually exits right after, taking the window with it.
+    fn fail(&self, message: &str) {
+        let held_buttons = gamepad_buttons();
+        let shown = self.show(true, |contents| {
+            contents.detail = format!("{}\n\nPress any button to close", message);
+            contents.error = true;
+            contents.held_buttons = held_buttons;
+        });
+        if shown {
+            let splash = self.splash.lock().unwrap_or_else(|p| p.into_inner()).take();
+            if let Some(splash) = splash {
+                splash.wait();
+            }
+        }
+    }
+}
+
+impl Reporter for SplashReporter {
+    fn message(&self, level: Level, text: &str) {
+        self.primary.message(level, text);
+        if level == Level::Error {
+            self.fail(text.trim_start_matches("❌").trim());
+        }
+    }
+
+    fn event(&self, event: &Event) {
+        self.primary.event(event);
+        match event {
+            Event::LaunchRequested { aumid } => {
+                // Until the app's own name is known
+                let name = aumid
+                    .rsplit(['\\', '/'])
+                    .next()
+                    .unwrap_or(aumid)
+                    .to_string();
+                self.show(true, |contents| {
+                    contents.title = format!("Launching {}…", name);
+                    contents.detail.clear();
+                });
+            }
+            Event::AppFound {
+                display_name: Some(name),
+                ..
+            } => {
+                self.show(false, |contents| {
+                    contents.title = format!("Launching {}…", name)
+                });
+            }
+            Event::DependencyStarting { name } | Event::DependencyWaiting { name, .. } => {
+                self.show(false, |contents| {
+                    contents.detail = format!("Waiting for {}…", name)
+                });
+            }
+            Event::PackageDeploying { .. } => {
+                self.show(false, |contents| {
+                    contents.detail =
+                        "Waiting for the Store to finish updating the game…".to_string()
+                });
+            }
+            Event::Launched { pid }
+            | Event::MonitoringStarted { pid, .. }
+            | Event::ReplacementFound { pid, .. } => {
+                let pid = *pid;
+                self.show(false, |contents| {
+                    contents.detail = "Waiting for the game's window…".to_string();
+                    contents.tracked_pid = Some(pid);
+                });
+            }
+            Event::Error { context, error } => {
+                // Often followed by a fallback, so shown without waiting
+                let text = format!("{}: {}", context, error);
+                self.show(false, |contents| contents.detail = text);
+            }
+            Event::GameReady { .. }
+            | Event::WindowPlaced { .. }
+            | Event::WindowModeChanged { .. }
+            | Event::SessionEnded { .. } => self.close(),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,401 @@
+//! Adds librarylink launches to a Steam user's library as non-Steam games,
+//! by editing the `shortcuts.vdf` Steam keeps them in. Steam reads the file
+//! at startup and writes its own copy back on exit, so it has to be closed
+//! for the change to stick.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use windows::Win32::System::Registry::{
+    HKEY_CURRENT_USER, RRF_RT_REG_DWORD, RRF_RT_REG_SZ, RegGetValueW,
+};
+use windows::core::HSTRING;
+
+use crate::atomic_file;
+use crate::paths;
+use crate::process::find_processes_by_name;
+use crate::report::warning;
+use crate::vdf::{self, Map, Value};
+use crate::{apps, icon};
+
+const STEAM_KEY: &str = r"Software\Valve\Steam";
+const ACTIVE_PROCESS_KEY: &str = r"Software\Valve\Steam\ActiveProcess";
+
+/// Where Steam is installed, from the path it records at every start.
+pub fn install_dir() -> Option<PathBuf> {
+    read_string(STEAM_KEY, "SteamPath").map(PathBuf::from)
+}
+
+/// Whether Steam is running, in which case it would overwrite the edit.
+pub fn is_running() -> bool {
+    !find_processes_by_name("steam.exe").is_empty()
+}
+
+/// A Steam account's local data.
+#[derive(Debug, Clone)]
+pub struct SteamUser {
+    /// The account's 32-bit ID, as named by its `userdata` folder
+    pub id: u32,
+    pub shortcuts: PathBuf,
+}
+
+/// The account `id`, or without one the account signed in to Steam, or the
+/// only account that has signed in on this machine.
+pub fn user(id: Option<u32>) -> Result<SteamUser, Box<dyn std::error::Error>> {
+    let steam = install_dir().ok_or("Steam doesn't appear to be installed")?;
+    let userdata = steam.join("userdata");
+    let known: Vec<u32> = fs::read_dir(&userdata)
+        .map_err(|e| format!("Could not read {}: {}", userdata.display(), e))?
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+        // 0 is the anonymous account's folder
+        .filter(|&id| id != 0)
+        .collect();
+
+    let id = match id.or_else(|| read_dword(ACTIVE_PROCESS_KEY, "ActiveUser").filter(|&id| id != 0))
+    {
+        Some(id) if known.contains(&id) => id,
+        Some(id) => return Err(format!("Steam account {} has no data on this machine", id).into()),
+        None => match known.as_slice() {
+            [id] => *id,
+            [] => return Err("No Steam account has signed in on this machine".into()),
+            _ => {
+                let ids: Vec<String> = known.iter().map(u32::to_string).collect();
+                return Err(format!(
+                    "Several Steam accounts have signed in here ({}); pick one with --steam-user",
+                    ids.join(", ")
+                )
+                .into());
+            }
+        },
+    };
+    Ok(SteamUser {
+        id,
+        shortcuts: userdata
+            .join(id.to_string())
+            .join("config")
+            .join("shortcuts.vdf"),
+    })
+}
+
+/// A non-Steam game entry.
+#[derive(Debug, Clone)]
+pub struct Shortcut {
+    pub name: String,
+    pub executable: PathBuf,
+    pub start_dir: PathBuf,
+    pub launch_options: String,
+    pub icon: Option<PathBuf>,
+}
+
+impl Shortcut {
+    fn quoted_executable(&self) -> String {
+        format!("\"{}\"", self.executable.display())
+    }
+
+    /// The ID Steam derives for the entry, which its artwork and controller
+    /// configurations are keyed on.
+    pub fn app_id(&self) -> u32 {
+        crc32(format!("{}{}", self.quoted_executable(), self.name).as_bytes()) | 0x8000_0000
+    }
+}
+
+/// What [`add_shortcut`] did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShortcutChange {
+    Added {
+        app_id: u32,
+    },
+    /// An entry with the same command line was there already and now has the
+    /// new name and icon
+    Updated {
+        app_id: u32,
+    },
+}
+
+/// Adds `shortcut` to the user's library, or updates the entry that already
+/// runs the same command. The previous file is kept as `shortcuts.vdf.bak`.
+pub fn add_shortcut(
+    user: &SteamUser,
+    shortcut: &Shortcut,
+) -> Result<ShortcutChange, Box<dyn std::error::Error>> {
+    let mut root = match fs::read(&user.shortcuts) {
+        Ok(bytes) => vdf::parse(&bytes)
+            .map_err(|e| format!("{} is damaged: {}", user.shortcuts.display(), e))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Map::new(),
+        Err(e) => return Err(format!("Could not read {}: {}", user.shortcuts.display(), e).into()),
+    };
+    if vdf::get(&root, "shortcuts").is_none() {
+        root.push(("shortcuts".to_string(), Value::Map(Map::new())));
+    }
+    let Some((_, Value::Map(entries))) = root
+        .iter_mut()
+        .find(|(key, _)| key.eq_ignore_ascii_case("shortcuts"))
+    else {
+        return Err(format!("{} has no shortcuts list", user.shortcuts.display()).into());
+    };
+
+    let executable = shortcut.quoted_executable();
+    let existing = entries.iter_mut().find_map(|(_, entry)| match entry {
+        Value::Map(fields)
+            if field(fields, "Exe").is_some_and(|exe| exe.eq_ignore_ascii_case(&executable))
+                && field(fields, "LaunchOptions") == Some(shortcut.launch_options.as_str()) =>
+        {
+            Some(fields)
+        }
+        _ => None,
+    });
+    let change = match existing {
+        Some(fields) => {
+            // Keep the existing ID so the entry's artwork and play time stay
+            let app_id = match vdf::get(fields, "appid") {
+                Some(Value::Int(app_id)) => *app_id,
+                _ => shortcut.app_id(),
+            };
+            set(fields, "AppName", Value::String(shortcut.name.clone()));
+            set(
+                fields,
+                "StartDir",
+                Value::String(quoted_dir(&shortcut.start_dir)),
+            );
+            set(fields, "icon", Value::String(icon_field(shortcut)));
+            ShortcutChange::Updated { app_id }
+        }
+        None => {
+            let app_id = shortcut.app_id();
+            let index = next_index(entries);
+            entries.push((index, Value::Map(new_entry(shortcut, app_id))));
+            ShortcutChange::Added { app_id }
+        }
+    };
+
+    atomic_file::write_with_backup(&user.shortcuts, vdf::write(&root))
+        .map_err(|e| format!("Could not write {}: {}", user.shortcuts.display(), e))?;
+    Ok(change)
+}
+
+/// What [`add_app`] added.
+#[derive(Debug, Clone)]
+pub struct AddedApp {
+    pub user: SteamUser,
+    pub shortcut: Shortcut,
+    pub change: ShortcutChange,
+}
+
+/// Adds a non-Steam game entry to Steam account `steam_user`'s library (see
+/// [`user`]) that runs `uwp-launch <AUMID>` through this librarylink. It's
+/// named `name`, or after the app, and iconed after the app.
+pub fn add_app(
+    aumid: &str,
+    name: Option<String>,
+    steam_user: Option<u32>,
+) -> Result<AddedApp, Box<dyn std::error::Error>> {
+    let name = name.or_else(|| apps::display_name(aumid)).ok_or_else(|| {
+        format!(
+            "Could not find an app named {}; pass --name to add it anyway",
+            aumid
+        )
+    })?;
+    let user = user(steam_user)?;
+    if is_running() {
+        return Err(
+            "Steam is running and would overwrite the change when it exits; close Steam and try again"
+                .into(),
+        );
+    }
+    let executable = std::env::current_exe()
+        .map_err(|e| format!("Could not find librarylink's own path: {}", e))?;
+
+    let mut shortcut = Shortcut {
+        name,
+        start_dir: executable
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default(),
+        executable,
+        launch_options: format!("uwp-launch {}", aumid),
+        icon: None,
+    };
+    match icon::best_logo(aumid) {
+        Some(logo) => match keep_icon(&logo, shortcut.app_id()) {
+            Ok(kept) => shortcut.icon = Some(kept),
+            Err(e) => warning!("⚠️ Could not copy the app's icon: {}", e),
+        },
+        None => warning!("⚠️ No icon found in the app's package; Steam will show its default"),
+    }
+
+    let change = add_shortcut(&user, &shortcut)
+        .map_err(|e| format!("Could not update Steam's shortcuts: {}", e))?;
+    Ok(AddedApp {
+        user,
+        shortcut,
+        change,
+    })
+}
+
+/// Copies `icon` out of the package, whose install path changes with every
+/// update, to librarylink's data directory.
+pub fn keep_icon(icon: &Path, app_id: u32) -> std::io::Result<PathBuf> {
+    let directory = paths::data_dir().join("icons");
+    fs::create_dir_all(&directory)?;
+    let extension = icon
+        .extension()
+        .map_or("png".into(), |extension| extension.to_string_lossy());
+    let kept = directory.join(format!("{}.{}", app_id, extension));
+    fs::copy(icon, &kept)?;
+    Ok(kept)
+}
+
+fn new_entry(shortcut: &Shortcut, app_id: u32) -> Map {
+    let string = |value: &str| Value::String(value.to_string());
+    vec![
+        ("appid".to_string(), Value::Int(app_id)),
+        ("AppName".to_string(), string(&shortcut.name)),
+        ("Exe".to_string(), string(&shortcut.quoted_executable())),
+        (
+            "StartDir".to_string(),
+            string(&quoted_dir(&shortcut.start_dir)),
+        ),
+        ("icon".to_string(), string(&icon_field(shortcut))),
+        ("ShortcutPath".to_string(), string("")),
+        (
+            "LaunchOptions".to_string(),
+            string(&shortcut.launch_options),
+        ),
+        ("IsHidden".to_string(), Value::Int(0)),
+        ("AllowDesktopConfig".to_string(), Value::Int(1)),
+        ("AllowOverlay".to_string(), Value::Int(1)),
+        ("OpenVR".to_string(), Value::Int(0)),
+        ("Devkit".to_string(), Value::Int(0)),
+        ("DevkitGameID".to_string(), string("")),
+        ("DevkitOverrideAppID".to_string(), Value::Int(0)),
+        ("LastPlayTime".to_string(), Value::Int(0)),
+        ("FlatpakAppID".to_string(), string("")),
+        ("tags".to_string(), Value::Map(Map::new())),
+    ]
+}
+
+fn quoted_dir(directory: &Path) -> String {
+    format!("\"{}\"", directory.display())
+}
+
+fn icon_field(shortcut: &Shortcut) -> String {
+    shortcut
+        .icon
+        .as_ref()
+        .map(|icon| icon.display().to_string())
+        .unwrap_or_default()
+}
+
+fn field<'a>(fields: &'a Map, key: &str) -> Option<&'a str> {
+    vdf::get(fields, key).and_then(Value::as_str)
+}
+
+/// The key for a new entry of the shortcuts list, which numbers them from
+/// 0. Entries removed from the middle leave gaps, so the count may already
+/// be taken.
+fn next_index(entries: &Map) -> String {
+    entries
+        .iter()
+        .filter_map(|(key, _)| key.parse::<u64>().ok())
+        .max()
+        .map_or(0, |last| last + 1)
+        .to_string()
+}
+
+/// Replaces `key`'s value, keeping the key's existing casing and position.
+fn set(fields: &mut Map, key: &str, value: Value) {
+    match fields
+        .iter_mut()
+        .find(|(name, _)| name.eq_ignore_ascii_case(key))
+    {
+        Some((_, existing)) => *existing = value,
+        None => fields.push((key.to_string(), value)),
+    }
+}
+
+/// CRC-32 (IEEE), as Steam uses for shortcut IDs.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn read_string(key: &str, value: &str) -> Option<String> {
+    let (key, value) = (HSTRING::from(key), HSTRING::from(value));
+    unsafe {
+        let mut length = 0u32;
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            &key,
+            &value,
+            RRF_RT_REG_SZ,
+            None,
+            None,
+            Some(&mut length),
+        )
+        .ok()
+        .ok()?;
+        let mut buffer = vec![0u16; (length as usize).div_ceil(2)];
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            &key,
+            &value,
+            RRF_RT_REG_SZ,
+            None,
+            Some(buffer.as_mut_ptr().cast()),
+            Some(&mut length),
+        )
+        .ok()
+        .ok()?;
+        let end = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+        Some(String::from_utf16_lossy(&buffer[..end]))
+    }
+}
+
+fn read_dword(key: &str, value: &str) -> Option<u32> {
+    let mut data = 0u32;
+    let mut length = size_of::<u32>() as u32;
+    unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            &HSTRING::from(key),
+            &HSTRING::from(value),
+            RRF_RT_REG_DWORD,
+            None,
+            Some((&mut data as *mut u32).cast()),
+            Some(&mut length),
+        )
+        .ok()
+        .ok()?;
+    }
+    Some(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(keys: &[&str]) -> Map {
+        keys.iter()
+            .map(|key| (key.to_string(), Value::Map(Map::new())))
+            .collect()
+    }
+
+    #[test]
+    fn next_index_follows_the_highest_key() {
+        assert_eq!(next_index(&Map::new()), "0");
+        assert_eq!(next_index(&entries(&["0", "1"])), "2");
+        // After the entry for "1" was removed in Steam
+        assert_eq!(next_index(&entries(&["0", "2"])), "3");
+        assert_eq!(next_index(&entries(&["7", "x"])), "8");
+    }
+}
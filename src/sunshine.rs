@@ -0,0 +1,250 @@
+//! Keeps a Sunshine or Apollo host's stream metadata accurate for launches it
+//! didn't start. Sunshine names a stream after the app entry the client
+//! picked, usually "Desktop" when the game was started from the host's Start
+//! menu, so for the length of a session that entry is renamed after the game
+//! and named back when it ends, through the web API behind Sunshine's own
+//! configuration page.
+//!
+//! Configured through the environment, so every launch command picks it up:
+//! `LIBRARYLINK_SUNSHINE_USER` and `LIBRARYLINK_SUNSHINE_PASSWORD` (the web
+//! UI's credentials) turn it on; `LIBRARYLINK_SUNSHINE_URL` and
+//! `LIBRARYLINK_SUNSHINE_APP` override the address and the entry renamed.
+//! The password can be stored with `secret set sunshine_password` instead.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use serde_json::{Value, json};
+
+use crate::atomic_file;
+use crate::http::{self, Request};
+use crate::paths;
+use crate::secret;
+
+pub const URL_VARIABLE: &str = "LIBRARYLINK_SUNSHINE_URL";
+pub const USER_VARIABLE: &str = "LIBRARYLINK_SUNSHINE_USER";
+pub const PASSWORD_VARIABLE: &str = "LIBRARYLINK_SUNSHINE_PASSWORD";
+pub const APP_VARIABLE: &str = "LIBRARYLINK_SUNSHINE_APP";
+
+/// The stored secret used when [`PASSWORD_VARIABLE`] isn't set.
+pub const PASSWORD_SECRET_NAME: &str = "sunshine_password";
+
+/// Where Sunshine's web UI listens by default.
+pub const DEFAULT_URL: &str = "https://localhost:47990";
+
+/// The entry streams started outside Sunshine usually run under.
+pub const DEFAULT_APP: &str = "Desktop";
+
+#[derive(Debug, Clone)]
+pub struct SunshineConfig {
+    pub url: String,
+    pub username: String,
+    pub password: String,
+    /// Name of the app entry to rename for the session
+    pub app: String,
+}
+
+impl SunshineConfig {
+    /// The configuration from the environment, or `None` when no
+    /// credentials are set.
+    pub fn from_env() -> Option<Self> {
+        let non_empty = |name| env::var(name).ok().filter(|value| !value.is_empty());
+        Some(SunshineConfig {
+            url: non_empty(URL_VARIABLE)
+                .unwrap_or_else(|| DEFAULT_URL.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            username: non_empty(USER_VARIABLE)?,
+            password: secret::setting(PASSWORD_VARIABLE, PASSWORD_SECRET_NAME)?,
+            app: non_empty(APP_VARIABLE).unwrap_or_else(|| DEFAULT_APP.to_string()),
+        })
+    }
+
+    fn request<'a>(&self, body: Option<&'a [u8]>) -> Request<'a> {
+        let mut headers = vec![(
+            "Authorization",
+            http::basic_auth(&self.username, &self.password),
+        )];
+        if body.is_some() {
+            headers.push(("Content-Type", "application/json".to_string()));
+        }
+        Request {
+            headers,
+            body,
+            // Sunshine serves its UI with a self-signed certificate, which is
+            // only trusted on this machine, where nobody can sit in between
+            insecure: http::is_loopback(&self.url),
+        }
+    }
+
+    fn apps(&self) -> Result<Vec<Value>, Box<dyn std::error::Error>> {
+        let response = http::send(
+            "GET",
+            &format!("{}/api/apps", self.url),
+            &self.request(None),
+        )?;
+        if !response.is_success() {
+            return Err(format!("Sunshine answered HTTP {} listing apps", response.status).into());
+        }
+        let listing: Value = serde_json::from_str(&response.body)?;
+        Ok(listing
+            .get("apps")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    /// Renames the first entry called `from` to `to`, and returns which
+    /// entry it was.
+    fn rename_named(&self, from: &str, to: &str) -> Result<Entry, Box<dyn std::error::Error>> {
+        let apps = self.apps()?;
+        let index = apps
+            .iter()
+            .position(|app| name_of(app) == Some(from))
+            .ok_or_else(|| format!("Sunshine has no app named '{}'", from))?;
+        let entry = Entry {
+            index,
+            uuid: uuid_of(&apps[index]),
+        };
+        self.rename(&apps, index, to)?;
+        Ok(entry)
+    }
+
+    /// Renames `entry` from `from` back to `to`, unless it has since been
+    /// removed or renamed by someone else.
+    fn rename_back(
+        &self,
+        entry: &Entry,
+        from: &str,
+        to: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let apps = self.apps()?;
+        // Positions shift when entries are added or removed; uuids don't
+        let index = match &entry.uuid {
+            Some(uuid) => apps
+                .iter()
+                .position(|app| uuid_of(app).as_ref() == Some(uuid)),
+            None => Some(entry.index).filter(|&index| index < apps.len()),
+        }
+        .filter(|&index| name_of(&apps[index]) == Some(from))
+        .ok_or_else(|| format!("Sunshine's '{}' entry was changed during the session", from))?;
+        self.rename(&apps, index, to)
+    }
+
+    /// Renames the entry at `index` in `apps` to `to`, keeping the rest of it.
+    fn rename(
+        &self,
+        apps: &[Value],
+        index: usize,
+        to: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let from = name_of(&apps[index]).unwrap_or_default();
+        let mut entry = apps[index].clone();
+        entry["name"] = json!(to);
+        entry["index"] = json!(index);
+        let body = serde_json::to_vec(&entry)?;
+        let response = http::send(
+            "POST",
+            &format!("{}/api/apps", self.url),
+            &self.request(Some(&body)),
+        )?;
+        if !response.is_success() {
+            return Err(format!(
+                "Sunshine answered HTTP {} renaming '{}'",
+                response.status, from
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+fn name_of(app: &Value) -> Option<&str> {
+    app.get("name").and_then(Value::as_str)
+}
+
+fn uuid_of(app: &Value) -> Option<String> {
+    app.get("uuid").and_then(Value::as_str).map(str::to_string)
+}
+
+/// The app entry a session renamed, so the one named back is the same even
+/// if the user has another entry by the game's name.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct Entry {
+    /// Position in the app list, for Sunshine versions without uuids
+    index: usize,
+    /// Apollo and newer Sunshine versions give each entry one
+    uuid: Option<String>,
+}
+
+/// A rename waiting to be undone, kept on disk so one left behind by a
+/// crashed session is undone by the next.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct PendingRename {
+    original: String,
+    shown: String,
+    entry: Entry,
+}
+
+pub(crate) fn pending_path() -> PathBuf {
+    paths::data_dir().join("sunshine.json")
+}
+
+/// The app entry renamed for a running session.
+#[derive(Debug)]
+pub struct Announcement {
+    config: SunshineConfig,
+    shown: String,
+    /// The entry renamed, if the game's name differs from the entry's
+    entry: Option<Entry>,
+}
+
+/// Renames the configured entry after `name` for the session.
+pub fn announce(
+    config: &SunshineConfig,
+    name: &str,
+) -> Result<Announcement, Box<dyn std::error::Error>> {
+    undo_pending(config);
+    let mut entry = None;
+    if name != config.app {
+        let renamed = config.rename_named(&config.app, name)?;
+        let pending = PendingRename {
+            original: config.app.clone(),
+            shown: name.to_string(),
+            entry: renamed.clone(),
+        };
+        atomic_file::write(&pending_path(), serde_json::to_string(&pending)?)?;
+        entry = Some(renamed);
+    }
+    Ok(Announcement {
+        config: config.clone(),
+        shown: name.to_string(),
+        entry,
+    })
+}
+
+impl Announcement {
+    /// Names the entry back once the session has ended.
+    pub fn restore(self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(entry) = &self.entry {
+            self.config
+                .rename_back(entry, &self.shown, &self.config.app)?;
+        }
+        let _ = fs::remove_file(pending_path());
+        Ok(())
+    }
+}
+
+/// Undoes a rename a previous session didn't get to.
+fn undo_pending(config: &SunshineConfig) {
+    let Some(pending) = fs::read_to_string(pending_path())
+        .ok()
+        .and_then(|contents| serde_json::from_str::<PendingRename>(&contents).ok())
+    else {
+        return;
+    };
+    // The user may have renamed or removed the entry since
+    let _ = config.rename_back(&pending.entry, &pending.shown, &pending.original);
+    let _ = fs::remove_file(pending_path());
+}
@@ -0,0 +1,808 @@
+//! System settings changed for the length of a session, with the original
+//! values restored on every way out: the session ending, Ctrl+C, the console
+//! closing, signing out or shutting down (see [`install_exit_handler`]), and
+//! crashes, since snapshots are persisted and the next guard restores any
+//! whose owner is gone.
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, Once, Weak};
+use std::thread;
+use windows::Win32::Foundation::{HLOCAL, HWND, LPARAM, LRESULT, LocalFree, WPARAM};
+use windows::Win32::Graphics::Gdi::{
+    CDS_TYPE, ChangeDisplaySettingsExW, DEVMODEW, DISP_CHANGE_BADMODE, DISP_CHANGE_FAILED,
+    DISP_CHANGE_RESTART, DISP_CHANGE_SUCCESSFUL, DM_DISPLAYFREQUENCY, DM_PELSHEIGHT, DM_PELSWIDTH,
+    ENUM_CURRENT_SETTINGS, EnumDisplaySettingsW,
+};
+use windows::Win32::System::Console::{
+    CTRL_BREAK_EVENT, CTRL_C_EVENT, CTRL_CLOSE_EVENT, CTRL_LOGOFF_EVENT, CTRL_SHUTDOWN_EVENT,
+    SetConsoleCtrlHandler,
+};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::System::Power::{PowerGetActiveScheme, PowerSetActiveScheme};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, MSG, RegisterClassW,
+    WINDOW_EX_STYLE, WM_ENDSESSION, WM_QUERYENDSESSION, WNDCLASSW, WS_OVERLAPPED,
+};
+use windows::core::{BOOL, GUID, HSTRING, PCWSTR, w};
+
+#[cfg(feature = "json")]
+use crate::atomic_file;
+use crate::audio;
+use crate::client::{self, ClientDisplay};
+use crate::error::WinError;
+use crate::patch::{ConfigFile, FileFormat, FilePatch};
+use crate::paths;
+use crate::placement;
+#[cfg(feature = "json")]
+use crate::process;
+use crate::registry::{self, RegistryData, RegistryValue};
+use crate::report::{self, Event, warning};
+
+/// Power plans that can be named instead of given as a GUID.
+const POWER_PLANS: &[(&str, &str)] = &[
+    ("balanced", "381b4222-f694-41f0-9685-ff5bb260df2e"),
+    ("high-performance", "8c5e7fda-e8bf-4a96-9a85-a6e23a8c635c"),
+    ("power-saver", "a1841308-3541-4fab-bc81-f71556f20b4a"),
+    ("ultimate", "e9a42b02-d5df-448d-aa00-03f14749eb61"),
+];
+
+/// Settings to apply for a session, as the `[system]` table of a profile.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "json", derive(serde::Deserialize))]
+#[cfg_attr(feature = "json", serde(deny_unknown_fields))]
+pub struct SystemSettings {
+    /// A power plan GUID, or `balanced`, `high-performance`, `power-saver`
+    /// or `ultimate`
+    #[cfg_attr(feature = "json", serde(default))]
+    pub power_plan: Option<String>,
+    #[cfg_attr(feature = "json", serde(default))]
+    pub display: Option<DisplayMode>,
+    /// HDR on or off, for `display`'s device or the primary display
+    #[cfg_attr(feature = "json", serde(default))]
+    pub hdr: Option<bool>,
+    /// The default playback device, by endpoint ID or exact name as
+    /// `list-audio-devices` shows them
+    #[cfg_attr(feature = "json", serde(default))]
+    pub audio_device: Option<String>,
+    /// Registry values to set, all or none
+    #[cfg_attr(feature = "json", serde(default))]
+    pub registry: Vec<RegistryValue>,
+    /// Config file values to set, all or none
+    #[cfg_attr(feature = "json", serde(default))]
+    pub files: Vec<FilePatch>,
+}
+
+impl SystemSettings {
+    pub fn is_empty(&self) -> bool {
+        self.power_plan.is_none()
+            && self.display.is_none()
+            && self.hdr.is_none()
+            && self.audio_device.is_none()
+            && self.registry.is_empty()
+            && self.files.is_empty()
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "json", serde(deny_unknown_fields))]
+pub struct DisplayMode {
+    /// Display device such as `\\.\DISPLAY2`; the primary display if unset
+    #[cfg_attr(feature = "json", serde(default))]
+    pub device: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    /// Hz; the display's current rate if unset
+    #[cfg_attr(feature = "json", serde(default))]
+    pub refresh_rate: Option<u32>,
+}
+
+impl DisplayMode {
+    /// Parses a `--resolution` value: `1920x1080`, `1920x1080@120`, or
+    /// `client` for the streaming client's mode. A mode without a rate
+    /// takes the client's frame rate when a client is streaming.
+    pub fn parse(value: &str) -> Result<DisplayMode, String> {
+        let client = ClientDisplay::from_environment();
+        if value.trim().eq_ignore_ascii_case("client") {
+            let client = client.ok_or_else(|| {
+                format!(
+                    "No streaming client to take the resolution from; {} and {} aren't set",
+                    client::WIDTH_VARIABLE,
+                    client::HEIGHT_VARIABLE
+                )
+            })?;
+            return Ok(DisplayMode {
+                device: None,
+                width: client.width,
+                height: client.height,
+                refresh_rate: client.fps,
+            });
+        }
+
+        let invalid = || {
+            format!(
+                "Invalid resolution '{}'; expected WIDTHxHEIGHT, WIDTHxHEIGHT@HZ or client",
+                value
+            )
+        };
+        let (size, refresh_rate) = match value.split_once('@') {
+            Some((size, rate)) => {
+                let rate = rate.trim();
+                let rate = rate.strip_suffix("Hz").unwrap_or(rate);
+                let rate = rate
+                    .parse()
+                    .ok()
+                    .filter(|&rate| rate > 0)
+                    .ok_or_else(invalid)?;
+                (size, Some(rate))
+            }
+            None => (value, client.and_then(|client| client.fps)),
+        };
+        let (width, height) = size.split_once(['x', 'X']).ok_or_else(invalid)?;
+        let (width, height): (u32, u32) = (
+            width.trim().parse().map_err(|_| invalid())?,
+            height.trim().parse().map_err(|_| invalid())?,
+        );
+        if width == 0 || height == 0 {
+            return Err(invalid());
+        }
+        Ok(DisplayMode {
+            device: None,
+            width,
+            height,
+            refresh_rate,
+        })
+    }
+}
+
+/// A setting's value from before the session changed it.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "json", serde(tag = "setting", rename_all = "snake_case"))]
+enum SavedSetting {
+    PowerPlan {
+        scheme: String,
+    },
+    DisplayMode {
+        mode: DisplayMode,
+    },
+    Hdr {
+        device: Option<String>,
+        enabled: bool,
+    },
+    /// The default playback device's endpoint ID
+    AudioDevice {
+        id: String,
+    },
+    /// `previous` is unset when the value didn't exist, and is deleted again
+    RegistryValue {
+        key: String,
+        name: String,
+        previous: Option<RegistryData>,
+    },
+    /// `previous` is the value as written in the file, or unset when the key
+    /// didn't exist and is removed again
+    FileValue {
+        path: PathBuf,
+        format: FileFormat,
+        key: String,
+        previous: Option<String>,
+    },
+}
+
+impl SavedSetting {
+    fn describe(&self) -> String {
+        match self {
+            SavedSetting::PowerPlan { scheme } => format!("power plan {}", scheme),
+            SavedSetting::DisplayMode { mode } => format!("display mode {}", describe_mode(mode)),
+            SavedSetting::Hdr { device, enabled } => describe_hdr(device.as_deref(), *enabled),
+            SavedSetting::AudioDevice { id } => format!("default playback device {}", id),
+            SavedSetting::RegistryValue { key, name, .. } => {
+                format!("registry value {}\\{}", key, name)
+            }
+            SavedSetting::FileValue { path, key, .. } => {
+                format!("{} in {}", key, path.display())
+            }
+        }
+    }
+
+    fn restore(&self) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            SavedSetting::PowerPlan { scheme } => Ok(set_power_plan(scheme)?),
+            SavedSetting::DisplayMode { mode } => Ok(set_display_mode(mode)?),
+            SavedSetting::Hdr { device, enabled } => {
+                Ok(placement::set_hdr(device.as_deref(), *enabled)?)
+            }
+            SavedSetting::AudioDevice { id } => Ok(audio::set_default_render_device(id)?),
+            SavedSetting::RegistryValue {
+                key,
+                name,
+                previous: Some(data),
+            } => registry::write(key, name, data),
+            SavedSetting::RegistryValue {
+                key,
+                name,
+                previous: None,
+            } => registry::delete(key, name),
+            SavedSetting::FileValue {
+                path,
+                format,
+                key,
+                previous,
+            } => {
+                let mut file = ConfigFile::open(path, *format)?;
+                match previous {
+                    Some(raw) => file.set(key, raw)?,
+                    None => file.remove(key)?,
+                }
+                Ok(file.save()?)
+            }
+        }
+    }
+}
+
+/// What's persisted for crash recovery.
+#[cfg(feature = "json")]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct Snapshot {
+    owner_pid: u32,
+    owner_created: u64,
+    saved: Vec<SavedSetting>,
+}
+
+struct GuardState {
+    id: String,
+    saved: Mutex<Vec<SavedSetting>>,
+}
+
+impl GuardState {
+    fn path(&self) -> PathBuf {
+        snapshots_dir().join(format!("{}.json", self.id))
+    }
+
+    /// Persists the current snapshot. Without the `json` feature snapshots
+    /// only live in memory and aren't recovered after a crash.
+    fn save(&self, saved: &[SavedSetting]) {
+        #[cfg(feature = "json")]
+        {
+            let owner_pid = std::process::id();
+            let snapshot = Snapshot {
+                owner_pid,
+                owner_created: process::get_process_creation_time(owner_pid).unwrap_or(0),
+                saved: saved.to_vec(),
+            };
+            let result = serde_json::to_string_pretty(&snapshot)
+                .map_err(|e| e.to_string())
+                .and_then(|json| atomic_file::write(&self.path(), json).map_err(|e| e.to_string()));
+            if let Err(e) = result {
+                warning!("⚠️ Could not save the system state snapshot: {}", e);
+            }
+        }
+        #[cfg(not(feature = "json"))]
+        let _ = saved;
+    }
+
+    /// Restores every saved setting, newest first. Runs once; later calls
+    /// find nothing left to restore.
+    fn restore(&self) {
+        let saved = std::mem::take(
+            &mut *self
+                .saved
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        );
+        restore_settings(&saved);
+        let _ = fs::remove_file(self.path());
+    }
+}
+
+/// Guards that haven't restored yet, for the console exit handler.
+static ACTIVE: Mutex<Vec<Weak<GuardState>>> = Mutex::new(Vec::new());
+
+static NEXT_GUARD: AtomicU32 = AtomicU32::new(0);
+
+pub(crate) fn snapshots_dir() -> PathBuf {
+    paths::data_dir().join("system-state")
+}
+
+fn restore_settings(saved: &[SavedSetting]) {
+    for setting in saved.iter().rev() {
+        match setting.restore() {
+            Ok(()) => report::emit(Event::SettingRestored {
+                setting: setting.describe(),
+            }),
+            Err(e) => warning!("⚠️ Could not restore {}: {}", setting.describe(), e),
+        }
+    }
+}
+
+/// Snapshots settings before changing them and puts them back when dropped
+/// or restored, whichever comes first.
+pub struct SystemStateGuard {
+    state: Arc<GuardState>,
+}
+
+impl SystemStateGuard {
+    /// Creates an empty guard, first restoring snapshots left behind by
+    /// librarylink processes that didn't get to restore them.
+    pub fn new() -> Self {
+        restore_orphaned();
+
+        let state = Arc::new(GuardState {
+            id: format!(
+                "{}-{}",
+                std::process::id(),
+                NEXT_GUARD.fetch_add(1, Ordering::Relaxed)
+            ),
+            saved: Mutex::new(Vec::new()),
+        });
+        let mut active = ACTIVE
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        active.retain(|guard| guard.strong_count() > 0);
+        active.push(Arc::downgrade(&state));
+        SystemStateGuard { state }
+    }
+
+    /// Applies the settings, recording each original value first. Stops at
+    /// the first failure; whatever was applied is still restored later.
+    pub fn apply(&self, settings: &SystemSettings) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(plan) = &settings.power_plan {
+            let scheme = resolve_power_plan(plan)?;
+            self.record(SavedSetting::PowerPlan {
+                scheme: get_power_plan()?,
+            });
+            set_power_plan(&scheme)?;
+            report::emit(Event::SettingApplied {
+                setting: format!("power plan {}", plan),
+            });
+        }
+
+        if let Some(mode) = &settings.display {
+            self.record(SavedSetting::DisplayMode {
+                mode: get_display_mode(mode.device.as_deref())?,
+            });
+            set_display_mode(mode)?;
+            report::emit(Event::SettingApplied {
+                setting: format!("display mode {}", describe_mode(mode)),
+            });
+        }
+
+        if let Some(enabled) = settings.hdr {
+            let device = settings
+                .display
+                .as_ref()
+                .and_then(|mode| mode.device.clone());
+            let previous = placement::hdr_enabled(device.as_deref())?;
+            if previous != enabled {
+                self.record(SavedSetting::Hdr {
+                    device: device.clone(),
+                    enabled: previous,
+                });
+                placement::set_hdr(device.as_deref(), enabled)?;
+                report::emit(Event::SettingApplied {
+                    setting: describe_hdr(device.as_deref(), enabled),
+                });
+            }
+        }
+
+        if let Some(name) = &settings.audio_device {
+            let device = audio::find_render_device(name)?;
+            if !device.default {
+                // Without a default device there's nothing to switch back to
+                if let Some(id) = audio::default_render_device()? {
+                    self.record(SavedSetting::AudioDevice { id });
+                }
+                audio::set_default_render_device(&device.id)?;
+                report::emit(Event::SettingApplied {
+                    setting: format!("default playback device {}", device.name),
+                });
+            }
+        }
+
+        if !settings.registry.is_empty() {
+            let checkpoint = self.saved_count();
+            if let Err(e) = self.apply_registry(&settings.registry) {
+                self.roll_back_to(checkpoint);
+                return Err(e);
+            }
+        }
+
+        if !settings.files.is_empty() {
+            let checkpoint = self.saved_count();
+            if let Err(e) = self.apply_files(&settings.files) {
+                self.roll_back_to(checkpoint);
+                return Err(e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sets each registry value, checking every key before changing any.
+    fn apply_registry(&self, values: &[RegistryValue]) -> Result<(), Box<dyn std::error::Error>> {
+        for value in values {
+            registry::validate_key(&value.key)?;
+        }
+        for value in values {
+            self.record(SavedSetting::RegistryValue {
+                key: value.key.clone(),
+                name: value.name.clone(),
+                previous: registry::read(&value.key, &value.name)
+                    .map_err(|e| format!("Could not read {}: {}", value, e))?,
+            });
+            registry::write(&value.key, &value.name, &value.data)
+                .map_err(|e| format!("Could not set {}: {}", value, e))?;
+            report::emit(Event::SettingApplied {
+                setting: value.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Edits each file, reading every one and finding every key in it before
+    /// changing any.
+    fn apply_files(&self, patches: &[FilePatch]) -> Result<(), Box<dyn std::error::Error>> {
+        let mut files = Vec::new();
+        for patch in patches {
+            let file = ConfigFile::open(&patch.resolved_path(), patch.file_format()?)?;
+            for key in patch.set.keys() {
+                file.get(key)
+                    .map_err(|e| format!("{} in {}: {}", key, file.path.display(), e))?;
+            }
+            files.push((patch, file));
+        }
+        for (patch, mut file) in files {
+            let mut changed = Vec::new();
+            for (key, value) in &patch.set {
+                let raw = value.raw(file.format);
+                let previous = file.get(key)?;
+                if previous.as_deref() == Some(raw.as_str()) {
+                    continue;
+                }
+                if patch.revert {
+                    self.record(SavedSetting::FileValue {
+                        path: file.path.clone(),
+                        format: file.format,
+                        key: key.clone(),
+                        previous,
+                    });
+                }
+                file.set(key, &raw).map_err(|e| {
+                    format!("Could not set {} in {}: {}", key, file.path.display(), e)
+                })?;
+                changed.push(key);
+            }
+            if changed.is_empty() {
+                continue;
+            }
+            file.save()?;
+            for key in changed {
+                report::emit(Event::SettingApplied {
+                    setting: format!("{} in {}", key, file.path.display()),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn saved_count(&self) -> usize {
+        self.state
+            .saved
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .len()
+    }
+
+    /// Restores the settings recorded after the first `checkpoint`, leaving
+    /// earlier ones for the session's end.
+    fn roll_back_to(&self, checkpoint: usize) {
+        let rolled_back = {
+            let mut saved = self
+                .state
+                .saved
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let checkpoint = checkpoint.min(saved.len());
+            let rolled_back = saved.split_off(checkpoint);
+            self.state.save(&saved);
+            rolled_back
+        };
+        restore_settings(&rolled_back);
+    }
+
+    fn record(&self, setting: SavedSetting) {
+        let mut saved = self
+            .state
+            .saved
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        saved.push(setting);
+        self.state.save(&saved);
+    }
+
+    /// Puts back everything this guard changed.
+    pub fn restore(&self) {
+        self.state.restore();
+    }
+}
+
+impl Default for SystemStateGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for SystemStateGuard {
+    fn drop(&mut self) {
+        self.state.restore();
+    }
+}
+
+/// Restores every active guard in this process. Called from the console exit
+/// handler, and usable by hosts that have their own shutdown path.
+pub fn restore_all() {
+    let active = std::mem::take(
+        &mut *ACTIVE
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()),
+    );
+    for state in active.iter().filter_map(Weak::upgrade) {
+        state.restore();
+    }
+}
+
+/// Restores snapshots whose librarylink process exited without restoring
+/// them, such as after a crash or a forced kill.
+#[cfg(feature = "json")]
+pub fn restore_orphaned() {
+    let Ok(entries) = fs::read_dir(snapshots_dir()) else {
+        return;
+    };
+
+    for path in entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+    {
+        let Some(snapshot) = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<Snapshot>(&contents).ok())
+        else {
+            continue;
+        };
+        if process::is_same_process_running(snapshot.owner_pid, snapshot.owner_created) {
+            continue;
+        }
+        restore_settings(&snapshot.saved);
+        let _ = fs::remove_file(&path);
+    }
+}
+
+#[cfg(not(feature = "json"))]
+pub fn restore_orphaned() {}
+
+unsafe extern "system" fn console_exit_handler(control_type: u32) -> BOOL {
+    if matches!(
+        control_type,
+        CTRL_C_EVENT
+            | CTRL_BREAK_EVENT
+            | CTRL_CLOSE_EVENT
+            | CTRL_LOGOFF_EVENT
+            | CTRL_SHUTDOWN_EVENT
+    ) {
+        restore_all();
+    }
+    // Let the default handling (exiting) proceed
+    BOOL(0)
+}
+
+unsafe extern "system" fn session_end_procedure(
+    hwnd: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    match message {
+        // Never holds up signing out or shutting down
+        WM_QUERYENDSESSION => LRESULT(1),
+        // The process is ended once this returns
+        WM_ENDSESSION => {
+            if wparam.0 != 0 {
+                restore_all();
+            }
+            LRESULT(0)
+        }
+        _ => unsafe { DefWindowProcW(hwnd, message, wparam, lparam) },
+    }
+}
+
+/// Runs a hidden window for the whole process so signing out and shutting
+/// down, which processes with windows hear about through
+/// `WM_QUERYENDSESSION` and `WM_ENDSESSION` rather than console events,
+/// restore the active guards too.
+fn watch_session_end() {
+    unsafe {
+        let Ok(instance) = GetModuleHandleW(None) else {
+            return;
+        };
+        let class = WNDCLASSW {
+            lpfnWndProc: Some(session_end_procedure),
+            hInstance: instance.into(),
+            lpszClassName: w!("LibraryLinkSessionEnd"),
+            ..Default::default()
+        };
+        RegisterClassW(&class);
+        // Top-level rather than message-only, which isn't told of the end
+        if CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            w!("LibraryLinkSessionEnd"),
+            w!("librarylink"),
+            WS_OVERLAPPED,
+            0,
+            0,
+            0,
+            0,
+            None,
+            None,
+            Some(instance.into()),
+            None,
+        )
+        .is_err()
+        {
+            warning!(
+                "⚠️ Could not watch for signing out; settings may not be restored on shutdown: {}",
+                WinError::from_last_error("CreateWindowExW")
+            );
+            return;
+        }
+        let mut message = MSG::default();
+        while GetMessageW(&mut message, None, 0, 0).as_bool() {
+            DispatchMessageW(&message);
+        }
+    }
+}
+
+static EXIT_HANDLER: Once = Once::new();
+
+/// Restores active guards on Ctrl+C, Ctrl+Break, the console closing,
+/// signing out and shutting down before the process exits. Console events
+/// cover console processes; a hidden window covers signing out and
+/// shutting down for those with windows, such as the `librarylink` binary,
+/// which is a GUI-subsystem program. Only the first call has an effect.
+pub fn install_exit_handler() {
+    EXIT_HANDLER.call_once(|| {
+        unsafe {
+            if let Err(e) = SetConsoleCtrlHandler(Some(console_exit_handler), true) {
+                warning!(
+                    "⚠️ Could not install the exit handler; settings may not be restored on Ctrl+C: {}",
+                    WinError::new("SetConsoleCtrlHandler", &e)
+                );
+            }
+        }
+        thread::spawn(watch_session_end);
+    });
+}
+
+fn resolve_power_plan(plan: &str) -> Result<String, String> {
+    let scheme = POWER_PLANS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(plan))
+        .map_or(plan, |(_, guid)| guid);
+    GUID::try_from(scheme)
+        .map(|_| scheme.to_string())
+        .map_err(|_| format!("Unknown power plan '{}'", plan))
+}
+
+fn get_power_plan() -> Result<String, WinError> {
+    unsafe {
+        let mut scheme: *mut GUID = std::ptr::null_mut();
+        PowerGetActiveScheme(None, &mut scheme)
+            .ok()
+            .map_err(|e| WinError::new("PowerGetActiveScheme", &e))?;
+        let guid = format!("{:?}", *scheme).to_lowercase();
+        let _ = LocalFree(Some(HLOCAL(scheme as _)));
+        Ok(guid)
+    }
+}
+
+fn set_power_plan(scheme: &str) -> Result<(), WinError> {
+    let guid = GUID::try_from(scheme).map_err(|e| WinError::new("GUID::try_from", &e))?;
+    unsafe {
+        PowerSetActiveScheme(None, Some(&guid))
+            .ok()
+            .map_err(|e| WinError::new("PowerSetActiveScheme", &e))
+    }
+}
+
+fn describe_hdr(device: Option<&str>, enabled: bool) -> String {
+    format!(
+        "HDR {} on {}",
+        if enabled { "on" } else { "off" },
+        device.unwrap_or("the primary display")
+    )
+}
+
+fn describe_mode(mode: &DisplayMode) -> String {
+    let mut description = format!("{}x{}", mode.width, mode.height);
+    if let Some(refresh_rate) = mode.refresh_rate {
+        description.push_str(&format!("@{}Hz", refresh_rate));
+    }
+    if let Some(device) = &mode.device {
+        description.push_str(&format!(" on {}", device));
+    }
+    description
+}
+
+fn device_pointer(device: &Option<HSTRING>) -> PCWSTR {
+    device
+        .as_ref()
+        .map_or(PCWSTR::null(), |device| PCWSTR(device.as_ptr()))
+}
+
+fn get_display_mode(device: Option<&str>) -> Result<DisplayMode, WinError> {
+    let device_hstring = device.map(HSTRING::from);
+    let mut devmode = DEVMODEW {
+        dmSize: std::mem::size_of::<DEVMODEW>() as u16,
+        ..Default::default()
+    };
+    unsafe {
+        if !EnumDisplaySettingsW(
+            device_pointer(&device_hstring),
+            ENUM_CURRENT_SETTINGS,
+            &mut devmode,
+        )
+        .as_bool()
+        {
+            return Err(WinError::from_last_error("EnumDisplaySettingsW"));
+        }
+    }
+    Ok(DisplayMode {
+        device: device.map(str::to_string),
+        width: devmode.dmPelsWidth,
+        height: devmode.dmPelsHeight,
+        refresh_rate: Some(devmode.dmDisplayFrequency),
+    })
+}
+
+fn set_display_mode(mode: &DisplayMode) -> Result<(), WinError> {
+    let device_hstring = mode.device.as_deref().map(HSTRING::from);
+    let mut devmode = DEVMODEW {
+        dmSize: std::mem::size_of::<DEVMODEW>() as u16,
+        dmFields: DM_PELSWIDTH | DM_PELSHEIGHT,
+        dmPelsWidth: mode.width,
+        dmPelsHeight: mode.height,
+        ..Default::default()
+    };
+    if let Some(refresh_rate) = mode.refresh_rate {
+        devmode.dmFields |= DM_DISPLAYFREQUENCY;
+        devmode.dmDisplayFrequency = refresh_rate;
+    }
+
+    // Change the mode dynamically without saving it as the display's default
+    let result = unsafe {
+        ChangeDisplaySettingsExW(
+            device_pointer(&device_hstring),
+            Some(&devmode),
+            None,
+            CDS_TYPE(0),
+            None,
+        )
+    };
+    if result == DISP_CHANGE_SUCCESSFUL {
+        return Ok(());
+    }
+    // DISP_CHANGE codes aren't HRESULTs, so describe them here
+    let message = match result {
+        DISP_CHANGE_BADMODE => "The display doesn't support that mode",
+        DISP_CHANGE_RESTART => "The mode only applies after a restart",
+        DISP_CHANGE_FAILED => "The display driver rejected the mode",
+        _ => "The display mode could not be changed",
+    };
+    Err(WinError {
+        call: "ChangeDisplaySettingsExW".to_string(),
+        hresult: format!("0x{:08X}", result.0 as u32),
+        message: message.to_string(),
+    })
+}
@@ -0,0 +1,200 @@
+//! `{variable}` substitution in profile fields, such as
+//! `path = '{install_path}\Content\Engine.ini'`, resolved when the profile
+//! is launched so hooks and patches don't need paths librarylink already
+//! knows hard-coded. `{env:NAME}` reads an environment variable, and `{{`
+//! and `}}` stand for literal braces. A brace that doesn't start a variable
+//! name, as in inline JSON, is left as is, and so are the braces that close
+//! it, even when two of them meet as `}}`.
+
+use std::collections::BTreeMap;
+use std::env;
+
+use crate::client::ClientDisplay;
+use crate::package;
+use crate::paths;
+
+/// Every variable a launch can have. Some have no value for a given launch,
+/// such as `install_path` for an app that isn't installed.
+pub const NAMES: &[&str] = &[
+    "aumid",
+    "package_family_name",
+    "install_path",
+    "version",
+    "profile",
+    "data_dir",
+    "client_width",
+    "client_height",
+    "client_fps",
+];
+
+/// The values variables expand to.
+#[derive(Debug, Clone, Default)]
+pub struct Variables {
+    values: BTreeMap<String, String>,
+}
+
+impl Variables {
+    /// The variables for launching `aumid` from the profile `profile`.
+    pub fn for_launch(aumid: &str, profile: &str) -> Variables {
+        let mut variables = Variables::default();
+        variables.set("aumid", aumid);
+        variables.set("profile", profile);
+        variables.set("data_dir", paths::data_dir().display().to_string());
+        if let Some(family) = package::family_name(aumid) {
+            variables.set("package_family_name", family);
+        }
+        if let Some(path) = package::installed_path(aumid) {
+            variables.set("install_path", path.display().to_string());
+        }
+        if let Some(version) = package::installed_version(aumid) {
+            variables.set("version", version);
+        }
+        if let Some(client) = ClientDisplay::from_environment() {
+            variables.set("client_width", client.width.to_string());
+            variables.set("client_height", client.height.to_string());
+            if let Some(fps) = client.fps {
+                variables.set("client_fps", fps.to_string());
+            }
+        }
+        variables
+    }
+
+    pub fn set(&mut self, name: &str, value: impl Into<String>) {
+        self.values.insert(name.to_string(), value.into());
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    /// `text` with every variable replaced. Variables without a value and
+    /// names that aren't variables are errors, so a typo can't reach a
+    /// command line or a config file.
+    pub fn expand(&self, text: &str) -> Result<String, String> {
+        let mut expanded = String::with_capacity(text.len());
+        let mut rest = text;
+        // Braces left as is that are still to be closed
+        let mut open = 0;
+        while let Some(start) = rest.find(['{', '}']) {
+            expanded.push_str(&rest[..start]);
+            let tail = &rest[start..];
+            if tail.starts_with('}') && open > 0 {
+                open -= 1;
+                expanded.push('}');
+                rest = &tail[1..];
+                continue;
+            }
+            if tail.starts_with("{{") || tail.starts_with("}}") {
+                expanded.push_str(&tail[..1]);
+                rest = &tail[2..];
+                continue;
+            }
+            let name = tail
+                .strip_prefix('{')
+                .and_then(|inner| inner.find('}').map(|end| &inner[..end]));
+            match name.filter(|name| is_variable_name(name)) {
+                Some(name) => {
+                    expanded.push_str(&self.value(name)?);
+                    rest = &tail[name.len() + 2..];
+                }
+                None => {
+                    if tail.starts_with('{') {
+                        open += 1;
+                    }
+                    expanded.push_str(&tail[..1]);
+                    rest = &tail[1..];
+                }
+            }
+        }
+        expanded.push_str(rest);
+        Ok(expanded)
+    }
+
+    fn value(&self, name: &str) -> Result<String, String> {
+        if let Some(variable) = name.strip_prefix("env:") {
+            return env::var(variable)
+                .map_err(|_| format!("Environment variable {} isn't set", variable));
+        }
+        match self.get(name) {
+            Some(value) => Ok(value.to_string()),
+            None if NAMES.contains(&name) => {
+                Err(format!("{{{}}} has no value for this launch", name))
+            }
+            None => Err(format!(
+                "Unknown variable {{{}}}; the variables are {}",
+                name,
+                NAMES
+                    .iter()
+                    .map(|name| format!("{{{}}}", name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        }
+    }
+}
+
+/// Whether `{name}` reads as a variable rather than literal text.
+fn is_variable_name(name: &str) -> bool {
+    let (name, environment) = match name.strip_prefix("env:") {
+        Some(variable) => (variable, true),
+        None => (name, false),
+    };
+    // Environment variables such as ProgramFiles(x86) have parentheses
+    !name.is_empty()
+        && name.chars().all(|c| {
+            c.is_ascii_alphanumeric() || c == '_' || (environment && (c == '(' || c == ')'))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables() -> Variables {
+        let mut variables = Variables::default();
+        variables.set("profile", "forza");
+        variables.set("install_path", r"C:\XboxGames\Forza");
+        variables
+    }
+
+    #[test]
+    fn expands_variables() {
+        assert_eq!(
+            variables().expand(r"{install_path}\Content\{profile}.ini"),
+            Ok(r"C:\XboxGames\Forza\Content\forza.ini".to_string())
+        );
+    }
+
+    #[test]
+    fn doubled_braces_are_literal() {
+        assert_eq!(
+            variables().expand("{{profile}} is {profile}"),
+            Ok("{profile} is forza".to_string())
+        );
+        assert_eq!(variables().expand("}}"), Ok("}".to_string()));
+    }
+
+    #[test]
+    fn inline_json_is_left_as_is() {
+        let json = r#"{"window": {"mode": "fullscreen"}}"#;
+        assert_eq!(variables().expand(json), Ok(json.to_string()));
+        assert_eq!(
+            variables().expand(r#"{"profile": "{profile}", "flags": {"a": {}}}"#),
+            Ok(r#"{"profile": "forza", "flags": {"a": {}}}"#.to_string())
+        );
+    }
+
+    #[test]
+    fn missing_and_unknown_variables_are_errors() {
+        assert!(
+            variables()
+                .expand("{version}")
+                .is_err_and(|e| e.contains("no value"))
+        );
+        assert!(
+            variables()
+                .expand("{install_dir}")
+                .is_err_and(|e| e.contains("Unknown variable"))
+        );
+    }
+}
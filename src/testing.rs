@@ -0,0 +1,8 @@
+//! Fixtures shared by the unit tests.
+
+use std::path::PathBuf;
+
+/// A path in the temp directory that's unique to `name` and this test run.
+pub(crate) fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("librarylink-{}-{}", name, std::process::id()))
+}
@@ -0,0 +1,166 @@
+//! `--timings`: how long each phase of a launch took, so a slow start can be
+//! traced to package enumeration, the app lookup, COM, activation or the
+//! game itself. Phases are recorded process-wide while enabled and reported
+//! together in one [`Event::Timings`] when the launch ends.
+
+use std::fmt;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::report::{Event, Reporter};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static RECORDED: Mutex<Recorded> = Mutex::new(Recorded {
+    phases: Vec::new(),
+    activated: None,
+    reported: false,
+});
+
+/// A phase of a launch, in the order a launch goes through them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+#[cfg_attr(feature = "json", serde(rename_all = "snake_case"))]
+pub enum Phase {
+    /// Package queries through `PackageManager`
+    Enumeration,
+    /// Finding the app for an AUMID, or the handler for a URI
+    Lookup,
+    /// Initializing COM for activation
+    ComInit,
+    /// Activating the app, or starting the command
+    Activation,
+    /// From activation until the game's own process was being tracked
+    FirstProcess,
+    /// From activation until a tracked process showed its first window
+    FirstWindow,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Phase::Enumeration => "enumeration",
+            Phase::Lookup => "lookup",
+            Phase::ComInit => "COM init",
+            Phase::Activation => "activation",
+            Phase::FirstProcess => "first process",
+            Phase::FirstWindow => "first window",
+        })
+    }
+}
+
+impl Phase {
+    /// Whether the phase is measured from activation rather than timed on
+    /// its own.
+    pub fn is_milestone(self) -> bool {
+        matches!(self, Phase::FirstProcess | Phase::FirstWindow)
+    }
+}
+
+/// The time spent in one phase, over every time it ran.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct PhaseTiming {
+    pub phase: Phase,
+    pub millis: u64,
+    /// How many times the phase ran, such as COM being initialized for
+    /// each activation
+    pub count: u32,
+}
+
+struct Recorded {
+    phases: Vec<PhaseTiming>,
+    /// When the first activation returned
+    activated: Option<Instant>,
+    reported: bool,
+}
+
+/// Starts recording phases for the rest of the process.
+pub fn enable() {
+    ENABLED.store(true, Ordering::SeqCst);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+}
+
+/// Runs `f` as one run of `phase`.
+pub fn time<T>(phase: Phase, f: impl FnOnce() -> T) -> T {
+    if !is_enabled() {
+        return f();
+    }
+    let started = Instant::now();
+    let result = f();
+    record(phase, started.elapsed());
+    if phase == Phase::Activation {
+        let mut recorded = RECORDED
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        recorded.activated.get_or_insert_with(Instant::now);
+    }
+    result
+}
+
+/// Records that a milestone was reached now. Only the first time counts,
+/// and only once something was activated.
+pub fn milestone(phase: Phase) {
+    if !is_enabled() {
+        return;
+    }
+    let mut recorded = RECORDED
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let Some(activated) = recorded.activated else {
+        return;
+    };
+    if !recorded.phases.iter().any(|timing| timing.phase == phase) {
+        let millis = activated.elapsed().as_millis() as u64;
+        recorded.phases.push(PhaseTiming {
+            phase,
+            millis,
+            count: 1,
+        });
+    }
+}
+
+fn record(phase: Phase, elapsed: Duration) {
+    let mut recorded = RECORDED
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let millis = elapsed.as_millis() as u64;
+    match recorded
+        .phases
+        .iter_mut()
+        .find(|timing| timing.phase == phase)
+    {
+        Some(timing) => {
+            timing.millis += millis;
+            timing.count += 1;
+        }
+        None => recorded.phases.push(PhaseTiming {
+            phase,
+            millis,
+            count: 1,
+        }),
+    }
+}
+
+/// Reports the phases recorded so far, once per process.
+pub fn report(reporter: &dyn Reporter) {
+    if !is_enabled() {
+        return;
+    }
+    let phases = {
+        let mut recorded = RECORDED
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if recorded.reported {
+            return;
+        }
+        recorded.reported = true;
+        let mut phases = recorded.phases.clone();
+        phases.sort_by_key(|timing| timing.phase);
+        phases
+    };
+    reporter.event(&Event::Timings { phases });
+}
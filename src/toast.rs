@@ -0,0 +1,115 @@
+//! `--notify`: raises a Windows toast when a launch fails, since librarylink
+//! is usually started from a shortcut or a frontend with no console to show
+//! its errors in. Toasts come from the app ID librarylink registers for
+//! itself under `HKCU\Software\Classes\AppUserModelId`, which is all an
+//! unpackaged app needs for the Action Center to name it.
+
+use std::sync::Mutex;
+use windows::Data::Xml::Dom::XmlDocument;
+use windows::UI::Notifications::{ToastNotification, ToastNotificationManager};
+use windows::core::HSTRING;
+
+use crate::error::WinError;
+use crate::monitor::{EndReason, MonitorState};
+use crate::patch::xml_escape;
+use crate::registry::{self, RegistryData};
+use crate::report::{Event, Level, Reporter};
+
+/// The app ID toasts are raised under.
+const APP_ID: &str = "librarylink";
+
+/// Shows a toast with `title` and `text`. A newer toast replaces the last one,
+/// so a failure reported several ways shows up once.
+pub fn show(title: &str, text: &str) -> Result<(), WinError> {
+    register();
+    let xml = format!(
+        "<toast><visual><binding template=\"ToastGeneric\"><text>{}</text><text>{}</text></binding></visual></toast>",
+        xml_escape(title),
+        xml_escape(text)
+    );
+    let document = XmlDocument::new().map_err(|e| WinError::new("XmlDocument", &e))?;
+    document
+        .LoadXml(&HSTRING::from(xml))
+        .map_err(|e| WinError::new("XmlDocument::LoadXml", &e))?;
+    let toast = ToastNotification::CreateToastNotification(&document)
+        .map_err(|e| WinError::new("CreateToastNotification", &e))?;
+    let _ = toast.SetTag(&HSTRING::from("failure"));
+    let _ = toast.SetGroup(&HSTRING::from(APP_ID));
+    ToastNotificationManager::CreateToastNotifierWithId(&HSTRING::from(APP_ID))
+        .and_then(|notifier| notifier.Show(&toast))
+        .map_err(|e| WinError::new("ToastNotifier::Show", &e))
+}
+
+/// Gives the app ID its display name, without which Windows drops the toast.
+fn register() {
+    let key = format!("HKCU\\Software\\Classes\\AppUserModelId\\{}", APP_ID);
+    if !matches!(registry::read(&key, "DisplayName"), Ok(Some(_))) {
+        let _ = registry::write(
+            &key,
+            "DisplayName",
+            &RegistryData::String(APP_ID.to_string()),
+        );
+    }
+}
+
+/// Reports to `primary`, and raises a toast when activation fails, the game
+/// never appears or nothing takes over from its launcher.
+pub struct NotifyReporter {
+    primary: Box<dyn Reporter>,
+    /// The launched app's name, once it's known
+    app_name: Mutex<Option<String>>,
+}
+
+impl NotifyReporter {
+    pub fn new(primary: Box<dyn Reporter>) -> Self {
+        NotifyReporter {
+            primary,
+            app_name: Mutex::new(None),
+        }
+    }
+
+    fn notify(&self, text: &str) {
+        let title = match &*self.app_name.lock().unwrap_or_else(|p| p.into_inner()) {
+            Some(name) => format!("Couldn't launch {}", name),
+            None => "librarylink".to_string(),
+        };
+        if let Err(e) = show(&title, text) {
+            // Not through the reporter, which would try another toast
+            self.primary.message(
+                Level::Warning,
+                &format!("⚠️ Could not show a notification: {}", e),
+            );
+        }
+    }
+}
+
+impl Reporter for NotifyReporter {
+    fn message(&self, level: Level, text: &str) {
+        self.primary.message(level, text);
+        if level == Level::Error {
+            let text = text.trim_start_matches("❌").trim_start();
+            self.notify(text.strip_prefix("Error: ").unwrap_or(text));
+        }
+    }
+
+    fn event(&self, event: &Event) {
+        self.primary.event(event);
+        match event {
+            Event::AppFound {
+                display_name: Some(name),
+                ..
+            } => *self.app_name.lock().unwrap_or_else(|p| p.into_inner()) = Some(name.clone()),
+            Event::Error { context, error } => self.notify(&format!("{}: {}", context, error)),
+            Event::StateChanged {
+                state: MonitorState::Ended { reason },
+            } => match reason {
+                EndReason::ActivationTimeout => self.notify("The game never started"),
+                EndReason::HandoffTimeout => {
+                    self.notify("The launcher exited without the game taking over")
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,235 @@
+//! API tokens for the daemon, so a shared dashboard can be handed one that
+//! queues launches without it also being able to cancel everyone else's.
+//! A token carries one [`Scope`], and each scope allows what the ones before
+//! it do.
+//!
+//! Tokens are stored in `%APPDATA%\librarylink\tokens.json` as SHA-256
+//! hashes, so the file never holds one that could be used. Until a token is
+//! added the daemon serves every client as `admin`, as it always has. A
+//! file that exists but can't be read or parsed fails closed: every client
+//! is refused until it's fixed or removed, rather than served as `admin`.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use windows::Win32::Security::Cryptography::{
+    BCRYPT_SHA256_ALG_HANDLE, BCRYPT_USE_SYSTEM_PREFERRED_RNG, BCryptGenRandom, BCryptHash,
+};
+
+use crate::atomic_file;
+use crate::error::WinError;
+use crate::paths;
+use crate::session::unix_now;
+
+/// Marks librarylink tokens, so one pasted in the wrong place is recognizable.
+const TOKEN_PREFIX: &str = "ll_";
+
+/// What a token's holder may do, from least to most.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    /// See the queue
+    List,
+    /// Queue launches and cancel its own
+    Launch,
+    /// Cancel any launch
+    Admin,
+}
+
+impl Scope {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "list" => Some(Scope::List),
+            "launch" => Some(Scope::Launch),
+            "admin" => Some(Scope::Admin),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::List => "list",
+            Scope::Launch => "launch",
+            Scope::Admin => "admin",
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A stored token, without the token itself.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TokenEntry {
+    pub name: String,
+    pub scope: Scope,
+    /// Hex SHA-256 of the token
+    hash: String,
+    pub created_at: u64,
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct Tokens {
+    tokens: Vec<TokenEntry>,
+}
+
+fn tokens_path() -> PathBuf {
+    paths::data_dir().join("tokens.json")
+}
+
+fn load() -> Result<Tokens, Box<dyn Error>> {
+    read(&tokens_path())
+}
+
+/// The tokens in `path`: none when the file doesn't exist, an error when it
+/// exists but is unreadable or malformed, such as one cut short by a crash.
+fn read(path: &Path) -> Result<Tokens, Box<dyn Error>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Tokens::default()),
+        Err(e) => return Err(format!("Could not read {}: {}", path.display(), e).into()),
+    };
+    serde_json::from_str(&contents)
+        .map_err(|e| format!("{} is malformed: {}", path.display(), e).into())
+}
+
+/// Whether `tokens` means clients need a token. An unreadable file does.
+fn requires_token(tokens: &Result<Tokens, Box<dyn Error>>) -> bool {
+    tokens
+        .as_ref()
+        .map_or(true, |tokens| !tokens.tokens.is_empty())
+}
+
+fn save(tokens: &Tokens) -> Result<(), Box<dyn Error>> {
+    atomic_file::write(&tokens_path(), serde_json::to_string_pretty(tokens)?)?;
+    Ok(())
+}
+
+/// Every stored token, in the order they were added.
+pub fn list() -> Result<Vec<TokenEntry>, Box<dyn Error>> {
+    Ok(load()?.tokens)
+}
+
+/// Creates a token named `name` with `scope` and returns it. Only its hash
+/// is stored, so this is the one chance to see it.
+pub fn add(name: &str, scope: Scope) -> Result<String, Box<dyn Error>> {
+    if name.trim().is_empty() {
+        return Err("A token needs a name".into());
+    }
+    // An unreadable file is left for the user rather than replaced
+    let mut tokens = load()?;
+    if tokens.tokens.iter().any(|entry| entry.name == name) {
+        return Err(format!("A token named '{}' already exists", name).into());
+    }
+
+    let mut random = [0u8; 32];
+    unsafe { BCryptGenRandom(None, &mut random, BCRYPT_USE_SYSTEM_PREFERRED_RNG) }
+        .ok()
+        .map_err(|e| WinError::new("BCryptGenRandom", &e))?;
+    let token = format!("{}{}", TOKEN_PREFIX, hex(&random));
+
+    tokens.tokens.push(TokenEntry {
+        name: name.to_string(),
+        scope,
+        hash: hash(&token)?,
+        created_at: unix_now(),
+    });
+    save(&tokens)?;
+    Ok(token)
+}
+
+/// Revokes the token named `name`. Returns whether there was one.
+pub fn remove(name: &str) -> Result<bool, Box<dyn Error>> {
+    let mut tokens = load()?;
+    let before = tokens.tokens.len();
+    tokens.tokens.retain(|entry| entry.name != name);
+    if tokens.tokens.len() == before {
+        return Ok(false);
+    }
+    save(&tokens)?;
+    Ok(true)
+}
+
+/// Whether any token has been added, which is when the daemon starts
+/// requiring one. Also true when the token file can't be read.
+pub fn required() -> bool {
+    requires_token(&load())
+}
+
+/// The stored token `token` matches. Fails when it matches none, or when
+/// the token file can't be read, so that no token is accepted then.
+pub fn verify(token: &str) -> Result<TokenEntry, String> {
+    let tokens = load().map_err(|e| format!("The daemon's tokens can't be checked: {}", e))?;
+    let hash = hash(token).map_err(|e| e.to_string())?;
+    tokens
+        .tokens
+        .into_iter()
+        .find(|entry| entry.hash == hash)
+        .ok_or_else(|| "Unknown token".to_string())
+}
+
+fn hash(token: &str) -> Result<String, WinError> {
+    let mut digest = [0u8; 32];
+    unsafe {
+        BCryptHash(
+            BCRYPT_SHA256_ALG_HANDLE,
+            None,
+            token.as_bytes(),
+            &mut digest,
+        )
+    }
+    .ok()
+    .map_err(|e| WinError::new("BCryptHash", &e))?;
+    Ok(hex(&digest))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_path;
+
+    #[test]
+    fn missing_file_requires_no_token() {
+        let tokens = read(&temp_path("tokens-missing.json"));
+        assert!(tokens.as_ref().is_ok_and(|tokens| tokens.tokens.is_empty()));
+        assert!(!requires_token(&tokens));
+    }
+
+    #[test]
+    fn unreadable_file_requires_a_token() {
+        let path = temp_path("tokens-truncated.json");
+        fs::write(&path, r#"{"tokens":[{"name":"tablet","scope":"admin","#).unwrap();
+        let tokens = read(&path);
+        let _ = fs::remove_file(&path);
+        assert!(tokens.is_err());
+        assert!(requires_token(&tokens));
+    }
+
+    #[test]
+    fn stored_tokens_require_a_token() {
+        let path = temp_path("tokens-stored.json");
+        let stored = Tokens {
+            tokens: vec![TokenEntry {
+                name: "tablet".to_string(),
+                scope: Scope::Launch,
+                hash: "00".to_string(),
+                created_at: 0,
+            }],
+        };
+        fs::write(&path, serde_json::to_string(&stored).unwrap()).unwrap();
+        let tokens = read(&path);
+        let _ = fs::remove_file(&path);
+        assert!(requires_token(&tokens));
+    }
+}
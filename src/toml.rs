@@ -0,0 +1,485 @@
+//! Parser for the subset of TOML that librarylink's own files use: tables,
+//! arrays of tables, dotted keys, strings, integers, floats, booleans, arrays
+//! and inline tables. Dates and multi-line strings aren't supported.
+//!
+//! Documents parse into a `serde_json::Value`, so they deserialize with the
+//! same serde derives as the JSON state files.
+
+use serde_json::{Map, Number, Value};
+
+type Table = Map<String, Value>;
+
+/// Parses a document into a JSON object. Errors name the offending line.
+pub fn parse(text: &str) -> Result<Value, String> {
+    let mut parser = Parser {
+        chars: text.chars().collect(),
+        pos: 0,
+        line: 1,
+    };
+    parser
+        .document()
+        .map_err(|message| format!("line {}: {}", parser.line, message))
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.advance();
+                Ok(())
+            }
+            Some('\n') => Err(format!(
+                "expected '{}', found the end of the line",
+                expected
+            )),
+            Some(c) => Err(format!("expected '{}', found '{}'", expected, c)),
+            None => Err(format!(
+                "expected '{}', found the end of the file",
+                expected
+            )),
+        }
+    }
+
+    /// Skips spaces and tabs on the current line.
+    fn skip_spaces(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.advance();
+        }
+    }
+
+    /// Skips whitespace, newlines and comments.
+    fn skip_blank(&mut self) {
+        loop {
+            match self.peek() {
+                Some(' ' | '\t' | '\r' | '\n') => {
+                    self.advance();
+                }
+                Some('#') => self.skip_comment(),
+                _ => return,
+            }
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        while self.peek().is_some_and(|c| c != '\n') {
+            self.advance();
+        }
+    }
+
+    /// Allows only a comment between a value or header and the next line.
+    fn end_of_line(&mut self) -> Result<(), String> {
+        self.skip_spaces();
+        if self.peek() == Some('#') {
+            self.skip_comment();
+        }
+        if self.peek() == Some('\r') {
+            self.advance();
+        }
+        match self.advance() {
+            None | Some('\n') => Ok(()),
+            Some(c) => Err(format!("expected the end of the line, found '{}'", c)),
+        }
+    }
+
+    fn document(&mut self) -> Result<Value, String> {
+        let mut root = Table::new();
+        let mut current: Vec<String> = Vec::new();
+        // Headers of the tables defined so far, each only allowed once
+        let mut defined: Vec<Vec<String>> = Vec::new();
+
+        loop {
+            self.skip_blank();
+            match self.peek() {
+                None => break,
+                Some('[') => {
+                    self.advance();
+                    let is_array = self.peek() == Some('[');
+                    if is_array {
+                        self.advance();
+                    }
+                    self.skip_spaces();
+                    let path = self.key()?;
+                    self.expect(']')?;
+                    if is_array {
+                        self.expect(']')?;
+                        push_table(&mut root, &path)?;
+                        // The new element's subtables are yet to be defined
+                        defined.retain(|header| !header.starts_with(&path));
+                    } else {
+                        if defined.contains(&path) {
+                            return Err(format!("table '{}' is defined twice", path.join(".")));
+                        }
+                        table_at(&mut root, &path)?;
+                        defined.push(path.clone());
+                    }
+                    current = path;
+                    self.end_of_line()?;
+                }
+                Some(_) => {
+                    let path = self.key()?;
+                    self.expect('=')?;
+                    self.skip_spaces();
+                    let value = self.value()?;
+                    insert(table_at(&mut root, &current)?, &path, value)?;
+                    self.end_of_line()?;
+                }
+            }
+        }
+
+        Ok(Value::Object(root))
+    }
+
+    /// A possibly dotted key, followed by any spaces.
+    fn key(&mut self) -> Result<Vec<String>, String> {
+        let mut path = Vec::new();
+        loop {
+            let part = match self.peek() {
+                Some('"') => self.basic_string()?,
+                Some('\'') => self.literal_string()?,
+                _ => {
+                    let mut part = String::new();
+                    while let Some(c) = self
+                        .peek()
+                        .filter(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-')
+                    {
+                        part.push(c);
+                        self.advance();
+                    }
+                    if part.is_empty() {
+                        return Err(match self.peek() {
+                            Some(c) => format!("expected a key, found '{}'", c),
+                            None => "expected a key, found the end of the file".to_string(),
+                        });
+                    }
+                    part
+                }
+            };
+            path.push(part);
+
+            self.skip_spaces();
+            if self.peek() != Some('.') {
+                return Ok(path);
+            }
+            self.advance();
+            self.skip_spaces();
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.peek() {
+            Some('"') => self.basic_string().map(Value::String),
+            Some('\'') => self.literal_string().map(Value::String),
+            Some('[') => self.array(),
+            Some('{') => self.inline_table(),
+            Some(_) => self.scalar(),
+            None => Err("expected a value, found the end of the file".to_string()),
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String, String> {
+        if self.peek_at(1) == Some('"') && self.peek_at(2) == Some('"') {
+            return Err("multi-line strings are not supported".to_string());
+        }
+        self.advance();
+
+        let mut value = String::new();
+        loop {
+            match self.advance() {
+                Some('"') => return Ok(value),
+                Some('\\') => {
+                    let escaped = match self.advance() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('u') => self.unicode_escape(4)?,
+                        Some('U') => self.unicode_escape(8)?,
+                        Some(c) => return Err(format!("invalid escape '\\{}' in string", c)),
+                        None => return Err("unterminated string".to_string()),
+                    };
+                    value.push(escaped);
+                }
+                None | Some('\n') => return Err("unterminated string".to_string()),
+                Some(c) => value.push(c),
+            }
+        }
+    }
+
+    fn unicode_escape(&mut self, digits: usize) -> Result<char, String> {
+        let hex: String = (0..digits).filter_map(|_| self.advance()).collect();
+        u32::from_str_radix(&hex, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| format!("invalid unicode escape '{}'", hex))
+    }
+
+    fn literal_string(&mut self) -> Result<String, String> {
+        if self.peek_at(1) == Some('\'') && self.peek_at(2) == Some('\'') {
+            return Err("multi-line strings are not supported".to_string());
+        }
+        self.advance();
+
+        let mut value = String::new();
+        loop {
+            match self.advance() {
+                Some('\'') => return Ok(value),
+                None | Some('\n') => return Err("unterminated string".to_string()),
+                Some(c) => value.push(c),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, String> {
+        self.advance();
+        let mut items = Vec::new();
+        loop {
+            self.skip_blank();
+            if self.peek() == Some(']') {
+                self.advance();
+                return Ok(Value::Array(items));
+            }
+            items.push(self.value()?);
+            self.skip_blank();
+            match self.advance() {
+                Some(',') => {}
+                Some(']') => return Ok(Value::Array(items)),
+                Some(c) => return Err(format!("expected ',' or ']' in array, found '{}'", c)),
+                None => return Err("unterminated array".to_string()),
+            }
+        }
+    }
+
+    fn inline_table(&mut self) -> Result<Value, String> {
+        self.advance();
+        let mut table = Table::new();
+        self.skip_spaces();
+        if self.peek() == Some('}') {
+            self.advance();
+            return Ok(Value::Object(table));
+        }
+        loop {
+            self.skip_spaces();
+            let path = self.key()?;
+            self.expect('=')?;
+            self.skip_spaces();
+            let value = self.value()?;
+            insert(&mut table, &path, value)?;
+            self.skip_spaces();
+            match self.advance() {
+                Some(',') => {}
+                Some('}') => return Ok(Value::Object(table)),
+                Some(c) => {
+                    return Err(format!(
+                        "expected ',' or '}}' in inline table, found '{}'",
+                        c
+                    ));
+                }
+                None => return Err("unterminated inline table".to_string()),
+            }
+        }
+    }
+
+    /// Booleans, integers and floats.
+    fn scalar(&mut self) -> Result<Value, String> {
+        let mut token = String::new();
+        while let Some(c) = self
+            .peek()
+            .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.' | '_'))
+        {
+            token.push(c);
+            self.advance();
+        }
+
+        match token.as_str() {
+            "true" => return Ok(Value::Bool(true)),
+            "false" => return Ok(Value::Bool(false)),
+            "" => {
+                return Err(match self.peek() {
+                    Some(c) => format!("expected a value, found '{}'", c),
+                    None => "expected a value, found the end of the file".to_string(),
+                });
+            }
+            _ => {}
+        }
+
+        let digits = token.replace('_', "");
+        if let Ok(integer) = digits.parse::<i64>() {
+            return Ok(Value::Number(integer.into()));
+        }
+        digits
+            .parse::<f64>()
+            .ok()
+            .filter(|_| digits.starts_with(|c: char| c.is_ascii_digit() || c == '+' || c == '-'))
+            .and_then(Number::from_f64)
+            .map(Value::Number)
+            .ok_or_else(|| format!("invalid value '{}'", token))
+    }
+}
+
+/// The table at a header path, creating missing tables along the way. Arrays
+/// of tables resolve to their last element, as TOML specifies.
+fn table_at<'a>(root: &'a mut Table, path: &[String]) -> Result<&'a mut Table, String> {
+    let mut table = root;
+    for key in path {
+        let entry = table
+            .entry(key.clone())
+            .or_insert_with(|| Value::Object(Table::new()));
+        table = match entry {
+            Value::Object(table) => table,
+            Value::Array(items) => match items.last_mut() {
+                Some(Value::Object(table)) => table,
+                _ => return Err(format!("'{}' is not a table", key)),
+            },
+            _ => return Err(format!("'{}' is not a table", key)),
+        };
+    }
+    Ok(table)
+}
+
+/// Appends a new table for an `[[array.of.tables]]` header.
+fn push_table(root: &mut Table, path: &[String]) -> Result<(), String> {
+    let (last, parents) = path.split_last().expect("keys have at least one part");
+    let entry = table_at(root, parents)?
+        .entry(last.clone())
+        .or_insert_with(|| Value::Array(Vec::new()));
+    match entry {
+        Value::Array(items) => {
+            items.push(Value::Object(Table::new()));
+            Ok(())
+        }
+        _ => Err(format!("'{}' is not an array of tables", last)),
+    }
+}
+
+/// Sets a possibly dotted key, rejecting keys that are already defined.
+fn insert(table: &mut Table, path: &[String], value: Value) -> Result<(), String> {
+    let (last, parents) = path.split_last().expect("keys have at least one part");
+    let table = table_at(table, parents)?;
+    if table.contains_key(last) {
+        return Err(format!("duplicate key '{}'", last));
+    }
+    table.insert(last.clone(), value);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_values() {
+        let document = parse(
+            "name = \"Forza\" # comment\n\
+             path = 'C:\\Games\\Forza'\n\
+             escaped = \"tab\\tquote\\\"\\u00e9\"\n\
+             count = 1_000\n\
+             negative = -3\n\
+             ratio = 1.5\n\
+             enabled = true\n\
+             list = [1, \"two\", [3]]\n\
+             inline = { x = 1, y.z = 2 }\n",
+        )
+        .unwrap();
+        assert_eq!(
+            document,
+            json!({
+                "name": "Forza",
+                "path": "C:\\Games\\Forza",
+                "escaped": "tab\tquote\"é",
+                "count": 1000,
+                "negative": -3,
+                "ratio": 1.5,
+                "enabled": true,
+                "list": [1, "two", [3]],
+                "inline": { "x": 1, "y": { "z": 2 } },
+            })
+        );
+    }
+
+    #[test]
+    fn parses_tables_and_arrays_of_tables() {
+        let document = parse(
+            "[monitor]\n\
+             scope = \"package\"\n\
+             [apps.\"Game!App\"]\n\
+             grace_period = 10\n\
+             [[dependency]]\n\
+             name = \"EA app\"\n\
+             [dependency.ready]\n\
+             port = 1\n\
+             [[dependency]]\n\
+             name = \"Xbox\"\n\
+             [dependency.ready]\n\
+             port = 2\n",
+        )
+        .unwrap();
+        assert_eq!(
+            document,
+            json!({
+                "monitor": { "scope": "package" },
+                "apps": { "Game!App": { "grace_period": 10 } },
+                "dependency": [
+                    { "name": "EA app", "ready": { "port": 1 } },
+                    { "name": "Xbox", "ready": { "port": 2 } },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn allows_a_parent_table_after_its_child() {
+        let document = parse("[a.b]\nx = 1\n[a]\ny = 2\n").unwrap();
+        assert_eq!(document, json!({ "a": { "b": { "x": 1 }, "y": 2 } }));
+    }
+
+    #[test]
+    fn rejects_duplicates() {
+        assert_eq!(
+            parse("[monitor]\nscope = \"package\"\n[monitor]\n"),
+            Err("line 3: table 'monitor' is defined twice".to_string())
+        );
+        assert_eq!(
+            parse("[[dependency]]\n[dependency.ready]\n[dependency.ready]\n"),
+            Err("line 3: table 'dependency.ready' is defined twice".to_string())
+        );
+        assert_eq!(
+            parse("a = 1\na = 2\n"),
+            Err("line 2: duplicate key 'a'".to_string())
+        );
+    }
+
+    #[test]
+    fn names_the_line_of_an_error() {
+        assert!(parse("a = 1\nb = \n").is_err_and(|e| e.starts_with("line 2:")));
+        assert!(parse("a = \"open\n").is_err_and(|e| e.contains("unterminated string")));
+        assert!(parse("a = 1 b\n").is_err_and(|e| e.contains("end of the line")));
+        assert!(parse("a = \"\"\"x\"\"\"\n").is_err_and(|e| e.contains("multi-line")));
+    }
+}
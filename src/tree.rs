@@ -0,0 +1,195 @@
+use std::collections::{HashMap, HashSet};
+use windows::Win32::Foundation::{CloseHandle, ERROR_INSUFFICIENT_BUFFER, HANDLE, WIN32_ERROR};
+use windows::Win32::Storage::Packaging::Appx::{GetPackageFamilyName, GetPackageFullName};
+use windows::Win32::System::Diagnostics::ToolHelp::{
+    CreateToolhelp32Snapshot, PROCESSENTRY32W, Process32FirstW, Process32NextW, TH32CS_SNAPPROCESS,
+};
+use windows::Win32::System::Threading::{OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION};
+use windows::core::PWSTR;
+
+use crate::error::WinError;
+use crate::process;
+use crate::report::{error, info};
+
+#[derive(Debug, Clone)]
+pub struct ProcessEntry {
+    pub pid: u32,
+    pub parent_pid: u32,
+    pub exe_name: String,
+}
+
+/// Takes a snapshot of every running process along with its parent PID.
+pub fn snapshot_processes() -> Result<Vec<ProcessEntry>, Box<dyn std::error::Error>> {
+    let mut entries = Vec::new();
+
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0)
+            .map_err(|e| WinError::new("CreateToolhelp32Snapshot", &e))?;
+
+        let mut entry = PROCESSENTRY32W {
+            dwSize: std::mem::size_of::<PROCESSENTRY32W>() as u32,
+            ..Default::default()
+        };
+
+        let mut result = Process32FirstW(snapshot, &mut entry);
+        while result.is_ok() {
+            let name_len = entry
+                .szExeFile
+                .iter()
+                .position(|&c| c == 0)
+                .unwrap_or(entry.szExeFile.len());
+
+            entries.push(ProcessEntry {
+                pid: entry.th32ProcessID,
+                parent_pid: entry.th32ParentProcessID,
+                exe_name: String::from_utf16_lossy(&entry.szExeFile[..name_len]),
+            });
+
+            result = Process32NextW(snapshot, &mut entry);
+        }
+
+        let _ = CloseHandle(snapshot);
+    }
+
+    Ok(entries)
+}
+
+/// Returns the package full name of a process, or `None` for unpackaged processes.
+pub fn get_package_full_name(process_id: u32) -> Option<String> {
+    query_package_string(process_id, |handle, length, buffer| unsafe {
+        GetPackageFullName(handle, length, buffer)
+    })
+}
+
+/// Returns the package family name of a process, or `None` for unpackaged processes.
+pub fn get_package_family_name(process_id: u32) -> Option<String> {
+    query_package_string(process_id, |handle, length, buffer| unsafe {
+        GetPackageFamilyName(handle, length, buffer)
+    })
+}
+
+fn query_package_string(
+    process_id: u32,
+    query: impl Fn(HANDLE, &mut u32, Option<PWSTR>) -> WIN32_ERROR,
+) -> Option<String> {
+    unsafe {
+        let process_handle =
+            OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, process_id).ok()?;
+
+        // First call reports the required buffer length
+        let mut length: u32 = 0;
+        let result = query(process_handle, &mut length, None);
+        if result != ERROR_INSUFFICIENT_BUFFER || length == 0 {
+            let _ = CloseHandle(process_handle);
+            return None;
+        }
+
+        let mut buffer: Vec<u16> = vec![0; length as usize];
+        let result = query(
+            process_handle,
+            &mut length,
+            Some(PWSTR(buffer.as_mut_ptr())),
+        );
+        let _ = CloseHandle(process_handle);
+
+        if result.is_err() {
+            return None;
+        }
+
+        // The reported length includes the null terminator
+        let name_len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+        Some(String::from_utf16_lossy(&buffer[..name_len]))
+    }
+}
+
+/// Prints the process tree rooted at `root_pid`, annotating each node with its
+/// package identity and marking the process currently tracked by the monitor.
+pub fn print_process_tree(root_pid: u32, tracked_pid: Option<u32>) {
+    let entries = match snapshot_processes() {
+        Ok(entries) => entries,
+        Err(e) => {
+            error!("Could not build process tree: {}", e);
+            return;
+        }
+    };
+
+    let mut children: HashMap<u32, Vec<&ProcessEntry>> = HashMap::new();
+    for entry in &entries {
+        // PID 0 and 4 report themselves as their own parents
+        if entry.pid != entry.parent_pid {
+            children.entry(entry.parent_pid).or_default().push(entry);
+        }
+    }
+
+    let root_label = match entries.iter().find(|entry| entry.pid == root_pid) {
+        Some(root) => describe_process(root, tracked_pid),
+        None => format!("{} <exited>", root_pid),
+    };
+    info!("{}", root_label);
+
+    let mut visited = HashSet::from([root_pid]);
+    print_children(root_pid, "", &children, &mut visited, tracked_pid);
+}
+
+fn print_children(
+    parent_pid: u32,
+    prefix: &str,
+    children: &HashMap<u32, Vec<&ProcessEntry>>,
+    visited: &mut HashSet<u32>,
+    tracked_pid: Option<u32>,
+) {
+    let Some(child_entries) = children.get(&parent_pid) else {
+        return;
+    };
+
+    // Parent PIDs are never updated, so a reused PID can claim children that
+    // were created before it. Drop those when both creation times are known.
+    let parent_created = process::get_process_creation_time(parent_pid);
+    let child_entries: Vec<&&ProcessEntry> = child_entries
+        .iter()
+        .filter(|child| {
+            match (
+                parent_created,
+                process::get_process_creation_time(child.pid),
+            ) {
+                (Some(parent), Some(child)) => child >= parent,
+                _ => true,
+            }
+        })
+        // Guard against cycles when creation times are unavailable
+        .filter(|child| visited.insert(child.pid))
+        .collect();
+
+    for (index, child) in child_entries.iter().enumerate() {
+        let is_last = index + 1 == child_entries.len();
+        let (branch, indent) = if is_last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+
+        info!(
+            "{}{}{}",
+            prefix,
+            branch,
+            describe_process(child, tracked_pid)
+        );
+        print_children(
+            child.pid,
+            &format!("{}{}", prefix, indent),
+            children,
+            visited,
+            tracked_pid,
+        );
+    }
+}
+
+fn describe_process(entry: &ProcessEntry, tracked_pid: Option<u32>) -> String {
+    let package = get_package_full_name(entry.pid).unwrap_or_else(|| "unpackaged".to_string());
+    let marker = if tracked_pid == Some(entry.pid) {
+        "  ◀ tracked"
+    } else {
+        ""
+    };
+    format!("{} {} [{}]{}", entry.pid, entry.exe_name, package, marker)
+}
@@ -0,0 +1,213 @@
+//! Steam's binary KeyValues format, which `shortcuts.vdf` keeps a user's
+//! non-Steam games in. Each entry is a type byte, a NUL-terminated key and a
+//! value; maps end with [`END`]. Strings that aren't UTF-8, as a path
+//! written in a legacy code page can be, are kept byte for byte so writing
+//! the file back doesn't change them.
+
+/// A nested map, in file order.
+pub type Map = Vec<(String, Value)>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Map(Map),
+    String(String),
+    /// A string that isn't UTF-8, as Steam wrote it
+    Bytes(Vec<u8>),
+    Int(u32),
+    Long(u64),
+}
+
+const MAP: u8 = 0x00;
+const STRING: u8 = 0x01;
+const INT: u8 = 0x02;
+const LONG: u8 = 0x07;
+const END: u8 = 0x08;
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&Map> {
+        match self {
+            Value::Map(map) => Some(map),
+            _ => None,
+        }
+    }
+}
+
+/// The value under `key`, matched case-insensitively since Steam has written
+/// keys such as `AppName` in both cases over the years.
+pub fn get<'a>(map: &'a Map, key: &str) -> Option<&'a Value> {
+    map.iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(key))
+        .map(|(_, value)| value)
+}
+
+/// Parses a whole file. A missing final terminator is tolerated, as Steam
+/// itself does.
+pub fn parse(bytes: &[u8]) -> Result<Map, String> {
+    let mut position = 0;
+    parse_map(bytes, &mut position)
+}
+
+fn parse_map(bytes: &[u8], position: &mut usize) -> Result<Map, String> {
+    let mut map = Map::new();
+    while let Some(&kind) = bytes.get(*position) {
+        *position += 1;
+        if kind == END {
+            return Ok(map);
+        }
+        let key_offset = *position;
+        let key = String::from_utf8(read_string(bytes, position)?.to_vec())
+            .map_err(|_| format!("key at offset {} isn't UTF-8", key_offset))?;
+        let value = match kind {
+            MAP => Value::Map(parse_map(bytes, position)?),
+            STRING => {
+                let text = read_string(bytes, position)?.to_vec();
+                match String::from_utf8(text) {
+                    Ok(text) => Value::String(text),
+                    Err(e) => Value::Bytes(e.into_bytes()),
+                }
+            }
+            INT => Value::Int(u32::from_le_bytes(read_bytes(bytes, position)?)),
+            LONG => Value::Long(u64::from_le_bytes(read_bytes(bytes, position)?)),
+            _ => {
+                return Err(format!(
+                    "unknown value type 0x{:02x} at offset {}",
+                    kind,
+                    *position - 1
+                ));
+            }
+        };
+        map.push((key, value));
+    }
+    Ok(map)
+}
+
+/// The NUL-terminated string at `position`, without its terminator.
+fn read_string<'a>(bytes: &'a [u8], position: &mut usize) -> Result<&'a [u8], String> {
+    let length = bytes[*position..]
+        .iter()
+        .position(|&byte| byte == 0)
+        .ok_or_else(|| format!("unterminated string at offset {}", *position))?;
+    let text = &bytes[*position..*position + length];
+    *position += length + 1;
+    Ok(text)
+}
+
+fn read_bytes<const N: usize>(bytes: &[u8], position: &mut usize) -> Result<[u8; N], String> {
+    let value = bytes
+        .get(*position..*position + N)
+        .and_then(|slice| slice.try_into().ok())
+        .ok_or_else(|| format!("truncated value at offset {}", *position))?;
+    *position += N;
+    Ok(value)
+}
+
+/// Serializes a whole file, the inverse of [`parse`].
+pub fn write(map: &Map) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    write_map(map, &mut bytes);
+    bytes
+}
+
+fn write_map(map: &Map, bytes: &mut Vec<u8>) {
+    for (key, value) in map {
+        let kind = match value {
+            Value::Map(_) => MAP,
+            Value::String(_) | Value::Bytes(_) => STRING,
+            Value::Int(_) => INT,
+            Value::Long(_) => LONG,
+        };
+        bytes.push(kind);
+        write_string(key.as_bytes(), bytes);
+        match value {
+            Value::Map(map) => write_map(map, bytes),
+            Value::String(text) => write_string(text.as_bytes(), bytes),
+            Value::Bytes(text) => write_string(text, bytes),
+            Value::Int(number) => bytes.extend_from_slice(&number.to_le_bytes()),
+            Value::Long(number) => bytes.extend_from_slice(&number.to_le_bytes()),
+        }
+    }
+    bytes.push(END);
+}
+
+fn write_string(text: &[u8], bytes: &mut Vec<u8>) {
+    bytes.extend_from_slice(text);
+    bytes.push(0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `shortcuts.vdf` with one entry, as Steam writes it.
+    fn shortcuts_file() -> Vec<u8> {
+        let mut bytes = vec![MAP];
+        bytes.extend_from_slice(b"shortcuts\0");
+        bytes.push(MAP);
+        bytes.extend_from_slice(b"0\0");
+        bytes.push(INT);
+        bytes.extend_from_slice(b"appid\0");
+        bytes.extend_from_slice(&0x8000_1234u32.to_le_bytes());
+        bytes.push(STRING);
+        bytes.extend_from_slice(b"AppName\0Forza\0");
+        bytes.push(LONG);
+        bytes.extend_from_slice(b"LastPlayTime\0");
+        bytes.extend_from_slice(&42u64.to_le_bytes());
+        bytes.push(MAP);
+        bytes.extend_from_slice(b"tags\0");
+        bytes.push(END);
+        bytes.push(END);
+        bytes.push(END);
+        bytes.push(END);
+        bytes
+    }
+
+    #[test]
+    fn parses_nested_maps_and_values() {
+        let root = parse(&shortcuts_file()).unwrap();
+        let shortcuts = get(&root, "Shortcuts").and_then(Value::as_map).unwrap();
+        let entry = get(shortcuts, "0").and_then(Value::as_map).unwrap();
+        assert_eq!(get(entry, "appid"), Some(&Value::Int(0x8000_1234)));
+        assert_eq!(get(entry, "appname").and_then(Value::as_str), Some("Forza"));
+        assert_eq!(get(entry, "LastPlayTime"), Some(&Value::Long(42)));
+        assert_eq!(get(entry, "tags"), Some(&Value::Map(Map::new())));
+    }
+
+    #[test]
+    fn writes_back_what_it_parsed() {
+        let bytes = shortcuts_file();
+        assert_eq!(write(&parse(&bytes).unwrap()), bytes);
+    }
+
+    #[test]
+    fn keeps_strings_that_arent_utf8() {
+        let mut bytes = vec![STRING];
+        bytes.extend_from_slice(b"StartDir\0\"C:\\Spiele\\Caf\xE9\"\0");
+        bytes.push(END);
+        let root = parse(&bytes).unwrap();
+        assert!(matches!(get(&root, "StartDir"), Some(Value::Bytes(_))));
+        assert_eq!(write(&root), bytes);
+    }
+
+    #[test]
+    fn tolerates_a_missing_final_terminator() {
+        let mut bytes = shortcuts_file();
+        bytes.pop();
+        let root = parse(&bytes).unwrap();
+        assert!(get(&root, "shortcuts").is_some());
+    }
+
+    #[test]
+    fn rejects_damaged_files() {
+        assert!(parse(&[0x05, b'k', 0]).is_err_and(|e| e.contains("unknown value type")));
+        assert!(parse(&[STRING, b'k', 0, b'v']).is_err_and(|e| e.contains("unterminated")));
+        assert!(parse(&[INT, b'k', 0, 1, 2]).is_err_and(|e| e.contains("truncated")));
+        assert!(parse(&[STRING, 0xFF, 0, b'v', 0]).is_err_and(|e| e.contains("isn't UTF-8")));
+    }
+}
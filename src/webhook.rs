@@ -0,0 +1,110 @@
+//! Tells another machine when the game is actually up, for wake-on-LAN and
+//! auto-launch workflows where the streaming client should connect only
+//! once there's something to stream. The [`Event::GameReady`] event is
+//! POSTed as JSON to a URL given with `--ready-url` or
+//! `LIBRARYLINK_READY_URL`.
+
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::http::{self, Request};
+use crate::report::{self, Event, Level, Reporter, warning};
+
+/// Environment variable with a URL to notify on every invocation.
+pub const READY_URL_VARIABLE: &str = "LIBRARYLINK_READY_URL";
+
+/// Attempts per notification; the receiver may still be waking up.
+const ATTEMPTS: u32 = 3;
+
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// How long the end of the session waits for a notification still being
+/// sent, since librarylink exits right after.
+const FINISH_TIMEOUT: Duration = Duration::from_secs(10);
+
+const FINISH_CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Reports to `primary`, and POSTs `game_ready` events to `url` in the
+/// background so monitoring isn't held up by a slow receiver.
+pub struct ReadyWebhook {
+    primary: Box<dyn Reporter>,
+    url: String,
+    pending: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl ReadyWebhook {
+    pub fn new(primary: Box<dyn Reporter>, url: impl Into<String>) -> Self {
+        ReadyWebhook {
+            primary,
+            url: url.into(),
+            pending: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Waits up to [`FINISH_TIMEOUT`] for the notifications still being sent.
+    fn finish(&self) {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap_or_else(|p| p.into_inner()));
+        let deadline = Instant::now() + FINISH_TIMEOUT;
+        for notification in pending {
+            while !notification.is_finished() && Instant::now() < deadline {
+                thread::sleep(FINISH_CHECK_INTERVAL);
+            }
+            if notification.is_finished() {
+                let _ = notification.join();
+            } else {
+                warning!("⚠️ Gave up waiting to notify that the game was ready");
+                return;
+            }
+        }
+    }
+}
+
+impl Reporter for ReadyWebhook {
+    fn message(&self, level: Level, text: &str) {
+        self.primary.message(level, text);
+    }
+
+    fn event(&self, event: &Event) {
+        self.primary.event(event);
+        match event {
+            Event::GameReady { .. } => {
+                if let Some(body) = report::event_line(event) {
+                    let url = self.url.clone();
+                    let notification = thread::spawn(move || notify(&url, body));
+                    self.pending
+                        .lock()
+                        .unwrap_or_else(|p| p.into_inner())
+                        .push(notification);
+                }
+            }
+            // librarylink exits after either
+            Event::SessionEnded { .. } | Event::Failed { .. } => self.finish(),
+            _ => {}
+        }
+    }
+}
+
+fn notify(url: &str, body: String) {
+    let request = Request {
+        headers: vec![("Content-Type", "application/json".to_string())],
+        body: Some(body.as_bytes()),
+        insecure: false,
+    };
+    let mut last_error = String::new();
+    for attempt in 1..=ATTEMPTS {
+        match http::send("POST", url, &request) {
+            Ok(response) if response.is_success() => return,
+            Ok(response) => last_error = format!("HTTP {}", response.status),
+            Err(e) => last_error = e.to_string(),
+        }
+        if attempt < ATTEMPTS {
+            thread::sleep(RETRY_DELAY);
+        }
+    }
+    warning!(
+        "⚠️ Could not notify {} that the game is ready: {}",
+        url,
+        last_error
+    );
+}
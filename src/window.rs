@@ -0,0 +1,62 @@
+use windows::Win32::Foundation::{HWND, LPARAM};
+use windows::Win32::UI::WindowsAndMessaging::{
+    EnumWindows, GetWindowTextW, GetWindowThreadProcessId, IsWindowVisible,
+};
+use windows::core::BOOL;
+
+/// A visible top-level window.
+#[derive(Debug, Clone)]
+pub struct WindowInfo {
+    pub handle: HWND,
+    pub process_id: u32,
+    pub title: String,
+}
+
+unsafe extern "system" fn collect_window(hwnd: HWND, lparam: LPARAM) -> BOOL {
+    let windows = unsafe { &mut *(lparam.0 as *mut Vec<WindowInfo>) };
+    unsafe {
+        if IsWindowVisible(hwnd).as_bool() {
+            let mut process_id: u32 = 0;
+            GetWindowThreadProcessId(hwnd, Some(&mut process_id));
+
+            let mut title: [u16; 512] = [0; 512];
+            let length = GetWindowTextW(hwnd, &mut title).max(0) as usize;
+            windows.push(WindowInfo {
+                handle: hwnd,
+                process_id,
+                title: String::from_utf16_lossy(&title[..length]),
+            });
+        }
+    }
+    // Keep enumerating
+    BOOL(1)
+}
+
+/// The process's first visible window with a title, which for games is the
+/// one they draw in.
+pub fn main_window(process_id: u32) -> Option<HWND> {
+    visible_windows(Some(&[process_id]))
+        .into_iter()
+        .find(|window| !window.title.is_empty())
+        .map(|window| window.handle)
+}
+
+/// Visible top-level windows, optionally only those owned by the given
+/// processes.
+pub fn visible_windows(process_ids: Option<&[u32]>) -> Vec<WindowInfo> {
+    let mut windows: Vec<WindowInfo> = Vec::new();
+    unsafe {
+        let _ = EnumWindows(
+            Some(collect_window),
+            LPARAM(&mut windows as *mut Vec<WindowInfo> as isize),
+        );
+    }
+
+    match process_ids {
+        Some(process_ids) => windows
+            .into_iter()
+            .filter(|window| process_ids.contains(&window.process_id))
+            .collect(),
+        None => windows,
+    }
+}
@@ -0,0 +1,228 @@
+//! Follows whether the game's window is fullscreen, borderless, windowed or
+//! minimized while it's monitored. On a headless streaming host a game that
+//! minimizes itself, as many do when focus moves in exclusive fullscreen,
+//! streams as a black screen with nobody there to click it back.
+
+use std::fmt;
+use std::thread;
+use std::time::{Duration, Instant};
+use windows::Win32::Foundation::{HWND, RECT};
+use windows::Win32::Graphics::Gdi::{MONITOR_DEFAULTTONEAREST, MonitorFromWindow};
+use windows::Win32::System::Threading::{AttachThreadInput, GetCurrentThreadId};
+use windows::Win32::UI::Shell::{QUNS_RUNNING_D3D_FULL_SCREEN, SHQueryUserNotificationState};
+use windows::Win32::UI::WindowsAndMessaging::{
+    BringWindowToTop, GWL_STYLE, GetForegroundWindow, GetWindowLongW, GetWindowRect,
+    GetWindowThreadProcessId, IsIconic, SW_RESTORE, SetForegroundWindow, ShowWindow, WS_CAPTION,
+};
+
+use crate::monitor::{MonitorControl, MonitorOptions, MonitorState};
+use crate::placement::{DpiAwareness, monitor_info};
+use crate::process::get_process_info;
+use crate::report::{Event, Reporter};
+use crate::timings::{self, Phase};
+use crate::window;
+
+/// How often the tracked process's window is looked at.
+const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How a window is shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+#[cfg_attr(feature = "json", serde(rename_all = "snake_case"))]
+pub enum WindowMode {
+    /// Exclusive fullscreen, with the game owning the display
+    Fullscreen,
+    /// A captionless window covering its whole monitor
+    Borderless,
+    Windowed,
+    Minimized,
+}
+
+impl fmt::Display for WindowMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            WindowMode::Fullscreen => "fullscreen",
+            WindowMode::Borderless => "borderless",
+            WindowMode::Windowed => "windowed",
+            WindowMode::Minimized => "minimized",
+        })
+    }
+}
+
+/// How `hwnd` is shown right now. Must be called from a per-monitor DPI
+/// aware thread, or a scaled window never appears to cover its monitor.
+pub fn window_mode(hwnd: HWND) -> WindowMode {
+    unsafe {
+        if IsIconic(hwnd).as_bool() {
+            return WindowMode::Minimized;
+        }
+        let mut bounds = RECT::default();
+        let covers_monitor = GetWindowRect(hwnd, &mut bounds).is_ok()
+            && monitor_info(MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST))
+                .is_some_and(|info| info.monitorInfo.rcMonitor == bounds);
+        if !covers_monitor {
+            return WindowMode::Windowed;
+        }
+        // Windows only says whether the foreground app is in exclusive
+        // fullscreen, so a covering window in the background counts as
+        // borderless
+        if GetForegroundWindow() == hwnd
+            && SHQueryUserNotificationState() == Ok(QUNS_RUNNING_D3D_FULL_SCREEN)
+        {
+            return WindowMode::Fullscreen;
+        }
+        let style = GetWindowLongW(hwnd, GWL_STYLE) as u32;
+        if style & WS_CAPTION.0 == WS_CAPTION.0 {
+            // Maximized with its title bar still showing
+            WindowMode::Windowed
+        } else {
+            WindowMode::Borderless
+        }
+    }
+}
+
+/// Why a window was restored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+#[cfg_attr(feature = "json", serde(rename_all = "snake_case"))]
+pub enum RestoreReason {
+    Minimized,
+    /// Another window had the foreground
+    LostFocus,
+}
+
+impl fmt::Display for RestoreReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            RestoreReason::Minimized => "minimized",
+            RestoreReason::LostFocus => "in the background",
+        })
+    }
+}
+
+/// Reports [`Event::GameReady`] once a tracked process, the launcher or the
+/// game, shows a window, then each change in its window mode until the
+/// session ends. With `auto_restore` the window is restored whenever it
+/// becomes minimized; with `restore_after` it's restored and brought back to
+/// the front once it has been minimized or in the background that long. Run
+/// alongside the monitor, which it only reads.
+pub(crate) fn watch_tracked_windows(
+    options: &MonitorOptions,
+    session_id: &str,
+    aumid: &str,
+    control: &MonitorControl,
+    reporter: &dyn Reporter,
+) {
+    let _awareness = DpiAwareness::enter();
+    let mut last: Option<(u32, WindowMode)> = None;
+    // Since when the window has been minimized or in the background, and
+    // whether a failed restore since it was last in front has been reported
+    let mut away_since: Option<(u32, Instant)> = None;
+    let mut failure_reported = false;
+    let mut ready_reported = false;
+    loop {
+        let process_id = match control.state() {
+            Some(MonitorState::Ended { .. }) => return,
+            Some(MonitorState::TrackingGame { pid } | MonitorState::TrackingStub { pid }) => pid,
+            _ => 0,
+        };
+        if process_id != 0
+            && let Some(hwnd) = window::main_window(process_id)
+        {
+            if !ready_reported {
+                ready_reported = true;
+                reporter.event(&Event::GameReady {
+                    session_id: session_id.to_string(),
+                    aumid: aumid.to_string(),
+                    pid: process_id,
+                    path: get_process_info(process_id).map(|info| info.path),
+                });
+            }
+            let mode = window_mode(hwnd);
+            let previous = last
+                .filter(|(pid, _)| *pid == process_id)
+                .map(|(_, mode)| mode);
+            if previous != Some(mode) {
+                timings::milestone(Phase::FirstWindow);
+                last = Some((process_id, mode));
+                reporter.event(&Event::WindowModeChanged {
+                    pid: process_id,
+                    mode,
+                    previous,
+                });
+                if mode == WindowMode::Minimized && options.auto_restore {
+                    restore(hwnd);
+                    reporter.event(&Event::WindowRestored {
+                        pid: process_id,
+                        reason: RestoreReason::Minimized,
+                    });
+                }
+            }
+
+            if let Some(after) = options.restore_after {
+                if mode != WindowMode::Minimized && has_foreground(process_id) {
+                    away_since = None;
+                    failure_reported = false;
+                } else {
+                    let since = match away_since {
+                        Some((pid, since)) if pid == process_id => since,
+                        _ => away_since.insert((process_id, Instant::now())).1,
+                    };
+                    if since.elapsed() >= after {
+                        let reason = if mode == WindowMode::Minimized {
+                            RestoreReason::Minimized
+                        } else {
+                            RestoreReason::LostFocus
+                        };
+                        // Either way, wait another full period before retrying
+                        away_since = None;
+                        if restore(hwnd) {
+                            reporter.event(&Event::WindowRestored {
+                                pid: process_id,
+                                reason,
+                            });
+                        } else if !failure_reported {
+                            failure_reported = true;
+                            reporter.event(&Event::WindowNotRestored {
+                                pid: process_id,
+                                reason,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        thread::sleep(CHECK_INTERVAL);
+    }
+}
+
+/// Whether the foreground window, whichever of its windows that is, belongs
+/// to `process_id`.
+fn has_foreground(process_id: u32) -> bool {
+    let mut owner = 0;
+    unsafe { GetWindowThreadProcessId(GetForegroundWindow(), Some(&mut owner)) };
+    owner == process_id
+}
+
+/// Un-minimizes `hwnd` and brings it to the front, returning whether it has
+/// the foreground now. Windows only lets the thread that has the foreground
+/// hand it over, so this briefly shares that thread's input state; it can
+/// still be refused, such as while the desktop is locked.
+pub(crate) fn restore(hwnd: HWND) -> bool {
+    unsafe {
+        if IsIconic(hwnd).as_bool() {
+            let _ = ShowWindow(hwnd, SW_RESTORE);
+        }
+        let current_thread = GetCurrentThreadId();
+        let foreground_thread = GetWindowThreadProcessId(GetForegroundWindow(), None);
+        let attached = foreground_thread != 0
+            && foreground_thread != current_thread
+            && AttachThreadInput(current_thread, foreground_thread, true).as_bool();
+        let _ = BringWindowToTop(hwnd);
+        let focused = SetForegroundWindow(hwnd).as_bool();
+        if attached {
+            let _ = AttachThreadInput(current_thread, foreground_thread, false);
+        }
+        focused
+    }
+}
@@ -0,0 +1,137 @@
+use std::sync::OnceLock;
+use windows::Win32::Foundation::CloseHandle;
+use windows::Win32::Storage::FileSystem::{
+    CreateFileW, FILE_FLAG_BACKUP_SEMANTICS, FILE_NAME_NORMALIZED, FILE_READ_ATTRIBUTES,
+    FILE_SHARE_DELETE, FILE_SHARE_READ, FILE_SHARE_WRITE, GETFINALPATHNAMEBYHANDLE_FLAGS,
+    GetFinalPathNameByHandleW, GetLongPathNameW, OPEN_EXISTING, QueryDosDeviceW, VOLUME_NAME_DOS,
+};
+use windows::core::HSTRING;
+
+const FINAL_PATH_FLAGS: GETFINALPATHNAMEBYHANDLE_FLAGS =
+    GETFINALPATHNAMEBYHANDLE_FLAGS(FILE_NAME_NORMALIZED.0 | VOLUME_NAME_DOS.0);
+
+/// Maps NT device names (`\Device\HarddiskVolume3`) to drive letters (`C:`).
+fn dos_devices() -> &'static [(String, String)] {
+    static DEVICES: OnceLock<Vec<(String, String)>> = OnceLock::new();
+    DEVICES.get_or_init(|| {
+        let mut devices = Vec::new();
+        for letter in b'A'..=b'Z' {
+            let drive = format!("{}:", letter as char);
+            let mut target: [u16; 512] = [0; 512];
+            let length = unsafe { QueryDosDeviceW(&HSTRING::from(&drive), Some(&mut target)) };
+            if length == 0 {
+                continue;
+            }
+
+            // The buffer holds a list of null-terminated names; the first is current
+            let end = target.iter().position(|&c| c == 0).unwrap_or(0);
+            if end > 0 {
+                devices.push((String::from_utf16_lossy(&target[..end]), drive));
+            }
+        }
+        devices
+    })
+}
+
+/// Removes the `\\?\`, `\\?\UNC\` and `\??\` prefixes used for long and NT paths.
+pub(crate) fn strip_prefixes(path: &str) -> String {
+    if let Some(rest) = path.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{}", rest)
+    } else if let Some(rest) = path
+        .strip_prefix(r"\\?\")
+        .or_else(|| path.strip_prefix(r"\??\"))
+    {
+        rest.to_string()
+    } else {
+        path.to_string()
+    }
+}
+
+/// Rewrites `\Device\...` paths onto the drive letter they are mounted at.
+fn map_device_path(path: &str) -> String {
+    if !path.starts_with(r"\Device\") {
+        return path.to_string();
+    }
+
+    for (device, drive) in dos_devices() {
+        if let Some(rest) = path.strip_prefix(device.as_str())
+            && (rest.is_empty() || rest.starts_with('\\'))
+        {
+            return format!("{}{}", drive, rest);
+        }
+    }
+
+    path.to_string()
+}
+
+/// Resolves junctions, symlinks and 8.3 names by asking the file system for the
+/// final path of an open handle.
+fn final_path(path: &str) -> Option<String> {
+    unsafe {
+        let handle = CreateFileW(
+            &HSTRING::from(path),
+            FILE_READ_ATTRIBUTES.0,
+            FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+            None,
+            OPEN_EXISTING,
+            // Required to open directories
+            FILE_FLAG_BACKUP_SEMANTICS,
+            None,
+        )
+        .ok()?;
+
+        let mut buffer: Vec<u16> = vec![0; 512];
+        let mut length = GetFinalPathNameByHandleW(handle, &mut buffer, FINAL_PATH_FLAGS);
+        if length as usize > buffer.len() {
+            buffer.resize(length as usize, 0);
+            length = GetFinalPathNameByHandleW(handle, &mut buffer, FINAL_PATH_FLAGS);
+        }
+        let _ = CloseHandle(handle);
+
+        if length == 0 || length as usize > buffer.len() {
+            return None;
+        }
+        Some(String::from_utf16_lossy(&buffer[..length as usize]))
+    }
+}
+
+/// Expands 8.3 short names when the final path can't be queried (for example
+/// inside `WindowsApps`, where opening the file is often denied).
+fn long_path(path: &str) -> Option<String> {
+    unsafe {
+        let short = HSTRING::from(path);
+        let length = GetLongPathNameW(&short, None);
+        if length == 0 {
+            return None;
+        }
+
+        let mut buffer: Vec<u16> = vec![0; length as usize];
+        let length = GetLongPathNameW(&short, Some(&mut buffer));
+        if length == 0 || length as usize > buffer.len() {
+            return None;
+        }
+        Some(String::from_utf16_lossy(&buffer[..length as usize]))
+    }
+}
+
+/// Normalizes a path for comparison: strips long-path and NT prefixes, maps
+/// device paths to drive letters, resolves reparse points and short names, and
+/// removes trailing separators. Paths that no longer exist are normalized
+/// textually.
+pub fn normalize_path(path: &str) -> String {
+    let path = path.replace('/', "\\");
+    let path = map_device_path(&strip_prefixes(&path));
+
+    let resolved = final_path(&path)
+        .or_else(|| long_path(&path))
+        .map(|resolved| map_device_path(&strip_prefixes(&resolved)))
+        .unwrap_or(path);
+
+    let trimmed = resolved.trim_end_matches('\\');
+    // Keep the separator for drive roots so "C:" doesn't mean "current dir on C:"
+    if trimmed.len() == 2 && trimmed.ends_with(':') {
+        format!("{}\\", trimmed)
+    } else {
+        trimmed.to_string()
+    }
+}
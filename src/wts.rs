@@ -0,0 +1,340 @@
+use std::fmt;
+use windows::Win32::Foundation::{CloseHandle, HANDLE};
+use windows::Win32::Security::{
+    DuplicateTokenEx, SecurityIdentification, TOKEN_ALL_ACCESS, TokenPrimary,
+};
+use windows::Win32::System::Environment::{CreateEnvironmentBlock, DestroyEnvironmentBlock};
+use windows::Win32::System::RemoteDesktop::{
+    ProcessIdToSessionId, WTS_CURRENT_SERVER_HANDLE, WTS_INFO_CLASS, WTS_SESSION_INFOW,
+    WTSClientProtocolType, WTSEnumerateSessionsW, WTSFreeMemory, WTSGetActiveConsoleSessionId,
+    WTSQuerySessionInformationW, WTSQueryUserToken, WTSUserName, WTSWinStationName,
+};
+use windows::Win32::System::Threading::{
+    CREATE_UNICODE_ENVIRONMENT, CreateProcessAsUserW, GetExitCodeProcess, INFINITE,
+    PROCESS_INFORMATION, STARTUPINFOW, WaitForSingleObject,
+};
+use windows::core::PWSTR;
+
+use crate::error::WinError;
+use crate::paths;
+use crate::process;
+
+/// Returned by WTSGetActiveConsoleSessionId when no session is attached to the console
+const NO_CONSOLE_SESSION: u32 = 0xFFFF_FFFF;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionKind {
+    /// Session 0, where services run and no desktop is visible
+    Services,
+    /// The session attached to the physical console
+    Console,
+    /// A Remote Desktop session
+    Remote,
+    /// A local session that isn't attached to the console (e.g. switched-away user)
+    Background,
+}
+
+impl fmt::Display for SessionKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            SessionKind::Services => "services (session 0)",
+            SessionKind::Console => "console",
+            SessionKind::Remote => "remote desktop",
+            SessionKind::Background => "background (not attached to the console)",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SessionInfo {
+    pub id: u32,
+    pub kind: SessionKind,
+    pub station_name: String,
+}
+
+/// Which session `uwp-launch` requires the activation to land in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionRequirement {
+    Any,
+    Console,
+}
+
+impl SessionRequirement {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "any" => Some(SessionRequirement::Any),
+            "console" => Some(SessionRequirement::Console),
+            _ => None,
+        }
+    }
+}
+
+pub fn session_id_for_process(process_id: u32) -> Option<u32> {
+    let mut session_id: u32 = 0;
+    unsafe { ProcessIdToSessionId(process_id, &mut session_id) }.ok()?;
+    Some(session_id)
+}
+
+pub fn active_console_session_id() -> Option<u32> {
+    let session_id = unsafe { WTSGetActiveConsoleSessionId() };
+    (session_id != NO_CONSOLE_SESSION).then_some(session_id)
+}
+
+fn query_session_info(session_id: u32, info_class: WTS_INFO_CLASS) -> Option<Vec<u8>> {
+    unsafe {
+        let mut buffer = PWSTR::null();
+        let mut bytes_returned: u32 = 0;
+        WTSQuerySessionInformationW(
+            Some(WTS_CURRENT_SERVER_HANDLE),
+            session_id,
+            info_class,
+            &mut buffer,
+            &mut bytes_returned,
+        )
+        .ok()?;
+
+        let bytes =
+            std::slice::from_raw_parts(buffer.0 as *const u8, bytes_returned as usize).to_vec();
+        WTSFreeMemory(buffer.0 as *mut _);
+        Some(bytes)
+    }
+}
+
+fn wide_bytes_to_string(bytes: &[u8]) -> String {
+    let wide: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .take_while(|&c| c != 0)
+        .collect();
+    String::from_utf16_lossy(&wide)
+}
+
+pub fn describe_session(session_id: u32) -> SessionInfo {
+    // WTSClientProtocolType is a USHORT: 0 = console, 1 = legacy ICA, 2 = RDP
+    let protocol = query_session_info(session_id, WTSClientProtocolType)
+        .filter(|bytes| bytes.len() >= 2)
+        .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+        .unwrap_or(0);
+
+    let station_name = query_session_info(session_id, WTSWinStationName)
+        .map(|bytes| wide_bytes_to_string(&bytes))
+        .unwrap_or_default();
+
+    let kind = if session_id == 0 {
+        SessionKind::Services
+    } else if protocol != 0 {
+        SessionKind::Remote
+    } else if active_console_session_id() == Some(session_id) {
+        SessionKind::Console
+    } else {
+        SessionKind::Background
+    };
+
+    SessionInfo {
+        id: session_id,
+        kind,
+        station_name,
+    }
+}
+
+/// Checks whether an activation from the current session would be visible,
+/// returning an explanation when it would not.
+pub fn check_launch_session(requirement: SessionRequirement) -> Result<SessionInfo, String> {
+    let session_id = session_id_for_process(std::process::id())
+        .ok_or_else(|| "Could not determine the current Windows session".to_string())?;
+    let info = describe_session(session_id);
+
+    match info.kind {
+        SessionKind::Services => Err(
+            "librarylink is running in session 0 (as a service or scheduled task without a logged-on desktop). \
+             Apps activated from here are never visible; run librarylink inside the user's session instead."
+                .to_string(),
+        ),
+        SessionKind::Remote | SessionKind::Background
+            if requirement == SessionRequirement::Console =>
+        {
+            let console = match active_console_session_id() {
+                Some(id) => format!("the console is session {}", id),
+                None => "no session is attached to the console".to_string(),
+            };
+            Err(format!(
+                "librarylink is running in session {} ({}), but --session console was requested and {}. \
+                 The app would launch where the stream can't see it.",
+                info.id, info.kind, console
+            ))
+        }
+        _ => Ok(info),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LoggedOnSession {
+    pub id: u32,
+    pub user_name: String,
+}
+
+/// Lists sessions that have a user logged on.
+pub fn logged_on_sessions() -> Vec<LoggedOnSession> {
+    let mut sessions = Vec::new();
+
+    unsafe {
+        let mut session_info: *mut WTS_SESSION_INFOW = std::ptr::null_mut();
+        let mut count: u32 = 0;
+        if WTSEnumerateSessionsW(
+            Some(WTS_CURRENT_SERVER_HANDLE),
+            0,
+            1,
+            &mut session_info,
+            &mut count,
+        )
+        .is_err()
+        {
+            return sessions;
+        }
+
+        for info in std::slice::from_raw_parts(session_info, count as usize) {
+            let user_name = query_session_info(info.SessionId, WTSUserName)
+                .map(|bytes| wide_bytes_to_string(&bytes))
+                .unwrap_or_default();
+            if !user_name.is_empty() {
+                sessions.push(LoggedOnSession {
+                    id: info.SessionId,
+                    user_name,
+                });
+            }
+        }
+
+        WTSFreeMemory(session_info as *mut _);
+    }
+
+    sessions
+}
+
+/// Resolves `--user-session` values, which may be a session ID or a user name.
+pub fn resolve_user_session(spec: &str) -> Result<u32, String> {
+    if let Ok(session_id) = spec.parse::<u32>() {
+        return Ok(session_id);
+    }
+
+    let sessions = logged_on_sessions();
+    sessions
+        .iter()
+        .find(|session| session.user_name.eq_ignore_ascii_case(spec))
+        .map(|session| session.id)
+        .ok_or_else(|| {
+            let available: Vec<String> = sessions
+                .iter()
+                .map(|session| format!("{} (session {})", session.user_name, session.id))
+                .collect();
+            format!(
+                "No logged-on session found for user '{}'. Logged-on users: {}",
+                spec,
+                if available.is_empty() {
+                    "none".to_string()
+                } else {
+                    available.join(", ")
+                }
+            )
+        })
+}
+
+/// Runs librarylink itself inside another user's session and waits for it to
+/// exit, returning its exit code.
+///
+/// UWP activation always targets the caller's own session, so the only way to
+/// start a Store app for another user is to run the activation from a process
+/// in their session. This requires the caller to run as LocalSystem (for
+/// example from a service), since WTSQueryUserToken needs SeTcbPrivilege.
+pub fn run_in_user_session(session_id: u32, arguments: &[String]) -> Result<u32, String> {
+    let executable = std::env::current_exe()
+        .map_err(|e| format!("Could not locate the librarylink executable: {}", e))?;
+
+    let mut command_line = process::quote_argument(&executable.to_string_lossy());
+    // The child would otherwise look for its state in the user's profile
+    if paths::is_portable() {
+        command_line.push_str(" --portable");
+    }
+    if !arguments.is_empty() {
+        command_line.push(' ');
+        command_line.push_str(&process::join_arguments(arguments));
+    }
+    let mut command_line: Vec<u16> = command_line.encode_utf16().chain(Some(0)).collect();
+    let mut desktop: Vec<u16> = "winsta0\\default".encode_utf16().chain(Some(0)).collect();
+
+    unsafe {
+        let mut user_token = HANDLE::default();
+        WTSQueryUserToken(session_id, &mut user_token).map_err(|e| {
+            format!(
+                "Could not get the user token for session {} ({}). Launching into another user's session requires running as LocalSystem.",
+                session_id,
+                WinError::new("WTSQueryUserToken", &e)
+            )
+        })?;
+
+        let mut primary_token = HANDLE::default();
+        let duplicated = DuplicateTokenEx(
+            user_token,
+            TOKEN_ALL_ACCESS,
+            None,
+            SecurityIdentification,
+            TokenPrimary,
+            &mut primary_token,
+        );
+        let _ = CloseHandle(user_token);
+        duplicated.map_err(|e| {
+            format!(
+                "Could not duplicate the user token: {}",
+                WinError::new("DuplicateTokenEx", &e)
+            )
+        })?;
+
+        // Give the child the target user's environment (profile paths, APPDATA)
+        let mut environment: *mut core::ffi::c_void = std::ptr::null_mut();
+        let environment = CreateEnvironmentBlock(&mut environment, Some(primary_token), false)
+            .ok()
+            .map(|_| environment);
+
+        let startup_info = STARTUPINFOW {
+            cb: std::mem::size_of::<STARTUPINFOW>() as u32,
+            lpDesktop: PWSTR(desktop.as_mut_ptr()),
+            ..Default::default()
+        };
+        let mut process_info = PROCESS_INFORMATION::default();
+
+        let created = CreateProcessAsUserW(
+            Some(primary_token),
+            None,
+            Some(PWSTR(command_line.as_mut_ptr())),
+            None,
+            None,
+            false,
+            CREATE_UNICODE_ENVIRONMENT,
+            environment.map(|block| block as *const _),
+            None,
+            &startup_info,
+            &mut process_info,
+        );
+
+        if let Some(block) = environment {
+            let _ = DestroyEnvironmentBlock(block);
+        }
+        let _ = CloseHandle(primary_token);
+        created.map_err(|e| {
+            format!(
+                "Could not start librarylink in session {}: {}",
+                session_id,
+                WinError::new("CreateProcessAsUserW", &e)
+            )
+        })?;
+
+        let _ = CloseHandle(process_info.hThread);
+        WaitForSingleObject(process_info.hProcess, INFINITE);
+
+        let mut exit_code: u32 = 0;
+        let _ = GetExitCodeProcess(process_info.hProcess, &mut exit_code);
+        let _ = CloseHandle(process_info.hProcess);
+
+        Ok(exit_code)
+    }
+}
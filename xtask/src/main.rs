@@ -0,0 +1,189 @@
+//! Developer tasks, run with `cargo xtask <task>`.
+
+mod sha256;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const REPOSITORY: &str = "https://github.com/dguenther/librarylink";
+const DESCRIPTION: &str =
+    "Launch and monitor UWP and Game Pass games from Steam and other launchers";
+const WINGET_ID: &str = "dguenther.librarylink";
+const WINGET_MANIFEST_VERSION: &str = "1.6.0";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let result = match args.first().map(String::as_str) {
+        Some("release-manifests") => release_manifests(&args[1..]),
+        _ => {
+            eprintln!("Usage: cargo xtask <task>");
+            eprintln!("Tasks:");
+            eprintln!(
+                "  release-manifests --exe <librarylink.exe> --msi <librarylink.msi> [--out <dir>]"
+            );
+            eprintln!("      Generate winget and Scoop manifests for the current version");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn workspace_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .expect("xtask lives inside the workspace")
+        .to_path_buf()
+}
+
+/// Reads the librarylink version from the `[package]` table of the root manifest.
+fn package_version() -> Result<String, Box<dyn std::error::Error>> {
+    let manifest = fs::read_to_string(workspace_root().join("Cargo.toml"))?;
+    let mut in_package = false;
+    for line in manifest.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+        } else if in_package
+            && let Some(value) = line.strip_prefix("version")
+            && let Some(value) = value.trim_start().strip_prefix('=')
+        {
+            return Ok(value.trim().trim_matches('"').to_string());
+        }
+    }
+    Err("No version found in the [package] table of Cargo.toml".into())
+}
+
+fn hash_file(path: &Path) -> Result<String, Box<dyn std::error::Error>> {
+    let contents =
+        fs::read(path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+    Ok(sha256::hex_digest(&contents))
+}
+
+fn release_manifests(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut exe: Option<PathBuf> = None;
+    let mut msi: Option<PathBuf> = None;
+    let mut out = workspace_root().join("target").join("manifests");
+
+    // Parse arguments
+    let mut i = 0;
+    while i < args.len() {
+        let value = args.get(i + 1).map(PathBuf::from);
+        match args[i].as_str() {
+            "--exe" => exe = value,
+            "--msi" => msi = value,
+            "--out" => out = value.ok_or("--out requires a directory")?,
+            other => return Err(format!("Unknown option '{}'", other).into()),
+        }
+        i += 2;
+    }
+    let exe = exe.ok_or("--exe is required (the release build of librarylink.exe)")?;
+    let msi = msi.ok_or("--msi is required (the installer built by cargo wix)")?;
+
+    let version = package_version()?;
+    let release_url = format!("{}/releases/download/v{}", REPOSITORY, version);
+    let exe_hash = hash_file(&exe)?;
+    let msi_hash = hash_file(&msi)?;
+
+    // Scoop installs the bare executable
+    let scoop_dir = out.join("scoop");
+    fs::create_dir_all(&scoop_dir)?;
+    let scoop = format!(
+        r#"{{
+    "version": "{version}",
+    "description": "{DESCRIPTION}",
+    "homepage": "{REPOSITORY}",
+    "license": "MIT",
+    "architecture": {{
+        "64bit": {{
+            "url": "{release_url}/librarylink.exe",
+            "hash": "{exe_hash}"
+        }}
+    }},
+    "bin": "librarylink.exe",
+    "checkver": "github",
+    "autoupdate": {{
+        "architecture": {{
+            "64bit": {{
+                "url": "{REPOSITORY}/releases/download/v$version/librarylink.exe"
+            }}
+        }}
+    }}
+}}
+"#
+    );
+    let scoop_path = scoop_dir.join("librarylink.json");
+    fs::write(&scoop_path, scoop)?;
+    println!("Wrote {}", scoop_path.display());
+
+    // winget uses the installer, laid out the way winget-pkgs expects
+    let winget_dir = out
+        .join("winget")
+        .join("manifests")
+        .join("d")
+        .join("dguenther")
+        .join("librarylink")
+        .join(&version);
+    fs::create_dir_all(&winget_dir)?;
+
+    let header = |kind: &str| {
+        format!(
+            "# yaml-language-server: $schema=https://aka.ms/winget-manifest.{kind}.{WINGET_MANIFEST_VERSION}.schema.json\n\
+             PackageIdentifier: {WINGET_ID}\n\
+             PackageVersion: {version}\n"
+        )
+    };
+    let files = [
+        (
+            format!("{}.yaml", WINGET_ID),
+            format!(
+                "{}DefaultLocale: en-US\nManifestType: version\nManifestVersion: {}\n",
+                header("version"),
+                WINGET_MANIFEST_VERSION
+            ),
+        ),
+        (
+            format!("{}.installer.yaml", WINGET_ID),
+            format!(
+                "{}InstallerType: wix\nScope: machine\nInstallModes:\n  - interactive\n  - silent\n\
+                 Commands:\n  - librarylink\nProtocols:\n  - librarylink\nInstallers:\n  \
+                 - Architecture: x64\n    InstallerUrl: {}/librarylink.msi\n    InstallerSha256: {}\n\
+                 ManifestType: installer\nManifestVersion: {}\n",
+                header("installer"),
+                release_url,
+                msi_hash.to_uppercase(),
+                WINGET_MANIFEST_VERSION
+            ),
+        ),
+        (
+            format!("{}.locale.en-US.yaml", WINGET_ID),
+            format!(
+                "{}PackageLocale: en-US\nPublisher: Derek Guenther\nPackageName: librarylink\n\
+                 PackageUrl: {}\nLicense: MIT\nLicenseUrl: {}/blob/main/LICENSE\n\
+                 ShortDescription: {}\nTags:\n  - game-pass\n  - steam\n  - uwp\n\
+                 ManifestType: defaultLocale\nManifestVersion: {}\n",
+                header("defaultLocale"),
+                REPOSITORY,
+                REPOSITORY,
+                DESCRIPTION,
+                WINGET_MANIFEST_VERSION
+            ),
+        ),
+    ];
+    for (name, contents) in files {
+        let path = winget_dir.join(name);
+        fs::write(&path, contents)?;
+        println!("Wrote {}", path.display());
+    }
+
+    Ok(())
+}